@@ -0,0 +1,73 @@
+//! Embeds build metadata (git commit, build time, compiler version) as
+//! compile-time environment variables read by `src/build_info.rs`.
+//!
+//! No `rerun-if-changed` is emitted, so Cargo reruns this script whenever a
+//! package file changes and the metadata always describes the current build.
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // ---
+
+    let git_sha = command_output("git", &["rev-parse", "--short=12", "HEAD"])
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds byte-identical
+    let build_secs = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version =
+        command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=VORTEX_GIT_SHA={git_sha}");
+    println!(
+        "cargo:rustc-env=VORTEX_BUILD_TIME={}",
+        format_utc(build_secs)
+    );
+    println!("cargo:rustc-env=VORTEX_RUSTC_VERSION={rustc_version}");
+}
+
+/// Runs `program` and returns its trimmed stdout, or `None` if it fails.
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    // ---
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Formats Unix seconds as an RFC 3339 UTC timestamp (`2025-09-27T14:03:00Z`).
+fn format_utc(secs: u64) -> String {
+    // ---
+
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
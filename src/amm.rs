@@ -0,0 +1,264 @@
+//! Constant-product AMM math.
+//!
+//! Pure functions for quoting Uniswap V2-style pools, measuring price impact,
+//! and sizing sandwich trades. Nothing here touches RPC or mock data, so the
+//! numeric core stays deterministic and independent of the detectors using it.
+//!
+//! All intermediate products are computed in 512 bits, and reserves grown by
+//! a trade saturate at `U256::MAX`, so no reserves can overflow. Products
+//! that would pass 512 bits (inputs near `U256::MAX`) drop their low bits,
+//! and are only approximate.
+
+use ethers::types::{U256, U512};
+
+/// Basis-point denominator (10_000 bps = 100%).
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+// ---
+
+/// Returns the output amount for swapping `amount_in` through a constant-product pool.
+///
+/// Mirrors Uniswap V2's `getAmountOut`: the LP fee (`fee_bps`, e.g. 30 = 0.3%)
+/// is taken from the input before applying `x * y = k`. Returns zero for empty
+/// pools or zero input.
+pub fn get_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256, fee_bps: u16) -> U256 {
+    // ---
+
+    if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
+        return U256::zero();
+    }
+
+    let amount_in_with_fee = amount_in.full_mul(fee_multiplier(fee_bps));
+    let denominator = reserve_in.full_mul(U256::from(BPS_DENOMINATOR)) + amount_in_with_fee;
+
+    // Inputs past any real pool could carry the numerator beyond 512 bits, so
+    // both sides give up their low bits first; the denominator is at least
+    // the input, so it stays nonzero
+    let shift = (amount_in_with_fee.bits() + reserve_out.bits()).saturating_sub(511);
+    let numerator = (amount_in_with_fee >> shift) * U512::from(reserve_out);
+    let denominator = denominator >> shift;
+
+    // Output is strictly below reserve_out, so it always fits back into 256 bits
+    U256::try_from(numerator / denominator).unwrap_or(reserve_out)
+}
+
+/// Returns how far the execution price of `amount_in` falls below the pool's
+/// spot price, in basis points (excluding the LP fee itself).
+///
+/// For a constant-product pool this is `in / (reserve_in + in)` on the
+/// post-fee input, independent of `reserve_out`.
+pub fn price_impact_bps(amount_in: U256, reserve_in: U256, fee_bps: u16) -> u64 {
+    // ---
+
+    if amount_in.is_zero() {
+        return 0;
+    }
+    if reserve_in.is_zero() {
+        return BPS_DENOMINATOR;
+    }
+
+    let amount_in_with_fee = amount_in.full_mul(fee_multiplier(fee_bps));
+    let denominator = reserve_in.full_mul(U256::from(BPS_DENOMINATOR)) + amount_in_with_fee;
+
+    (amount_in_with_fee * U512::from(BPS_DENOMINATOR) / denominator).low_u64()
+}
+
+/// Amounts moved by each leg of a sandwich, as [`quote_sandwich`] prices them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandwichQuote {
+    // ---
+    /// `token_out` the frontrun buys, and so what the backrun sells
+    pub frontrun_out: U256,
+
+    /// `token_in` the backrun gets back, once the victim has moved the price
+    pub backrun_out: U256,
+}
+
+/// Prices a frontrun of `frontrun_in` ahead of a victim swap of
+/// `victim_amount_in`, backrun by selling everything the frontrun bought into
+/// the pool as the frontrun and victim leave it.
+///
+/// All three swaps pay the pool fee. A `victim_amount_in` of zero prices the
+/// backrun as if the victim never executed.
+pub fn quote_sandwich(
+    frontrun_in: U256,
+    victim_amount_in: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee_bps: u16,
+) -> SandwichQuote {
+    // ---
+
+    // Frontrun: buy token_out ahead of the victim
+    let frontrun_out = get_amount_out(frontrun_in, reserve_in, reserve_out, fee_bps);
+    let (reserve_in, reserve_out) = (
+        reserve_in.saturating_add(frontrun_in),
+        reserve_out - frontrun_out,
+    );
+
+    // Victim trades against the worsened price
+    let victim_out = get_amount_out(victim_amount_in, reserve_in, reserve_out, fee_bps);
+    let (reserve_in, reserve_out) = (
+        reserve_in.saturating_add(victim_amount_in),
+        reserve_out - victim_out,
+    );
+
+    // Backrun: sell what the frontrun bought back into the moved pool
+    let backrun_out = get_amount_out(frontrun_out, reserve_out, reserve_in, fee_bps);
+
+    SandwichQuote {
+        frontrun_out,
+        backrun_out,
+    }
+}
+
+/// Returns the largest frontrun (at most `max_frontrun`) that still lets the
+/// victim receive at least `victim_min_out`, i.e. stay within their slippage guard.
+///
+/// A larger frontrun moves the price further for the victim, so the victim's
+/// output is monotonically decreasing in the frontrun size and the limit is
+/// found by binary search. Returns zero when even the unfrontrun victim swap
+/// would miss `victim_min_out`.
+pub fn optimal_sandwich_frontrun(
+    victim_amount_in: U256,
+    victim_min_out: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee_bps: u16,
+    max_frontrun: U256,
+) -> U256 {
+    // ---
+
+    let victim_out_after = |frontrun_in: U256| {
+        let frontrun_out = get_amount_out(frontrun_in, reserve_in, reserve_out, fee_bps);
+        get_amount_out(
+            victim_amount_in,
+            reserve_in.saturating_add(frontrun_in),
+            reserve_out - frontrun_out,
+            fee_bps,
+        )
+    };
+
+    if victim_out_after(U256::zero()) < victim_min_out {
+        return U256::zero();
+    }
+    if victim_out_after(max_frontrun) >= victim_min_out {
+        return max_frontrun;
+    }
+
+    // Invariant: `low` is feasible, `high` is not
+    let (mut low, mut high) = (U256::zero(), max_frontrun);
+    while high - low > U256::one() {
+        let mid = low + (high - low) / 2;
+        if victim_out_after(mid) >= victim_min_out {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// Returns the share of input kept after a `fee_bps` LP fee, scaled by 10_000.
+fn fee_multiplier(fee_bps: u16) -> U256 {
+    // ---
+    U256::from(BPS_DENOMINATOR.saturating_sub(u64::from(fee_bps)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eth(n: u64) -> U256 {
+        U256::exp10(18) * n
+    }
+
+    #[test]
+    fn amount_out_matches_uniswap_v2() {
+        // 1 ETH into 100/200, after the 0.3% fee
+        assert_eq!(
+            get_amount_out(eth(1), eth(100), eth(200), 30),
+            U256::from(1_974_316_068_794_122_597u64)
+        );
+    }
+
+    #[test]
+    fn empty_pools_and_zero_input_quote_nothing() {
+        assert_eq!(
+            get_amount_out(U256::zero(), eth(1), eth(1), 30),
+            U256::zero()
+        );
+        assert_eq!(
+            get_amount_out(eth(1), U256::zero(), eth(1), 30),
+            U256::zero()
+        );
+        assert_eq!(
+            get_amount_out(eth(1), eth(1), U256::zero(), 30),
+            U256::zero()
+        );
+    }
+
+    #[test]
+    fn fees_and_fractions_round_down() {
+        // 499.25 and 2.30 exactly, floored like the pool contract
+        assert_eq!(
+            get_amount_out(U256::from(1_000), U256::from(1_000), U256::from(1_000), 30),
+            U256::from(499)
+        );
+        assert_eq!(
+            get_amount_out(U256::from(3), U256::from(10), U256::from(10), 30),
+            U256::from(2)
+        );
+        // A single wei into a deep pool buys nothing
+        assert_eq!(
+            get_amount_out(U256::one(), U256::exp10(6), U256::exp10(6), 30),
+            U256::zero()
+        );
+        // A fee of 100% or more leaves nothing to swap
+        assert_eq!(get_amount_out(eth(1), eth(1), eth(1), 10_000), U256::zero());
+        assert_eq!(
+            get_amount_out(eth(1), eth(1), eth(1), u16::MAX),
+            U256::zero()
+        );
+    }
+
+    #[test]
+    fn maximal_reserves_do_not_overflow() {
+        let out = get_amount_out(U256::MAX, U256::MAX, U256::MAX, 30);
+        assert!(out < U256::MAX);
+        assert!(out > U256::MAX / 2 - U256::MAX / 100);
+
+        let quote = quote_sandwich(U256::MAX, U256::MAX, U256::MAX, U256::MAX, 30);
+        assert!(quote.frontrun_out <= U256::MAX);
+        assert_eq!(
+            optimal_sandwich_frontrun(U256::MAX, U256::zero(), U256::MAX, U256::MAX, 30, U256::MAX),
+            U256::MAX
+        );
+    }
+
+    #[test]
+    fn sandwich_backrun_profits_from_the_victim() {
+        let quote = quote_sandwich(eth(1), eth(5), eth(100), eth(200), 30);
+        assert_eq!(quote.frontrun_out, U256::from(1_974_316_068_794_122_597u64));
+        assert_eq!(quote.backrun_out, U256::from(1_094_240_236_904_285_693u64));
+        assert!(quote.backrun_out > eth(1));
+    }
+
+    #[test]
+    fn sandwich_without_a_victim_loses_the_fees() {
+        let quote = quote_sandwich(eth(1), U256::zero(), eth(100), eth(200), 30);
+        assert!(quote.backrun_out < eth(1));
+    }
+
+    #[test]
+    fn sandwich_on_an_empty_pool_buys_nothing() {
+        let quote = quote_sandwich(eth(1), eth(5), U256::zero(), U256::zero(), 30);
+        assert_eq!(
+            quote,
+            SandwichQuote {
+                frontrun_out: U256::zero(),
+                backrun_out: U256::zero(),
+            }
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Per-transaction decision log.
+//!
+//! `--audit-file <PATH>` records what became of every transaction the
+//! listener took in, one JSON object per line: skipped before analysis (and
+//! why), no opportunity, detected but not acted on, submitted, or rejected
+//! with the reason its bundle wasn't sent. Each analysis task fills in an
+//! [`AuditEntry`] that sends its record when dropped, so every transaction
+//! yields exactly one record whichever branch it leaves by. Records go to a
+//! writer thread over a channel, so analysis never waits on the disk.
+
+use ethers::types::TxHash;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+use tracing::error;
+
+/// Longest wait for the writer to flush outstanding records at shutdown.
+pub const AUDIT_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// ---
+
+/// Final disposition of an analyzed transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposition {
+    // ---
+    /// Never analyzed: excluded, below the value filter, without fee data, not
+    /// fetched, or timed out
+    Skipped,
+
+    /// Analyzed; no detector found an opportunity
+    NoOpportunity,
+
+    /// An opportunity was found but no bundle was attempted (`--no-submit`)
+    Detected,
+
+    /// A bundle was built and sent (or simulated, under `--simulate`)
+    Submitted,
+
+    /// An opportunity was found but its bundle was not sent
+    Rejected,
+}
+
+/// One line of the audit file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    // ---
+    pub tx_hash: TxHash,
+
+    /// Decoded call type (e.g. `uniswap_v2_swap`), or `None` if the body was never fetched
+    pub tx_type: Option<String>,
+
+    pub disposition: Disposition,
+
+    /// Why the transaction was skipped or its opportunity rejected
+    pub reason: Option<String>,
+}
+
+/// Sending end of the audit file; disabled (`Default`) when no file is set.
+/// Clones feed the same writer.
+#[derive(Debug, Clone, Default)]
+pub struct AuditLog {
+    // ---
+    records: Option<Sender<AuditRecord>>,
+}
+
+/// Handle on the writer thread, used to wait for it to drain at shutdown.
+#[derive(Debug)]
+pub struct AuditWriter {
+    // ---
+    /// Signalled once every record has been written and flushed
+    done: Receiver<()>,
+}
+
+/// The record of one transaction, sent to the audit file when dropped.
+#[derive(Debug)]
+pub struct AuditEntry {
+    // ---
+    records: Option<Sender<AuditRecord>>,
+    record: AuditRecord,
+}
+
+// ---
+
+impl AuditLog {
+    // ---
+
+    /// Creates (or truncates) `path` and starts the thread writing to it.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be created or the thread can't start.
+    pub fn create(path: &Path) -> anyhow::Result<(Self, AuditWriter)> {
+        // ---
+
+        let file = File::create(path)
+            .map_err(|e| anyhow::anyhow!("Cannot create audit file {}: {}", path.display(), e))?;
+        let (records, incoming) = mpsc::channel();
+        let (finished, done) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("audit-writer".to_string())
+            .spawn(move || {
+                write_records(BufWriter::new(file), incoming);
+                let _ = finished.send(());
+            })
+            .map_err(|e| anyhow::anyhow!("Cannot start audit writer: {}", e))?;
+
+        Ok((
+            Self {
+                records: Some(records),
+            },
+            AuditWriter { done },
+        ))
+    }
+
+    /// Starts the record of `tx_hash`, as skipped until resolved otherwise.
+    pub fn entry(&self, tx_hash: TxHash) -> AuditEntry {
+        // ---
+        AuditEntry {
+            records: self.records.clone(),
+            record: AuditRecord {
+                tx_hash,
+                tx_type: None,
+                disposition: Disposition::Skipped,
+                reason: Some("abandoned".to_string()),
+            },
+        }
+    }
+}
+
+impl AuditWriter {
+    // ---
+
+    /// Waits up to `timeout` for the writer to flush every record, once all
+    /// [`AuditLog`] clones have been dropped. Returns whether it finished.
+    pub fn finish(self, timeout: Duration) -> bool {
+        // ---
+        self.done.recv_timeout(timeout).is_ok()
+    }
+}
+
+impl AuditEntry {
+    // ---
+
+    /// Sets the transaction's call type, computed only when auditing is on.
+    pub fn set_tx_type(&mut self, tx_type: impl FnOnce() -> &'static str) {
+        // ---
+        if self.records.is_some() {
+            self.record.tx_type = Some(tx_type().to_string());
+        }
+    }
+
+    /// Sets the transaction's final disposition and the reason for it.
+    pub fn resolve(&mut self, disposition: Disposition, reason: Option<&str>) {
+        // ---
+        self.record.disposition = disposition;
+        self.record.reason = reason.map(str::to_string);
+    }
+}
+
+impl Drop for AuditEntry {
+    fn drop(&mut self) {
+        // ---
+        if let Some(records) = self.records.take() {
+            // The writer only goes away if writing failed, which it has logged
+            let _ = records.send(self.record.clone());
+        }
+    }
+}
+
+/// Writes each record as a JSON line, flushing whenever the channel drains,
+/// until every sender is gone.
+fn write_records(mut out: BufWriter<File>, incoming: Receiver<AuditRecord>) {
+    // ---
+
+    let write = |out: &mut BufWriter<File>, record: AuditRecord| -> std::io::Result<()> {
+        serde_json::to_writer(&mut *out, &record)?;
+        out.write_all(b"\n")
+    };
+
+    while let Ok(record) = incoming.recv() {
+        let written = std::iter::once(record)
+            .chain(incoming.try_iter())
+            .try_for_each(|record| write(&mut out, record))
+            .and_then(|()| out.flush());
+        if let Err(e) = written {
+            error!("❌ Audit file write failed; no further records: {}", e);
+            return;
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Profitability backtest over captured transactions.
+//!
+//! `--backtest <CAPTURE>` replays a capture of pending transactions through
+//! the detectors and totals what the opportunities found would have paid:
+//! gross profit, gas, net profit, and the net profit expected to be realized
+//! once bundles land only as often as the first relay's configured inclusion
+//! probability. A capture is JSON lines, one transaction per line in the
+//! `eth_getTransactionByHash` format.
+//!
+//! Detection runs offline, like `--bench`, with a static oracle. Pools are
+//! priced from the state at capture time where the capture records it: a
+//! [`ReserveSnapshot`] line (`{"block": …, "reserves": {pair: [reserve0,
+//! reserve1]}}`, optionally with a `base_fee`) sets the block, and the
+//! reserves of the pairs it lists, for the transactions after it. Pools a
+//! capture never lists are priced from the built-in mock reserves.
+
+use crate::bench;
+use crate::reserves::PoolReserveCache;
+use crate::searcher;
+use crate::types::{MEVConfig, RelayConfiguration};
+use crate::units;
+use ethers::types::{Address, Block, Transaction, H256, U256, U64};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+// ---
+
+/// One line of a capture.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureEntry {
+    // ---
+    /// A pending transaction to replay
+    Transaction(Box<Transaction>),
+
+    /// Chain state the transactions after it are priced at
+    Reserves(ReserveSnapshot),
+}
+
+/// Pool reserves recorded at a block.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ReserveSnapshot {
+    // ---
+    /// Block the reserves were read at
+    pub block: u64,
+
+    /// Base fee of that block in wei (a hex quantity), if recorded
+    #[serde(default)]
+    pub base_fee: Option<U256>,
+
+    /// `(reserve0, reserve1)` per V2-style pair, as `getReserves()` returned
+    /// them, in hex quantities
+    pub reserves: HashMap<Address, (U256, U256)>,
+}
+
+/// Totals for one strategy's opportunities.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StrategyTotals {
+    // ---
+    /// Opportunities found
+    pub opportunities: usize,
+
+    /// Profit before gas, in wei
+    pub gross_profit: U256,
+
+    /// Estimated gas cost, in wei
+    pub gas_cost: U256,
+
+    /// Profit after gas, in wei
+    pub net_profit: U256,
+}
+
+/// Results of a backtest run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestReport {
+    // ---
+    /// Captured transactions replayed
+    pub transactions: usize,
+
+    /// Chance a submitted bundle lands (0.0-1.0)
+    pub inclusion_probability: f64,
+
+    /// Totals by strategy
+    pub strategies: BTreeMap<&'static str, StrategyTotals>,
+}
+
+// ---
+
+impl BacktestReport {
+    // ---
+
+    /// Returns the totals across every strategy.
+    pub fn total(&self) -> StrategyTotals {
+        // ---
+        self.strategies
+            .values()
+            .fold(StrategyTotals::default(), |total, totals| StrategyTotals {
+                opportunities: total.opportunities + totals.opportunities,
+                gross_profit: total.gross_profit.saturating_add(totals.gross_profit),
+                gas_cost: total.gas_cost.saturating_add(totals.gas_cost),
+                net_profit: total.net_profit.saturating_add(totals.net_profit),
+            })
+    }
+
+    /// Returns the share of replayed transactions that yielded `totals`'
+    /// opportunities (0.0-1.0).
+    pub fn hit_rate(&self, totals: &StrategyTotals) -> f64 {
+        // ---
+        totals.opportunities as f64 / self.transactions.max(1) as f64
+    }
+
+    /// Returns the net profit `totals` would have realized, in ETH, with bundles
+    /// landing at the configured inclusion probability.
+    pub fn expected_profit_eth(&self, totals: &StrategyTotals) -> f64 {
+        // ---
+        eth(totals.net_profit) * self.inclusion_probability
+    }
+
+    /// Returns the summary table printed by `--backtest`: one row per strategy
+    /// with opportunities, then the totals.
+    pub fn summary_table(&self) -> String {
+        // ---
+
+        let mut table = format!(
+            "🧾 Backtest over {} captured txs ({:.1}% inclusion probability)\n{:<22} {:>6} {:>9} {:>12} {:>12} {:>12} {:>12}\n",
+            self.transactions,
+            self.inclusion_probability * 100.0,
+            "strategy",
+            "opps",
+            "hit rate",
+            "gross ETH",
+            "gas ETH",
+            "net ETH",
+            "expected ETH"
+        );
+
+        let mut row = |label: &str, totals: &StrategyTotals| {
+            let _ = writeln!(
+                table,
+                "{:<22} {:>6} {:>8.2}% {:>12.6} {:>12.6} {:>12.6} {:>12.6}",
+                label,
+                totals.opportunities,
+                self.hit_rate(totals) * 100.0,
+                eth(totals.gross_profit),
+                eth(totals.gas_cost),
+                eth(totals.net_profit),
+                self.expected_profit_eth(totals)
+            );
+        };
+
+        for (strategy, totals) in &self.strategies {
+            row(strategy, totals);
+        }
+        row("total", &self.total());
+        table.trim_end().to_string()
+    }
+}
+
+/// Returns the inclusion probability of the relay a sequential submission
+/// tries first: the first enabled relay in `priority_order`, or zero if none.
+pub fn inclusion_probability(relays: &RelayConfiguration) -> f64 {
+    // ---
+    relays
+        .priority_order
+        .iter()
+        .filter_map(|name| relays.relays.get(name))
+        .find(|relay| relay.enabled)
+        .map_or(0.0, |relay| relay.inclusion_probability.clamp(0.0, 1.0))
+}
+
+/// Reads a capture file of JSON-lines transactions and reserve snapshots.
+///
+/// # Errors
+/// Returns an error if the file can't be read or a line is neither.
+pub fn load_capture(path: &Path) -> anyhow::Result<Vec<CaptureEntry>> {
+    // ---
+    let capture = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read capture {}: {}", path.display(), e))?;
+    parse_capture(&capture)
+        .map_err(|e| anyhow::anyhow!("Malformed capture {}: {}", path.display(), e))
+}
+
+/// Parses JSON-lines transactions and reserve snapshots, skipping blank
+/// lines. Lines with a `reserves` field are snapshots.
+///
+/// # Errors
+/// Returns an error naming the first line that is neither.
+pub fn parse_capture(capture: &str) -> anyhow::Result<Vec<CaptureEntry>> {
+    // ---
+    capture
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let entry = serde_json::from_str::<serde_json::Value>(line).and_then(|value| {
+                if value.get("reserves").is_some() {
+                    serde_json::from_value(value).map(CaptureEntry::Reserves)
+                } else {
+                    serde_json::from_value(value).map(CaptureEntry::Transaction)
+                }
+            });
+            entry.map_err(|e| anyhow::anyhow!("line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Replays a capture through detection under `config`, in capture order,
+/// pricing each transaction at the latest reserve snapshot before it.
+pub async fn run(
+    capture: &[CaptureEntry],
+    config: MEVConfig,
+    inclusion_probability: f64,
+) -> BacktestReport {
+    // ---
+
+    let mut ctx = bench::offline_context(config);
+    // Recorded reserves hold until the next snapshot, however long the replay takes
+    ctx.pool_reserves = PoolReserveCache::new(Duration::MAX, ctx.base_fees.clone());
+
+    let mut transactions = 0;
+    let mut strategies: BTreeMap<&'static str, StrategyTotals> = BTreeMap::new();
+    for entry in capture {
+        let tx = match entry {
+            CaptureEntry::Transaction(tx) => tx,
+            CaptureEntry::Reserves(snapshot) => {
+                // A new block scopes out the previous snapshot's reserves
+                ctx.base_fees.observe_block(&Block::<H256> {
+                    number: Some(U64::from(snapshot.block)),
+                    base_fee_per_gas: Some(
+                        snapshot.base_fee.unwrap_or_else(|| ctx.base_fees.current()),
+                    ),
+                    gas_used: U256::one(),
+                    gas_limit: U256::from(2),
+                    ..Default::default()
+                });
+                for (pool, reserves) in &snapshot.reserves {
+                    ctx.pool_reserves.insert(*pool, *reserves);
+                }
+                continue;
+            }
+        };
+        transactions += 1;
+        let Some(opportunity) = searcher::evaluate_opportunity(&ctx, tx).await else {
+            continue;
+        };
+
+        let base_fee = ctx.pricing_base_fee();
+        let net_profit = searcher::calculate_net_profit(&opportunity, base_fee);
+        let gas_cost = searcher::calculate_gas_cost(&opportunity, base_fee);
+        let totals = strategies.entry(opportunity.strategy()).or_default();
+        totals.opportunities += 1;
+        totals.gross_profit = totals
+            .gross_profit
+            .saturating_add(net_profit.saturating_add(gas_cost));
+        totals.gas_cost = totals.gas_cost.saturating_add(gas_cost);
+        totals.net_profit = totals.net_profit.saturating_add(net_profit);
+    }
+
+    BacktestReport {
+        transactions,
+        inclusion_probability,
+        strategies,
+    }
+}
+
+/// Converts wei to ETH for display.
+fn eth(wei: U256) -> f64 {
+    // ---
+    units::wei_to_eth_f64(wei)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::SEED;
+    use crate::mock;
+    use crate::types::{DexRegistry, TokenRegistry};
+
+    fn capture(count: usize) -> String {
+        let routers = DexRegistry::from_config(&Default::default()).unwrap();
+        bench::synthetic_transactions(count, SEED, &TokenRegistry::with_defaults(), &routers)
+            .iter()
+            .map(|tx| serde_json::to_string(tx).unwrap() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn capture_lines_parse_and_errors_name_the_line() {
+        let transactions = parse_capture(&format!("\n{}\n", capture(3))).unwrap();
+        assert_eq!(transactions.len(), 3);
+
+        let err = parse_capture(&format!("{}{{\"hash\": 1}}\n", capture(2))).unwrap_err();
+        assert!(err.to_string().starts_with("line 3:"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn swaps_are_priced_at_the_captured_reserves() {
+        let swap = mock::pair_swap_tx(
+            Address::repeat_byte(0xaa),
+            U256::exp10(16),
+            ["WETH", "USDC"],
+        );
+        let swap = serde_json::to_string(&swap).unwrap();
+        // Uniswap's WETH/USDC pair at 2,250 USDC per WETH (USDC is reserve0),
+        // 10% under SushiSwap's built-in price
+        let snapshot = serde_json::json!({
+            "block": 19_000_000,
+            "reserves": {
+                "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc": [
+                    U256::exp10(6) * 9_000_000u64,
+                    U256::exp10(18) * 4_000,
+                ],
+            },
+        });
+
+        let replay = |capture: String| async move {
+            let mut config = MEVConfig::default();
+            config.liquidation.enabled = false;
+            run(&parse_capture(&capture).unwrap(), config, 1.0).await
+        };
+        let built_in = replay(swap.clone()).await;
+        let captured = replay(format!("{snapshot}\n{swap}")).await;
+
+        // The swap is too small to open a gap at the built-in prices
+        assert_eq!(built_in.total().opportunities, 0);
+        assert_eq!(captured.transactions, 1);
+        assert_eq!(captured.total().opportunities, 1);
+        assert!(captured
+            .strategies
+            .keys()
+            .all(|strategy| strategy.ends_with("arbitrage")));
+    }
+
+    #[test]
+    fn inclusion_follows_the_first_enabled_relay() {
+        let mut relays = RelayConfiguration::default();
+        assert_eq!(inclusion_probability(&relays), 0.85);
+
+        relays.relays.get_mut("flashbots").unwrap().enabled = false;
+        assert_eq!(inclusion_probability(&relays), 0.75);
+
+        relays.priority_order.clear();
+        assert_eq!(inclusion_probability(&relays), 0.0);
+    }
+
+    #[tokio::test]
+    async fn report_totals_profit_per_strategy() {
+        let transactions = parse_capture(&capture(200)).unwrap();
+        let mut config = MEVConfig::default();
+        config.sandwich.enabled = true;
+
+        let report = run(&transactions, config, 0.5).await;
+
+        assert_eq!(report.transactions, 200);
+        assert!(report.strategies.len() > 1);
+        for totals in report.strategies.values() {
+            assert!(totals.opportunities > 0);
+            assert_eq!(totals.gross_profit, totals.net_profit + totals.gas_cost);
+        }
+
+        let total = report.total();
+        assert_eq!(
+            total.opportunities,
+            report
+                .strategies
+                .values()
+                .map(|totals| totals.opportunities)
+                .sum::<usize>()
+        );
+        assert!(report.hit_rate(&total) <= 1.0);
+        let expected = report.expected_profit_eth(&total);
+        assert!((expected - eth(total.net_profit) / 2.0).abs() < 1e-9);
+
+        let table = report.summary_table();
+        assert_eq!(table.lines().count(), report.strategies.len() + 3);
+        assert!(table.lines().last().unwrap().starts_with("total"));
+    }
+}
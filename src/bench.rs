@@ -0,0 +1,407 @@
+//! Detection-only throughput benchmark over synthetic transactions.
+//!
+//! `--bench <N>` measures the decode + detection path without a node: it
+//! generates N pending transactions carrying real calldata for the selectors
+//! the decoder understands, runs each through [`searcher::evaluate_opportunity`]
+//! back to back, and reports throughput and a latency histogram. Generation is
+//! seeded, so runs of the same size are comparable across builds.
+
+use crate::gas::BaseFeeTracker;
+use crate::oracle::StaticPriceOracle;
+use crate::reserves::{PoolReserveCache, POOL_RESERVE_TTL};
+use crate::searcher::{self, AnalysisContext, DEX};
+use crate::types::{DexRegistry, MEVConfig, MEVMetrics, TokenRegistry};
+use async_trait::async_trait;
+use ethers::abi::{self, Token};
+use ethers::core::rand::rngs::StdRng;
+use ethers::core::rand::{Rng, SeedableRng};
+use ethers::providers::{JsonRpcClient, JsonRpcError, MockError, Provider};
+use ethers::types::{Address, Transaction, H256, U256};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Write};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Seed for the synthetic transaction generator.
+pub const SEED: u64 = 0x766f_7274_6578;
+
+/// Base fee the benchmark prices gas at (20 gwei).
+const BENCH_BASE_FEE_WEI: u64 = 20_000_000_000;
+
+/// Tokens the synthetic swaps trade, all in the default registry.
+const TOKENS: [&str; 4] = ["WETH", "USDC", "USDT", "DAI"];
+
+/// Rough WETH price used to size stablecoin swaps like WETH ones.
+const WETH_PRICE_USD: f64 = 2_500.0;
+
+/// Upper bounds of the latency histogram buckets, in microseconds.
+const LATENCY_BUCKETS_US: [u64; 7] = [10, 50, 100, 500, 1_000, 5_000, 10_000];
+
+// ---
+
+/// Node stand-in for benchmark runs.
+///
+/// `eth_call` succeeds with empty return data, so sandwich victims simulate as
+/// non-reverting; every other method fails immediately. Detection never waits
+/// on the network, so the benchmark measures only local work.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OfflineNode;
+
+#[async_trait]
+impl JsonRpcClient for OfflineNode {
+    type Error = MockError;
+
+    async fn request<T, R>(&self, method: &str, _params: T) -> Result<R, MockError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        // ---
+        match method {
+            "eth_call" => Ok(serde_json::from_value(serde_json::json!("0x"))?),
+            _ => Err(MockError::JsonRpcError(JsonRpcError {
+                code: -32601,
+                message: format!("{method} is not available offline"),
+                data: None,
+            })),
+        }
+    }
+}
+
+/// Results of a benchmark run.
+#[derive(Debug)]
+pub struct BenchReport {
+    // ---
+    /// Wall-clock time for the whole run
+    pub elapsed: Duration,
+
+    /// Per-transaction analysis latency, sorted ascending
+    pub latencies: Vec<Duration>,
+
+    /// Opportunities found, by strategy
+    pub opportunities: BTreeMap<&'static str, usize>,
+}
+
+// ---
+
+impl BenchReport {
+    // ---
+
+    /// Returns analyzed transactions per second over the whole run.
+    pub fn throughput(&self) -> f64 {
+        // ---
+        self.latencies.len() as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Returns the latency at `quantile` (0.0-1.0), or zero for an empty run.
+    pub fn percentile(&self, quantile: f64) -> Duration {
+        // ---
+        let Some(last) = self.latencies.len().checked_sub(1) else {
+            return Duration::ZERO;
+        };
+        let index = (last as f64 * quantile).round() as usize;
+        self.latencies[index.min(last)]
+    }
+
+    /// Returns the summary table printed at the end of `--bench`.
+    pub fn summary_table(&self) -> String {
+        // ---
+
+        let total = self.latencies.len();
+        let mut table = format!(
+            "📏 Benchmark: {} synthetic txs in {:.3}s ({:.1} txs/sec)\n",
+            total,
+            self.elapsed.as_secs_f64(),
+            self.throughput()
+        );
+
+        let found: Vec<String> = self
+            .opportunities
+            .iter()
+            .map(|(strategy, count)| format!("{strategy}={count}"))
+            .collect();
+        let _ = writeln!(
+            table,
+            "opportunities: {}",
+            if found.is_empty() {
+                "none".to_string()
+            } else {
+                found.join(", ")
+            }
+        );
+
+        let _ = writeln!(table, "\n{:<12} {:>8} {:>7}", "latency", "txs", "share");
+        let mut lower = 0;
+        for upper in LATENCY_BUCKETS_US {
+            let count = self.count_between(lower, upper);
+            let _ = writeln!(
+                table,
+                "{:<12} {:>8} {:>6.1}%",
+                format!("<= {}", format_us(upper)),
+                count,
+                share(count, total)
+            );
+            lower = upper;
+        }
+        let slowest = self.count_between(lower, u64::MAX);
+        let _ = writeln!(
+            table,
+            "{:<12} {:>8} {:>6.1}%",
+            format!("> {}", format_us(lower)),
+            slowest,
+            share(slowest, total)
+        );
+
+        let _ = write!(
+            table,
+            "\np50 {:?}  p90 {:?}  p99 {:?}  max {:?}",
+            self.percentile(0.50),
+            self.percentile(0.90),
+            self.percentile(0.99),
+            self.percentile(1.0)
+        );
+        table
+    }
+
+    /// Counts latencies above `lower_us` and at most `upper_us` microseconds.
+    fn count_between(&self, lower_us: u64, upper_us: u64) -> usize {
+        // ---
+        self.latencies
+            .iter()
+            .map(|latency| u64::try_from(latency.as_micros()).unwrap_or(u64::MAX))
+            .filter(|us| (lower_us == 0 || *us > lower_us) && *us <= upper_us)
+            .count()
+    }
+}
+
+/// Generates `count` pending transactions: Uniswap V2 and V3 swaps between
+/// the default tokens, plus an occasional plain ERC-20 transfer, with sizes
+/// spread log-uniformly from 0.01 to 1000 ETH-equivalent and gas prices from
+/// 1 to 100 gwei. The same `seed` always yields the same transactions.
+pub fn synthetic_transactions(
+    count: usize,
+    seed: u64,
+    tokens: &TokenRegistry,
+    routers: &DexRegistry,
+) -> Vec<Transaction> {
+    // ---
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let tokens: Vec<(Address, u8)> = TOKENS
+        .iter()
+        .filter_map(|symbol| tokens.get(&tokens.address_of(symbol)?))
+        .map(|token| (token.address, token.decimals))
+        .collect();
+
+    (0..count)
+        .map(|i| {
+            let in_index = rng.gen_range(0..tokens.len());
+            let out_index = (in_index + rng.gen_range(1..tokens.len())) % tokens.len();
+            let (token_in, decimals) = tokens[in_index];
+            let (token_out, _) = tokens[out_index];
+
+            // Size in ETH terms, so stablecoin trades are comparable to WETH ones
+            let eth_size = 10f64.powf(rng.gen_range(-2.0..3.0));
+            let units = if in_index == 0 {
+                eth_size
+            } else {
+                eth_size * WETH_PRICE_USD
+            };
+            let amount_in = U256::from((units * 1e6) as u128) * U256::exp10(decimals as usize - 6);
+
+            let sender = Address::from_low_u64_be(rng.gen());
+            let (to, input) = match rng.gen_range(0..100) {
+                0..=9 => (token_in, encode_transfer(sender, amount_in)),
+                10..=54 => (
+                    routers.router(DEX::UniswapV2).unwrap_or_default(),
+                    encode_v2_swap(token_in, token_out, amount_in, sender),
+                ),
+                _ => (
+                    routers.router(DEX::UniswapV3).unwrap_or_default(),
+                    encode_v3_swap(token_in, token_out, amount_in, sender, &mut rng),
+                ),
+            };
+
+            Transaction {
+                hash: H256::from_low_u64_be(i as u64 + 1),
+                from: sender,
+                to: Some(to),
+                input: input.into(),
+                gas_price: Some(U256::from(rng.gen_range(1_000_000_000u64..100_000_000_000))),
+                gas: U256::from(250_000),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Builds an analysis context over [`OfflineNode`], with the listener's
+/// registries and stablecoin prices and gas priced at a fixed base fee.
+pub fn offline_context(config: MEVConfig) -> AnalysisContext<OfflineNode> {
+    // ---
+
+    let token_registry = TokenRegistry::with_defaults();
+    let oracle = ["USDC", "USDT", "DAI"]
+        .iter()
+        .filter_map(|symbol| token_registry.address_of(symbol))
+        .fold(StaticPriceOracle::default(), |oracle, stablecoin| {
+            oracle.with_token_price(stablecoin, 1.0)
+        });
+    let base_fees = Arc::new(BaseFeeTracker::new(U256::from(BENCH_BASE_FEE_WEI)));
+    AnalysisContext {
+        provider: Arc::new(Provider::new(OfflineNode)),
+        oracle: Arc::new(oracle),
+        config: Arc::new(RwLock::new(config)),
+        pools: Arc::new(searcher::default_pools(&token_registry)),
+        token_registry: Arc::new(token_registry),
+        base_fees: base_fees.clone(),
+        victim_simulations: searcher::VictimSimulationCache::new(searcher::VICTIM_SIMULATION_TTL),
+        pool_reserves: PoolReserveCache::new(POOL_RESERVE_TTL, base_fees.clone()),
+        tx_types: searcher::TxTypeCache::new(searcher::TX_TYPE_CACHE_CAPACITY, base_fees),
+        metrics: Arc::new(Mutex::new(MEVMetrics::default())),
+        inclusion_odds: searcher::InclusionOdds::default(),
+    }
+}
+
+/// Runs `count` synthetic transactions through detection one after another.
+pub async fn run(count: usize, config: MEVConfig, routers: &DexRegistry) -> BenchReport {
+    // ---
+
+    let transactions =
+        synthetic_transactions(count, SEED, &TokenRegistry::with_defaults(), routers);
+    let ctx = offline_context(config);
+
+    let mut latencies = Vec::with_capacity(count);
+    let mut opportunities = BTreeMap::new();
+    let start = Instant::now();
+    for tx in &transactions {
+        let analyzed = Instant::now();
+        let found = searcher::evaluate_opportunity(&ctx, tx).await;
+        latencies.push(analyzed.elapsed());
+
+        if let Some(opportunity) = found {
+            *opportunities.entry(opportunity.strategy()).or_default() += 1;
+        }
+    }
+    let elapsed = start.elapsed();
+
+    latencies.sort_unstable();
+    BenchReport {
+        elapsed,
+        latencies,
+        opportunities,
+    }
+}
+
+// ---
+// Calldata encoding for the decoder's selectors
+// ---
+
+/// ERC-20 `transfer(address,uint256)`.
+fn encode_transfer(recipient: Address, amount: U256) -> Vec<u8> {
+    // ---
+    let mut data = vec![0xa9, 0x05, 0x9c, 0xbb];
+    data.extend(abi::encode(&[
+        Token::Address(recipient),
+        Token::Uint(amount),
+    ]));
+    data
+}
+
+/// Uniswap V2 `swapExactTokensForTokens` over a direct path.
+fn encode_v2_swap(token_in: Address, token_out: Address, amount: U256, to: Address) -> Vec<u8> {
+    // ---
+    let mut data = vec![0x38, 0xed, 0x17, 0x39];
+    data.extend(abi::encode(&[
+        Token::Uint(amount),
+        Token::Uint(U256::zero()), // amountOutMin
+        Token::Array(vec![Token::Address(token_in), Token::Address(token_out)]),
+        Token::Address(to),
+        Token::Uint(U256::from(u32::MAX)), // deadline
+    ]));
+    data
+}
+
+/// Uniswap V3 `exactInputSingle` on a randomly chosen fee tier.
+fn encode_v3_swap(
+    token_in: Address,
+    token_out: Address,
+    amount: U256,
+    recipient: Address,
+    rng: &mut StdRng,
+) -> Vec<u8> {
+    // ---
+    let fee = [500u32, 3_000, 10_000][rng.gen_range(0..3)];
+    let mut data = vec![0x41, 0x4b, 0xf3, 0x89];
+    data.extend(abi::encode(&[Token::Tuple(vec![
+        Token::Address(token_in),
+        Token::Address(token_out),
+        Token::Uint(U256::from(fee)),
+        Token::Address(recipient),
+        Token::Uint(U256::from(u32::MAX)), // deadline
+        Token::Uint(amount),
+        Token::Uint(U256::zero()), // amountOutMinimum
+        Token::Uint(U256::zero()), // sqrtPriceLimitX96
+    ])]));
+    data
+}
+
+/// Renders a microsecond bucket bound, switching to milliseconds from 1ms.
+fn format_us(us: u64) -> String {
+    // ---
+    if us >= 1_000 {
+        format!("{}ms", us / 1_000)
+    } else {
+        format!("{us}µs")
+    }
+}
+
+/// Returns `count` as a percentage of `total`.
+fn share(count: usize, total: usize) -> f64 {
+    // ---
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mainnet_routers() -> DexRegistry {
+        DexRegistry::from_config(&Default::default()).unwrap()
+    }
+
+    #[test]
+    fn generation_is_seeded_and_covers_decoded_selectors() {
+        let tokens = TokenRegistry::with_defaults();
+        let first = synthetic_transactions(200, SEED, &tokens, &mainnet_routers());
+        let again = synthetic_transactions(200, SEED, &tokens, &mainnet_routers());
+
+        assert_eq!(first, again);
+        for selector in [
+            [0xa9, 0x05, 0x9c, 0xbb],
+            [0x38, 0xed, 0x17, 0x39],
+            [0x41, 0x4b, 0xf3, 0x89],
+        ] {
+            assert!(first.iter().any(|tx| tx.input[..4] == selector));
+        }
+    }
+
+    #[tokio::test]
+    async fn run_reports_every_transaction() {
+        let report = run(100, MEVConfig::default(), &mainnet_routers()).await;
+
+        assert_eq!(report.latencies.len(), 100);
+        assert!(!report.opportunities.is_empty());
+        assert!(report.percentile(0.5) <= report.percentile(1.0));
+
+        let table = report.summary_table();
+        assert!(table.contains("100 synthetic txs"));
+        assert!(table.contains("p99"));
+    }
+}
@@ -0,0 +1,47 @@
+//! Build metadata embedded at compile time by `build.rs`.
+//!
+//! Lets operators tie logs and metrics to the exact binary that produced them:
+//! the startup banner, `--build-info`, and the `mev_build_info` Prometheus
+//! gauge all report the same values.
+
+/// Crate version from `Cargo.toml`.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Abbreviated git commit the binary was built from (`unknown` outside a checkout).
+pub const GIT_SHA: &str = env!("VORTEX_GIT_SHA");
+
+/// UTC build timestamp (RFC 3339).
+pub const BUILD_TIME: &str = env!("VORTEX_BUILD_TIME");
+
+/// `rustc --version` of the compiler that built the binary.
+pub const RUSTC_VERSION: &str = env!("VORTEX_RUSTC_VERSION");
+
+// ---
+
+/// Returns a one-line summary for the startup banner, e.g.
+/// `v0.2.0 (3f2a9c1d0b7e, built 2025-09-27T14:03:00Z)`.
+pub fn summary() -> String {
+    // ---
+    format!("v{VERSION} ({GIT_SHA}, built {BUILD_TIME})")
+}
+
+/// Returns the multi-line report printed by `--build-info`.
+pub fn report() -> String {
+    // ---
+    format!(
+        "mempool-vortex {VERSION}\n\
+         git sha:    {GIT_SHA}\n\
+         build time: {BUILD_TIME}\n\
+         rustc:      {RUSTC_VERSION}"
+    )
+}
+
+/// Returns the Prometheus `mev_build_info` gauge: a constant 1 labeled with
+/// the build metadata.
+pub fn prometheus_gauge() -> String {
+    // ---
+    format!(
+        "# TYPE mev_build_info gauge\n\
+         mev_build_info{{version=\"{VERSION}\",git_sha=\"{GIT_SHA}\",build_time=\"{BUILD_TIME}\",rustc=\"{RUSTC_VERSION}\"}} 1\n"
+    )
+}
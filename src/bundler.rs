@@ -4,27 +4,92 @@
 //! and their submission to block builders via Flashbots or other MEV relays.
 //! It manages transaction sequencing, gas pricing, and bundle optimization.
 
-use crate::searcher::{MEVOpportunity, Protocol, DEX};
-use ethers::types::{Address, Bytes, TransactionRequest, U256, U64};
+use crate::amm::BPS_DENOMINATOR;
+use crate::flashloan::{self, FlashLoanProvider};
+use crate::gas::{self, BaseFeeTracker};
+use crate::oracle::{self, PriceOracle};
+use crate::positions;
+use crate::relay_stats::RelayStats;
+use crate::searcher::{self, MEVOpportunity, Protocol, DEX};
+use crate::simulation;
+use crate::trace;
+use crate::types::{
+    DexRegistry, MEVConfig, MEVMetrics, PriorityFeeStrategy, TokenRegistry, VictimInclusion,
+};
+use crate::units;
+use crate::BroadcastWait;
+use ethers::abi::{self, Token};
+use ethers::providers::Middleware;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, BlockNumber, Bytes, TransactionRequest, TxHash, H256, U256, U64};
+use futures::stream::{FuturesUnordered, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
 use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Gas budgeted for a single ERC-20 `approve` transaction.
+const APPROVAL_GAS: u64 = 50_000;
+
+/// Gas budgeted for the trailing coinbase-payment transaction.
+const COINBASE_PAYMENT_GAS: u64 = 30_000;
+
+/// Gas of the plain ETH transfer paying the fee recipient its profit share.
+const FEE_TRANSFER_GAS: u64 = 21_000;
+
+/// Compound comptroller (mainnet), target of Compound liquidations.
+const COMPOUND_COMPTROLLER: &str = "0x3d9819210A31b4961b30EF54bE2aeD79B9c9Cd3B";
+
+/// Relay requests allowed in flight at once unless `--max-relay-concurrency` says otherwise.
+pub const DEFAULT_RELAY_CONCURRENCY: usize = 16;
+
+/// Time between blocks (one mainnet slot), used to estimate when a bundle's
+/// target block will be built.
+const BLOCK_TIME: Duration = Duration::from_secs(12);
+
+/// How often the chain head is checked while waiting for a sent bundle's target block.
+const INCLUSION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Block times past a target block's due time after which its inclusion is no
+/// longer awaited.
+const INCLUSION_WAIT_BLOCKS: u32 = 3;
+
+/// Longest a relay may take to answer a cancellation.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Searcher account receiving swap proceeds, granting router approvals, and
+/// signing bundle transactions.
+static SEARCHER_WALLET: OnceLock<LocalWallet> = OnceLock::new();
 
 // ---
 
 /// Represents a complete MEV bundle ready for submission.
+///
+/// Bundles compare equal when their contents do: `bundle_id` is fresh for every
+/// build and is ignored, so rebuilding the same opportunity yields an equal
+/// bundle with the same [`MEVBundle::content_hash`].
 #[derive(Debug, Clone)]
 pub struct MEVBundle {
     // ---
-    /// List of transactions in execution order
-    pub transactions: Vec<TransactionRequest>,
+    /// List of transactions in execution order, each tagged with its role
+    pub transactions: Vec<BundleTx>,
 
     /// Target block number for inclusion
     pub target_block: U64,
 
     /// Minimum timestamp for bundle validity
+    #[allow(dead_code)] // set once timestamp-bounded bundles are supported
     pub min_timestamp: Option<U256>,
 
-    /// Maximum timestamp for bundle validity  
+    /// Maximum timestamp for bundle validity (the swaps' router deadline)
     pub max_timestamp: Option<U256>,
 
     /// Bundle UUID for tracking
@@ -33,12 +98,219 @@ pub struct MEVBundle {
     /// Estimated total gas usage
     pub total_gas: U256,
 
-    /// Expected profit in ETH
+    /// Expected profit in ETH, net of `builder_payment` and `fee_payment`
     pub expected_profit: U256,
+
+    /// ETH paid to the block builder by the trailing coinbase-payment transaction
+    pub builder_payment: U256,
+
+    /// ETH transferred to the configured fee recipient as its profit share
+    pub fee_payment: U256,
+
+    /// Flash loan funding the bundle, if any
+    pub flash_loan: Option<FlashLoan>,
+}
+
+/// A flash loan taken and repaid within a bundle.
+///
+/// The borrowed funds only exist during the lender's callback, so the calls
+/// spending and repaying them are made by the executor from that callback,
+/// carried in the loan transaction's calldata, rather than sent as bundle
+/// transactions of their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlashLoan {
+    // ---
+    /// Provider the loan is taken from
+    pub provider: FlashLoanProvider,
+
+    /// Amount borrowed, in the token's units
+    pub principal: U256,
+
+    /// Amount the repay call returns to the lender
+    pub repayment: U256,
+
+    /// Calls the executor makes from the loan callback, in order, each
+    /// tagged with its role; only their target and calldata are used
+    pub callback: Vec<BundleTx>,
+}
+
+/// One transaction of a bundle.
+///
+/// Serializes untagged: our own transactions as request objects, third-party
+/// transactions as their raw signed RLP hex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum BundleTransaction {
+    // ---
+    /// Transaction built by the searcher, signed at submission
+    Searcher(Box<TransactionRequest>),
+
+    /// Someone else's already-signed transaction, included verbatim (e.g. a sandwich victim)
+    Raw(Bytes),
+}
+
+/// A bundle transaction and the part it plays in the strategy.
+///
+/// Serializes as its bare [`BundleTransaction`]: relays only see the order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleTx {
+    // ---
+    pub request: BundleTransaction,
+    pub role: TxRole,
+}
+
+/// The part a transaction plays in its bundle, which fixes where it may sit.
+///
+/// Roles are listed in execution order: a bundle's roles may repeat but never
+/// go back to an earlier one (see [`MEVBundle::check_sequence`]). Arbitrage
+/// legs are all [`TxRole::Swap`], in path order; trades sharing a stage
+/// (swaps, a frontrun, a liquidation) don't occur in the same bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxRole {
+    // ---
+    /// ERC-20 approval a later trade needs
+    Approve,
+
+    /// Flash loan funding the trade
+    FlashLoan,
+
+    /// Arbitrage leg
+    Swap,
+
+    /// Sandwich buy ahead of the victim
+    Frontrun,
+
+    /// Liquidation of an unhealthy position, made from a flash loan's callback
+    Liquidate,
+
+    /// The sandwiched third-party transaction
+    Victim,
+
+    /// Sandwich sell after the victim
+    Backrun,
+
+    /// Flash loan repayment, principal plus fee, made from the loan's callback
+    Repay,
+
+    /// Profit share sent to the fee recipient
+    FeeShare,
+
+    /// Coinbase payment to the block builder
+    Bribe,
+}
+
+/// Bundle-building settings drawn from the loaded configuration.
+#[derive(Debug, Clone)]
+pub struct BundleSettings {
+    // ---
+    /// Safety margin applied to `eth_estimateGas` results
+    pub gas_limit_multiplier: f64,
+
+    /// How bundle transactions tip the block builder
+    pub priority_fee_strategy: PriorityFeeStrategy,
+
+    /// Bump on bundle transactions' computed gas price, in basis points
+    pub price_bump_bps: u16,
+
+    /// Highest gas price bundle transactions bid, in gwei: the gas config's
+    /// ceiling, lowered to the strategy config's by [`BundleSettings::with_strategy`]
+    pub max_gas_price_gwei: u64,
+
+    /// Whether sandwich bundles carry the victim transaction
+    pub victim_inclusion: VictimInclusion,
+
+    /// Share of expected profit paid to the block builder (0.0-100.0; 0 disables)
+    pub builder_bribe_percent: f64,
+
+    /// Account receiving `fee_bps` of the profit left after the builder payment
+    pub fee_recipient: Option<Address>,
+
+    /// Profit share transferred to `fee_recipient`, in basis points
+    pub fee_bps: u16,
+
+    /// Seconds past the latest block's timestamp that router swaps stay valid
+    pub swap_deadline_secs: u64,
+
+    /// Required net profit above break-even, in basis points of bundle costs
+    pub profit_margin_bps: u16,
+
+    /// Blocks an opportunity may lag the chain head before it is discarded
+    pub max_staleness_blocks: u64,
+
+    /// Flash-loan providers liquidations may borrow from, in order of preference
+    pub flash_loan_providers: Vec<FlashLoanProvider>,
+
+    /// Router address of each DEX on the configured chain
+    pub routers: Arc<DexRegistry>,
+
+    /// Searcher's executor contract, paying builders and receiving flash loans
+    pub executor: Option<Address>,
+
+    /// Market prices, for valuing token-denominated costs in ETH
+    pub oracle: Arc<dyn PriceOracle>,
+
+    /// Known token metadata, for the decimals of token-denominated costs
+    pub token_registry: Arc<TokenRegistry>,
+
+    /// Bundles already sent this session, shared by every submission
+    pub sent_bundles: Arc<SentBundles>,
+
+    /// Largest bundle a relay is sent; bigger ones are split or rejected
+    pub limits: BundleLimits,
+
+    /// Decimals shown for ETH amounts in log messages
+    pub value_decimals: usize,
+
+    /// Session metrics, where each relay's answers are recorded
+    pub metrics: Arc<Mutex<MEVMetrics>>,
+
+    /// Try relays in order of learned score rather than configured priority
+    pub adaptive_relay_order: bool,
+
+    /// Trace each built bundle's transactions and log their gas use and reverts
+    pub trace_bundles: bool,
+
+    /// Simulate each built bundle with `eth_callBundle` and check the builder's take
+    pub simulate_bundles: bool,
+
+    /// Relay requests allowed in flight at once, shared by every submission
+    pub relay_permits: RelayPermits,
+}
+
+/// Size caps for a single bundle, from the relay configuration's
+/// `max_bundle_gas` and `max_bundle_txs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundleLimits {
+    // ---
+    /// Gas a single bundle may use
+    pub max_gas: u64,
+
+    /// Transactions a single bundle may carry
+    pub max_txs: usize,
+}
+
+/// Why a bundle can't be brought within its [`BundleLimits`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleLimitError {
+    // ---
+    /// The strategy must land atomically and has more transactions than one bundle may carry
+    TooManyTransactions {
+        strategy: &'static str,
+        txs: usize,
+        max_txs: usize,
+    },
+
+    /// The strategy must land atomically and needs more gas than one bundle
+    /// may use, or a single transaction does on its own
+    GasLimitExceeded {
+        strategy: &'static str,
+        gas: U256,
+        max_gas: u64,
+    },
 }
 
 /// Bundle submission result from MEV relays.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubmissionResult {
     // ---
     pub bundle_hash: String,
@@ -46,16 +318,89 @@ pub struct SubmissionResult {
     pub relay: String,
     pub block_number: Option<U64>,
     pub inclusion_probability: Option<f64>,
+
+    /// Gas-weighted average gas price of the bundle's own transactions, in wei
+    pub effective_gas_price: U256,
+
+    /// Estimated gas of the whole bundle
+    pub total_gas: U256,
+
+    /// ETH paid to the block builder by the coinbase-payment transaction
+    pub builder_payment: U256,
+
+    /// Gas the whole bundle used when simulated with `eth_callBundle`
+    /// (`--simulate-bundles`); unknown without simulation, or for split parts
+    pub gas_used: Option<U256>,
+
+    /// Hash of the bundle's first own transaction as signed, whose receipt
+    /// shows whether the bundle landed; unknown without a signing key
+    pub landing_tx: Option<TxHash>,
 }
 
 /// Status of bundle submission to relays.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SubmissionStatus {
     Submitted,
     Included,
     Failed,
     Expired,
     Reverted,
+    Cancelled,
+}
+
+/// Per-relay outcome of broadcasting one bundle to every relay at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiSubmissionResult {
+    // ---
+    /// Each relay's response in priority order: its result, or the rejection reason
+    pub per_relay: Vec<(String, Result<SubmissionResult, String>)>,
+
+    /// Accepted submission with the highest inclusion probability, if any
+    pub best: Option<SubmissionResult>,
+}
+
+/// How a built bundle is delivered to relays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmissionMode {
+    // ---
+    /// Build the bundle but never send it
+    Simulate,
+
+    /// Try relays in priority order, stopping at the first that accepts
+    Sequential,
+
+    /// Send to every relay concurrently and collect each relay's response
+    Broadcast {
+        /// Whether to report at the first acceptance or once every relay answered
+        wait: BroadcastWait,
+
+        /// How long each relay may take before it is recorded as timed out
+        relay_timeout: Duration,
+    },
+}
+
+/// What [`create_and_send_bundle`] returns, depending on the [`SubmissionMode`].
+#[derive(Debug, Clone)]
+pub enum SubmissionOutcome {
+    // ---
+    /// Simulated or sequential submission: the one relay that accepted
+    Single(SubmissionResult),
+
+    /// Broadcast submission: every relay's response
+    Multi(MultiSubmissionResult),
+
+    /// Discarded before bundle creation: the chain moved on since detection
+    Stale,
+
+    /// Not sent: a relay already accepted an identical bundle for the same block
+    Duplicate,
+
+    /// Not sent: the liquidated position was healthy again at the chain head
+    Healed,
+
+    /// Split to fit [`BundleLimits`]: each part's outcome, in target-block
+    /// order, up to the first part that failed to send
+    Split(Vec<SubmissionOutcome>),
 }
 
 /// Configuration for MEV relay endpoints.
@@ -64,9 +409,80 @@ pub struct RelayConfig {
     pub name: String,
     pub endpoint: String,
     pub signing_key: Option<String>,
+    /// Environment variable `signing_key` is read from
+    pub key_var: &'static str,
     pub enabled: bool,
 }
 
+/// Tracks ERC-20 allowances granted by the searcher account to DEX routers.
+///
+/// Allowances observed on-chain are cached so later bundles for the same
+/// token/router pair neither repeat the `allowance` RPC nor prepend a
+/// redundant `approve` transaction. An `approve` a bundle carries is not
+/// cached: the bundle may never land, so the allowance is only trusted once it
+/// is read back from the chain.
+#[derive(Debug, Default)]
+pub struct ApprovalManager {
+    // ---
+    /// Searcher account that holds the tokens being swapped
+    owner: Address,
+
+    /// Known allowances keyed by (token, spender)
+    allowances: Mutex<HashMap<(Address, Address), U256>>,
+}
+
+/// Bundles a relay already accepted, so an identical rebuild isn't built or
+/// sent twice.
+///
+/// Rebuilding an opportunity that re-surfaces (a re-announced swap, or a
+/// liquidation seen again with the next transaction) yields an equal bundle.
+/// Each accepted bundle is recorded under its opportunity's
+/// [`SentBundles::opportunity_key`], checked before building, and its
+/// [`MEVBundle::content_hash`], which also catches a different opportunity
+/// building the same bundle. Since the target block is part of both, entries
+/// for earlier blocks can never match again and are dropped.
+#[derive(Debug, Default)]
+pub struct SentBundles {
+    // ---
+    /// Target block of each accepted bundle, under both of its keys
+    sent: Mutex<HashMap<H256, U64>>,
+}
+
+/// Caps the relay requests in flight at once, across every bundle and relay.
+///
+/// Broadcasting many bundles to many relays opens a connection per pair,
+/// which can exhaust the client or trip relay rate limits. Every submission
+/// holds a permit while its request is out; when none is free it queues until
+/// one is, or until its target block is due, after which it fails since the
+/// bundle could no longer land. Clones share the same permits.
+#[derive(Debug, Clone)]
+pub struct RelayPermits {
+    // ---
+    /// Most relay requests in flight at once
+    limit: usize,
+
+    /// Permits not currently held by a submission
+    semaphore: Arc<Semaphore>,
+}
+
+/// Suppresses repeat bundles for the same trade within a window of blocks.
+///
+/// A large swap can be re-announced and re-analyzed while it sits in the
+/// mempool, producing identical opportunities and competing bundles. Each
+/// opportunity is keyed on its token pair and strategy. The first analysis to
+/// claim a key acts on it, and concurrent or later ones are suppressed until
+/// `window_blocks` blocks have passed. An attempt that ends without a relay
+/// accepting its bundle releases the claim, so it suppresses nothing.
+#[derive(Debug, Default)]
+pub struct OpportunityCooldown {
+    // ---
+    /// Blocks a key stays suppressed after being acted upon (0 disables)
+    window_blocks: u64,
+
+    /// Block at which each (token_a, token_b, strategy) key was last acted upon
+    last_acted: Mutex<HashMap<(Address, Address, &'static str), U64>>,
+}
+
 // ---
 
 /// Creates and submits MEV bundles based on detected opportunities.
@@ -76,52 +492,458 @@ pub struct RelayConfig {
 ///
 /// # Arguments
 /// * `opportunity` - The MEV opportunity to execute
-/// * `simulate` - Whether to simulate bundle creation without submission
+/// * `provider` - Chain client used for allowance lookups
+/// * `approvals` - Shared cache of router approvals granted by the searcher
+/// * `base_fees` - Shared base-fee tracker used to price bundle transactions
+/// * `settings` - Gas margin, sandwich victim handling, builder bribe, swap
+///   deadline, and staleness limit
+/// * `relays` - Relays to submit to, in priority order (see [`resolve_relays`])
+/// * `mode` - Whether to simulate, submit sequentially, or broadcast to all relays
 ///
 /// # Returns
-/// * `Ok(SubmissionResult)` if bundle was created and submitted successfully
-/// * `Err` if bundle creation or submission failed
-pub async fn create_and_send_bundle(
+/// * `Ok(SubmissionOutcome::Single)` if the bundle was simulated or a relay
+///   accepted it
+/// * `Ok(SubmissionOutcome::Multi)` once a broadcast completes (every relay
+///   answered or timed out, or the first accepted, per its [`BroadcastWait`]),
+///   even if all of them rejected it
+/// * `Ok(SubmissionOutcome::Stale)` if the opportunity was detected more than
+///   `max_staleness_blocks` blocks ago; nothing is built or sent
+/// * `Ok(SubmissionOutcome::Duplicate)` if a relay already accepted a bundle
+///   for the same opportunity and block, in which case nothing is built, or
+///   an identical bundle (see [`SentBundles`]), in which case it is built but
+///   not sent
+/// * `Ok(SubmissionOutcome::Healed)` if a liquidated position is no longer
+///   liquidatable at the chain head; nothing is built or sent
+/// * `Ok(SubmissionOutcome::Split)` if an arbitrage bundle exceeded
+///   `settings.limits` and was sent as parts targeting consecutive blocks
+/// * `Err` if bundle creation failed, `eth_callBundle` simulation (when
+///   `settings.simulate_bundles`) pays the builder less than intended, a
+///   strategy that must land atomically exceeds the limits
+///   ([`BundleLimitError`]), or no relay accepted a sequential submission (of
+///   the first part, when split)
+pub async fn create_and_send_bundle<M: Middleware>(
     opportunity: MEVOpportunity,
-    simulate: bool,
-) -> anyhow::Result<SubmissionResult> {
+    provider: &M,
+    approvals: &ApprovalManager,
+    base_fees: &BaseFeeTracker,
+    settings: BundleSettings,
+    relays: &[RelayConfig],
+    mode: SubmissionMode,
+) -> anyhow::Result<SubmissionOutcome> {
     // ---
 
-    info!(
-        "🎯 Creating MEV bundle for opportunity: {:?}",
-        std::mem::discriminant(&opportunity)
+    // Conditions seen at an older block may no longer hold; don't trade on them
+    let latest_block = base_fees.block_number();
+    let detected_block = opportunity.detected_block();
+    if latest_block > detected_block + settings.max_staleness_blocks {
+        warn!(
+            %opportunity,
+            "🥀 Discarding stale opportunity: detected at block {}, chain is at {}",
+            detected_block,
+            latest_block
+        );
+        return Ok(SubmissionOutcome::Stale);
+    }
+
+    info!(%opportunity, "🎯 Creating MEV bundle");
+
+    // A simulated bundle may route through a placeholder executor; a sent one can't
+    let settings = match (settings.executor, mode) {
+        (None, SubmissionMode::Simulate) => BundleSettings {
+            executor: Some(Address::zero()),
+            ..settings
+        },
+        _ => settings,
+    };
+
+    // Every bundle targets, and is priced for, the block after the latest observed one
+    let target_block = base_fees.block_number() + 1;
+    let gas_price = gas::bundle_gas_price(
+        &settings.priority_fee_strategy,
+        settings.price_bump_bps,
+        settings.max_gas_price_gwei,
+        base_fees,
+    )?;
+
+    // Routers revert swaps whose deadline has passed; anchor it to chain time
+    let latest_timestamp = base_fees.timestamp();
+    let deadline = latest_timestamp + settings.swap_deadline_secs;
+
+    // Arbitrage legs each leave the searcher holding a token, so they can land
+    // in consecutive blocks; sandwiches and flash-loan liquidations can't
+    let strategy = opportunity.strategy();
+    let splittable = matches!(
+        opportunity,
+        MEVOpportunity::Arbitrage { .. } | MEVOpportunity::MultiHopArbitrage { .. }
     );
 
+    // An opportunity a relay already took for this block would rebuild into
+    // the same bundle; don't spend the RPCs building it again
+    let sent_key = SentBundles::opportunity_key(&opportunity, target_block);
+    if settings.sent_bundles.was_sent(sent_key, target_block) {
+        info!(
+            %opportunity,
+            "♻️ A bundle for this opportunity was already accepted for block {}; not rebuilding",
+            target_block
+        );
+        return Ok(SubmissionOutcome::Duplicate);
+    }
+
+    // The borrower may have repaid or added collateral since detection
+    if let MEVOpportunity::Liquidation {
+        protocol,
+        position_owner,
+        ..
+    } = opportunity
+    {
+        if position_healed(provider, protocol, position_owner).await {
+            warn!(
+                "💊 Aborting liquidation: position {:?} is no longer liquidatable",
+                position_owner
+            );
+            return Ok(SubmissionOutcome::Healed);
+        }
+    }
+
     // Create bundle based on opportunity type
-    let bundle = match opportunity {
-        MEVOpportunity::Arbitrage { .. } => create_arbitrage_bundle(opportunity).await?,
-        MEVOpportunity::Sandwich { .. } => create_sandwich_bundle(opportunity).await?,
-        MEVOpportunity::Liquidation { .. } => create_liquidation_bundle(opportunity).await?,
+    let mut bundle = match opportunity {
+        MEVOpportunity::Arbitrage { .. } => {
+            create_arbitrage_bundle(
+                opportunity,
+                provider,
+                approvals,
+                &settings.routers,
+                target_block,
+                gas_price,
+                deadline,
+            )
+            .await?
+        }
+        MEVOpportunity::MultiHopArbitrage { .. } => {
+            create_multi_hop_bundle(
+                opportunity,
+                provider,
+                approvals,
+                &settings.routers,
+                target_block,
+                gas_price,
+                deadline,
+            )
+            .await?
+        }
+        MEVOpportunity::Sandwich { .. } => {
+            create_sandwich_bundle(
+                opportunity,
+                provider,
+                approvals,
+                &settings,
+                target_block,
+                gas_price,
+                deadline,
+            )
+            .await?
+        }
+        MEVOpportunity::Liquidation { .. } => {
+            create_liquidation_bundle(opportunity, &settings, target_block, gas_price)?
+        }
     };
 
+    // Pay the builder last, so the payment only executes if every trade succeeded
+    if settings.builder_bribe_percent > 0.0 {
+        append_coinbase_payment(
+            &mut bundle,
+            settings.builder_bribe_percent,
+            settings.executor,
+            gas_price,
+        )?;
+    }
+
+    // The fee share is taken from what the searcher realizes after the builder
+    if let Some(recipient) = settings.fee_recipient {
+        append_fee_transfer(&mut bundle, recipient, settings.fee_bps, gas_price);
+    }
+
+    // Our transactions execute back to back, so each needs the next nonce
+    assign_nonces(provider, approvals.owner, &mut bundle).await?;
+
+    // Replace the builders' fixed gas budgets with node estimates where possible
+    if let Some(total_gas) = estimate_bundle_gas(
+        provider,
+        approvals.owner,
+        &mut bundle.transactions,
+        settings.gas_limit_multiplier,
+    )
+    .await
+    {
+        bundle.total_gas = total_gas;
+    }
+
     info!(
-        "📦 Bundle created with {} transactions, estimated profit: {} ETH",
+        "📦 Bundle created with {} transactions ({} gas), estimated profit: {} ETH (builder payment: {} ETH, fee share: {} ETH)",
         bundle.transactions.len(),
-        ethers::utils::format_ether(bundle.expected_profit)
+        bundle.total_gas,
+        units::format_eth(bundle.expected_profit, settings.value_decimals),
+        units::format_eth(bundle.builder_payment, settings.value_decimals),
+        units::format_eth(bundle.fee_payment, settings.value_decimals)
     );
 
-    if simulate {
-        info!("🧪 Simulation mode: Bundle created but not submitted");
-        return Ok(SubmissionResult {
-            bundle_hash: "simulated".to_string(),
-            status: SubmissionStatus::Submitted,
-            relay: "simulation".to_string(),
-            block_number: Some(bundle.target_block),
-            inclusion_probability: Some(1.0),
-        });
+    if settings.trace_bundles {
+        trace::log_bundle_trace(provider, approvals.owner, &bundle).await;
+    }
+
+    validate_bundle(&bundle, latest_timestamp, settings.profit_margin_bps)?;
+
+    // The builder must actually receive what the bundle's economics assume
+    let mut simulated_gas = None;
+    if settings.simulate_bundles {
+        match simulation::simulate_bundle(provider, &bundle).await {
+            Ok(simulated) => {
+                simulation::verify_builder_payment(
+                    &simulated,
+                    &bundle,
+                    settings.profit_margin_bps,
+                )?;
+                simulated_gas = Some(simulated.total_gas_used);
+            }
+            Err(e) => warn!("🧪 Bundle {} not simulated ({})", bundle.bundle_id, e),
+        }
+    }
+    let parts = bundle.split_to_limits(settings.limits, strategy, splittable)?;
+
+    let sent_keys = [sent_key, bundle.content_hash()];
+    if settings.sent_bundles.was_sent(sent_keys[1], target_block) {
+        info!(
+            "♻️ Bundle {} is identical to one already sent for block {}; not sending",
+            bundle.bundle_id, bundle.target_block
+        );
+        return Ok(SubmissionOutcome::Duplicate);
+    }
+
+    let relays = if settings.adaptive_relay_order {
+        let stats = settings.metrics.lock().expect("metrics poisoned");
+        rank_relays(relays, &stats.relay_stats)
+    } else {
+        relays.to_vec()
+    };
+    let metrics = &settings.metrics;
+    let permits = &settings.relay_permits;
+
+    let outcome = if parts.len() == 1 {
+        let mut outcome = send_bundle(bundle, base_fees, &relays, mode, metrics, permits).await?;
+        if let Some(gas_used) = simulated_gas {
+            outcome.set_gas_used(gas_used);
+        }
+        outcome
+    } else {
+        info!(
+            "✂️ Bundle {} exceeds {} txs / {} gas; sending as {} bundles for blocks {} to {}",
+            bundle.bundle_id,
+            settings.limits.max_txs,
+            settings.limits.max_gas,
+            parts.len(),
+            bundle.target_block,
+            bundle.target_block + parts.len() - 1
+        );
+
+        // Later parts only make sense once the earlier ones are on their way
+        let count = parts.len();
+        let mut outcomes = Vec::with_capacity(count);
+        for (i, part) in parts.into_iter().enumerate() {
+            match send_bundle(part, base_fees, &relays, mode, metrics, permits).await {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(e) if outcomes.is_empty() => return Err(e),
+                Err(e) => {
+                    warn!(
+                        "❌ Split bundle part {} of {} not sent ({}); dropping the rest",
+                        i + 1,
+                        count,
+                        e
+                    );
+                    break;
+                }
+            }
+        }
+        SubmissionOutcome::Split(outcomes)
+    };
+
+    // Only a bundle a relay took can land; a rejected one may be rebuilt and retried
+    if !outcome.accepted().is_empty() {
+        settings.sent_bundles.record(&sent_keys, target_block);
+    }
+    Ok(outcome)
+}
+
+/// Sends a validated bundle according to `mode`, cancelling submissions whose
+/// target block was mined meanwhile. Relay answers are recorded in `metrics`,
+/// and each relay request holds one of `permits`.
+async fn send_bundle(
+    bundle: MEVBundle,
+    base_fees: &BaseFeeTracker,
+    relays: &[RelayConfig],
+    mode: SubmissionMode,
+    metrics: &Mutex<MEVMetrics>,
+    permits: &RelayPermits,
+) -> anyhow::Result<SubmissionOutcome> {
+    // ---
+
+    let bundle_id = bundle.bundle_id.clone();
+    let target_block = bundle.target_block;
+    let due = target_block_due(base_fees, target_block);
+
+    match mode {
+        SubmissionMode::Simulate => {
+            info!("🧪 Simulation mode: Bundle created but not submitted");
+            Ok(SubmissionOutcome::Single(SubmissionResult::submitted(
+                &bundle,
+                "simulation",
+                "simulated".to_string(),
+                1.0,
+            )))
+        }
+        SubmissionMode::Sequential => {
+            let mut result = submit_bundle_to_relays(bundle, relays, metrics, permits, due).await?;
+
+            if base_fees.block_number() >= target_block {
+                cancel_stale_submission(&bundle_id, &mut result, relays).await;
+            }
+
+            Ok(SubmissionOutcome::Single(result))
+        }
+        SubmissionMode::Broadcast {
+            wait,
+            relay_timeout,
+        } => {
+            let mut per_relay = broadcast_bundle_to_relays(
+                &bundle,
+                relays,
+                wait,
+                relay_timeout,
+                metrics,
+                permits,
+                due,
+            )
+            .await;
+
+            if base_fees.block_number() >= target_block {
+                for result in per_relay.iter_mut().filter_map(|(_, r)| r.as_mut().ok()) {
+                    cancel_stale_submission(&bundle_id, result, relays).await;
+                }
+            }
+
+            let multi = MultiSubmissionResult::new(per_relay);
+            multi.log_summary(&bundle_id);
+            Ok(SubmissionOutcome::Multi(multi))
+        }
+    }
+}
+
+/// Cancels a submission whose target block was mined while it was being sent.
+///
+/// Such a bundle can no longer land where intended, so it is cancelled rather
+/// than left pending; on success `result` is marked
+/// [`SubmissionStatus::Cancelled`].
+async fn cancel_stale_submission(
+    bundle_id: &str,
+    result: &mut SubmissionResult,
+    relays: &[RelayConfig],
+) {
+    // ---
+
+    if let Some(relay) = relays.iter().find(|relay| relay.name == result.relay) {
+        match cancel_bundle(bundle_id, relay).await {
+            Ok(()) => result.status = SubmissionStatus::Cancelled,
+            Err(e) => warn!("❌ Failed to cancel stale bundle {}: {}", bundle_id, e),
+        }
+    }
+}
+
+/// Cancels a previously submitted bundle by its UUID.
+///
+/// Only Flashbots supports cancellation by `replacementUuid`: the relay is sent
+/// `eth_cancelBundle`, signed with its key in an `X-Flashbots-Signature`
+/// header. Other relays return an error.
+///
+/// # Arguments
+/// * `bundle_id` - The bundle's UUID, as sent in `replacementUuid`
+/// * `relay` - The relay the bundle was submitted to
+///
+/// # Errors
+/// Returns an error if the relay does not support cancellation, its key is
+/// missing or malformed, or the relay doesn't accept the request within
+/// [`CANCEL_TIMEOUT`].
+pub async fn cancel_bundle(bundle_id: &str, relay: &RelayConfig) -> anyhow::Result<()> {
+    // ---
+
+    if relay.name != "flashbots" {
+        anyhow::bail!("Relay {} does not support bundle cancellation", relay.name);
+    }
+    let signer: LocalWallet = relay
+        .signing_key
+        .as_deref()
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("{} is not set", relay.key_var))?
+        .parse()
+        .map_err(|e| anyhow::anyhow!("{} is not a valid private key: {}", relay.key_var, e))?;
+
+    let body = cancel_bundle_body(bundle_id);
+    debug!("eth_cancelBundle request: {}", body);
+    let response = reqwest::Client::new()
+        .post(&relay.endpoint)
+        .timeout(CANCEL_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(
+            "X-Flashbots-Signature",
+            flashbots_signature_header(&signer, &body).await?,
+        )
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("eth_cancelBundle to {} failed: {}", relay.name, e))?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "{} answered eth_cancelBundle with {}",
+            relay.name,
+            response.status()
+        );
+    }
+
+    let reply: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| anyhow::anyhow!("Unreadable eth_cancelBundle reply: {}", e))?;
+    if let Some(error) = reply.get("error") {
+        anyhow::bail!("{} refused eth_cancelBundle: {}", relay.name, error);
     }
+    info!("🛑 Flashbots bundle {} cancelled", bundle_id);
+    Ok(())
+}
 
-    // Submit bundle to MEV relays
-    submit_bundle_to_relays(bundle).await
+/// Returns the JSON-RPC body cancelling the bundle sent with `bundle_id` as
+/// its `replacementUuid`.
+fn cancel_bundle_body(bundle_id: &str) -> String {
+    // ---
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_cancelBundle",
+        "params": [{ "replacementUuid": bundle_id }],
+    })
+    .to_string()
 }
 
 /// Creates a bundle for executing an arbitrage opportunity.
-async fn create_arbitrage_bundle(opportunity: MEVOpportunity) -> anyhow::Result<MEVBundle> {
+///
+/// Any router approvals the searcher still needs are prepended ahead of the swaps.
+async fn create_arbitrage_bundle<M: Middleware>(
+    opportunity: MEVOpportunity,
+    provider: &M,
+    approvals: &ApprovalManager,
+    routers: &DexRegistry,
+    target_block: U64,
+    gas_price: U256,
+    deadline: U256,
+) -> anyhow::Result<MEVBundle> {
     // ---
 
     if let MEVOpportunity::Arbitrage {
@@ -129,149 +951,494 @@ async fn create_arbitrage_bundle(opportunity: MEVOpportunity) -> anyhow::Result<
         token_b,
         buy_dex,
         sell_dex,
+        amount_in,
+        token_b_amount,
         net_profit_eth,
         ..
     } = opportunity
     {
-        let current_block = get_current_block_number().await?;
-        let target_block = current_block + 1;
-
-        let mut transactions = Vec::new();
+        // Approvals: each router must be allowed to pull the token it receives
+        let mut transactions = approvals
+            .required_approvals(
+                provider,
+                &[
+                    (token_a, routers.router(buy_dex)?, amount_in),
+                    (token_b, routers.router(sell_dex)?, token_b_amount),
+                ],
+                gas_price,
+            )
+            .await
+            .into_iter()
+            .map(|approval| BundleTx::new(TxRole::Approve, approval))
+            .collect::<Vec<_>>();
+        let approval_gas = U256::from(APPROVAL_GAS) * transactions.len();
 
         // Transaction 1: Buy tokens on cheaper DEX
         let buy_tx = create_dex_swap_transaction(
-            buy_dex,
-            token_a,
-            token_b,
-            calculate_optimal_swap_amount(&opportunity),
-            target_block,
+            routers, buy_dex, token_a, token_b, amount_in, gas_price, deadline,
         )?;
-        transactions.push(buy_tx);
+        transactions.push(BundleTx::new(TxRole::Swap, buy_tx));
 
         // Transaction 2: Sell tokens on more expensive DEX
         let sell_tx = create_dex_swap_transaction(
+            routers,
             sell_dex,
             token_b,
             token_a,
-            calculate_optimal_swap_amount(&opportunity),
-            target_block,
+            token_b_amount,
+            gas_price,
+            deadline,
         )?;
-        transactions.push(sell_tx);
+        transactions.push(BundleTx::new(TxRole::Swap, sell_tx));
 
         Ok(MEVBundle {
             transactions,
             target_block,
             min_timestamp: None,
-            max_timestamp: None,
+            max_timestamp: Some(deadline),
             bundle_id: generate_bundle_id(),
-            total_gas: U256::from(400_000), // Estimated gas for 2 swaps
+            total_gas: U256::from(400_000) + approval_gas, // Estimated gas for 2 swaps
             expected_profit: net_profit_eth,
+            builder_payment: U256::zero(),
+            fee_payment: U256::zero(),
+            flash_loan: None,
         })
     } else {
         anyhow::bail!("Invalid opportunity type for arbitrage bundle");
     }
 }
 
-/// Creates a bundle for executing a sandwich attack.
-async fn create_sandwich_bundle(opportunity: MEVOpportunity) -> anyhow::Result<MEVBundle> {
+/// Creates a bundle chaining the swaps of a multi-hop arbitrage cycle.
+///
+/// Each hop's router must be approved for the amount that hop sells; missing
+/// approvals are prepended ahead of the first swap.
+async fn create_multi_hop_bundle<M: Middleware>(
+    opportunity: MEVOpportunity,
+    provider: &M,
+    approvals: &ApprovalManager,
+    routers: &DexRegistry,
+    target_block: U64,
+    gas_price: U256,
+    deadline: U256,
+) -> anyhow::Result<MEVBundle> {
     // ---
 
-    if let MEVOpportunity::Sandwich {
-        _victim_tx_hash,
-        token_in,
-        token_out,
-        frontrun_amount,
-        backrun_amount,
-        estimated_profit_eth,
+    if let MEVOpportunity::MultiHopArbitrage {
+        path,
+        hop_amounts_in,
+        net_profit_eth,
         ..
     } = opportunity
     {
-        let current_block = get_current_block_number().await?;
-        let target_block = current_block + 1;
-
-        let mut transactions = Vec::new();
+        if path.len() != hop_amounts_in.len() {
+            anyhow::bail!("Multi-hop path and hop amounts differ in length");
+        }
 
-        // Transaction 1: Frontrun - Buy tokens before victim
-        let frontrun_tx =
-            create_frontrun_transaction(token_in, token_out, frontrun_amount, target_block)?;
-        transactions.push(frontrun_tx);
+        let mut requirements = Vec::with_capacity(path.len());
+        for ((dex, token_in, _), amount_in) in path.iter().zip(&hop_amounts_in) {
+            requirements.push((*token_in, routers.router(*dex)?, *amount_in));
+        }
 
-        // Transaction 2: Victim transaction (we don't control this)
-        // Note: In reality, victim tx is already in mempool
+        let mut transactions = approvals
+            .required_approvals(provider, &requirements, gas_price)
+            .await
+            .into_iter()
+            .map(|approval| BundleTx::new(TxRole::Approve, approval))
+            .collect::<Vec<_>>();
+        let approval_gas = U256::from(APPROVAL_GAS) * transactions.len();
 
-        // Transaction 3: Backrun - Sell tokens after victim
-        let backrun_tx =
-            create_backrun_transaction(token_out, token_in, backrun_amount, target_block)?;
-        transactions.push(backrun_tx);
+        // One swap per hop, each selling the previous hop's output
+        for ((dex, token_in, token_out), amount_in) in path.iter().zip(&hop_amounts_in) {
+            let swap_tx = create_dex_swap_transaction(
+                routers, *dex, *token_in, *token_out, *amount_in, gas_price, deadline,
+            )?;
+            transactions.push(BundleTx::new(TxRole::Swap, swap_tx));
+        }
 
         Ok(MEVBundle {
             transactions,
             target_block,
             min_timestamp: None,
-            max_timestamp: None,
+            max_timestamp: Some(deadline),
             bundle_id: generate_bundle_id(),
-            total_gas: U256::from(500_000), // Estimated gas for sandwich
-            expected_profit: estimated_profit_eth,
+            total_gas: U256::from(200_000) * path.len() + approval_gas, // Estimated gas per swap
+            expected_profit: net_profit_eth,
+            builder_payment: U256::zero(),
+            fee_payment: U256::zero(),
+            flash_loan: None,
+        })
+    } else {
+        anyhow::bail!("Invalid opportunity type for multi-hop bundle");
+    }
+}
+
+/// Creates a bundle for executing a sandwich attack.
+///
+/// Any router approvals the searcher still needs are prepended ahead of the frontrun.
+/// With [`VictimInclusion::Bundled`], the victim's raw signed transaction sits
+/// between frontrun and backrun so the three execute atomically; the bundle
+/// fails to build if the victim's raw transaction can't be obtained. Either way
+/// the backrun sells for at least `backrun_min_out`, so it can't execute at a
+/// loss against a pool the victim never moved.
+async fn create_sandwich_bundle<M: Middleware>(
+    opportunity: MEVOpportunity,
+    provider: &M,
+    approvals: &ApprovalManager,
+    settings: &BundleSettings,
+    target_block: U64,
+    gas_price: U256,
+    deadline: U256,
+) -> anyhow::Result<MEVBundle> {
+    // ---
+
+    if let MEVOpportunity::Sandwich {
+        victim_tx_hash,
+        token_in,
+        token_out,
+        frontrun_amount,
+        backrun_amount,
+        backrun_min_out,
+        estimated_profit_eth,
+        ..
+    } = opportunity
+    {
+        // Approvals: both legs of the sandwich trade through the Uniswap V2 router
+        let router = settings.routers.router(DEX::UniswapV2)?;
+        let mut transactions: Vec<BundleTx> = approvals
+            .required_approvals(
+                provider,
+                &[
+                    (token_in, router, frontrun_amount),
+                    (token_out, router, backrun_amount),
+                ],
+                gas_price,
+            )
+            .await
+            .into_iter()
+            .map(|approval| BundleTx::new(TxRole::Approve, approval))
+            .collect();
+        let approval_gas = U256::from(APPROVAL_GAS) * transactions.len();
+
+        // Transaction 1: Frontrun - Buy tokens before victim
+        let frontrun_tx = create_frontrun_transaction(
+            &settings.routers,
+            token_in,
+            token_out,
+            frontrun_amount,
+            gas_price,
+            deadline,
+        )?;
+        transactions.push(BundleTx::new(TxRole::Frontrun, frontrun_tx));
+
+        // Transaction 2: Victim transaction (we don't control this)
+        match settings.victim_inclusion {
+            VictimInclusion::Bundled => {
+                let raw_victim = fetch_raw_transaction(provider, victim_tx_hash).await?;
+                debug!("🥪 Bundling victim tx {:?}", victim_tx_hash);
+                transactions.push(BundleTx::new(
+                    TxRole::Victim,
+                    BundleTransaction::Raw(raw_victim),
+                ));
+            }
+            VictimInclusion::Mempool => {
+                debug!(
+                    "🥪 Sandwiching victim tx {:?} (assumed to land from mempool)",
+                    victim_tx_hash
+                );
+            }
+        }
+
+        // Transaction 3: Backrun - Sell tokens after victim, reverting (and
+        // with it the bundle) if the victim didn't move the price
+        let backrun_tx = create_backrun_transaction(
+            &settings.routers,
+            token_out,
+            token_in,
+            backrun_amount,
+            backrun_min_out,
+            gas_price,
+            deadline,
+        )?;
+        transactions.push(BundleTx::new(TxRole::Backrun, backrun_tx));
+
+        Ok(MEVBundle {
+            transactions,
+            target_block,
+            min_timestamp: None,
+            max_timestamp: Some(deadline),
+            bundle_id: generate_bundle_id(),
+            total_gas: U256::from(500_000) + approval_gas, // Estimated gas for sandwich
+            expected_profit: estimated_profit_eth,
+            builder_payment: U256::zero(),
+            fee_payment: U256::zero(),
+            flash_loan: None,
         })
     } else {
         anyhow::bail!("Invalid opportunity type for sandwich bundle");
     }
 }
 
+/// Appends a coinbase payment of `bribe_percent` of the bundle's expected
+/// profit, deducting it from `expected_profit`.
+///
+/// Builders rank bundles by what they pay; a direct `block.coinbase` transfer
+/// pays only if the bundle lands, unlike priority fees on reverted txs.
+///
+/// # Errors
+/// Returns an error if there is a payment to make but no `executor` to make it.
+fn append_coinbase_payment(
+    bundle: &mut MEVBundle,
+    bribe_percent: f64,
+    executor: Option<Address>,
+    gas_price: U256,
+) -> anyhow::Result<()> {
+    // ---
+
+    let bribe_bps = (bribe_percent * 100.0).round() as u64;
+    let payment = bundle.expected_profit * bribe_bps / BPS_DENOMINATOR;
+    if payment.is_zero() {
+        return Ok(());
+    }
+    let executor = executor.ok_or_else(no_executor)?;
+
+    debug!(
+        "💸 Paying builder {} ETH ({}% of expected profit)",
+        ethers::utils::format_ether(payment),
+        bribe_percent
+    );
+
+    bundle.transactions.push(BundleTx::new(
+        TxRole::Bribe,
+        create_coinbase_payment_transaction(payment, executor, gas_price),
+    ));
+    bundle.total_gas += U256::from(COINBASE_PAYMENT_GAS);
+    bundle.expected_profit -= payment;
+    bundle.builder_payment = payment;
+    Ok(())
+}
+
+/// Transfers `fee_bps` of the bundle's expected profit to `recipient`,
+/// deducting it from `expected_profit`.
+///
+/// The transfer goes ahead of any coinbase payment, which stays last so the
+/// builder is only paid once everything else has succeeded.
+fn append_fee_transfer(bundle: &mut MEVBundle, recipient: Address, fee_bps: u16, gas_price: U256) {
+    // ---
+
+    let fee = bundle.expected_profit - searcher::after_fee_share(bundle.expected_profit, fee_bps);
+    if fee.is_zero() {
+        return;
+    }
+
+    debug!(
+        "🤝 Sharing {} ETH with {:?} ({} bps of expected profit)",
+        ethers::utils::format_ether(fee),
+        recipient,
+        fee_bps
+    );
+
+    let transfer = TransactionRequest {
+        to: Some(recipient.into()),
+        value: Some(fee),
+        gas: Some(U256::from(FEE_TRANSFER_GAS)),
+        gas_price: Some(gas_price),
+        ..Default::default()
+    };
+    let at = bundle
+        .transactions
+        .iter()
+        .position(|tx| tx.role == TxRole::Bribe)
+        .unwrap_or(bundle.transactions.len());
+    bundle
+        .transactions
+        .insert(at, BundleTx::new(TxRole::FeeShare, transfer));
+    bundle.total_gas += U256::from(FEE_TRANSFER_GAS);
+    bundle.expected_profit -= fee;
+    bundle.fee_payment = fee;
+}
+
+/// Numbers the bundle's own transactions with consecutive nonces, in bundle
+/// order, starting from `owner`'s pending transaction count.
+///
+/// # Errors
+/// Returns an error if the transaction count can't be read.
+async fn assign_nonces<M: Middleware>(
+    provider: &M,
+    owner: Address,
+    bundle: &mut MEVBundle,
+) -> anyhow::Result<()> {
+    // ---
+    let next = provider
+        .get_transaction_count(owner, Some(BlockNumber::Pending.into()))
+        .await
+        .map_err(|e| anyhow::anyhow!("Cannot read the searcher's nonce: {}", e))?;
+    let requests = bundle
+        .transactions
+        .iter_mut()
+        .filter_map(|tx| tx.request.as_request_mut());
+    for (offset, tx) in (0u64..).zip(requests) {
+        tx.nonce = Some(next + offset);
+    }
+    Ok(())
+}
+
+/// Returns the raw signed RLP of a pending transaction.
+///
+/// Uses `eth_getRawTransactionByHash` where the node supports it, otherwise
+/// re-encodes the transaction returned by `eth_getTransactionByHash`.
+///
+/// # Errors
+/// Returns an error if the node no longer knows the transaction.
+async fn fetch_raw_transaction<M: Middleware>(
+    provider: &M,
+    tx_hash: TxHash,
+) -> anyhow::Result<Bytes> {
+    // ---
+
+    let raw: Result<Option<Bytes>, _> = provider
+        .provider()
+        .request("eth_getRawTransactionByHash", [tx_hash])
+        .await;
+    match raw {
+        Ok(Some(raw)) if !raw.is_empty() => return Ok(raw),
+        Ok(_) => {}
+        Err(e) => debug!(
+            "eth_getRawTransactionByHash failed for {:?}: {}",
+            tx_hash, e
+        ),
+    }
+
+    let tx = provider
+        .get_transaction(tx_hash)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to fetch victim tx {:?}: {}", tx_hash, e))?
+        .ok_or_else(|| anyhow::anyhow!("Victim tx {:?} is no longer pending", tx_hash))?;
+    Ok(tx.rlp())
+}
+
+/// Returns whether `owner`'s position on `protocol` is no longer liquidatable
+/// at the chain head.
+///
+/// A position that can't be read is assumed unchanged: a liquidation of a
+/// healthy position reverts, and relays don't land reverting bundles.
+async fn position_healed<M: Middleware>(provider: &M, protocol: Protocol, owner: Address) -> bool {
+    // ---
+    match positions::fetch_health_factor(provider, protocol, owner, None).await {
+        Ok(Some(health_factor)) => health_factor >= positions::LIQUIDATABLE_BELOW,
+        Ok(None) => false,
+        Err(e) => {
+            warn!("⚠️ Could not re-check position {:?}: {}", owner, e);
+            false
+        }
+    }
+}
+
 /// Creates a bundle for executing a liquidation.
-async fn create_liquidation_bundle(opportunity: MEVOpportunity) -> anyhow::Result<MEVBundle> {
+///
+/// The repayment is borrowed from the cheapest of `settings.flash_loan_providers`
+/// that lends the debt token. The liquidation and the loan's repayment run in
+/// the lender's callback, so the bundle is the single loan transaction, and
+/// the provider's fee, valued in ETH via `settings.oracle`, comes out of the
+/// expected profit.
+///
+/// # Errors
+/// Returns an error if no configured provider lends the debt token, the fee
+/// can't be valued in ETH, or `settings` has no executor to borrow into.
+fn create_liquidation_bundle(
+    opportunity: MEVOpportunity,
+    settings: &BundleSettings,
+    target_block: U64,
+    gas_price: U256,
+) -> anyhow::Result<MEVBundle> {
     if let MEVOpportunity::Liquidation {
         protocol,
         position_owner,
         collateral_token,
         debt_token,
-        debt_amount,
+        repay_amount,
         liquidation_bonus_eth,
         ..
     } = opportunity
     {
-        let current_block = get_current_block_number().await?;
-        let target_block = current_block + 1;
+        // Everything is sized to the close-factor-capped repayment, not the full debt
+        let provider =
+            flashloan::cheapest_provider(&settings.flash_loan_providers, debt_token, repay_amount)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("No configured flash loan provider lends {:?}", debt_token)
+                })?;
+        let mut flash_loan = FlashLoan {
+            provider,
+            principal: repay_amount,
+            repayment: provider.repayment(repay_amount),
+            callback: Vec::new(),
+        };
 
-        let mut transactions = Vec::new();
-
-        // Transaction 1: Flash loan to get liquidation capital
-        let flash_loan_tx = create_flash_loan_transaction(debt_token, debt_amount, target_block)?;
-        transactions.push(flash_loan_tx);
+        // The fee is owed in the debt token, while the bonus is in ETH
+        let flash_loan_fee = flash_loan.repayment - flash_loan.principal;
+        let flash_loan_fee_eth = oracle::token_value_wei(
+            settings.oracle.as_ref(),
+            &settings.token_registry,
+            debt_token,
+            flash_loan_fee,
+        )
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Cannot value the {} flash loan fee in {:?} as ETH",
+                provider,
+                debt_token
+            )
+        })?;
+        info!(
+            "🏦 Borrowing liquidation capital from {} (fee: {} ETH)",
+            provider,
+            units::format_eth(flash_loan_fee_eth, settings.value_decimals)
+        );
 
-        // Transaction 2: Liquidate the position
+        // From the callback: liquidate the position, then repay principal + fee
         let liquidation_tx = create_liquidation_transaction(
             protocol,
             position_owner,
             collateral_token,
             debt_token,
-            debt_amount,
-            target_block,
+            repay_amount,
+            gas_price,
         )?;
-        transactions.push(liquidation_tx);
+        let repay_tx = create_flash_loan_repay_transaction(&flash_loan, debt_token, gas_price)?;
+        flash_loan.callback = vec![
+            BundleTx::new(TxRole::Liquidate, liquidation_tx),
+            BundleTx::new(TxRole::Repay, repay_tx),
+        ];
 
-        // Transaction 3: Repay flash loan + profit
-        let repay_tx = create_flash_loan_repay_transaction(debt_token, debt_amount, target_block)?;
-        transactions.push(repay_tx);
+        let executor = settings.executor.ok_or_else(no_executor)?;
+        let flash_loan_tx =
+            create_flash_loan_transaction(&flash_loan, debt_token, executor, gas_price)?;
 
         Ok(MEVBundle {
-            transactions,
+            transactions: vec![BundleTx::new(TxRole::FlashLoan, flash_loan_tx)],
             target_block,
             min_timestamp: None,
             max_timestamp: None,
             bundle_id: generate_bundle_id(),
             total_gas: U256::from(600_000), // Estimated gas for liquidation
-            expected_profit: liquidation_bonus_eth,
+            expected_profit: liquidation_bonus_eth.saturating_sub(flash_loan_fee_eth),
+            builder_payment: U256::zero(),
+            fee_payment: U256::zero(),
+            flash_loan: Some(flash_loan),
         })
     } else {
         anyhow::bail!("Invalid opportunity type for liquidation bundle");
     }
 }
 
-/// Submits the bundle to configured MEV relays.
-async fn submit_bundle_to_relays(bundle: MEVBundle) -> anyhow::Result<SubmissionResult> {
-    let relays = get_relay_configs();
-
+/// Submits the bundle to the given MEV relays, trying each in priority order.
+/// A relay request waiting on `permits` gives up at `due`.
+async fn submit_bundle_to_relays(
+    bundle: MEVBundle,
+    relays: &[RelayConfig],
+    metrics: &Mutex<MEVMetrics>,
+    permits: &RelayPermits,
+    due: Instant,
+) -> anyhow::Result<SubmissionResult> {
     for relay in relays {
         if !relay.enabled {
             continue;
@@ -282,7 +1449,9 @@ async fn submit_bundle_to_relays(bundle: MEVBundle) -> anyhow::Result<Submission
             bundle.bundle_id, relay.name
         );
 
-        match submit_to_relay(&bundle, &relay).await {
+        let response = submit_to_relay(&bundle, relay, permits, due).await;
+        record_relay_answer(metrics, &relay.name, response.as_ref().ok());
+        match response {
             Ok(result) => {
                 info!(
                     "✅ Bundle submitted successfully to {}: {:?}",
@@ -300,11 +1469,241 @@ async fn submit_bundle_to_relays(bundle: MEVBundle) -> anyhow::Result<Submission
     anyhow::bail!("Failed to submit bundle to any relay");
 }
 
-/// Submits bundle to a specific MEV relay.
+/// Submits the bundle to every enabled relay concurrently.
+///
+/// Unlike [`submit_bundle_to_relays`], a rejection does not stop the others:
+/// responses are returned in priority order, with errors reduced to their
+/// messages. See [`broadcast_with`] for timeouts and `wait`; a relay request
+/// waiting on `permits` gives up at `due`.
+async fn broadcast_bundle_to_relays(
+    bundle: &MEVBundle,
+    relays: &[RelayConfig],
+    wait: BroadcastWait,
+    relay_timeout: Duration,
+    metrics: &Mutex<MEVMetrics>,
+    permits: &RelayPermits,
+    due: Instant,
+) -> Vec<(String, Result<SubmissionResult, String>)> {
+    // ---
+    broadcast_with(
+        bundle,
+        relays,
+        wait,
+        relay_timeout,
+        metrics,
+        |bundle, relay| {
+            let permits = permits.clone();
+            async move { submit_to_relay(&bundle, &relay, &permits, due).await }
+        },
+    )
+    .await
+}
+
+/// Broadcasts the bundle through `submit`, one task per enabled relay.
+///
+/// A relay that doesn't answer within `relay_timeout` is recorded as an error.
+/// With [`BroadcastWait::First`] this returns at the first acceptance; relays
+/// still pending keep submitting in the background and are reported as not
+/// yet answered. Answers (timeouts included) are recorded in `metrics`.
+async fn broadcast_with<F, Fut>(
+    bundle: &MEVBundle,
+    relays: &[RelayConfig],
+    wait: BroadcastWait,
+    relay_timeout: Duration,
+    metrics: &Mutex<MEVMetrics>,
+    submit: F,
+) -> Vec<(String, Result<SubmissionResult, String>)>
+where
+    F: Fn(Arc<MEVBundle>, RelayConfig) -> Fut,
+    Fut: Future<Output = anyhow::Result<SubmissionResult>> + Send + 'static,
+{
+    // ---
+
+    let enabled: Vec<&RelayConfig> = relays.iter().filter(|relay| relay.enabled).collect();
+    info!(
+        "📡 Broadcasting bundle {} to {} relays",
+        bundle.bundle_id,
+        enabled.len()
+    );
+
+    // Spawned, so submissions outlive an early return instead of being cancelled
+    let bundle = Arc::new(bundle.clone());
+    let mut pending: FuturesUnordered<_> = enabled
+        .iter()
+        .enumerate()
+        .map(|(index, relay)| {
+            let task = tokio::spawn(tokio::time::timeout(
+                relay_timeout,
+                submit(bundle.clone(), (*relay).clone()),
+            ));
+            async move { (index, task.await) }
+        })
+        .collect();
+
+    let mut responses: Vec<Option<Result<SubmissionResult, String>>> = vec![None; enabled.len()];
+    while let Some((index, joined)) = pending.next().await {
+        let response = match joined {
+            Ok(Ok(response)) => response.map_err(|e| e.to_string()),
+            Ok(Err(_)) => Err(format!("timed out after {:?}", relay_timeout)),
+            Err(e) => Err(format!("submission task failed: {}", e)),
+        };
+        record_relay_answer(metrics, &enabled[index].name, response.as_ref().ok());
+        let accepted = response.is_ok();
+        responses[index] = Some(response);
+
+        if accepted && wait == BroadcastWait::First {
+            break;
+        }
+    }
+
+    enabled
+        .iter()
+        .zip(responses)
+        .map(|(relay, response)| {
+            let response = response.unwrap_or_else(|| {
+                Err("no response yet (returned at first acceptance)".to_string())
+            });
+            (relay.name.clone(), response)
+        })
+        .collect()
+}
+
+/// Records a relay's answer to a submission: whether it accepted the bundle,
+/// and whether the bundle landed when the answer reports its fate.
+fn record_relay_answer(
+    metrics: &Mutex<MEVMetrics>,
+    relay: &str,
+    accepted: Option<&SubmissionResult>,
+) {
+    // ---
+
+    let mut metrics = metrics.lock().expect("metrics poisoned");
+    metrics.record_relay_response(relay, accepted.is_some());
+    let included = match accepted.map(|result| &result.status) {
+        Some(SubmissionStatus::Included) => Some(true),
+        Some(SubmissionStatus::Failed | SubmissionStatus::Expired | SubmissionStatus::Reverted) => {
+            Some(false)
+        }
+        _ => None,
+    };
+    if let Some(included) = included {
+        metrics.record_relay_inclusion(relay, included);
+    }
+}
+
+/// Waits for the target block of every submission `outcome` accepted, then
+/// records in `metrics` whether the bundle landed there, judged by the
+/// receipt of its first own transaction: included if that transaction
+/// succeeded in the target block, not included otherwise.
+///
+/// A receipt doesn't say which relay's builder landed a broadcast bundle, so
+/// every relay that accepted it is credited. Submissions without a
+/// [`SubmissionResult::landing_tx`] (no signing key) aren't tracked, nor are
+/// target blocks the chain hasn't reached [`INCLUSION_WAIT_BLOCKS`] block
+/// times after they were due, or whose receipt can't be read.
+pub async fn track_inclusion<M: Middleware>(
+    provider: &M,
+    base_fees: &BaseFeeTracker,
+    outcome: SubmissionOutcome,
+    metrics: &Mutex<MEVMetrics>,
+) {
+    // ---
+
+    for result in outcome.accepted() {
+        let (Some(tx_hash), Some(target_block)) = (result.landing_tx, result.block_number) else {
+            continue;
+        };
+
+        let give_up =
+            target_block_due(base_fees, target_block) + BLOCK_TIME * INCLUSION_WAIT_BLOCKS;
+        while base_fees.block_number() < target_block {
+            if Instant::now() >= give_up {
+                debug!(
+                    "⏳ Block {} not seen; inclusion of {} not recorded",
+                    target_block, result.bundle_hash
+                );
+                return;
+            }
+            tokio::time::sleep(INCLUSION_POLL_INTERVAL).await;
+        }
+
+        let included = match provider.get_transaction_receipt(tx_hash).await {
+            Ok(receipt) => receipt.is_some_and(|receipt| {
+                receipt.block_number == Some(target_block) && receipt.status == Some(U64::one())
+            }),
+            Err(e) => {
+                warn!("Cannot read the receipt of bundle tx {:?}: {}", tx_hash, e);
+                continue;
+            }
+        };
+        info!(
+            "{} Bundle {} via {} {} block {}",
+            if included { "🏁" } else { "🫥" },
+            result.bundle_hash,
+            result.relay,
+            if included { "landed in" } else { "missed" },
+            target_block
+        );
+        metrics
+            .lock()
+            .expect("metrics poisoned")
+            .record_relay_inclusion(&result.relay, included);
+    }
+}
+
+/// Returns when `target_block` is expected to be built: one block time per
+/// block past the latest observed one, counted from that block's timestamp.
+fn target_block_due(base_fees: &BaseFeeTracker, target_block: U64) -> Instant {
+    // ---
+    let blocks_ahead = target_block
+        .saturating_sub(base_fees.block_number())
+        .as_u32();
+    let due_secs = base_fees.timestamp().low_u64() + BLOCK_TIME.as_secs() * u64::from(blocks_ahead);
+    let now_secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Instant::now() + Duration::from_secs(due_secs.saturating_sub(now_secs))
+}
+
+/// Orders `relays` by learned score, best first, for `--adaptive-relay-order`.
+///
+/// Relays without stats yet go first, so each gets measured; ties keep the
+/// configured priority.
+fn rank_relays(relays: &[RelayConfig], stats: &BTreeMap<String, RelayStats>) -> Vec<RelayConfig> {
+    // ---
+    let score = |relay: &RelayConfig| {
+        stats
+            .get(&relay.name)
+            .map_or(f64::INFINITY, RelayStats::score)
+    };
+    let mut ranked = relays.to_vec();
+    ranked.sort_by(|a, b| score(b).total_cmp(&score(a)));
+    debug!(
+        "📡 Relay order by learned score: {}",
+        ranked
+            .iter()
+            .map(|relay| relay.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    ranked
+}
+
+/// Submits bundle to a specific MEV relay, holding one of `permits` for the
+/// duration of the request.
+///
+/// # Errors
+/// Returns an error if no permit frees up before `due`, or the relay rejects
+/// the bundle.
 async fn submit_to_relay(
     bundle: &MEVBundle,
     relay: &RelayConfig,
+    permits: &RelayPermits,
+    due: Instant,
 ) -> anyhow::Result<SubmissionResult> {
+    // ---
+    let _permit = permits.acquire(&relay.name, due).await?;
     match relay.name.as_str() {
         "flashbots" => submit_to_flashbots(bundle, relay).await,
         "bloXroute" => submit_to_bloxroute(bundle, relay).await,
@@ -321,21 +1720,23 @@ async fn submit_to_flashbots(
     debug!("Preparing Flashbots bundle submission...");
 
     // In a real implementation, this would:
-    // 1. Sign bundle with private key
-    // 2. Create Flashbots bundle format
-    // 3. Submit via eth_sendBundle JSON-RPC
-    // 4. Handle response and track inclusion
+    // 1. Submit via eth_sendBundle JSON-RPC
+    // 2. Handle response and track inclusion
+
+    // The bundle ID doubles as replacementUuid, so resubmissions replace rather than duplicate
+    let mut params = signed_bundle_params(bundle)?;
+    params["replacementUuid"] = bundle.bundle_id.clone().into();
+    debug!("eth_sendBundle params: {}", params);
 
     // Mock submission for demonstration
     info!("🔥 Flashbots bundle submitted (simulated)");
 
-    Ok(SubmissionResult {
-        bundle_hash: format!("fb_{}", bundle.bundle_id),
-        status: SubmissionStatus::Submitted,
-        relay: "flashbots".to_string(),
-        block_number: Some(bundle.target_block),
-        inclusion_probability: Some(0.85),
-    })
+    Ok(SubmissionResult::submitted(
+        bundle,
+        "flashbots",
+        format!("fb_{}", bundle.bundle_id),
+        0.85,
+    ))
 }
 
 /// Submits bundle to bloXroute relay.
@@ -345,16 +1746,18 @@ async fn submit_to_bloxroute(
 ) -> anyhow::Result<SubmissionResult> {
     debug!("Preparing bloXroute bundle submission...");
 
+    let params = signed_bundle_params(bundle)?;
+    debug!("blxr_submit_bundle params: {}", params);
+
     // Mock submission
     info!("🌐 bloXroute bundle submitted (simulated)");
 
-    Ok(SubmissionResult {
-        bundle_hash: format!("bx_{}", bundle.bundle_id),
-        status: SubmissionStatus::Submitted,
-        relay: "bloXroute".to_string(),
-        block_number: Some(bundle.target_block),
-        inclusion_probability: Some(0.75),
-    })
+    Ok(SubmissionResult::submitted(
+        bundle,
+        "bloXroute",
+        format!("bx_{}", bundle.bundle_id),
+        0.75,
+    ))
 }
 
 /// Submits bundle to Eden relay.
@@ -364,52 +1767,43 @@ async fn submit_to_eden(
 ) -> anyhow::Result<SubmissionResult> {
     debug!("Preparing Eden bundle submission...");
 
+    // Eden takes Flashbots-format eth_sendBundle params
+    let params = signed_bundle_params(bundle)?;
+    debug!("eth_sendBundle params: {}", params);
+
     // Mock submission
     info!("🌿 Eden bundle submitted (simulated)");
 
-    Ok(SubmissionResult {
-        bundle_hash: format!("eden_{}", bundle.bundle_id),
-        status: SubmissionStatus::Submitted,
-        relay: "eden".to_string(),
-        block_number: Some(bundle.target_block),
-        inclusion_probability: Some(0.70),
-    })
+    Ok(SubmissionResult::submitted(
+        bundle,
+        "eden",
+        format!("eden_{}", bundle.bundle_id),
+        0.70,
+    ))
 }
 
 // ---
 // Transaction creation helper functions
 // ---
 
-/// Creates a DEX swap transaction for arbitrage.
+/// Creates a DEX swap transaction for arbitrage, valid until the `deadline` timestamp.
 fn create_dex_swap_transaction(
+    routers: &DexRegistry,
     dex: DEX,
     token_in: Address,
     token_out: Address,
     amount: U256,
-    target_block: U64,
+    gas_price: U256,
+    deadline: U256,
 ) -> anyhow::Result<TransactionRequest> {
-    let (to_address, call_data) = match dex {
+    let to_address = routers.router(dex)?;
+    let call_data = match dex {
+        // amountOutMin is left at zero until slippage bounds are computed
         DEX::UniswapV2 => {
-            let router = Address::from_slice(
-                &hex::decode("7a250d5630B4cF539739dF2C5dAcb4c659F2488D").unwrap(),
-            );
-            let data = encode_uniswap_v2_swap(token_in, token_out, amount)?;
-            (router, data)
-        }
-        DEX::UniswapV3 => {
-            let router = Address::from_slice(
-                &hex::decode("E592427A0AEce92De3Edee1F18E0157C05861564").unwrap(),
-            );
-            let data = encode_uniswap_v3_swap(token_in, token_out, amount)?;
-            (router, data)
-        }
-        DEX::SushiSwap => {
-            let router = Address::from_slice(
-                &hex::decode("d9e1cE17f2641f24aE83637ab66a2cca9C378B9F").unwrap(),
-            );
-            let data = encode_sushiswap_swap(token_in, token_out, amount)?;
-            (router, data)
+            encode_uniswap_v2_swap(token_in, token_out, amount, U256::zero(), deadline)?
         }
+        DEX::UniswapV3 => encode_uniswap_v3_swap(token_in, token_out, amount, deadline)?,
+        DEX::SushiSwap => encode_sushiswap_swap(token_in, token_out, amount, deadline)?,
         _ => anyhow::bail!("Unsupported DEX: {:?}", dex),
     };
 
@@ -417,7 +1811,7 @@ fn create_dex_swap_transaction(
         to: Some(to_address.into()),
         data: Some(call_data),
         gas: Some(U256::from(200_000)),
-        gas_price: Some(calculate_optimal_gas_price(target_block)),
+        gas_price: Some(gas_price),
         value: if token_in == Address::zero() {
             Some(amount)
         } else {
@@ -427,44 +1821,110 @@ fn create_dex_swap_transaction(
     })
 }
 
+/// Creates an ERC-20 `approve(spender, amount)` transaction on `token`.
+fn create_approval_transaction(
+    token: Address,
+    spender: Address,
+    amount: U256,
+    gas_price: U256,
+) -> TransactionRequest {
+    // ---
+    TransactionRequest {
+        to: Some(token.into()),
+        data: Some(encode_erc20_approve(spender, amount)),
+        gas: Some(U256::from(APPROVAL_GAS)),
+        gas_price: Some(gas_price),
+        ..Default::default()
+    }
+}
+
 /// Creates a frontrun transaction for sandwich attacks.
 fn create_frontrun_transaction(
+    routers: &DexRegistry,
     token_in: Address,
     token_out: Address,
     amount: U256,
-    target_block: U64,
+    gas_price: U256,
+    deadline: U256,
 ) -> anyhow::Result<TransactionRequest> {
     // Use highest priority DEX for frontrunning
-    create_dex_swap_transaction(DEX::UniswapV2, token_in, token_out, amount, target_block)
+    create_dex_swap_transaction(
+        routers,
+        DEX::UniswapV2,
+        token_in,
+        token_out,
+        amount,
+        gas_price,
+        deadline,
+    )
 }
 
 /// Creates a backrun transaction for sandwich attacks.
+///
+/// The swap reverts unless it returns at least `amount_out_min`, so a backrun
+/// whose victim never executed can't sell into the unmoved pool at a loss.
 fn create_backrun_transaction(
+    routers: &DexRegistry,
     token_in: Address,
     token_out: Address,
     amount: U256,
-    target_block: U64,
+    amount_out_min: U256,
+    gas_price: U256,
+    deadline: U256,
 ) -> anyhow::Result<TransactionRequest> {
     // Use same DEX as frontrun for consistency
-    create_dex_swap_transaction(DEX::UniswapV2, token_in, token_out, amount, target_block)
+    let to_address = routers.router(DEX::UniswapV2)?;
+    let call_data = encode_uniswap_v2_swap(token_in, token_out, amount, amount_out_min, deadline)?;
+
+    Ok(TransactionRequest {
+        to: Some(to_address.into()),
+        data: Some(call_data),
+        gas: Some(U256::from(200_000)),
+        gas_price: Some(gas_price),
+        ..Default::default()
+    })
 }
 
-/// Creates a flash loan transaction for liquidations.
+/// Creates the transaction borrowing `loan.principal` of `token` into the
+/// `executor` contract, whose callback makes `loan.callback`'s calls.
+///
+/// The gas budget covers the loan itself plus every callback call's budget.
 fn create_flash_loan_transaction(
+    loan: &FlashLoan,
     token: Address,
-    amount: U256,
-    target_block: U64,
+    executor: Address,
+    gas_price: U256,
 ) -> anyhow::Result<TransactionRequest> {
-    // Aave flash loan contract
-    let aave_pool =
-        Address::from_slice(&hex::decode("7d2768dE32b0b80b7a3454c06BdAc94A69DDc7A9").unwrap());
-    let call_data = encode_aave_flash_loan(token, amount)?;
+    // ---
+    let calls: Vec<&TransactionRequest> = loan
+        .callback
+        .iter()
+        .filter_map(|tx| tx.request.as_request())
+        .collect();
+    let payload = flashloan::encode_callback(
+        &calls
+            .iter()
+            .map(|call| {
+                let to = call.to.as_ref().and_then(|to| to.as_address()).copied();
+                (
+                    to.unwrap_or_default(),
+                    call.data.clone().unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+    let (lender, call_data) = loan
+        .provider
+        .loan_call(token, loan.principal, executor, payload)?;
+    let gas = calls.iter().fold(U256::from(300_000), |gas, call| {
+        gas.saturating_add(call.gas.unwrap_or_default())
+    });
 
     Ok(TransactionRequest {
-        to: Some(aave_pool.into()),
+        to: Some(lender.into()),
         data: Some(call_data),
-        gas: Some(U256::from(300_000)),
-        gas_price: Some(calculate_optimal_gas_price(target_block)),
+        gas: Some(gas),
+        gas_price: Some(gas_price),
         ..Default::default()
     })
 }
@@ -476,21 +1936,17 @@ fn create_liquidation_transaction(
     collateral_token: Address,
     debt_token: Address,
     debt_amount: U256,
-    target_block: U64,
+    gas_price: U256,
 ) -> anyhow::Result<TransactionRequest> {
     let (contract_address, call_data) = match protocol {
         Protocol::Aave => {
-            let aave_pool = Address::from_slice(
-                &hex::decode("7d2768dE32b0b80b7a3454c06BdAc94A69DDc7A9").unwrap(),
-            );
+            let aave_pool = parse_address(positions::AAVE_V3_POOL)?;
             let data =
                 encode_aave_liquidation(position_owner, collateral_token, debt_token, debt_amount)?;
             (aave_pool, data)
         }
         Protocol::Compound => {
-            let compound_comptroller = Address::from_slice(
-                &hex::decode("3d9819210A31b4961b30EF54bE2aeD79B9c9Cd3B").unwrap(),
-            );
+            let compound_comptroller = parse_address(COMPOUND_COMPTROLLER)?;
             let data = encode_compound_liquidation(
                 position_owner,
                 collateral_token,
@@ -506,68 +1962,122 @@ fn create_liquidation_transaction(
         to: Some(contract_address.into()),
         data: Some(call_data),
         gas: Some(U256::from(400_000)),
-        gas_price: Some(calculate_optimal_gas_price(target_block)),
+        gas_price: Some(gas_price),
         ..Default::default()
     })
 }
 
-/// Creates a flash loan repayment transaction.
+/// Creates the transaction returning `loan.repayment`, principal plus fee, to the lender.
 fn create_flash_loan_repay_transaction(
+    loan: &FlashLoan,
     token: Address,
-    amount: U256,
-    target_block: U64,
+    gas_price: U256,
 ) -> anyhow::Result<TransactionRequest> {
-    // This would be handled in the flash loan callback
-    // For simplicity, creating a mock repayment transaction
-    let call_data = encode_flash_loan_repay(token, amount)?;
+    // ---
+    let (to, call_data) = loan.provider.repayment_call(token, loan.repayment)?;
 
     Ok(TransactionRequest {
-        to: Some(token.into()), // Token contract for approval/transfer
+        to: Some(to.into()), // Token contract for approval/transfer
         data: Some(call_data),
         gas: Some(U256::from(100_000)),
-        gas_price: Some(calculate_optimal_gas_price(target_block)),
+        gas_price: Some(gas_price),
         ..Default::default()
     })
 }
 
+/// Returns what the bundle's loan transaction returns to the lender, decoded
+/// from the repay call in the callback payload it actually sends.
+///
+/// # Errors
+/// Returns an error if the bundle has no loan transaction, or its calldata
+/// carries no repayment to the lender where `loan.callback` puts it.
+fn flash_loan_repaid(bundle: &MEVBundle, loan: &FlashLoan) -> anyhow::Result<U256> {
+    // ---
+    let data = bundle
+        .transactions
+        .iter()
+        .find(|tx| tx.role == TxRole::FlashLoan)
+        .and_then(|tx| tx.request.as_request())
+        .and_then(|tx| tx.data.as_ref())
+        .ok_or_else(|| anyhow::anyhow!("Bundle carries no flash loan transaction"))?;
+    let calls = loan.provider.callback_calls(data)?;
+
+    let repay_at = loan.callback.iter().position(|tx| tx.role == TxRole::Repay);
+    repay_at
+        .and_then(|i| calls.get(i))
+        .and_then(|(_, repay)| loan.provider.repaid_by(repay))
+        .ok_or_else(|| anyhow::anyhow!("Flash loan callback never repays {}", loan.provider))
+}
+
+/// Creates the transaction paying `amount` to the block builder.
+///
+/// EOAs can't reach `block.coinbase`, so the ETH is sent to the searcher's
+/// `executor` contract, which forwards it with `block.coinbase.transfer`.
+fn create_coinbase_payment_transaction(
+    amount: U256,
+    executor: Address,
+    gas_price: U256,
+) -> TransactionRequest {
+    // ---
+    TransactionRequest {
+        to: Some(executor.into()),
+        value: Some(amount),
+        data: Some(encode_coinbase_payment(amount)),
+        gas: Some(U256::from(COINBASE_PAYMENT_GAS)),
+        gas_price: Some(gas_price),
+        ..Default::default()
+    }
+}
+
 // ---
 // ABI encoding functions (simplified implementations)
 // ---
 
 fn encode_uniswap_v2_swap(
-    _token_in: Address,
-    _token_out: Address,
+    token_in: Address,
+    token_out: Address,
     amount: U256,
+    amount_out_min: U256,
+    deadline: U256,
 ) -> anyhow::Result<Bytes> {
     // ---
     // swapExactTokensForTokens(uint256,uint256,address[],address,uint256)
     // Function selector: 0x38ed1739
     let mut data = vec![0x38, 0xed, 0x17, 0x39];
 
-    // Encode parameters (simplified - real implementation would use ethers ABI)
-    let mut encoded_amount = [0u8; 32];
-    amount.to_big_endian(&mut encoded_amount);
-    data.extend_from_slice(&encoded_amount);
-
-    // Add other parameters (amounts, path, recipient, deadline)
-    // This is highly simplified - real implementation needs proper ABI encoding
-    data.extend_from_slice(&[0u8; 128]); // Placeholder for other params
+    data.extend(abi::encode(&[
+        Token::Uint(amount),
+        Token::Uint(amount_out_min),
+        Token::Array(vec![Token::Address(token_in), Token::Address(token_out)]),
+        Token::Address(searcher_address()),
+        Token::Uint(deadline),
+    ]));
 
     Ok(data.into())
 }
 
 fn encode_uniswap_v3_swap(
-    _token_in: Address,
-    _token_out: Address,
-    _amount: U256,
+    token_in: Address,
+    token_out: Address,
+    amount: U256,
+    deadline: U256,
 ) -> anyhow::Result<Bytes> {
     // ---
-    // exactInputSingle(ExactInputSingleParams)
+    // exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))
     // Function selector: 0x414bf389
     let mut data = vec![0x41, 0x4b, 0xf3, 0x89];
 
-    // Encode ExactInputSingleParams struct (simplified)
-    data.extend_from_slice(&[0u8; 160]); // Placeholder for params
+    // ExactInputSingleParams on the 0.3% fee tier, with no output or price limit
+    data.extend(abi::encode(&[Token::Tuple(vec![
+        Token::Address(token_in),
+        Token::Address(token_out),
+        Token::Uint(U256::from(3000)),
+        Token::Address(searcher_address()),
+        Token::Uint(deadline),
+        Token::Uint(amount),
+        Token::Uint(U256::zero()),
+        Token::Uint(U256::zero()),
+    ])]));
 
     Ok(data.into())
 }
@@ -576,21 +2086,10 @@ fn encode_sushiswap_swap(
     token_in: Address,
     token_out: Address,
     amount: U256,
+    deadline: U256,
 ) -> anyhow::Result<Bytes> {
     // SushiSwap uses same interface as Uniswap V2
-    encode_uniswap_v2_swap(token_in, token_out, amount)
-}
-
-fn encode_aave_flash_loan(_token: Address, _amount: U256) -> anyhow::Result<Bytes> {
-    // ---
-    // flashLoan(address,address[],uint256[],uint256[],address,bytes,uint16)
-    // Function selector: 0xab9c4b5d
-    let mut data = vec![0xab, 0x9c, 0x4b, 0x5d];
-
-    // Encode parameters (simplified)
-    data.extend_from_slice(&[0u8; 224]); // Placeholder for flash loan params
-
-    Ok(data.into())
+    encode_uniswap_v2_swap(token_in, token_out, amount, U256::zero(), deadline)
 }
 
 fn encode_aave_liquidation(
@@ -627,112 +2126,3014 @@ fn encode_compound_liquidation(
     Ok(data.into())
 }
 
-fn encode_flash_loan_repay(_token: Address, _amount: U256) -> anyhow::Result<Bytes> {
+fn encode_erc20_approve(spender: Address, amount: U256) -> Bytes {
     // ---
-    // transfer(address,uint256) - ERC20 transfer for repayment
-    // Function selector: 0xa9059cbb
-    let mut data = vec![0xa9, 0x05, 0x9c, 0xbb];
+    // approve(address,uint256)
+    // Function selector: 0x095ea7b3
+    let mut data = vec![0x09, 0x5e, 0xa7, 0xb3];
+    data.extend(abi::encode(&[Token::Address(spender), Token::Uint(amount)]));
+    data.into()
+}
 
-    // Encode recipient and amount
-    data.extend_from_slice(&[0u8; 64]); // Placeholder for transfer params
+fn encode_erc20_allowance(owner: Address, spender: Address) -> Bytes {
+    // ---
+    // allowance(address,address)
+    // Function selector: 0xdd62ed3e
+    let mut data = vec![0xdd, 0x62, 0xed, 0x3e];
+    data.extend(abi::encode(&[
+        Token::Address(owner),
+        Token::Address(spender),
+    ]));
+    data.into()
+}
 
-    Ok(data.into())
+fn encode_coinbase_payment(amount: U256) -> Bytes {
+    // ---
+    // payCoinbase(uint256) - executor forwards msg.value to block.coinbase
+    let mut data = ethers::utils::id("payCoinbase(uint256)").to_vec();
+    data.extend(abi::encode(&[Token::Uint(amount)]));
+    data.into()
 }
 
 // ---
 // Helper functions
 // ---
 
-/// Gets the current block number from the chain.
-async fn get_current_block_number() -> anyhow::Result<U64> {
-    // In a real implementation, this would query the RPC endpoint
-    // Mock current block number
-    Ok(U64::from(18_500_000))
+/// Parses a hex contract address, naming it in the error instead of panicking.
+fn parse_address(address: &str) -> anyhow::Result<Address> {
+    // ---
+    address
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid contract address '{}': {}", address, e))
 }
 
-/// Calculates optimal gas price for bundle inclusion.
-fn calculate_optimal_gas_price(_target_block: U64) -> U256 {
+/// Reads the searcher's executor contract address from `EXECUTOR_ADDRESS`.
+///
+/// Returns `None` when unset; only simulated bundles can then pay the builder
+/// or borrow a flash loan.
+///
+/// # Errors
+/// Returns an error if `EXECUTOR_ADDRESS` is set but isn't an address.
+pub fn executor_from_env() -> anyhow::Result<Option<Address>> {
     // ---
-    // Base gas price + priority fee for MEV bundles
-    let base_gas_price = U256::from(20).pow(9.into()); // 20 gwei base
-    let priority_fee = U256::from(5).pow(9.into()); // 5 gwei priority
-    base_gas_price + priority_fee
+    std::env::var("EXECUTOR_ADDRESS")
+        .ok()
+        .map(|address| parse_address(&address))
+        .transpose()
 }
 
-/// Calculates optimal swap amount for arbitrage.
-fn calculate_optimal_swap_amount(opportunity: &MEVOpportunity) -> U256 {
+/// The error for a bundle that needs the executor contract when none is set.
+fn no_executor() -> anyhow::Error {
     // ---
-    match opportunity {
-        MEVOpportunity::Arbitrage { profit_eth, .. } => {
-            // Use a fraction of expected profit as swap amount
-            *profit_eth / 10
-        }
-        _ => U256::from(10).pow(18.into()), // Default 1 ETH
-    }
+    anyhow::anyhow!(
+        "EXECUTOR_ADDRESS is not set; bundles paying the builder or borrowing a flash loan can only be simulated"
+    )
 }
 
-/// Generates a unique bundle ID for tracking.
-fn generate_bundle_id() -> String {
+/// Builds the `X-Flashbots-Signature` header authenticating a relay request:
+/// the signer's address and its EIP-191 signature over the hex keccak256 of
+/// the JSON-RPC body.
+pub async fn flashbots_signature_header(
+    signer: &LocalWallet,
+    body: &str,
+) -> anyhow::Result<String> {
     // ---
-
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    format!("bundle_{}", timestamp)
+    let body_hash = format!("{:?}", H256::from(ethers::utils::keccak256(body)));
+    let signature = signer.sign_message(body_hash).await?;
+    Ok(format!("{:?}:0x{}", signer.address(), signature))
 }
 
-/// Gets configured MEV relay endpoints.
-fn get_relay_configs() -> Vec<RelayConfig> {
+/// Signs the bundle's own transactions with `wallet` for `chain_id` and
+/// returns the raw signed RLP of every transaction, in bundle order, as relays
+/// expect it in `txs`.
+///
+/// Raw third-party transactions are already signed and pass through verbatim.
+///
+/// # Errors
+/// Returns an error naming the transaction that could not be signed.
+pub fn encode_signed_transactions(
+    bundle: &MEVBundle,
+    wallet: &LocalWallet,
+    chain_id: u64,
+) -> anyhow::Result<Vec<Bytes>> {
     // ---
+    bundle
+        .transactions
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| match &tx.request {
+            BundleTransaction::Searcher(request) => {
+                sign_transaction((**request).clone().into(), wallet, chain_id)
+                    .map_err(|e| anyhow::anyhow!("Cannot sign bundle tx {}: {}", i, e))
+            }
+            BundleTransaction::Raw(raw) => Ok(raw.clone()),
+        })
+        .collect()
+}
 
-    vec![
+/// Signs `tx` from `wallet` on `chain_id` and returns its raw RLP, enveloped
+/// as its own type: legacy (EIP-155) or EIP-1559.
+fn sign_transaction(
+    mut tx: TypedTransaction,
+    wallet: &LocalWallet,
+    chain_id: u64,
+) -> anyhow::Result<Bytes> {
+    // ---
+    tx.set_chain_id(chain_id);
+    tx.set_from(wallet.address());
+    let signature = wallet.sign_transaction_sync(&tx)?;
+    Ok(tx.rlp_signed(&signature))
+}
+
+/// Returns `bundle`'s `eth_sendBundle` params with its transactions signed by
+/// the searcher wallet, or listed unsigned when no key is configured
+/// (simulation).
+pub fn signed_bundle_params(bundle: &MEVBundle) -> anyhow::Result<serde_json::Value> {
+    // ---
+    let mut params = bundle.to_flashbots_json();
+    if let Some(wallet) = SEARCHER_WALLET.get() {
+        let txs = encode_signed_transactions(bundle, wallet, wallet.chain_id())?;
+        params["txs"] = serde_json::to_value(txs)?;
+    }
+    Ok(params)
+}
+
+/// Registers the searcher wallet loaded at startup, which signs bundle
+/// transactions for its chain id. Only the first call takes effect.
+pub fn set_searcher_wallet(wallet: LocalWallet) {
+    // ---
+    let _ = SEARCHER_WALLET.set(wallet);
+}
+
+/// Returns the searcher account address registered at startup.
+///
+/// Falls back to the zero address when no key is configured (simulation),
+/// in which case every allowance lookup reports zero.
+pub fn searcher_address() -> Address {
+    // ---
+    SEARCHER_WALLET
+        .get()
+        .map(LocalWallet::address)
+        .unwrap_or_default()
+}
+
+/// Sets each searcher transaction's gas limit from `eth_estimateGas` scaled by
+/// `gas_limit_multiplier`, returning the bundle's total gas.
+///
+/// Raw third-party transactions are already signed with their own limits and
+/// are neither estimated nor counted.
+///
+/// Estimation is all-or-nothing: if any transaction cannot be estimated (e.g. it
+/// depends on state changed by an earlier transaction in the same bundle), every
+/// transaction keeps its builder-assigned limit and `None` is returned so the
+/// bundle keeps its fixed total.
+///
+/// # Arguments
+/// * `provider` - Chain client used for `eth_estimateGas`
+/// * `from` - Searcher account the transactions are sent from
+/// * `transactions` - Bundle transactions, updated in place on success
+/// * `gas_limit_multiplier` - Safety margin (e.g. 1.2 = 20% over the estimate)
+async fn estimate_bundle_gas<M: Middleware>(
+    provider: &M,
+    from: Address,
+    transactions: &mut [BundleTx],
+    gas_limit_multiplier: f64,
+) -> Option<U256> {
+    // ---
+
+    let mut requests: Vec<&mut TransactionRequest> = transactions
+        .iter_mut()
+        .filter_map(|tx| tx.request.as_request_mut())
+        .collect();
+
+    let mut limits = Vec::with_capacity(requests.len());
+    for (i, tx) in requests.iter().enumerate() {
+        let request: TypedTransaction = (*tx).clone().from(from).into();
+        match provider.estimate_gas(&request, None).await {
+            Ok(estimate) => limits.push(apply_gas_multiplier(estimate, gas_limit_multiplier)),
+            Err(e) => {
+                debug!(
+                    "eth_estimateGas failed for bundle tx {}: {}; keeping fixed gas limits",
+                    i, e
+                );
+                return None;
+            }
+        }
+    }
+
+    for (tx, limit) in requests.iter_mut().zip(&limits) {
+        tx.gas = Some(*limit);
+    }
+    Some(
+        limits
+            .into_iter()
+            .fold(U256::zero(), |total, limit| total + limit),
+    )
+}
+
+/// Scales a gas estimate by `multiplier`, rounding to the nearest basis point.
+fn apply_gas_multiplier(gas: U256, multiplier: f64) -> U256 {
+    // ---
+    let multiplier_bps = (multiplier.max(0.0) * 10_000.0).round() as u64;
+    gas * U256::from(multiplier_bps) / U256::from(10_000)
+}
+
+/// Generates a unique bundle ID for tracking.
+///
+/// A random UUID v4: unique even for bundles built in the same instant, and
+/// usable as a relay idempotency key (Flashbots `replacementUuid`).
+fn generate_bundle_id() -> String {
+    // ---
+    Uuid::new_v4().to_string()
+}
+
+/// Resolves the relays to submit bundles to, in priority order.
+///
+/// With no names, the default relay set applies (enabled relays in their
+/// default order). Otherwise exactly the named relays are enabled, in the order
+/// given, and all others are dropped. Names match case-insensitively and
+/// repeated names are ignored.
+///
+/// # Errors
+///
+/// Returns an error listing the valid relay names if any name is unknown.
+pub fn resolve_relays(names: &[String]) -> anyhow::Result<Vec<RelayConfig>> {
+    // ---
+
+    let known = get_relay_configs();
+    if names.is_empty() {
+        return Ok(known.into_iter().filter(|relay| relay.enabled).collect());
+    }
+
+    let mut selected: Vec<RelayConfig> = Vec::new();
+    for name in names {
+        let name = name.trim();
+        let relay = known
+            .iter()
+            .find(|relay| relay.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let valid: Vec<&str> = known.iter().map(|relay| relay.name.as_str()).collect();
+                anyhow::anyhow!(
+                    "Unknown relay '{}'; valid relays: {}",
+                    name,
+                    valid.join(", ")
+                )
+            })?;
+
+        if !selected.iter().any(|r| r.name == relay.name) {
+            selected.push(RelayConfig {
+                enabled: true,
+                ..relay.clone()
+            });
+        }
+    }
+
+    Ok(selected)
+}
+
+/// Gets configured MEV relay endpoints.
+fn get_relay_configs() -> Vec<RelayConfig> {
+    // ---
+
+    vec![
         RelayConfig {
             name: "flashbots".to_string(),
             endpoint: "https://relay.flashbots.net".to_string(),
             signing_key: std::env::var("FLASHBOTS_SIGNING_KEY").ok(),
+            key_var: "FLASHBOTS_SIGNING_KEY",
             enabled: true,
         },
         RelayConfig {
             name: "bloXroute".to_string(),
             endpoint: "https://mev.api.blxrbdn.com".to_string(),
             signing_key: std::env::var("BLOXROUTE_AUTH_HEADER").ok(),
+            key_var: "BLOXROUTE_AUTH_HEADER",
             enabled: true,
         },
         RelayConfig {
             name: "eden".to_string(),
             endpoint: "https://api.edennetwork.io".to_string(),
             signing_key: std::env::var("EDEN_API_KEY").ok(),
+            key_var: "EDEN_API_KEY",
             enabled: false, // Disabled by default
         },
     ]
 }
 
+impl BundleSettings {
+    // ---
+
+    /// Returns these settings with the strategy settings (`victim_inclusion`,
+    /// the builder bribe and fee share, swap deadline, profit margin,
+    /// staleness, and flash-loan providers) taken from `config`, so a reload
+    /// or control API patch applies to the next bundle built.
+    ///
+    /// Bundles bid at most the lower of the two gas price ceilings, so
+    /// lowering `max_gas_price_gwei` at runtime also caps what they pay.
+    pub fn with_strategy(self, config: &MEVConfig) -> Self {
+        // ---
+        Self {
+            max_gas_price_gwei: self.max_gas_price_gwei.min(config.max_gas_price_gwei),
+            victim_inclusion: config.sandwich.victim_inclusion,
+            builder_bribe_percent: config.builder_bribe_percent,
+            fee_recipient: config.fee_recipient,
+            fee_bps: config.fee_bps,
+            swap_deadline_secs: config.swap_deadline_secs,
+            profit_margin_bps: config.profit_margin_bps,
+            max_staleness_blocks: config.max_staleness_blocks,
+            // The live config was validated, so its providers parse
+            flash_loan_providers: config
+                .liquidation
+                .providers()
+                .unwrap_or(self.flash_loan_providers),
+            ..self
+        }
+    }
+}
+
+impl From<TransactionRequest> for BundleTransaction {
+    fn from(tx: TransactionRequest) -> Self {
+        BundleTransaction::Searcher(Box::new(tx))
+    }
+}
+
+impl BundleTransaction {
+    /// Returns the transaction request if this is one of our own transactions.
+    pub fn as_request(&self) -> Option<&TransactionRequest> {
+        // ---
+        match self {
+            BundleTransaction::Searcher(tx) => Some(tx),
+            BundleTransaction::Raw(_) => None,
+        }
+    }
+
+    /// Mutable counterpart of [`BundleTransaction::as_request`].
+    pub fn as_request_mut(&mut self) -> Option<&mut TransactionRequest> {
+        // ---
+        match self {
+            BundleTransaction::Searcher(tx) => Some(tx),
+            BundleTransaction::Raw(_) => None,
+        }
+    }
+
+    /// Gas limit of our own transaction; zero for third-party transactions,
+    /// whose gas the bundle doesn't set.
+    fn gas_limit(&self) -> U256 {
+        // ---
+        self.as_request().and_then(|tx| tx.gas).unwrap_or_default()
+    }
+}
+
+impl BundleTx {
+    // ---
+
+    /// Tags `request` with the `role` it plays in the bundle.
+    pub fn new(role: TxRole, request: impl Into<BundleTransaction>) -> Self {
+        // ---
+        Self {
+            request: request.into(),
+            role,
+        }
+    }
+}
+
+impl Serialize for BundleTx {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // ---
+        self.request.serialize(serializer)
+    }
+}
+
+impl TxRole {
+    // ---
+
+    /// Position of the role in a bundle's execution order; roles sharing a
+    /// stage may be interleaved.
+    fn stage(self) -> u8 {
+        // ---
+        match self {
+            TxRole::Approve => 0,
+            TxRole::FlashLoan => 1,
+            TxRole::Swap | TxRole::Frontrun | TxRole::Liquidate => 2,
+            TxRole::Victim => 3,
+            TxRole::Backrun => 4,
+            TxRole::Repay => 5,
+            TxRole::FeeShare => 6,
+            TxRole::Bribe => 7,
+        }
+    }
+
+    /// Name used in logs and errors.
+    pub fn label(self) -> &'static str {
+        // ---
+        match self {
+            TxRole::Approve => "approve",
+            TxRole::FlashLoan => "flash_loan",
+            TxRole::Swap => "swap",
+            TxRole::Frontrun => "frontrun",
+            TxRole::Liquidate => "liquidate",
+            TxRole::Victim => "victim",
+            TxRole::Backrun => "backrun",
+            TxRole::Repay => "repay",
+            TxRole::FeeShare => "fee_share",
+            TxRole::Bribe => "bribe",
+        }
+    }
+}
+
+impl fmt::Display for TxRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str(self.label())
+    }
+}
+
+impl MEVBundle {
+    /// Checks that the bundle's steps are sequenced as their roles require:
+    /// approvals first, a flash loan ahead of the trade it funds, frontrun
+    /// before victim before backrun, repayment after the trade, the fee share
+    /// after that and the builder payment last. A flash loan must be repaid in
+    /// the same bundle, and vice versa.
+    ///
+    /// Steps are the bundle's transactions in order, with a flash loan's
+    /// callback calls following the loan transaction, as they execute.
+    ///
+    /// # Errors
+    /// Returns an error naming the first step out of sequence, or the
+    /// unmatched flash loan or repayment.
+    pub fn check_sequence(&self) -> anyhow::Result<()> {
+        // ---
+
+        let callback = self.flash_loan.iter().flat_map(|loan| &loan.callback);
+        let mut steps = Vec::new();
+        for tx in &self.transactions {
+            steps.push(tx);
+            if tx.role == TxRole::FlashLoan {
+                steps.extend(callback.clone());
+            }
+        }
+
+        let mut latest: Option<(usize, TxRole)> = None;
+        for (i, tx) in steps.iter().enumerate() {
+            match latest {
+                Some((j, role)) if tx.role.stage() < role.stage() => anyhow::bail!(
+                    "Bundle step {} ({}) is sequenced after step {} ({})",
+                    i,
+                    tx.role,
+                    j,
+                    role
+                ),
+                Some((_, role)) if tx.role.stage() == role.stage() => {}
+                _ => latest = Some((i, tx.role)),
+            }
+        }
+
+        let has = |role| steps.iter().any(|tx| tx.role == role);
+        match (has(TxRole::FlashLoan), has(TxRole::Repay)) {
+            (true, false) => anyhow::bail!("Bundle takes a flash loan it never repays"),
+            (false, true) => anyhow::bail!("Bundle repays a flash loan it never takes"),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the gas-weighted average gas price of the bundle's own
+    /// transactions, or zero if none has both a gas limit and a price.
+    ///
+    /// Third-party transactions (e.g. a sandwich victim) are excluded: their
+    /// price says nothing about how competitively this bundle was priced.
+    pub fn effective_gas_price(&self) -> U256 {
+        // ---
+        let (gas, fees) = self
+            .transactions
+            .iter()
+            .filter_map(|tx| tx.request.as_request())
+            .filter_map(|tx| Some((tx.gas?, tx.gas_price?)))
+            .fold(
+                (U256::zero(), U256::zero()),
+                |(gas, fees), (limit, price)| {
+                    (
+                        gas.saturating_add(limit),
+                        fees.saturating_add(limit.saturating_mul(price)),
+                    )
+                },
+            );
+        fees.checked_div(gas).unwrap_or_default()
+    }
+
+    /// Returns the bundle as the `eth_sendBundle` parameter object relays
+    /// expect: `txs`, the hex `blockNumber`, and `minTimestamp`/`maxTimestamp`
+    /// when set.
+    ///
+    /// Raw third-party transactions are listed as their signed hex; our own
+    /// are listed unsigned, and submitters replace the list with
+    /// [`encode_signed_transactions`]. Timestamps
+    /// are the relays' 64-bit seconds, which [`validate_bundle`] ensures they
+    /// fit; larger ones saturate.
+    pub fn to_flashbots_json(&self) -> serde_json::Value {
+        // ---
+        let seconds = |timestamp: U256| u64::try_from(timestamp).unwrap_or(u64::MAX);
+        let mut params = serde_json::json!({
+            "txs": self.transactions,
+            "blockNumber": format!("{:#x}", self.target_block),
+        });
+        if let Some(min_timestamp) = self.min_timestamp {
+            params["minTimestamp"] = seconds(min_timestamp).into();
+        }
+        if let Some(max_timestamp) = self.max_timestamp {
+            params["maxTimestamp"] = seconds(max_timestamp).into();
+        }
+        params
+    }
+
+    /// Returns the hash of the bundle's first own transaction signed by
+    /// `wallet`, or `None` if it has none or it can't be signed.
+    pub fn landing_tx(&self, wallet: &LocalWallet) -> Option<TxHash> {
+        // ---
+        let first = self
+            .transactions
+            .iter()
+            .position(|tx| tx.request.as_request().is_some())?;
+        let signed = encode_signed_transactions(self, wallet, wallet.chain_id()).ok()?;
+        Some(H256::from(ethers::utils::keccak256(&signed[first])))
+    }
+
+    /// Returns the keccak256 of the bundle's contents: equal for bundles that
+    /// compare equal, so an identical rebuild can be recognized before it is
+    /// sent again.
+    pub fn content_hash(&self) -> H256 {
+        // ---
+
+        // Destructured so a new field can't be left out by accident
+        let Self {
+            transactions,
+            target_block,
+            min_timestamp,
+            max_timestamp,
+            bundle_id: _,
+            total_gas,
+            expected_profit,
+            builder_payment,
+            fee_payment,
+            flash_loan,
+        } = self;
+        let flash_loan = flash_loan.as_ref().map(|loan| {
+            serde_json::json!({
+                "provider": loan.provider.to_string(),
+                "principal": loan.principal,
+                "repayment": loan.repayment,
+                "callback": loan.callback,
+            })
+        });
+
+        let content = serde_json::json!({
+            "transactions": transactions,
+            "target_block": target_block,
+            "min_timestamp": min_timestamp,
+            "max_timestamp": max_timestamp,
+            "total_gas": total_gas,
+            "expected_profit": expected_profit,
+            "builder_payment": builder_payment,
+            "fee_payment": fee_payment,
+            "flash_loan": flash_loan,
+        });
+        H256::from(ethers::utils::keccak256(content.to_string()))
+    }
+
+    /// Fits the bundle within `limits`, splitting it if `splittable`.
+    ///
+    /// A bundle within the limits comes back as the only part. Otherwise its
+    /// transactions are divided, in order, into as few parts as fit, each
+    /// targeting the block after the previous part's. The last part carries
+    /// the expected profit and the builder and fee payments, since the trade only pays off
+    /// once it completes.
+    ///
+    /// # Errors
+    /// Returns a [`BundleLimitError`] if the bundle exceeds the limits and is
+    /// not `splittable` or takes a flash loan (which must be repaid in the
+    /// same bundle), or if one of its transactions alone exceeds `max_gas`.
+    pub fn split_to_limits(
+        &self,
+        limits: BundleLimits,
+        strategy: &'static str,
+        splittable: bool,
+    ) -> Result<Vec<MEVBundle>, BundleLimitError> {
+        // ---
+
+        let max_gas = U256::from(limits.max_gas);
+        let total_gas = self.transactions.iter().fold(U256::zero(), |total, tx| {
+            total.saturating_add(tx.request.gas_limit())
+        });
+        if self.transactions.len() <= limits.max_txs && total_gas <= max_gas {
+            return Ok(vec![self.clone()]);
+        }
+
+        if !splittable || self.flash_loan.is_some() {
+            return Err(if self.transactions.len() > limits.max_txs {
+                BundleLimitError::TooManyTransactions {
+                    strategy,
+                    txs: self.transactions.len(),
+                    max_txs: limits.max_txs,
+                }
+            } else {
+                BundleLimitError::GasLimitExceeded {
+                    strategy,
+                    gas: total_gas,
+                    max_gas: limits.max_gas,
+                }
+            });
+        }
+
+        // Greedily fill each part up to whichever limit it reaches first
+        let mut parts: Vec<(Vec<BundleTx>, U256)> = Vec::new();
+        for tx in &self.transactions {
+            let gas = tx.request.gas_limit();
+            if gas > max_gas {
+                return Err(BundleLimitError::GasLimitExceeded {
+                    strategy,
+                    gas,
+                    max_gas: limits.max_gas,
+                });
+            }
+            match parts.last_mut() {
+                Some((part, part_gas))
+                    if part.len() < limits.max_txs && *part_gas + gas <= max_gas =>
+                {
+                    part.push(tx.clone());
+                    *part_gas += gas;
+                }
+                _ => parts.push((vec![tx.clone()], gas)),
+            }
+        }
+
+        let last = parts.len() - 1;
+        Ok(parts
+            .into_iter()
+            .enumerate()
+            .map(|(i, (transactions, total_gas))| MEVBundle {
+                transactions,
+                target_block: self.target_block + i,
+                min_timestamp: self.min_timestamp,
+                max_timestamp: self.max_timestamp,
+                bundle_id: generate_bundle_id(),
+                total_gas,
+                expected_profit: if i == last {
+                    self.expected_profit
+                } else {
+                    U256::zero()
+                },
+                builder_payment: if i == last {
+                    self.builder_payment
+                } else {
+                    U256::zero()
+                },
+                fee_payment: if i == last {
+                    self.fee_payment
+                } else {
+                    U256::zero()
+                },
+                flash_loan: None,
+            })
+            .collect())
+    }
+}
+
+impl PartialEq for MEVBundle {
+    fn eq(&self, other: &Self) -> bool {
+        // ---
+        let Self {
+            transactions,
+            target_block,
+            min_timestamp,
+            max_timestamp,
+            bundle_id: _,
+            total_gas,
+            expected_profit,
+            builder_payment,
+            fee_payment,
+            flash_loan,
+        } = self;
+
+        *transactions == other.transactions
+            && *target_block == other.target_block
+            && *min_timestamp == other.min_timestamp
+            && *max_timestamp == other.max_timestamp
+            && *total_gas == other.total_gas
+            && *expected_profit == other.expected_profit
+            && *builder_payment == other.builder_payment
+            && *fee_payment == other.fee_payment
+            && *flash_loan == other.flash_loan
+    }
+}
+
+impl Eq for MEVBundle {}
+
+impl fmt::Display for BundleLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        match self {
+            BundleLimitError::TooManyTransactions {
+                strategy,
+                txs,
+                max_txs,
+            } => write!(
+                f,
+                "{} bundle needs {} transactions, over the {}-transaction limit",
+                strategy, txs, max_txs
+            ),
+            BundleLimitError::GasLimitExceeded {
+                strategy,
+                gas,
+                max_gas,
+            } => write!(
+                f,
+                "{} bundle needs {} gas, over the {} gas limit",
+                strategy, gas, max_gas
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BundleLimitError {}
+
+impl SubmissionResult {
+    /// Describes `bundle` as accepted by `relay` under `bundle_hash`, with its
+    /// gas price, gas, and builder payment taken from the bundle.
+    fn submitted(
+        bundle: &MEVBundle,
+        relay: &str,
+        bundle_hash: String,
+        inclusion_probability: f64,
+    ) -> Self {
+        // ---
+        Self {
+            bundle_hash,
+            status: SubmissionStatus::Submitted,
+            relay: relay.to_string(),
+            block_number: Some(bundle.target_block),
+            inclusion_probability: Some(inclusion_probability),
+            effective_gas_price: bundle.effective_gas_price(),
+            total_gas: bundle.total_gas,
+            builder_payment: bundle.builder_payment,
+            gas_used: None,
+            landing_tx: SEARCHER_WALLET
+                .get()
+                .and_then(|wallet| bundle.landing_tx(wallet)),
+        }
+    }
+}
+
+impl SubmissionOutcome {
+    // ---
+
+    /// Sets the simulated `gas_used` of every submission of the bundle.
+    fn set_gas_used(&mut self, gas_used: U256) {
+        // ---
+        let results: Vec<&mut SubmissionResult> = match self {
+            SubmissionOutcome::Single(result) => vec![result],
+            SubmissionOutcome::Multi(multi) => multi
+                .per_relay
+                .iter_mut()
+                .filter_map(|(_, response)| response.as_mut().ok())
+                .chain(multi.best.as_mut())
+                .collect(),
+            _ => Vec::new(),
+        };
+        for result in results {
+            result.gas_used = Some(gas_used);
+        }
+    }
+
+    /// Returns the submissions relays accepted that may still land, in order.
+    pub fn accepted(&self) -> Vec<&SubmissionResult> {
+        // ---
+        let results = match self {
+            SubmissionOutcome::Single(result) => vec![result],
+            SubmissionOutcome::Multi(multi) => multi
+                .per_relay
+                .iter()
+                .filter_map(|(_, response)| response.as_ref().ok())
+                .collect(),
+            SubmissionOutcome::Split(parts) => parts.iter().flat_map(Self::accepted).collect(),
+            SubmissionOutcome::Stale | SubmissionOutcome::Duplicate | SubmissionOutcome::Healed => {
+                Vec::new()
+            }
+        };
+        results
+            .into_iter()
+            .filter(|result| matches!(result.status, SubmissionStatus::Submitted))
+            .collect()
+    }
+}
+
+impl MultiSubmissionResult {
+    /// Collects per-relay responses, picking the accepted submission with the
+    /// highest inclusion probability as `best`.
+    ///
+    /// Cancelled submissions are never `best`, since they can no longer land.
+    pub fn new(per_relay: Vec<(String, Result<SubmissionResult, String>)>) -> Self {
+        // ---
+
+        let best = per_relay
+            .iter()
+            .filter_map(|(_, response)| response.as_ref().ok())
+            .filter(|result| !matches!(result.status, SubmissionStatus::Cancelled))
+            .max_by(|a, b| {
+                let a = a.inclusion_probability.unwrap_or(0.0);
+                let b = b.inclusion_probability.unwrap_or(0.0);
+                a.total_cmp(&b)
+            })
+            .cloned();
+
+        Self { per_relay, best }
+    }
+
+    /// Returns how many relays accepted the bundle.
+    pub fn accepted(&self) -> usize {
+        // ---
+        self.per_relay
+            .iter()
+            .filter(|(_, response)| response.is_ok())
+            .count()
+    }
+
+    /// Logs one line per relay with its status or rejection reason.
+    ///
+    /// When every relay rejects the bundle for the same reason, that reason is
+    /// called out, since it usually points at the bundle rather than a relay.
+    pub fn log_summary(&self, bundle_id: &str) {
+        // ---
+
+        info!(
+            "📊 Broadcast of bundle {}: {}/{} relays accepted",
+            bundle_id,
+            self.accepted(),
+            self.per_relay.len()
+        );
+
+        for (relay, response) in &self.per_relay {
+            match response {
+                Ok(result) => info!(
+                    "   ✅ {:<10} {:<10} {} ({:.1}% inclusion)",
+                    relay,
+                    format!("{:?}", result.status),
+                    result.bundle_hash,
+                    result.inclusion_probability.unwrap_or(0.0) * 100.0
+                ),
+                Err(reason) => warn!("   ❌ {:<10} {:<10} {}", relay, "Rejected", reason),
+            }
+        }
+
+        let mut reasons = self
+            .per_relay
+            .iter()
+            .map(|(_, response)| response.as_ref().err());
+        if let Some(Some(first)) = reasons.next() {
+            if self.per_relay.len() > 1 && reasons.all(|reason| reason == Some(first)) {
+                warn!("❌ All relays rejected bundle {}: {}", bundle_id, first);
+            }
+        }
+    }
+}
+
 /// Validates bundle before submission.
-pub fn validate_bundle(bundle: &MEVBundle) -> anyhow::Result<()> {
+///
+/// Bundle costs are the gas of its own transactions at their gas prices plus
+/// the builder and fee payments; `expected_profit` must exceed them by
+/// `margin_bps`.
+///
+/// # Errors
+/// Returns an error if the bundle is empty, its transactions are out of
+/// sequence for their roles, its timestamps don't fit the
+/// relays' 64-bit fields, its `max_timestamp` deadline is not after
+/// `latest_timestamp`, its flash-loan repayment falls short of principal plus
+/// fee, its gas doesn't fit in a u64, it has no profit left after the builder
+/// and fee payments, or its profit misses the margin.
+pub fn validate_bundle(
+    bundle: &MEVBundle,
+    latest_timestamp: U256,
+    margin_bps: u16,
+) -> anyhow::Result<()> {
     // ---
 
     if bundle.transactions.is_empty() {
         anyhow::bail!("Bundle cannot be empty");
     }
 
+    // Relays execute transactions strictly in array order
+    bundle.check_sequence()?;
+
+    for timestamp in [bundle.min_timestamp, bundle.max_timestamp]
+        .into_iter()
+        .flatten()
+    {
+        if u64::try_from(timestamp).is_err() {
+            anyhow::bail!("Bundle timestamp {} does not fit in 64 bits", timestamp);
+        }
+    }
+
+    // Swaps past their router deadline revert, so the bundle could never land
+    if let Some(deadline) = bundle.max_timestamp {
+        if deadline <= latest_timestamp {
+            anyhow::bail!(
+                "Bundle deadline {} has passed (latest block timestamp {})",
+                deadline,
+                latest_timestamp
+            );
+        }
+    }
+
     if bundle.expected_profit == U256::zero() {
-        anyhow::bail!("Bundle must have positive expected profit");
+        if bundle.builder_payment.is_zero() && bundle.fee_payment.is_zero() {
+            anyhow::bail!("Bundle must have positive expected profit");
+        }
+        anyhow::bail!(
+            "Builder payment of {} ETH and fee share of {} ETH leave no profit",
+            ethers::utils::format_ether(bundle.builder_payment),
+            ethers::utils::format_ether(bundle.fee_payment)
+        );
+    }
+
+    // A repayment short of principal plus fee reverts the loan, and the bundle with it
+    if let Some(loan) = &bundle.flash_loan {
+        let repaid = flash_loan_repaid(bundle, loan)?;
+        if repaid < loan.provider.repayment(loan.principal) {
+            anyhow::bail!(
+                "Flash loan repayment {} does not cover principal {} plus {} fee",
+                repaid,
+                loan.principal,
+                loan.provider
+            );
+        }
     }
 
-    // Check gas limits
-    let total_gas: u64 = bundle
+    // Check gas limits; summed as U256 since node estimates are not trusted to fit u64
+    let total_gas = bundle
         .transactions
         .iter()
-        .map(|tx| tx.gas.unwrap_or_default().as_u64())
-        .sum();
+        .filter_map(|tx| tx.request.as_request())
+        .fold(U256::zero(), |total, tx| {
+            total.saturating_add(tx.gas.unwrap_or_default())
+        });
+
+    // Size limits are enforced by `MEVBundle::split_to_limits`; this only
+    // guards the u64 gas fields relays expect
+    if total_gas > U256::from(u64::MAX) {
+        anyhow::bail!("Bundle gas usage {} does not fit in u64", total_gas);
+    }
 
-    if total_gas > 12_000_000 {
-        // Approximate block gas limit
-        anyhow::bail!("Bundle gas usage exceeds block limit");
+    // Marginal bundles lose to slippage and gas misestimates; demand headroom
+    let cost = bundle
+        .transactions
+        .iter()
+        .filter_map(|tx| tx.request.as_request())
+        .fold(bundle.builder_payment + bundle.fee_payment, |total, tx| {
+            let gas_cost = tx
+                .gas
+                .unwrap_or_default()
+                .saturating_mul(tx.gas_price.unwrap_or_default());
+            total.saturating_add(gas_cost)
+        });
+    debug!(
+        "📐 Bundle margin over break-even: {} bps (required {})",
+        searcher::profit_margin_bps(bundle.expected_profit, cost)
+            .map_or_else(|| "∞".to_string(), |bps| bps.to_string()),
+        margin_bps
+    );
+    if !searcher::meets_profit_margin(bundle.expected_profit, cost, margin_bps) {
+        anyhow::bail!(
+            "Expected profit {} ETH is within the {} bps margin over costs of {} ETH",
+            ethers::utils::format_ether(bundle.expected_profit),
+            margin_bps,
+            ethers::utils::format_ether(cost)
+        );
     }
 
-    info!("✅ Bundle validation passed");
+    debug!("✅ Bundle validation passed");
     Ok(())
 }
+
+// ---
+
+impl OpportunityCooldown {
+    // ---
+
+    /// Creates a cooldown that suppresses repeats for `window_blocks` blocks.
+    pub fn new(window_blocks: u64) -> Self {
+        Self {
+            window_blocks,
+            last_acted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Claims `opportunity`'s token pair and strategy at `block`, starting its
+    /// window. Returns `false`, claiming nothing, if the key was claimed fewer
+    /// than `window_blocks` blocks before `block`.
+    pub fn try_acquire(&self, opportunity: &MEVOpportunity, block: U64) -> bool {
+        // ---
+
+        if self.window_blocks == 0 {
+            return true;
+        }
+        let window = U64::from(self.window_blocks);
+        let mut last_acted = self.last_acted.lock().unwrap();
+
+        // Expired keys can never suppress again; drop them to keep the map small
+        last_acted.retain(|_, acted| block.saturating_sub(*acted) < window);
+        match last_acted.entry(Self::key(opportunity)) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(block);
+                true
+            }
+        }
+    }
+
+    /// Gives up the claim [`try_acquire`](Self::try_acquire) made at `block`,
+    /// for an attempt that ended without a relay accepting its bundle.
+    pub fn release(&self, opportunity: &MEVOpportunity, block: U64) {
+        // ---
+        let mut last_acted = self.last_acted.lock().unwrap();
+        let key = Self::key(opportunity);
+        if last_acted.get(&key) == Some(&block) {
+            last_acted.remove(&key);
+        }
+    }
+
+    /// The (token_a, token_b, strategy) key `opportunity` is suppressed under.
+    fn key(opportunity: &MEVOpportunity) -> (Address, Address, &'static str) {
+        // ---
+        let (token_a, token_b) = opportunity.token_pair();
+        (token_a, token_b, opportunity.strategy())
+    }
+}
+
+impl RelayPermits {
+    // ---
+
+    /// Allows up to `limit` relay requests in flight at once.
+    pub fn new(limit: usize) -> Self {
+        // ---
+        Self {
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit)),
+        }
+    }
+
+    /// Waits for a free permit to submit to `relay`, at most until `deadline`.
+    ///
+    /// # Errors
+    /// Returns an error if every permit is still held at `deadline`.
+    async fn acquire(
+        &self,
+        relay: &str,
+        deadline: Instant,
+    ) -> anyhow::Result<OwnedSemaphorePermit> {
+        // ---
+        let queued = self.semaphore.clone().acquire_owned();
+        match tokio::time::timeout_at(deadline, queued).await {
+            Ok(permit) => Ok(permit.expect("relay permits are never closed")),
+            Err(_) => anyhow::bail!(
+                "Not submitted to {}: all {} relay request slots stayed busy until the target block was due (see --max-relay-concurrency)",
+                relay,
+                self.limit
+            ),
+        }
+    }
+}
+
+impl SentBundles {
+    // ---
+
+    /// Returns the key a bundle for `opportunity` targeting `target_block` is
+    /// recorded under, known before the bundle is built.
+    pub fn opportunity_key(opportunity: &MEVOpportunity, target_block: U64) -> H256 {
+        // ---
+        let content = serde_json::json!({
+            "opportunity": opportunity,
+            "target_block": target_block,
+        });
+        H256::from(ethers::utils::keccak256(content.to_string()))
+    }
+
+    /// Returns whether a bundle under `key` was accepted for `target_block`.
+    pub fn was_sent(&self, key: H256, target_block: U64) -> bool {
+        // ---
+
+        let mut sent = self.sent.lock().expect("sent bundles poisoned");
+
+        // Bundles for earlier blocks can no longer be rebuilt identically
+        sent.retain(|_, sent_for| *sent_for >= target_block);
+        sent.contains_key(&key)
+    }
+
+    /// Records a bundle accepted for `target_block` under each of `keys`.
+    pub fn record(&self, keys: &[H256], target_block: U64) {
+        // ---
+        let mut sent = self.sent.lock().expect("sent bundles poisoned");
+        sent.extend(keys.iter().map(|key| (*key, target_block)));
+    }
+}
+
+impl ApprovalManager {
+    // ---
+
+    /// Creates an approval tracker for the given searcher account.
+    pub fn new(owner: Address) -> Self {
+        Self {
+            owner,
+            allowances: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the `approve` transactions needed before the given swaps can execute.
+    ///
+    /// Each requirement is `(token, spender, amount)`. Native ETH (the zero address)
+    /// never needs an approval. The cached allowance is consulted first; otherwise
+    /// the on-chain `allowance(owner, spender)` is queried. A failed lookup is
+    /// treated as zero allowance, since a redundant approval only costs gas while a
+    /// missing one reverts the whole bundle.
+    ///
+    /// Approvals returned here are not recorded as granted; until one lands and
+    /// the allowance is read back on-chain, each bundle carries its own.
+    pub async fn required_approvals<M: Middleware>(
+        &self,
+        provider: &M,
+        requirements: &[(Address, Address, U256)],
+        gas_price: U256,
+    ) -> Vec<TransactionRequest> {
+        // ---
+
+        let mut approvals = Vec::new();
+
+        for &(token, spender, amount) in requirements {
+            if token == Address::zero() || self.cached_allowance(token, spender) >= amount {
+                continue;
+            }
+
+            let allowance = match self.query_allowance(provider, token, spender).await {
+                Ok(allowance) => allowance,
+                Err(e) => {
+                    warn!("Allowance lookup failed for token {:?}: {}", token, e);
+                    U256::zero()
+                }
+            };
+
+            if allowance >= amount {
+                self.record_allowance(token, spender, allowance);
+                continue;
+            }
+
+            debug!(
+                "🔓 Approving {:?} to spend {} of token {:?}",
+                spender, amount, token
+            );
+            approvals.push(create_approval_transaction(
+                token, spender, amount, gas_price,
+            ));
+        }
+
+        approvals
+    }
+
+    /// Returns the allowance cached for `(token, spender)`, or zero if unknown.
+    fn cached_allowance(&self, token: Address, spender: Address) -> U256 {
+        // ---
+        self.allowances
+            .lock()
+            .unwrap()
+            .get(&(token, spender))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Records an allowance read on-chain for `(token, spender)`.
+    fn record_allowance(&self, token: Address, spender: Address, amount: U256) {
+        // ---
+        self.allowances
+            .lock()
+            .unwrap()
+            .insert((token, spender), amount);
+    }
+
+    /// Queries the ERC-20 `allowance(owner, spender)` of `token` via `eth_call`.
+    async fn query_allowance<M: Middleware>(
+        &self,
+        provider: &M,
+        token: Address,
+        spender: Address,
+    ) -> anyhow::Result<U256> {
+        // ---
+        let call = TransactionRequest::new()
+            .to(token)
+            .data(encode_erc20_allowance(self.owner, spender));
+
+        let output = provider
+            .call(&call.into(), None)
+            .await
+            .map_err(|e| anyhow::anyhow!("eth_call failed: {}", e))?;
+
+        if output.len() < 32 {
+            anyhow::bail!("Malformed allowance response ({} bytes)", output.len());
+        }
+        Ok(U256::from_big_endian(&output[..32]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, account_data, MockProvider, TEST_BASE_FEE_WEI};
+    use crate::oracle::StaticPriceOracle;
+    use crate::relay_stats;
+    use crate::types::TokenInfo;
+    use ethers::abi::ParamType;
+    use ethers::providers::Provider;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Provider reporting unlimited allowances, a fixed gas estimate, and a
+    /// searcher nonce of 7.
+    fn approved_provider() -> MockProvider {
+        MockProvider::default()
+            .with_response("eth_call", Bytes::from(vec![0xff; 32]))
+            .with_response("eth_estimateGas", U256::from(150_000))
+            .with_response("eth_getTransactionCount", U256::from(7))
+    }
+
+    fn settings() -> BundleSettings {
+        BundleSettings {
+            gas_limit_multiplier: 1.2,
+            priority_fee_strategy: PriorityFeeStrategy::Fixed(5),
+            price_bump_bps: 0,
+            max_gas_price_gwei: 300,
+            victim_inclusion: VictimInclusion::Bundled,
+            builder_bribe_percent: 0.0,
+            fee_recipient: None,
+            fee_bps: 0,
+            swap_deadline_secs: 60,
+            profit_margin_bps: 50,
+            max_staleness_blocks: 0,
+            flash_loan_providers: vec![FlashLoanProvider::AaveV3, FlashLoanProvider::DyDx],
+            routers: Arc::new(mainnet_routers()),
+            executor: Some(Address::repeat_byte(0xec)),
+            oracle: Arc::new(
+                StaticPriceOracle::default().with_token_price(debt_token(), DEBT_TOKEN_PRICE_USD),
+            ),
+            token_registry: Arc::new(tokens()),
+            sent_bundles: Arc::default(),
+            limits: BundleLimits {
+                max_gas: 12_000_000,
+                max_txs: 16,
+            },
+            value_decimals: units::DEFAULT_VALUE_DECIMALS,
+            metrics: Arc::default(),
+            adaptive_relay_order: false,
+            trace_bundles: false,
+            simulate_bundles: false,
+            relay_permits: RelayPermits::new(DEFAULT_RELAY_CONCURRENCY),
+        }
+    }
+
+    /// Settings borrowing only from `providers`.
+    fn lending_from(providers: &[FlashLoanProvider]) -> BundleSettings {
+        BundleSettings {
+            flash_loan_providers: providers.to_vec(),
+            ..settings()
+        }
+    }
+
+    /// USD price of [`debt_token`], one ETH at the default oracle's price.
+    const DEBT_TOKEN_PRICE_USD: f64 = 2_500.0;
+
+    /// An 18-decimal debt token only Aave lends.
+    fn debt_token() -> Address {
+        Address::from_low_u64_be(0xdeb7)
+    }
+
+    /// The default tokens, plus [`debt_token`].
+    fn tokens() -> TokenRegistry {
+        let mut tokens = TokenRegistry::with_defaults();
+        tokens.insert(TokenInfo {
+            address: debt_token(),
+            symbol: "DEBT".to_string(),
+            name: "Debt Token".to_string(),
+            decimals: 18,
+            is_active: true,
+            liquidity_score: 1.0,
+            avg_daily_volume_usd: 0.0,
+        });
+        tokens
+    }
+
+    fn mainnet_routers() -> DexRegistry {
+        DexRegistry::from_config(&Default::default()).unwrap()
+    }
+
+    fn arbitrage() -> MEVOpportunity {
+        MEVOpportunity::Arbitrage {
+            token_a: Address::from_low_u64_be(1),
+            token_b: Address::from_low_u64_be(2),
+            buy_dex: DEX::UniswapV2,
+            sell_dex: DEX::SushiSwap,
+            amount_in: U256::exp10(19),
+            token_b_amount: U256::from(25_000_000_000u64),
+            profit_eth: U256::exp10(18),
+            gas_cost_eth: U256::exp10(16),
+            net_profit_eth: U256::exp10(18) - U256::exp10(16),
+            detected_block: U64::zero(),
+        }
+    }
+
+    /// A sandwich around victim transaction `victim`.
+    fn sandwich(victim: u64) -> MEVOpportunity {
+        MEVOpportunity::Sandwich {
+            victim_tx_hash: TxHash::from_low_u64_be(victim),
+            token_in: Address::from_low_u64_be(1),
+            token_out: Address::from_low_u64_be(2),
+            victim_amount_in: U256::exp10(20),
+            frontrun_amount: U256::exp10(19),
+            backrun_amount: U256::exp10(19),
+            backrun_min_out: U256::exp10(19),
+            estimated_profit_eth: U256::exp10(17),
+            gas_cost_eth: U256::exp10(15),
+            detected_block: U64::zero(),
+        }
+    }
+
+    #[tokio::test]
+    async fn aged_opportunity_is_dropped_before_bundle_creation() {
+        let mock = Arc::new(approved_provider());
+        let provider = Provider::new(mock.clone());
+        let base_fees = BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI));
+        base_fees.observe_block(&ethers::types::Block {
+            number: Some(U64::from(12)),
+            base_fee_per_gas: Some(U256::from(TEST_BASE_FEE_WEI)),
+            gas_used: U256::one(),
+            gas_limit: U256::from(2),
+            ..Default::default()
+        });
+
+        // Detected at block 10, two blocks behind the head
+        let mut opportunity = arbitrage();
+        if let MEVOpportunity::Arbitrage { detected_block, .. } = &mut opportunity {
+            *detected_block = U64::from(10);
+        }
+
+        let approvals = ApprovalManager::new(Address::zero());
+        let send = |max_staleness_blocks| {
+            create_and_send_bundle(
+                opportunity.clone(),
+                &provider,
+                &approvals,
+                &base_fees,
+                BundleSettings {
+                    max_staleness_blocks,
+                    ..settings()
+                },
+                &[],
+                SubmissionMode::Simulate,
+            )
+        };
+
+        let outcome = send(1).await.unwrap();
+        assert!(matches!(outcome, SubmissionOutcome::Stale));
+        // Nothing was built: no allowance lookups or gas estimates
+        assert_eq!(mock.call_count("eth_call"), 0);
+        assert_eq!(mock.call_count("eth_estimateGas"), 0);
+
+        let outcome = send(2).await.unwrap();
+        assert!(matches!(outcome, SubmissionOutcome::Single(_)));
+    }
+
+    #[tokio::test]
+    async fn simulated_bundle_is_not_submitted() {
+        let provider = Provider::new(approved_provider());
+        let base_fees = BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI));
+
+        let outcome = create_and_send_bundle(
+            arbitrage(),
+            &provider,
+            &ApprovalManager::new(Address::zero()),
+            &base_fees,
+            settings(),
+            &resolve_relays(&[]).unwrap(),
+            SubmissionMode::Simulate,
+        )
+        .await
+        .unwrap();
+
+        let SubmissionOutcome::Single(result) = outcome else {
+            panic!("simulation returns a single result");
+        };
+        assert_eq!(result.relay, "simulation");
+        assert!(matches!(result.status, SubmissionStatus::Submitted));
+        assert_eq!(result.block_number, Some(U64::one()));
+    }
+
+    #[tokio::test]
+    async fn oversized_arbitrage_is_split_and_liquidation_rejected() {
+        let provider = Provider::new(approved_provider());
+        let base_fees = BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI));
+        let approvals = ApprovalManager::new(Address::zero());
+        let send = |opportunity, max_gas, max_txs| {
+            create_and_send_bundle(
+                opportunity,
+                &provider,
+                &approvals,
+                &base_fees,
+                BundleSettings {
+                    limits: BundleLimits { max_gas, max_txs },
+                    ..settings()
+                },
+                &[],
+                SubmissionMode::Simulate,
+            )
+        };
+        let target_blocks = |outcome| match outcome {
+            SubmissionOutcome::Single(result) => vec![result.block_number],
+            SubmissionOutcome::Split(parts) => parts
+                .into_iter()
+                .map(|part| match part {
+                    SubmissionOutcome::Single(result) => result.block_number,
+                    other => panic!("unexpected part outcome {other:?}"),
+                })
+                .collect(),
+            other => panic!("unexpected outcome {other:?}"),
+        };
+
+        // Two swaps estimated at 180k gas each fit exactly
+        let outcome = send(arbitrage(), 360_000, 2).await.unwrap();
+        assert_eq!(target_blocks(outcome), [Some(U64::one())]);
+
+        // One gas or one transaction over: each swap lands in its own block
+        for (max_gas, max_txs) in [(359_999, 2), (360_000, 1)] {
+            let outcome = send(arbitrage(), max_gas, max_txs).await.unwrap();
+            assert_eq!(
+                target_blocks(outcome),
+                [Some(U64::one()), Some(U64::from(2))]
+            );
+        }
+
+        // The flash loan must be repaid in the bundle that takes it, so the
+        // loan and the builder payment after it can't be split apart
+        let weth = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+            .parse()
+            .unwrap();
+        let err = create_and_send_bundle(
+            liquidation(weth),
+            &provider,
+            &approvals,
+            &base_fees,
+            BundleSettings {
+                builder_bribe_percent: 10.0,
+                limits: BundleLimits {
+                    max_gas: 12_000_000,
+                    max_txs: 1,
+                },
+                ..settings()
+            },
+            &[],
+            SubmissionMode::Simulate,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<BundleLimitError>(),
+            Some(&BundleLimitError::TooManyTransactions {
+                strategy: "liquidation",
+                txs: 2,
+                max_txs: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn signed_transactions_decode_back_to_their_senders() {
+        // Anvil/Hardhat accounts #0 and #1
+        let searcher: LocalWallet =
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+                .parse()
+                .unwrap();
+        let victim: LocalWallet =
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+                .parse()
+                .unwrap();
+
+        // The victim's own EIP-1559 transaction, already signed
+        let victim_tx = ethers::types::Eip1559TransactionRequest::new()
+            .to(Address::repeat_byte(0x7a))
+            .nonce(9)
+            .gas(150_000)
+            .max_fee_per_gas(U256::exp10(10))
+            .max_priority_fee_per_gas(U256::exp10(9));
+        let raw_victim = sign_transaction(victim_tx.into(), &victim, 1).unwrap();
+
+        let frontrun = TransactionRequest::new()
+            .to(Address::repeat_byte(0x7a))
+            .nonce(3)
+            .gas(200_000)
+            .gas_price(U256::exp10(10));
+        let bundle = MEVBundle {
+            target_block: U64::from(7),
+            ..bundle_of(vec![
+                BundleTx::new(TxRole::Frontrun, frontrun),
+                BundleTx::new(TxRole::Victim, BundleTransaction::Raw(raw_victim.clone())),
+            ])
+        };
+
+        let encoded = encode_signed_transactions(&bundle, &searcher, 1).unwrap();
+        assert_eq!(encoded.len(), 2);
+        assert_eq!(encoded[1], raw_victim);
+
+        let decode = |raw: &Bytes| {
+            let mut tx: ethers::types::Transaction = ethers::utils::rlp::decode(raw).unwrap();
+            tx.recover_from_mut().unwrap();
+            tx
+        };
+        let ours = decode(&encoded[0]);
+        assert_eq!(ours.from, searcher.address());
+        assert_eq!(ours.nonce, U256::from(3));
+        assert_eq!(ours.chain_id, Some(U256::one()));
+        assert_eq!(ours.transaction_type, None);
+
+        let theirs = decode(&encoded[1]);
+        assert_eq!(theirs.from, victim.address());
+        assert_eq!(theirs.transaction_type, Some(U64::from(2)));
+    }
+
+    #[tokio::test]
+    async fn bundle_transactions_take_consecutive_nonces() {
+        // No allowances yet, so both swaps need an approval ahead of them
+        let mock = Arc::new(
+            approved_provider()
+                .with_response("eth_call", Bytes::from(vec![0; 32]))
+                .with_response(
+                    "eth_callBundle",
+                    serde_json::json!({ "coinbaseDiff": "1000000000000000000", "totalGasUsed": 1 }),
+                ),
+        );
+        let outcome = create_and_send_bundle(
+            arbitrage(),
+            &Provider::new(mock.clone()),
+            &ApprovalManager::new(Address::zero()),
+            &BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI)),
+            BundleSettings {
+                builder_bribe_percent: 10.0,
+                simulate_bundles: true,
+                ..settings()
+            },
+            &[],
+            SubmissionMode::Simulate,
+        )
+        .await
+        .unwrap();
+
+        // The simulated gas is reported with the submission
+        let SubmissionOutcome::Single(result) = outcome else {
+            panic!("expected a single submission");
+        };
+        assert_eq!(result.gas_used, Some(U256::one()));
+
+        // Two approvals, two swaps, and the bribe, numbered from the pending count
+        let simulated = mock.params("eth_callBundle");
+        let nonces: Vec<U256> = simulated[0][0]["txs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tx| serde_json::from_value(tx["nonce"].clone()).unwrap())
+            .collect();
+        assert_eq!(nonces, (7..12).map(U256::from).collect::<Vec<_>>());
+        assert_eq!(
+            mock.params("eth_getTransactionCount")[0],
+            serde_json::json!([Address::zero(), "pending"])
+        );
+    }
+
+    #[tokio::test]
+    async fn approval_is_cached_only_once_read_back_on_chain() {
+        let approvals = ApprovalManager::new(Address::zero());
+        let unapproved =
+            MockProvider::default().with_response("eth_call", Bytes::from(vec![0; 32]));
+        let unapproved = Provider::new(Arc::new(unapproved));
+        let approved = Provider::new(Arc::new(approved_provider()));
+        let swap = [(
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(9),
+            U256::exp10(18),
+        )];
+        let gas_price = U256::from(TEST_BASE_FEE_WEI);
+
+        // The first bundle approves; it failed to land, so the next approves again
+        for _ in 0..2 {
+            let needed = approvals
+                .required_approvals(&unapproved, &swap, gas_price)
+                .await;
+            assert_eq!(needed.len(), 1);
+        }
+
+        // Once the approval lands the allowance is read back and cached, so
+        // later bundles skip both the approval and the lookup
+        assert!(approvals
+            .required_approvals(&approved, &swap, gas_price)
+            .await
+            .is_empty());
+        assert!(approvals
+            .required_approvals(&unapproved, &swap, gas_price)
+            .await
+            .is_empty());
+        assert_eq!(unapproved.as_ref().call_count("eth_call"), 2);
+    }
+
+    #[test]
+    fn split_respects_gas_and_transaction_boundaries() {
+        let bundle = MEVBundle {
+            target_block: U64::from(7),
+            total_gas: U256::from(300_000),
+            builder_payment: U256::exp10(16),
+            ..bundle_of(vec![
+                BundleTx::new(
+                    TxRole::Swap,
+                    TransactionRequest::new().gas(100_000)
+                );
+                3
+            ])
+        };
+        let split = |max_gas, max_txs, splittable| {
+            bundle.split_to_limits(BundleLimits { max_gas, max_txs }, "arbitrage", splittable)
+        };
+
+        // At both limits exactly, the bundle is left whole
+        let parts = split(300_000, 3, false).unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0], bundle);
+        assert_eq!(parts[0].bundle_id, bundle.bundle_id);
+
+        // Past either limit, an atomic strategy can't fit
+        assert_eq!(
+            split(300_000, 2, false),
+            Err(BundleLimitError::TooManyTransactions {
+                strategy: "arbitrage",
+                txs: 3,
+                max_txs: 2,
+            })
+        );
+        assert_eq!(
+            split(299_999, 3, false),
+            Err(BundleLimitError::GasLimitExceeded {
+                strategy: "arbitrage",
+                gas: U256::from(300_000),
+                max_gas: 299_999,
+            })
+        );
+
+        // A splittable one fills parts in order, paying off in the last
+        let parts = split(299_999, 3, true).unwrap();
+        let shape: Vec<_> = parts
+            .iter()
+            .map(|part| (part.target_block.as_u64(), part.transactions.len()))
+            .collect();
+        assert_eq!(shape, [(7, 2), (8, 1)]);
+        assert_eq!(parts[0].total_gas, U256::from(200_000));
+        assert!(parts[0].expected_profit.is_zero() && parts[0].builder_payment.is_zero());
+        assert_eq!(parts[1].expected_profit, bundle.expected_profit);
+        assert_eq!(parts[1].builder_payment, bundle.builder_payment);
+
+        let parts = split(300_000, 1, true).unwrap();
+        assert_eq!(parts.len(), 3);
+
+        // No split helps when one transaction alone is over the gas limit
+        assert_eq!(
+            split(99_999, 3, true),
+            Err(BundleLimitError::GasLimitExceeded {
+                strategy: "arbitrage",
+                gas: U256::from(100_000),
+                max_gas: 99_999,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn accepted_opportunity_is_not_rebuilt() {
+        let mock = Arc::new(approved_provider());
+        let provider = Provider::new(mock.clone());
+        let base_fees = BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI));
+        let approvals = ApprovalManager::new(Address::zero());
+        let settings = settings();
+        let send = || {
+            create_and_send_bundle(
+                arbitrage(),
+                &provider,
+                &approvals,
+                &base_fees,
+                settings.clone(),
+                &[],
+                SubmissionMode::Simulate,
+            )
+        };
+
+        assert!(matches!(
+            send().await.unwrap(),
+            SubmissionOutcome::Single(_)
+        ));
+        let built = mock.call_count("eth_getTransactionCount");
+        assert!(matches!(
+            send().await.unwrap(),
+            SubmissionOutcome::Duplicate
+        ));
+        assert_eq!(mock.call_count("eth_getTransactionCount"), built);
+    }
+
+    #[tokio::test]
+    async fn rejected_bundle_may_be_rebuilt_and_retried() {
+        let provider = Provider::new(approved_provider());
+        let base_fees = BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI));
+        let approvals = ApprovalManager::new(Address::zero());
+        let settings = settings();
+
+        // No relay accepts, so nothing is recorded as sent
+        for _ in 0..2 {
+            let sent = create_and_send_bundle(
+                arbitrage(),
+                &provider,
+                &approvals,
+                &base_fees,
+                settings.clone(),
+                &[],
+                SubmissionMode::Sequential,
+            )
+            .await;
+            assert!(sent.unwrap_err().to_string().contains("any relay"));
+        }
+        let key = SentBundles::opportunity_key(&arbitrage(), base_fees.block_number() + 1);
+        assert!(!settings
+            .sent_bundles
+            .was_sent(key, base_fees.block_number() + 1));
+    }
+
+    #[test]
+    fn sent_bundles_expire_with_their_target_block() {
+        let sent = SentBundles::default();
+        let key = SentBundles::opportunity_key(&arbitrage(), U64::from(10));
+        assert_ne!(
+            key,
+            SentBundles::opportunity_key(&arbitrage(), U64::from(11))
+        );
+
+        sent.record(&[key], U64::from(10));
+        assert!(sent.was_sent(key, U64::from(10)));
+        assert!(!sent.was_sent(key, U64::from(11)));
+        assert!(!sent.was_sent(key, U64::from(10)));
+    }
+
+    #[test]
+    fn sandwiches_are_deduplicated_by_victim() {
+        let sent = SentBundles::default();
+        let target = U64::from(10);
+        sent.record(
+            &[SentBundles::opportunity_key(&sandwich(7), target)],
+            target,
+        );
+
+        // A second detection around the same victim is the same opportunity
+        assert!(sent.was_sent(SentBundles::opportunity_key(&sandwich(7), target), target));
+        assert!(!sent.was_sent(SentBundles::opportunity_key(&sandwich(8), target), target));
+
+        // The key covers the victim under its public name
+        let json = serde_json::to_value(sandwich(7)).unwrap();
+        assert_eq!(
+            json["Sandwich"]["victim_tx_hash"],
+            serde_json::to_value(TxHash::from_low_u64_be(7)).unwrap()
+        );
+        assert!(json["Sandwich"].get("_victim_tx_hash").is_none());
+    }
+
+    /// Enabled relays named in `latencies`, with each one's latency in seconds.
+    fn relays_with_latency(latencies: &[(&str, u64)]) -> (Vec<RelayConfig>, HashMap<String, u64>) {
+        let relays = latencies
+            .iter()
+            .map(|(name, _)| RelayConfig {
+                name: name.to_string(),
+                endpoint: String::new(),
+                signing_key: None,
+                key_var: "",
+                enabled: true,
+            })
+            .collect();
+        let latencies = latencies
+            .iter()
+            .map(|(name, secs)| (name.to_string(), *secs))
+            .collect();
+        (relays, latencies)
+    }
+
+    /// Broadcasts a bundle to relays that answer after their latency; `eden`
+    /// rejects it.
+    async fn broadcast(
+        wait: BroadcastWait,
+        latencies: &[(&str, u64)],
+    ) -> Vec<(String, Result<SubmissionResult, String>)> {
+        let (relays, latencies) = relays_with_latency(latencies);
+        let bundle = create_liquidation_bundle(
+            liquidation(Address::from_low_u64_be(0xdeb7)),
+            &lending_from(&[FlashLoanProvider::AaveV3]),
+            U64::one(),
+            U256::one(),
+        )
+        .unwrap();
+
+        broadcast_with(
+            &bundle,
+            &relays,
+            wait,
+            Duration::from_secs(10),
+            &Mutex::default(),
+            |bundle, relay| {
+                let latency = Duration::from_secs(latencies[&relay.name]);
+                async move {
+                    tokio::time::sleep(latency).await;
+                    anyhow::ensure!(relay.name != "eden", "bundle rejected");
+                    Ok(SubmissionResult::submitted(
+                        &bundle,
+                        &relay.name,
+                        relay.name.clone(),
+                        0.5,
+                    ))
+                }
+            },
+        )
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn relay_requests_stay_within_the_concurrency_cap() {
+        let permits = RelayPermits::new(2);
+        let (in_flight, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let (relays, _) = relays_with_latency(&[("flashbots", 0), ("bloXroute", 0), ("eden", 0)]);
+        let bundle = create_liquidation_bundle(
+            liquidation(Address::from_low_u64_be(0xdeb7)),
+            &lending_from(&[FlashLoanProvider::AaveV3]),
+            U64::one(),
+            U256::one(),
+        )
+        .unwrap();
+        let metrics = Mutex::default();
+        let start = tokio::time::Instant::now();
+        let due = start + BLOCK_TIME;
+
+        // A burst of four bundles broadcast to three relays: twelve requests,
+        // each taking a second once it holds a permit
+        let burst = (0..4).map(|_| {
+            broadcast_with(
+                &bundle,
+                &relays,
+                BroadcastWait::All,
+                Duration::from_secs(30),
+                &metrics,
+                |bundle, relay| {
+                    let permits = permits.clone();
+                    let (in_flight, peak) = (in_flight.clone(), peak.clone());
+                    async move {
+                        let _permit = permits.acquire(&relay.name, due).await?;
+                        let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        Ok(SubmissionResult::submitted(
+                            &bundle,
+                            &relay.name,
+                            relay.name.clone(),
+                            0.5,
+                        ))
+                    }
+                },
+            )
+        });
+        let results = futures::future::join_all(burst).await;
+
+        assert!(results
+            .iter()
+            .flatten()
+            .all(|(_, response)| response.is_ok()));
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(start.elapsed(), Duration::from_secs(6));
+
+        // Saturated until the target block is due: the queued request gives up
+        let _held = (
+            permits.acquire("flashbots", due).await.unwrap(),
+            permits.acquire("flashbots", due).await.unwrap(),
+        );
+        let err = permits.acquire("eden", due).await.unwrap_err();
+        assert!(err.to_string().contains("--max-relay-concurrency"));
+        assert_eq!(start.elapsed(), BLOCK_TIME);
+    }
+
+    #[test]
+    fn adaptive_order_favors_the_best_recent_inclusion_rate() {
+        let (relays, _) = relays_with_latency(&[("flashbots", 0), ("bloXroute", 0), ("eden", 0)]);
+        let names = |ranked: Vec<RelayConfig>| -> Vec<String> {
+            ranked.into_iter().map(|relay| relay.name).collect()
+        };
+        let mut metrics = MEVMetrics::default();
+        relay_stats::seed(
+            &mut metrics.relay_stats,
+            &Default::default(),
+            &["flashbots", "bloXroute", "eden"],
+        );
+        assert_eq!(
+            names(rank_relays(&relays, &metrics.relay_stats)),
+            ["flashbots", "bloXroute", "eden"]
+        );
+
+        // Flashbots' bundles stop landing; bloXroute keeps delivering
+        let metrics = Mutex::new(metrics);
+        let bundle = create_liquidation_bundle(
+            liquidation(Address::from_low_u64_be(0xdeb7)),
+            &lending_from(&[FlashLoanProvider::AaveV3]),
+            U64::one(),
+            U256::one(),
+        )
+        .unwrap();
+        let submitted = |status| SubmissionResult {
+            status,
+            ..SubmissionResult::submitted(&bundle, "", String::new(), 0.5)
+        };
+        for _ in 0..10 {
+            record_relay_answer(
+                &metrics,
+                "flashbots",
+                Some(&submitted(SubmissionStatus::Expired)),
+            );
+            record_relay_answer(
+                &metrics,
+                "bloXroute",
+                Some(&submitted(SubmissionStatus::Included)),
+            );
+        }
+        let mut metrics = metrics.into_inner().unwrap();
+        assert_eq!(
+            names(rank_relays(&relays, &metrics.relay_stats)),
+            ["bloXroute", "eden", "flashbots"]
+        );
+
+        // A relay that rejects everything drops behind, whatever it once landed
+        (0..10).for_each(|_| metrics.record_relay_response("bloXroute", false));
+        assert_eq!(
+            names(rank_relays(&relays, &metrics.relay_stats)),
+            ["eden", "bloXroute", "flashbots"]
+        );
+        assert_eq!(metrics.relay_stats["bloXroute"].submissions, 20);
+    }
+
+    #[tokio::test]
+    async fn inclusion_is_recorded_from_receipts_after_the_target_block() {
+        let bundle = create_liquidation_bundle(
+            liquidation(Address::from_low_u64_be(0xdeb7)),
+            &lending_from(&[FlashLoanProvider::AaveV3]),
+            U64::from(12),
+            U256::one(),
+        )
+        .unwrap();
+        let landed = H256::repeat_byte(0x1a);
+        let sent = |relay: &str| SubmissionResult {
+            landing_tx: Some(landed),
+            ..SubmissionResult::submitted(&bundle, relay, String::new(), 0.5)
+        };
+        let outcome = SubmissionOutcome::Multi(MultiSubmissionResult {
+            per_relay: vec![
+                ("flashbots".to_string(), Ok(sent("flashbots"))),
+                ("bloXroute".to_string(), Ok(sent("bloXroute"))),
+                ("eden".to_string(), Err("rejected".to_string())),
+            ],
+            best: None,
+        });
+
+        // The head has reached the target block
+        let base_fees = BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI));
+        base_fees.observe_block(&ethers::types::Block {
+            number: Some(U64::from(12)),
+            base_fee_per_gas: Some(U256::from(TEST_BASE_FEE_WEI)),
+            gas_used: U256::one(),
+            gas_limit: U256::from(2),
+            ..Default::default()
+        });
+        let receipt = ethers::types::TransactionReceipt {
+            transaction_hash: landed,
+            block_number: Some(U64::from(12)),
+            status: Some(U64::one()),
+            ..Default::default()
+        };
+        let mock =
+            Arc::new(MockProvider::default().with_response("eth_getTransactionReceipt", receipt));
+        let metrics = Mutex::new(MEVMetrics::default());
+        track_inclusion(&Provider::new(mock.clone()), &base_fees, outcome, &metrics).await;
+
+        // Both accepting relays are credited; the rejecting one has no outcome
+        assert_eq!(mock.call_count("eth_getTransactionReceipt"), 2);
+        let metrics = metrics.into_inner().unwrap();
+        for relay in ["flashbots", "bloXroute"] {
+            let stats = &metrics.relay_stats[relay];
+            assert_eq!((stats.outcomes, stats.included), (1, 1));
+        }
+        assert!(!metrics.relay_stats.contains_key("eden"));
+    }
+
+    #[tokio::test]
+    async fn missing_receipt_records_a_missed_inclusion() {
+        let bundle = create_liquidation_bundle(
+            liquidation(Address::from_low_u64_be(0xdeb7)),
+            &lending_from(&[FlashLoanProvider::AaveV3]),
+            U64::from(12),
+            U256::one(),
+        )
+        .unwrap();
+        let outcome = SubmissionOutcome::Single(SubmissionResult {
+            landing_tx: Some(H256::repeat_byte(0x2b)),
+            ..SubmissionResult::submitted(&bundle, "flashbots", String::new(), 0.5)
+        });
+        let base_fees = BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI));
+        base_fees.observe_block(&ethers::types::Block {
+            number: Some(U64::from(13)),
+            base_fee_per_gas: Some(U256::from(TEST_BASE_FEE_WEI)),
+            gas_used: U256::one(),
+            gas_limit: U256::from(2),
+            ..Default::default()
+        });
+        let mock = MockProvider::default()
+            .with_response("eth_getTransactionReceipt", serde_json::Value::Null);
+        let metrics = Mutex::new(MEVMetrics::default());
+        track_inclusion(&Provider::new(mock), &base_fees, outcome, &metrics).await;
+
+        let stats = &metrics.into_inner().unwrap().relay_stats["flashbots"];
+        assert_eq!((stats.outcomes, stats.included), (1, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn broadcast_records_slow_relays_as_timed_out() {
+        let start = tokio::time::Instant::now();
+        let per_relay = broadcast(
+            BroadcastWait::All,
+            &[("flashbots", 60), ("bloXroute", 1), ("eden", 2)],
+        )
+        .await;
+
+        // Bounded by the timeout, not the hung relay, and in priority order
+        assert_eq!(start.elapsed(), Duration::from_secs(10));
+        let names: Vec<&str> = per_relay.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["flashbots", "bloXroute", "eden"]);
+        assert!(per_relay[0].1.as_ref().unwrap_err().contains("timed out"));
+        assert!(per_relay[1].1.is_ok());
+        assert_eq!(per_relay[2].1.as_ref().unwrap_err(), "bundle rejected");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn broadcast_can_return_at_first_acceptance() {
+        let start = tokio::time::Instant::now();
+        let per_relay = broadcast(
+            BroadcastWait::First,
+            &[("flashbots", 5), ("bloXroute", 3), ("eden", 1)],
+        )
+        .await;
+
+        // Eden's earlier rejection doesn't end the wait; bloXroute's acceptance does
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+        assert!(per_relay[0]
+            .1
+            .as_ref()
+            .unwrap_err()
+            .contains("no response yet"));
+        assert_eq!(per_relay[1].1.as_ref().unwrap().relay, "bloXroute");
+        assert_eq!(per_relay[2].1.as_ref().unwrap_err(), "bundle rejected");
+        assert_eq!(MultiSubmissionResult::new(per_relay).accepted(), 1);
+    }
+
+    #[tokio::test]
+    async fn gas_limits_come_from_estimates() {
+        let provider = Provider::new(approved_provider());
+        let approvals = ApprovalManager::new(Address::zero());
+        let mut bundle = create_arbitrage_bundle(
+            arbitrage(),
+            &provider,
+            &approvals,
+            &mainnet_routers(),
+            U64::one(),
+            U256::one(),
+            U256::from(60),
+        )
+        .await
+        .unwrap();
+
+        let total = estimate_bundle_gas(&provider, Address::zero(), &mut bundle.transactions, 1.2)
+            .await
+            .unwrap();
+
+        // Allowances suffice, so only the two swaps remain, each at 150k * 1.2
+        assert_eq!(bundle.transactions.len(), 2);
+        assert_eq!(total, U256::from(360_000));
+    }
+
+    #[tokio::test]
+    async fn sandwich_bundle_wraps_victim() {
+        let raw_victim = Bytes::from(vec![0x02, 0xf8, 0x70]);
+        let provider = Provider::new(
+            approved_provider().with_response("eth_getRawTransactionByHash", raw_victim.clone()),
+        );
+        let opportunity = sandwich(7);
+
+        let bundle = create_sandwich_bundle(
+            opportunity,
+            &provider,
+            &ApprovalManager::new(Address::zero()),
+            &settings(),
+            U64::one(),
+            U256::one(),
+            U256::from(60),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(bundle.transactions.len(), 3);
+        assert!(bundle.transactions[0].request.as_request().is_some());
+        assert!(
+            matches!(&bundle.transactions[1].request, BundleTransaction::Raw(raw) if *raw == raw_victim)
+        );
+        assert!(bundle.transactions[2].request.as_request().is_some());
+    }
+
+    #[tokio::test]
+    async fn mempool_victim_backrun_is_guarded_by_min_out() {
+        let provider = Provider::new(approved_provider());
+        let mut opportunity = sandwich(7);
+        if let MEVOpportunity::Sandwich { backrun_amount, .. } = &mut opportunity {
+            *backrun_amount = U256::exp10(21);
+        }
+        let settings = BundleSettings {
+            victim_inclusion: VictimInclusion::Mempool,
+            ..settings()
+        };
+
+        let bundle = create_sandwich_bundle(
+            opportunity,
+            &provider,
+            &ApprovalManager::new(Address::zero()),
+            &settings,
+            U64::one(),
+            U256::one(),
+            U256::from(60),
+        )
+        .await
+        .unwrap();
+
+        // Without the victim in the bundle only the guard ties the backrun to
+        // it: selling back under the frontrun's cost reverts the swap
+        assert_eq!(bundle.transactions.len(), 2);
+        let decode = |index: usize| {
+            let data = bundle.transactions[index]
+                .request
+                .as_request()
+                .and_then(|request| request.data.clone())
+                .unwrap();
+            abi::decode(
+                &[
+                    ParamType::Uint(256),
+                    ParamType::Uint(256),
+                    ParamType::Array(Box::new(ParamType::Address)),
+                    ParamType::Address,
+                    ParamType::Uint(256),
+                ],
+                &data[4..],
+            )
+            .unwrap()
+        };
+        let backrun = decode(1);
+        assert_eq!(backrun[0], Token::Uint(U256::exp10(21)));
+        assert_eq!(backrun[1], Token::Uint(U256::exp10(19)));
+        assert_eq!(decode(0)[1], Token::Uint(U256::zero()));
+    }
+
+    #[test]
+    fn coinbase_payment_is_deducted_from_profit() {
+        let mut bundle = bundle_of(vec![BundleTx::new(TxRole::Swap, TransactionRequest::new())]);
+
+        append_coinbase_payment(&mut bundle, 10.0, settings().executor, U256::one()).unwrap();
+
+        let payment = U256::exp10(17);
+        assert_eq!(bundle.transactions.len(), 2);
+        let bribe_tx = bundle.transactions[1].request.as_request().unwrap();
+        assert_eq!(bribe_tx.value, Some(payment));
+        assert_eq!(bundle.builder_payment, payment);
+        assert_eq!(bundle.expected_profit, U256::exp10(18) - payment);
+        assert!(validate_bundle(&bundle, U256::zero(), 50).is_ok());
+    }
+
+    #[test]
+    fn bribe_leaving_no_profit_is_rejected() {
+        let mut bundle = bundle_of(vec![BundleTx::new(TxRole::Swap, TransactionRequest::new())]);
+
+        append_coinbase_payment(&mut bundle, 100.0, settings().executor, U256::one()).unwrap();
+
+        assert!(validate_bundle(&bundle, U256::zero(), 50).is_err());
+    }
+
+    #[test]
+    fn fee_share_is_transferred_ahead_of_the_builder_payment() {
+        let mut bundle = bundle_of(vec![BundleTx::new(TxRole::Swap, TransactionRequest::new())]);
+        let recipient = Address::repeat_byte(0xfe);
+
+        // 10% to the builder, then a quarter of the remaining 0.9 ETH as the fee
+        append_coinbase_payment(&mut bundle, 10.0, settings().executor, U256::one()).unwrap();
+        append_fee_transfer(&mut bundle, recipient, 2_500, U256::one());
+
+        let fee = U256::exp10(15) * 225;
+        assert_eq!(bundle.transactions.len(), 3);
+        let transfer = bundle.transactions[1].request.as_request().unwrap();
+        assert_eq!(transfer.to, Some(recipient.into()));
+        assert_eq!(transfer.value, Some(fee));
+        assert_eq!(
+            bundle.transactions[2].request.as_request().unwrap().value,
+            Some(U256::exp10(17))
+        );
+        assert_eq!(bundle.fee_payment, fee);
+        assert_eq!(bundle.expected_profit, U256::exp10(15) * 675);
+        assert_eq!(bundle.total_gas, U256::from(251_000));
+
+        // The fee is a cost: 0.675 ETH clears 0.1 ETH of bribe by 675%, but
+        // the 0.325 ETH paid out in all by only ~208%
+        assert!(validate_bundle(&bundle, U256::zero(), 20_000).is_ok());
+        assert!(validate_bundle(&bundle, U256::zero(), 25_000).is_err());
+    }
+
+    /// A bundle of `transactions` for block 1 using 200k gas and netting
+    /// 1 ETH, with no timestamp bounds, payments, or flash loan; tests
+    /// override the fields they exercise.
+    fn bundle_of(transactions: Vec<BundleTx>) -> MEVBundle {
+        MEVBundle {
+            transactions,
+            target_block: U64::one(),
+            min_timestamp: None,
+            max_timestamp: None,
+            bundle_id: generate_bundle_id(),
+            total_gas: U256::from(200_000),
+            expected_profit: U256::exp10(18),
+            builder_payment: U256::zero(),
+            fee_payment: U256::zero(),
+            flash_loan: None,
+        }
+    }
+
+    /// A profitable bundle of empty transactions playing `roles`, in order.
+    fn sequenced(roles: &[TxRole]) -> MEVBundle {
+        bundle_of(
+            roles
+                .iter()
+                .map(|role| BundleTx::new(*role, TransactionRequest::new()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn roles_in_execution_order_pass_the_sequence_check() {
+        use TxRole::*;
+
+        for roles in [
+            &[Approve, Approve, Frontrun, Victim, Backrun, FeeShare, Bribe][..],
+            &[Frontrun, Backrun][..],
+            &[FlashLoan, Liquidate, Repay, Bribe][..],
+            &[Approve, Swap, Swap, Swap][..],
+        ] {
+            assert!(sequenced(roles).check_sequence().is_ok(), "{roles:?}");
+        }
+    }
+
+    #[test]
+    fn roles_out_of_order_fail_the_sequence_check() {
+        use TxRole::*;
+
+        let err = sequenced(&[FlashLoan, Repay, Liquidate])
+            .check_sequence()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Bundle step 2 (liquidate) is sequenced after step 1 (repay)"
+        );
+        for roles in [
+            &[Frontrun, Backrun, Victim][..],
+            &[Swap, Approve][..],
+            &[Frontrun, Victim, Bribe, Backrun][..],
+            &[Swap, Bribe, FeeShare][..],
+        ] {
+            assert!(sequenced(roles).check_sequence().is_err(), "{roles:?}");
+        }
+
+        // A flash loan is repaid in the bundle that takes it
+        assert!(sequenced(&[FlashLoan, Liquidate]).check_sequence().is_err());
+        assert!(sequenced(&[Liquidate, Repay]).check_sequence().is_err());
+
+        // Bundle validation applies the check
+        let bundle = sequenced(&[Backrun, Frontrun]);
+        assert!(validate_bundle(&bundle, U256::zero(), 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn bundles_built_back_to_back_get_distinct_ids() {
+        let provider = Provider::new(approved_provider());
+        let approvals = ApprovalManager::new(Address::zero());
+        let routers = mainnet_routers();
+        let build = || {
+            create_arbitrage_bundle(
+                arbitrage(),
+                &provider,
+                &approvals,
+                &routers,
+                U64::one(),
+                U256::one(),
+                U256::from(60),
+            )
+        };
+
+        // The same opportunity within the same second still gets a fresh ID
+        let first = build().await.unwrap();
+        let second = build().await.unwrap();
+        assert_ne!(first.bundle_id, second.bundle_id);
+        assert!(Uuid::parse_str(&first.bundle_id).is_ok());
+    }
+
+    #[tokio::test]
+    async fn swaps_carry_future_deadline() {
+        let provider = Provider::new(approved_provider());
+        let deadline = U256::from(1_700_000_060u64);
+        let bundle = create_arbitrage_bundle(
+            arbitrage(),
+            &provider,
+            &ApprovalManager::new(Address::zero()),
+            &mainnet_routers(),
+            U64::one(),
+            U256::one(),
+            deadline,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(bundle.max_timestamp, Some(deadline));
+        for tx in bundle
+            .transactions
+            .iter()
+            .filter_map(|tx| tx.request.as_request())
+        {
+            // Deadline is the fifth head word of swapExactTokensForTokens
+            let data = tx.data.as_ref().unwrap();
+            assert_eq!(
+                U256::from_big_endian(&data[4 + 4 * 32..4 + 5 * 32]),
+                deadline
+            );
+        }
+        assert!(validate_bundle(&bundle, deadline - 60, 50).is_ok());
+    }
+
+    #[test]
+    fn expired_deadline_is_rejected() {
+        let bundle = MEVBundle {
+            max_timestamp: Some(U256::from(1000)),
+            ..bundle_of(vec![BundleTx::new(TxRole::Swap, TransactionRequest::new())])
+        };
+
+        assert!(validate_bundle(&bundle, U256::from(999), 50).is_ok());
+        assert!(validate_bundle(&bundle, U256::from(1000), 50).is_err());
+    }
+
+    #[test]
+    fn out_of_range_bundle_fields_are_errors() {
+        let mut bundle = MEVBundle {
+            max_timestamp: Some(U256::MAX),
+            ..bundle_of(vec![
+                BundleTx::new(
+                    TxRole::Swap,
+                    TransactionRequest::new().gas(U256::MAX)
+                );
+                2
+            ])
+        };
+
+        // A timestamp beyond the relays' 64-bit field fails validation
+        let err = validate_bundle(&bundle, U256::zero(), 50).unwrap_err();
+        assert!(err.to_string().contains("timestamp"));
+
+        // So does node-supplied gas beyond u64, instead of overflowing
+        bundle.max_timestamp = Some(U256::from(1000));
+        let err = validate_bundle(&bundle, U256::zero(), 50).unwrap_err();
+        assert!(err.to_string().contains("gas usage"));
+
+        bundle.transactions = vec![BundleTx::new(
+            TxRole::Swap,
+            TransactionRequest::new().gas(100_000),
+        )];
+        assert!(validate_bundle(&bundle, U256::zero(), 50).is_ok());
+    }
+
+    #[test]
+    fn flashbots_json_matches_the_relay_payload() {
+        let bundle = MEVBundle {
+            target_block: U64::from(0xb63dcd),
+            min_timestamp: Some(U256::zero()),
+            max_timestamp: Some(U256::from(1_615_920_932u64)),
+            ..bundle_of(vec![
+                BundleTx::new(
+                    TxRole::Swap,
+                    BundleTransaction::Raw(Bytes::from(vec![0x12, 0x3a, 0xbc])),
+                ),
+                BundleTx::new(
+                    TxRole::Swap,
+                    BundleTransaction::Raw(Bytes::from(vec![0x45, 0x6d, 0xef])),
+                ),
+            ])
+        };
+
+        // The eth_sendBundle example from the Flashbots RPC docs
+        let expected: serde_json::Value = serde_json::from_str(
+            r#"{
+                "txs": ["0x123abc", "0x456def"],
+                "blockNumber": "0xb63dcd",
+                "minTimestamp": 0,
+                "maxTimestamp": 1615920932
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(bundle.to_flashbots_json(), expected);
+
+        // Unset bounds are left out rather than sent as null
+        let unbounded = MEVBundle {
+            min_timestamp: None,
+            max_timestamp: None,
+            ..bundle
+        };
+        assert_eq!(
+            unbounded.to_flashbots_json(),
+            serde_json::json!({ "txs": ["0x123abc", "0x456def"], "blockNumber": "0xb63dcd" })
+        );
+    }
+
+    #[tokio::test]
+    async fn liquidation_of_a_healed_position_is_aborted() {
+        // Detected while the position is underwater
+        let detected =
+            Arc::new(MockProvider::default().with_response("eth_call", account_data(0.97)));
+        let ctx = mock::analysis_context(detected);
+        let priced = ethers::types::Transaction {
+            gas_price: Some(U256::exp10(10)),
+            ..Default::default()
+        };
+        let opportunity = searcher::evaluate_opportunity(&ctx, &priced)
+            .await
+            .expect("underwater position should be liquidatable");
+        assert!(matches!(
+            opportunity,
+            MEVOpportunity::Liquidation { health_factor, .. } if health_factor == 0.97
+        ));
+
+        // By submission the borrower has topped up; had they not, it is sent
+        for (health_factor, healed) in [(1.02, true), (0.98, false)] {
+            let mock = Arc::new(
+                approved_provider().with_response("eth_call", account_data(health_factor)),
+            );
+            let outcome = create_and_send_bundle(
+                opportunity.clone(),
+                &Provider::new(mock.clone()),
+                &ApprovalManager::new(Address::zero()),
+                &ctx.base_fees,
+                settings(),
+                &[],
+                SubmissionMode::Simulate,
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                matches!(outcome, SubmissionOutcome::Healed),
+                healed,
+                "{outcome:?}"
+            );
+
+            // A healed position is caught before anything is built
+            assert_eq!(mock.call_count("eth_getTransactionCount") == 0, healed);
+        }
+    }
+
+    #[test]
+    fn strategy_settings_follow_the_live_config() {
+        let mut config = MEVConfig {
+            builder_bribe_percent: 5.0,
+            fee_bps: 100,
+            profit_margin_bps: 25,
+            ..MEVConfig::default()
+        };
+        config.liquidation.flash_loan_providers = vec!["balancer".to_string()];
+
+        let live = settings().with_strategy(&config);
+        assert_eq!(live.builder_bribe_percent, 5.0);
+        assert_eq!(live.fee_bps, 100);
+        assert_eq!(live.profit_margin_bps, 25);
+        assert_eq!(live.flash_loan_providers, [FlashLoanProvider::Balancer]);
+
+        // The lower gas price ceiling wins, whichever config holds it
+        assert_eq!(live.max_gas_price_gwei, 200);
+        config.max_gas_price_gwei = 500;
+        assert_eq!(settings().with_strategy(&config).max_gas_price_gwei, 300);
+    }
+
+    #[tokio::test]
+    async fn runtime_gas_ceiling_caps_bundle_prices() {
+        let provider = Provider::new(approved_provider());
+        let base_fees = BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI));
+        let base_fee_gwei = TEST_BASE_FEE_WEI / 1_000_000_000;
+        let config = MEVConfig {
+            max_gas_price_gwei: base_fee_gwei,
+            ..MEVConfig::default()
+        };
+
+        // The gas config alone would allow it; the strategy ceiling doesn't
+        let sent = create_and_send_bundle(
+            arbitrage(),
+            &provider,
+            &ApprovalManager::new(Address::zero()),
+            &base_fees,
+            settings().with_strategy(&config),
+            &[],
+            SubmissionMode::Simulate,
+        )
+        .await;
+        assert!(sent.unwrap_err().to_string().contains("max_gas_price_gwei"));
+    }
+
+    #[tokio::test]
+    async fn liquidation_without_an_executor_is_only_simulated() {
+        let base_fees = &BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI));
+        let send = |mode| async move {
+            let mock = approved_provider().with_response("eth_call", account_data(0.98));
+            create_and_send_bundle(
+                liquidation(debt_token()),
+                &Provider::new(Arc::new(mock)),
+                &ApprovalManager::new(Address::zero()),
+                base_fees,
+                BundleSettings {
+                    executor: None,
+                    ..settings()
+                },
+                &[],
+                mode,
+            )
+            .await
+        };
+
+        let err = send(SubmissionMode::Sequential).await.unwrap_err();
+        assert!(err.to_string().contains("EXECUTOR_ADDRESS"), "{err}");
+        assert!(send(SubmissionMode::Simulate).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn bundle_underpaying_the_builder_in_simulation_is_rejected() {
+        let base_fees = &BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI));
+        let send = |coinbase_diff: &str| {
+            let mock = approved_provider().with_response(
+                "eth_callBundle",
+                serde_json::json!({ "coinbaseDiff": coinbase_diff, "totalGasUsed": 300_000 }),
+            );
+            async move {
+                create_and_send_bundle(
+                    arbitrage(),
+                    &Provider::new(Arc::new(mock)),
+                    &ApprovalManager::new(Address::zero()),
+                    base_fees,
+                    BundleSettings {
+                        builder_bribe_percent: 10.0,
+                        simulate_bundles: true,
+                        ..settings()
+                    },
+                    &[],
+                    SubmissionMode::Simulate,
+                )
+                .await
+            }
+        };
+
+        // A trade that returns less on-chain caps the payment below the bribe
+        let rejected = send("1000").await.unwrap_err().to_string();
+        assert!(
+            rejected.contains("below the intended builder payment"),
+            "{rejected}"
+        );
+
+        // Priority fees on top of the full bribe clear it
+        let outcome = send("100000000000000000").await.unwrap();
+        assert!(matches!(outcome, SubmissionOutcome::Single(_)));
+    }
+
+    fn liquidation(debt_token: Address) -> MEVOpportunity {
+        MEVOpportunity::Liquidation {
+            protocol: Protocol::Aave,
+            position_owner: Address::from_low_u64_be(0xb0b),
+            collateral_token: Address::from_low_u64_be(0xc011),
+            debt_token,
+            collateral_amount: U256::exp10(19),
+            debt_amount: U256::exp10(19),
+            repay_amount: U256::exp10(18),
+            liquidation_bonus_eth: U256::exp10(17),
+            health_factor: 0.95,
+            detected_block: U64::zero(),
+        }
+    }
+
+    #[test]
+    fn liquidation_borrows_from_cheapest_provider_and_repays_its_fee() {
+        let weth: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+            .parse()
+            .unwrap();
+        let flash_loan_target = |bundle: &MEVBundle| match &bundle.transactions[0].request {
+            BundleTransaction::Searcher(tx) => tx.to.clone(),
+            BundleTransaction::Raw(_) => None,
+        };
+
+        // dYdX lends WETH for 2 wei, undercutting Aave's 0.05%
+        let providers = [FlashLoanProvider::AaveV3, FlashLoanProvider::DyDx];
+        let bundle = create_liquidation_bundle(
+            liquidation(weth),
+            &lending_from(&providers),
+            U64::one(),
+            U256::one(),
+        )
+        .unwrap();
+        assert_eq!(
+            flash_loan_target(&bundle),
+            Some(FlashLoanProvider::DyDx.lender().unwrap().into())
+        );
+        assert_eq!(bundle.expected_profit, U256::exp10(17) - 2);
+
+        // Only Aave lends an arbitrary token, at 0.05% of the repayment
+        let other = Address::from_low_u64_be(0xdeb7);
+        let bundle = create_liquidation_bundle(
+            liquidation(other),
+            &lending_from(&providers),
+            U64::one(),
+            U256::one(),
+        )
+        .unwrap();
+        assert_eq!(
+            flash_loan_target(&bundle),
+            Some(FlashLoanProvider::AaveV3.lender().unwrap().into())
+        );
+        assert_eq!(
+            bundle.expected_profit,
+            U256::exp10(17) - U256::exp10(14) * 5
+        );
+
+        // The fee is owed in the debt token, so it's valued in ETH at the
+        // token's price: at half an ETH per token it costs half as much
+        let priced_at = |price_usd| {
+            let oracle = StaticPriceOracle::default().with_token_price(other, price_usd);
+            BundleSettings {
+                oracle: Arc::new(oracle),
+                ..lending_from(&providers)
+            }
+        };
+        let bundle = create_liquidation_bundle(
+            liquidation(other),
+            &priced_at(DEBT_TOKEN_PRICE_USD / 2.0),
+            U64::one(),
+            U256::one(),
+        )
+        .unwrap();
+        assert_eq!(
+            bundle.expected_profit,
+            U256::exp10(17) - U256::exp10(14) * 5 / 2
+        );
+
+        // A fee that can't be valued leaves the profit unknown
+        assert!(create_liquidation_bundle(
+            liquidation(other),
+            &priced_at(0.0),
+            U64::one(),
+            U256::one()
+        )
+        .is_err());
+
+        // No configured provider lends it at all
+        assert!(create_liquidation_bundle(
+            liquidation(other),
+            &lending_from(&[FlashLoanProvider::DyDx]),
+            U64::one(),
+            U256::one()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn flash_loan_repay_covers_principal_plus_fee() {
+        let debt_token = Address::from_low_u64_be(0xdeb7);
+        let mut bundle = create_liquidation_bundle(
+            liquidation(debt_token),
+            &lending_from(&[FlashLoanProvider::AaveV3]),
+            U64::one(),
+            U256::one(),
+        )
+        .unwrap();
+
+        // The loan is the only transaction; its callback liquidates, then
+        // approves the pool for principal plus the 0.05% premium
+        assert_eq!(bundle.transactions.len(), 1);
+        let loan = bundle.flash_loan.as_ref().unwrap();
+        let roles: Vec<TxRole> = loan.callback.iter().map(|call| call.role).collect();
+        assert_eq!(roles, [TxRole::Liquidate, TxRole::Repay]);
+        let principal = U256::exp10(18);
+        let owed = principal + U256::exp10(14) * 5;
+        let repay = loan.callback[1].request.as_request().unwrap();
+        assert_eq!(repay.to, Some(debt_token.into()));
+        let data = repay.data.as_ref().unwrap();
+        assert_eq!(data[..4], [0x09, 0x5e, 0xa7, 0xb3]);
+        assert_eq!(
+            abi::decode(&[ParamType::Address, ParamType::Uint(256)], &data[4..]).unwrap(),
+            vec![
+                Token::Address(FlashLoanProvider::AaveV3.lender().unwrap()),
+                Token::Uint(owed),
+            ]
+        );
+        assert!(validate_bundle(&bundle, U256::zero(), 0).is_ok());
+
+        // The repay call the loan carries is what's checked: one returning
+        // only the principal would revert the loan
+        let mut loan = bundle.flash_loan.clone().unwrap();
+        let short = create_flash_loan_repay_transaction(
+            &FlashLoan {
+                repayment: principal,
+                ..loan.clone()
+            },
+            debt_token,
+            U256::one(),
+        )
+        .unwrap();
+        loan.callback[1] = BundleTx::new(TxRole::Repay, short);
+        bundle.transactions[0] = BundleTx::new(
+            TxRole::FlashLoan,
+            create_flash_loan_transaction(&loan, debt_token, Address::zero(), U256::one()).unwrap(),
+        );
+        bundle.flash_loan = Some(loan);
+        let err = validate_bundle(&bundle, U256::zero(), 0).unwrap_err();
+        assert!(err.to_string().contains("does not cover"), "{err}");
+    }
+
+    #[test]
+    fn rebuilt_bundle_is_equal_and_hashes_the_same() {
+        let build = |debt_token, target_block| {
+            create_liquidation_bundle(
+                liquidation(debt_token),
+                &lending_from(&[FlashLoanProvider::AaveV3]),
+                target_block,
+                U256::one(),
+            )
+            .unwrap()
+        };
+        let debt_token = Address::from_low_u64_be(0xdeb7);
+
+        let first = build(debt_token, U64::one());
+        let rebuilt = build(debt_token, U64::one());
+        assert_ne!(first.bundle_id, rebuilt.bundle_id);
+        assert_eq!(first, rebuilt);
+        assert_eq!(first.content_hash(), rebuilt.content_hash());
+
+        let next_block = build(debt_token, U64::from(2));
+        assert_ne!(first, next_block);
+        assert_ne!(first.content_hash(), next_block.content_hash());
+
+        let weth = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+            .parse()
+            .unwrap();
+        let other_token = build(weth, U64::one());
+        assert_ne!(first, other_token);
+        assert_ne!(first.content_hash(), other_token.content_hash());
+
+        let mut bribed = first.clone();
+        bribed.builder_payment = U256::exp10(15);
+        assert_ne!(first, bribed);
+        assert_ne!(first.content_hash(), bribed.content_hash());
+    }
+
+    #[test]
+    fn malformed_contract_address_is_an_error() {
+        assert!(parse_address(positions::AAVE_V3_POOL).is_ok());
+        assert!(parse_address("0x7d2768dE32b0b80b7a3454c06BdAc94A69DDc7A").is_err());
+    }
+
+    #[tokio::test]
+    async fn submission_reports_bundle_gas_and_payment() {
+        // 100k gas at 10 gwei and 300k at 30 gwei average to 25 gwei; the
+        // victim's raw tx doesn't count
+        let gwei = U256::exp10(9);
+        let bundle = MEVBundle {
+            target_block: U64::from(7),
+            total_gas: U256::from(400_000),
+            expected_profit: U256::exp10(17),
+            builder_payment: U256::exp10(16),
+            ..bundle_of(vec![
+                BundleTx::new(
+                    TxRole::Frontrun,
+                    TransactionRequest::new().gas(100_000).gas_price(gwei * 10),
+                ),
+                BundleTx::new(
+                    TxRole::Victim,
+                    BundleTransaction::Raw(Bytes::from(vec![0x02])),
+                ),
+                BundleTx::new(
+                    TxRole::Backrun,
+                    TransactionRequest::new().gas(300_000).gas_price(gwei * 30),
+                ),
+            ])
+        };
+        assert_eq!(bundle.effective_gas_price(), gwei * 25);
+
+        let permits = RelayPermits::new(1);
+        for relay in resolve_relays(&[]).unwrap() {
+            let result = submit_to_relay(&bundle, &relay, &permits, tokio::time::Instant::now())
+                .await
+                .unwrap();
+            assert_eq!(result.effective_gas_price, gwei * 25, "{}", relay.name);
+            assert_eq!(result.total_gas, U256::from(400_000));
+            assert_eq!(result.builder_payment, U256::exp10(16));
+            assert_eq!(result.gas_used, None);
+        }
+    }
+
+    #[test]
+    fn bundle_profit_must_clear_margin() {
+        // 100k gas at 1 gwei costs 1e14 wei; a 50 bps margin is 5e11 wei
+        let mut bundle = MEVBundle {
+            total_gas: U256::from(100_000),
+            expected_profit: U256::from(500_000_000_000u64),
+            ..bundle_of(vec![BundleTx::new(
+                TxRole::Swap,
+                TransactionRequest::new()
+                    .gas(100_000)
+                    .gas_price(1_000_000_000u64),
+            )])
+        };
+        assert!(validate_bundle(&bundle, U256::zero(), 50).is_ok());
+
+        bundle.expected_profit -= U256::one();
+        assert!(validate_bundle(&bundle, U256::zero(), 50).is_err());
+        assert!(validate_bundle(&bundle, U256::zero(), 0).is_ok());
+    }
+
+    #[test]
+    fn cooldown_window_ends_after_window_blocks() {
+        let cooldown = OpportunityCooldown::new(3);
+        let opportunity = arbitrage();
+        let block = U64::from(100);
+
+        // The claim starts the window
+        assert!(cooldown.try_acquire(&opportunity, block));
+        assert!(!cooldown.try_acquire(&opportunity, block));
+        assert!(!cooldown.try_acquire(&opportunity, block + 2));
+        assert!(cooldown.try_acquire(&opportunity, block + 3));
+
+        // A released claim suppresses nothing
+        cooldown.release(&opportunity, block + 3);
+        assert!(cooldown.try_acquire(&opportunity, block + 3));
+    }
+
+    #[test]
+    fn concurrent_claims_let_one_through() {
+        let cooldown = Arc::new(OpportunityCooldown::new(3));
+        let start = Arc::new(std::sync::Barrier::new(2));
+        let claims: Vec<_> = (0..2)
+            .map(|_| {
+                let (cooldown, start) = (cooldown.clone(), start.clone());
+                std::thread::spawn(move || {
+                    start.wait();
+                    cooldown.try_acquire(&arbitrage(), U64::from(100))
+                })
+            })
+            .collect();
+
+        let granted: Vec<bool> = claims.into_iter().map(|c| c.join().unwrap()).collect();
+        assert_eq!(granted.iter().filter(|granted| **granted).count(), 1);
+    }
+
+    #[test]
+    fn cooldown_is_per_pair_and_strategy() {
+        let cooldown = OpportunityCooldown::new(3);
+        let block = U64::from(100);
+        assert!(cooldown.try_acquire(&arbitrage(), block));
+
+        let mut other_pair = arbitrage();
+        if let MEVOpportunity::Arbitrage { token_b, .. } = &mut other_pair {
+            *token_b = Address::from_low_u64_be(3);
+        }
+        assert!(cooldown.try_acquire(&other_pair, block));
+
+        // A zero window never suppresses
+        let disabled = OpportunityCooldown::new(0);
+        assert!(disabled.try_acquire(&arbitrage(), block));
+        assert!(disabled.try_acquire(&arbitrage(), block));
+    }
+
+    /// Nothing listens on port 1, so connections are refused immediately.
+    const CLOSED_RELAY: &str = "http://127.0.0.1:1";
+
+    /// Serves one HTTP request on a loopback port, answering `reply`, and
+    /// returns the endpoint with a handle yielding the raw request.
+    async fn one_shot_relay(reply: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let request = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read the headers, then as much body as they announce
+            loop {
+                let read = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length: usize = text
+                        .lines()
+                        .find_map(|line| {
+                            line.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().parse().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                reply.len(),
+                reply
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (endpoint, request)
+    }
+
+    fn flashbots_relay(endpoint: String) -> RelayConfig {
+        RelayConfig {
+            name: "flashbots".to_string(),
+            endpoint,
+            signing_key: Some(
+                "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".to_string(),
+            ),
+            key_var: "FLASHBOTS_SIGNING_KEY",
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn cancel_body_names_the_bundle_by_replacement_uuid() {
+        let body: serde_json::Value =
+            serde_json::from_str(&cancel_bundle_body("3f2a-bundle")).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_cancelBundle",
+                "params": [{ "replacementUuid": "3f2a-bundle" }],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn cancellation_is_sent_signed_to_the_relay() {
+        let (endpoint, request) = one_shot_relay(r#"{"jsonrpc":"2.0","id":1,"result":null}"#).await;
+        let relay = flashbots_relay(endpoint);
+        cancel_bundle("3f2a-bundle", &relay).await.unwrap();
+
+        let request = request.await.unwrap();
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        assert_eq!(body, cancel_bundle_body("3f2a-bundle"));
+
+        // Signed by the relay key over exactly this body
+        let signer: LocalWallet = relay.signing_key.unwrap().parse().unwrap();
+        let expected = flashbots_signature_header(&signer, body).await.unwrap();
+        assert!(head
+            .lines()
+            .any(|line| line.eq_ignore_ascii_case(&format!("x-flashbots-signature: {expected}"))));
+    }
+
+    #[tokio::test]
+    async fn refused_or_unsupported_cancellations_fail() {
+        let (endpoint, _request) = one_shot_relay(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"unknown uuid"}}"#,
+        )
+        .await;
+        let refused = cancel_bundle("3f2a-bundle", &flashbots_relay(endpoint)).await;
+        assert!(refused.unwrap_err().to_string().contains("unknown uuid"));
+
+        let mut unsigned = flashbots_relay(CLOSED_RELAY.to_string());
+        unsigned.signing_key = None;
+        assert!(cancel_bundle("3f2a-bundle", &unsigned).await.is_err());
+
+        let mut eden = flashbots_relay(CLOSED_RELAY.to_string());
+        eden.name = "eden".to_string();
+        assert!(cancel_bundle("3f2a-bundle", &eden).await.is_err());
+    }
+}
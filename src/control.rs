@@ -0,0 +1,418 @@
+//! Runtime control API for adjusting strategy parameters without a restart.
+//!
+//! Served over HTTP when `--control-addr` is given:
+//!
+//! * `GET /config` returns the live [`MEVConfig`] as JSON.
+//! * `PATCH /config` applies a [`ConfigPatch`] and returns the updated config.
+//! * `GET /metrics` returns the session's [`MEVMetrics`] in the Prometheus
+//!   text format, for scraping with a bearer token.
+//!
+//! Every request must carry `Authorization: Bearer <token>`. A patched config
+//! is checked with [`MEVConfig::validate`] before it replaces the live one, so
+//! a rejected change leaves the pipeline untouched. The patchable settings are
+//! read once per transaction, so accepted changes apply from the next one. On
+//! Unix, SIGHUP reloads the same config from file (see `reload`).
+
+use crate::types::{MEVConfig, MEVMetrics, SharedConfig};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+/// Path of the config resource.
+const CONFIG_PATH: &str = "/config";
+
+/// Path of the Prometheus metrics.
+const METRICS_PATH: &str = "/metrics";
+
+/// Content type of the Prometheus text exposition format.
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+// ---
+
+/// Runtime-adjustable subset of [`MEVConfig`]; omitted fields are unchanged.
+///
+/// Unknown fields are rejected, so a PATCH can't silently ignore a setting
+/// that only takes effect on restart.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigPatch {
+    // ---
+    /// Minimum net profit, in the config's profit denomination
+    #[serde(default, alias = "min_profit_eth")]
+    pub min_profit: Option<f64>,
+
+    /// Base fee ceiling in gwei above which transactions aren't analyzed
+    #[serde(default)]
+    pub max_gas_price_gwei: Option<u64>,
+
+    /// Arbitrage strategy toggle
+    #[serde(default)]
+    pub arbitrage: Option<StrategyPatch>,
+
+    /// Sandwich strategy toggle
+    #[serde(default)]
+    pub sandwich: Option<StrategyPatch>,
+
+    /// Liquidation strategy toggle
+    #[serde(default)]
+    pub liquidation: Option<StrategyPatch>,
+}
+
+/// Runtime-adjustable settings of one strategy.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyPatch {
+    // ---
+    /// Whether the strategy's opportunities are detected
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+/// Request handler holding the live config, the session metrics, and the
+/// token guarding them.
+#[derive(Debug)]
+pub struct ControlApi {
+    // ---
+    /// Config shared with the detectors
+    config: SharedConfig,
+
+    /// Metrics the running session accumulates into
+    metrics: Arc<Mutex<MEVMetrics>>,
+
+    /// Bearer token every request must present
+    token: String,
+}
+
+// ---
+
+impl ConfigPatch {
+    // ---
+
+    /// Writes the patched fields into `config`.
+    pub fn apply(&self, config: &mut MEVConfig) {
+        // ---
+        if let Some(min_profit) = self.min_profit {
+            config.min_profit = min_profit;
+        }
+        if let Some(max_gas_price_gwei) = self.max_gas_price_gwei {
+            config.max_gas_price_gwei = max_gas_price_gwei;
+        }
+        let enabled = |strategy: &Option<StrategyPatch>| strategy.as_ref()?.enabled;
+        if let Some(enabled) = enabled(&self.arbitrage) {
+            config.arbitrage.enabled = enabled;
+        }
+        if let Some(enabled) = enabled(&self.sandwich) {
+            config.sandwich.enabled = enabled;
+        }
+        if let Some(enabled) = enabled(&self.liquidation) {
+            config.liquidation.enabled = enabled;
+        }
+    }
+}
+
+impl ControlApi {
+    // ---
+
+    /// Creates a handler for `config` and `metrics` that accepts requests
+    /// bearing `token`.
+    pub fn new(config: SharedConfig, metrics: Arc<Mutex<MEVMetrics>>, token: String) -> Self {
+        // ---
+        Self {
+            config,
+            metrics,
+            token,
+        }
+    }
+
+    /// Applies `patch` to the live config if the result validates, returning
+    /// the config now in effect.
+    ///
+    /// # Errors
+    /// Returns the validation error; the live config is left unchanged.
+    pub fn update(&self, patch: &ConfigPatch) -> anyhow::Result<MEVConfig> {
+        // ---
+        let mut live = self.config.write().expect("config poisoned");
+        let mut patched = live.clone();
+        patch.apply(&mut patched);
+        patched.validate()?;
+        *live = patched.clone();
+        Ok(patched)
+    }
+
+    /// Routes one request.
+    pub async fn handle(&self, req: Request<Body>) -> Response<Body> {
+        // ---
+
+        if !self.is_authorized(&req) {
+            return error_response(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token");
+        }
+
+        match (req.method(), req.uri().path()) {
+            (&Method::GET, CONFIG_PATH) => {
+                let config = self.config.read().expect("config poisoned").clone();
+                json_response(StatusCode::OK, &config)
+            }
+            (&Method::PATCH, CONFIG_PATH) => {
+                let body = match hyper::body::to_bytes(req.into_body()).await {
+                    Ok(body) => body,
+                    Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+                };
+                let patch: ConfigPatch = match serde_json::from_slice(&body) {
+                    Ok(patch) => patch,
+                    Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+                };
+                match self.update(&patch) {
+                    Ok(config) => {
+                        info!("🎛️ Strategy config updated via control API: {:?}", patch);
+                        json_response(StatusCode::OK, &config)
+                    }
+                    Err(e) => {
+                        warn!("⚠️ Rejected control API config change {:?}: {}", patch, e);
+                        error_response(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string())
+                    }
+                }
+            }
+            (_, CONFIG_PATH) => error_response(StatusCode::METHOD_NOT_ALLOWED, "Use GET or PATCH"),
+            (&Method::GET, METRICS_PATH) => {
+                let metrics = self
+                    .metrics
+                    .lock()
+                    .expect("metrics poisoned")
+                    .to_prometheus();
+                Response::builder()
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)
+                    .body(Body::from(metrics))
+                    .expect("valid response")
+            }
+            (_, METRICS_PATH) => error_response(StatusCode::METHOD_NOT_ALLOWED, "Use GET"),
+            _ => error_response(StatusCode::NOT_FOUND, "Not found"),
+        }
+    }
+
+    /// Checks the request's bearer token, comparing in constant time.
+    fn is_authorized(&self, req: &Request<Body>) -> bool {
+        // ---
+        let presented = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default()
+            .as_bytes();
+        let expected = self.token.as_bytes();
+
+        presented.len() == expected.len()
+            && presented
+                .iter()
+                .zip(expected)
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+/// Binds `addr` and serves `api` in the background, returning the bound
+/// address.
+///
+/// # Errors
+/// Returns an error if the address cannot be bound.
+pub fn spawn(addr: SocketAddr, api: ControlApi) -> anyhow::Result<SocketAddr> {
+    // ---
+
+    let api = Arc::new(api);
+    let make_service = make_service_fn(move |_| {
+        let api = api.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let api = api.clone();
+                async move { Ok::<_, Infallible>(api.handle(req).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)
+        .map_err(|e| anyhow::anyhow!("Cannot bind control API to {}: {}", addr, e))?
+        .serve(make_service);
+    let bound = server.local_addr();
+
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            warn!("⚠️ Control API stopped: {}", e);
+        }
+    });
+
+    Ok(bound)
+}
+
+/// Builds a JSON response with `status`.
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    // ---
+    let body = serde_json::to_vec(body).expect("config serializes to JSON");
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .expect("valid response")
+}
+
+/// Builds a `{"error": ...}` response with `status`.
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    // ---
+    json_response(status, &serde_json::json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, MockProvider, TEST_BASE_FEE_WEI};
+    use crate::searcher::{self, AnalysisContext};
+    use ethers::types::U256;
+    use std::sync::Arc;
+
+    const TOKEN: &str = "s3cret";
+
+    fn control(ctx: &AnalysisContext<Arc<MockProvider>>) -> ControlApi {
+        ControlApi::new(ctx.config.clone(), ctx.metrics.clone(), TOKEN.to_string())
+    }
+
+    fn patch_request(body: &str, token: &str) -> Request<Body> {
+        Request::builder()
+            .method(Method::PATCH)
+            .uri(CONFIG_PATH)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn json_body(response: Response<Body>) -> serde_json::Value {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn patch_changes_detection_live() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let api = control(&ctx);
+        let swap = mock::swap_tx(U256::exp10(18) * 100);
+        assert!(searcher::evaluate_opportunity(&ctx, &swap).await.is_some());
+
+        // A threshold no opportunity clears
+        let response = api
+            .handle(patch_request(r#"{"min_profit": 1000000.0}"#, TOKEN))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json_body(response).await["min_profit"], 1_000_000.0);
+        assert!(searcher::evaluate_opportunity(&ctx, &swap).await.is_none());
+
+        // Back to a reachable threshold, but with gas capped below the base fee
+        let ceiling_gwei = TEST_BASE_FEE_WEI / 1_000_000_000 - 1;
+        let body = format!(r#"{{"min_profit": 0.01, "max_gas_price_gwei": {ceiling_gwei}}}"#);
+        let response = api.handle(patch_request(&body, TOKEN)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(searcher::evaluate_opportunity(&ctx, &swap).await.is_none());
+
+        let response = api
+            .handle(patch_request(r#"{"max_gas_price_gwei": 200}"#, TOKEN))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(searcher::evaluate_opportunity(&ctx, &swap).await.is_some());
+
+        // With every strategy switched off the swap offers nothing
+        let response = api
+            .handle(patch_request(
+                r#"{"arbitrage": {"enabled": false}, "liquidation": {"enabled": false}}"#,
+                TOKEN,
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let live = json_body(response).await;
+        assert_eq!(live["arbitrage"]["enabled"], false);
+        assert_eq!(live["liquidation"]["enabled"], false);
+        assert!(searcher::evaluate_opportunity(&ctx, &swap).await.is_none());
+
+        let response = api
+            .handle(patch_request(r#"{"arbitrage": {"enabled": true}}"#, TOKEN))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(searcher::evaluate_opportunity(&ctx, &swap).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn invalid_patch_is_rejected_and_config_kept() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let api = control(&ctx);
+        let before = ctx.config().min_profit;
+
+        let response = api
+            .handle(patch_request(r#"{"min_profit": -1.0}"#, TOKEN))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            json_body(response).await["error"],
+            "Minimum profit must be positive"
+        );
+        assert_eq!(ctx.config().min_profit, before);
+
+        // Settings outside the patch are not adjustable at runtime
+        let response = api
+            .handle(patch_request(r#"{"swap_deadline_secs": 1}"#, TOKEN))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn requests_need_the_bearer_token() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let api = control(&ctx);
+
+        let response = api
+            .handle(patch_request(r#"{"sandwich": {"enabled": true}}"#, "wrong"))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!ctx.config().sandwich.enabled);
+
+        let get = |token: &str| {
+            Request::builder()
+                .uri(CONFIG_PATH)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = api.handle(get(TOKEN)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json_body(response).await["sandwich"]["enabled"], false);
+    }
+
+    #[tokio::test]
+    async fn metrics_are_served_for_prometheus() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let api = control(&ctx);
+        ctx.metrics.lock().unwrap().transactions_analyzed = 42;
+
+        let get = |token: &str| {
+            Request::builder()
+                .uri(METRICS_PATH)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+        assert_eq!(
+            api.handle(get("wrong")).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let response = api.handle(get(TOKEN)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            PROMETHEUS_CONTENT_TYPE
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("\nmev_transactions_analyzed_total 42\n"));
+    }
+}
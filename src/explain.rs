@@ -0,0 +1,167 @@
+//! Dry run of a proposed strategy config against the current one.
+//!
+//! `--explain-config <PATH>` runs the same transaction sample through the
+//! detectors twice, once per config, and reports how many opportunities each
+//! strategy finds before and after. The sample is the benchmark's seeded
+//! synthetic transactions: counts compare configs against each other, not
+//! against live traffic (`--backtest` replays a real capture).
+
+use crate::bench::{self, SEED};
+use crate::searcher;
+use crate::types::{DexRegistry, MEVConfig, TokenRegistry};
+use ethers::types::Transaction;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Transactions in the dry-run sample.
+pub const EXPLAIN_SAMPLE_TXS: usize = 1_000;
+
+// ---
+
+/// Opportunities found per strategy under the current and proposed configs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDiff {
+    // ---
+    /// Transactions analyzed under each config
+    pub sample_size: usize,
+
+    /// Opportunities by strategy under the current config
+    pub current: BTreeMap<&'static str, usize>,
+
+    /// Opportunities by strategy under the proposed config
+    pub proposed: BTreeMap<&'static str, usize>,
+}
+
+// ---
+
+impl ConfigDiff {
+    // ---
+
+    /// Returns the summary table printed by `--explain-config`: one row per
+    /// strategy found under either config, then the totals.
+    pub fn summary_table(&self) -> String {
+        // ---
+
+        let mut table = format!(
+            "🔮 Config dry run over {} sample txs\n{:<22} {:>8} {:>9} {:>7}\n",
+            self.sample_size, "strategy", "current", "proposed", "change"
+        );
+
+        let strategies: BTreeSet<&str> = self
+            .current
+            .keys()
+            .chain(self.proposed.keys())
+            .copied()
+            .collect();
+        let count = |counts: &BTreeMap<&str, usize>, strategy| {
+            counts.get(strategy).copied().unwrap_or_default()
+        };
+        let mut row = |label: &str, before: usize, after: usize| {
+            let _ = writeln!(
+                table,
+                "{:<22} {:>8} {:>9} {:>+7}",
+                label,
+                before,
+                after,
+                after as i64 - before as i64
+            );
+        };
+
+        for strategy in strategies {
+            row(
+                strategy,
+                count(&self.current, strategy),
+                count(&self.proposed, strategy),
+            );
+        }
+        row(
+            "total",
+            self.current.values().sum(),
+            self.proposed.values().sum(),
+        );
+        table.trim_end().to_string()
+    }
+}
+
+/// Runs `count` sample transactions through detection under `current` and
+/// `proposed` side by side.
+///
+/// Each config gets its own analysis context, so caches warmed by one run
+/// don't shape the other.
+pub async fn run(
+    count: usize,
+    current: MEVConfig,
+    proposed: MEVConfig,
+    routers: &DexRegistry,
+) -> ConfigDiff {
+    // ---
+
+    let transactions =
+        bench::synthetic_transactions(count, SEED, &TokenRegistry::with_defaults(), routers);
+
+    ConfigDiff {
+        sample_size: transactions.len(),
+        current: detect(&transactions, current).await,
+        proposed: detect(&transactions, proposed).await,
+    }
+}
+
+/// Counts the opportunities `config` finds in `transactions`, by strategy.
+async fn detect(transactions: &[Transaction], config: MEVConfig) -> BTreeMap<&'static str, usize> {
+    // ---
+
+    let ctx = bench::offline_context(config);
+    let mut opportunities = BTreeMap::new();
+    for tx in transactions {
+        if let Some(opportunity) = searcher::evaluate_opportunity(&ctx, tx).await {
+            *opportunities.entry(opportunity.strategy()).or_default() += 1;
+        }
+    }
+    opportunities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mainnet_routers() -> DexRegistry {
+        DexRegistry::from_config(&Default::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn unchanged_config_has_no_diff() {
+        let diff = run(
+            200,
+            MEVConfig::default(),
+            MEVConfig::default(),
+            &mainnet_routers(),
+        )
+        .await;
+
+        assert_eq!(diff.sample_size, 200);
+        assert!(!diff.current.is_empty());
+        assert_eq!(diff.current, diff.proposed);
+    }
+
+    #[tokio::test]
+    async fn proposed_changes_are_counted_per_strategy() {
+        let mut proposed = MEVConfig::default();
+        proposed.sandwich.enabled = true;
+        proposed.liquidation.health_factor_threshold = 0.0;
+
+        let diff = run(200, MEVConfig::default(), proposed, &mainnet_routers()).await;
+
+        assert!(!diff.current.contains_key("sandwich"));
+        assert!(diff.proposed["sandwich"] > 0);
+        assert!(diff.current["liquidation"] > 0);
+        assert!(!diff.proposed.contains_key("liquidation"));
+
+        let table = diff.summary_table();
+        let sandwich = table
+            .lines()
+            .find(|line| line.starts_with("sandwich"))
+            .unwrap();
+        assert!(sandwich.ends_with(&format!("+{}", diff.proposed["sandwich"])));
+        assert!(table.lines().last().unwrap().starts_with("total"));
+    }
+}
@@ -0,0 +1,618 @@
+//! Flash-loan providers that fund liquidations.
+//!
+//! Each provider lends through its own contract and call shape and charges its
+//! own fee: Aave V3 a 0.05% premium, dYdX a flat 2 wei, Balancer nothing.
+//! [`cheapest_provider`] picks among the configured providers for the borrowed
+//! token, and the repayment is sized with the chosen provider's fee.
+//!
+//! The borrowed funds only exist inside the lender's callback into the
+//! executor contract, so the calls spending and repaying them travel in the
+//! loan call itself, as an [`encode_callback`] payload the executor runs in
+//! order before the lender settles.
+
+use crate::amm::BPS_DENOMINATOR;
+use crate::positions::AAVE_V3_POOL;
+use ethers::abi::{self, ParamType, Token};
+use ethers::types::{Address, Bytes, U256};
+use std::fmt;
+
+/// Balancer V2 vault (mainnet), lender of every token it holds.
+const BALANCER_VAULT: &str = "0xBA12222222228d8Ba445958a75a0704d566BF2C8";
+
+/// dYdX SoloMargin (mainnet).
+const DYDX_SOLO_MARGIN: &str = "0x1E0447b19BB6EcFdAe1e4AE1694b0C3659614e4e";
+
+/// Aave V3 flash-loan premium, in basis points.
+const AAVE_V3_PREMIUM_BPS: u64 = 5;
+
+/// dYdX settles a flash loan by depositing the withdrawn amount plus 2 wei.
+const DYDX_REPAY_OVERHEAD_WEI: u64 = 2;
+
+/// Tokens dYdX lends, with their SoloMargin market ids (WETH, USDC, DAI).
+const DYDX_MARKETS: [(&str, u64); 3] = [
+    ("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", 0),
+    ("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", 2),
+    ("0x6B175474E89094C44Da98b954EedeAC495271d0F", 3),
+];
+
+/// dYdX SoloMargin entry point: `operate(AccountInfo[], ActionArgs[])`.
+const DYDX_OPERATE: &str =
+    "operate((address,uint256)[],(uint8,uint256,(bool,uint8,uint8,uint256),uint256,uint256,address,uint256,bytes)[])";
+
+/// dYdX `ActionType` values used by a flash loan.
+const DYDX_DEPOSIT: u8 = 0;
+const DYDX_WITHDRAW: u8 = 1;
+const DYDX_CALL: u8 = 8;
+
+// ---
+
+/// A source of flash-loaned liquidation capital.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashLoanProvider {
+    // ---
+    /// Aave V3 `flashLoanSimple`; lends any listed reserve
+    AaveV3,
+
+    /// dYdX SoloMargin `operate` (withdraw, call, deposit); WETH, USDC, and DAI only
+    DyDx,
+
+    /// Balancer V2 vault `flashLoan`; fee-free
+    Balancer,
+}
+
+// ---
+
+impl FlashLoanProvider {
+    // ---
+
+    /// Parses a provider from its `flash_loan_providers` config name
+    /// (`aave` or `aave_v3`, `dydx`, `balancer`), case-insensitively.
+    pub fn from_name(name: &str) -> Option<Self> {
+        // ---
+        match name.to_ascii_lowercase().as_str() {
+            "aave" | "aave_v3" => Some(Self::AaveV3),
+            "dydx" => Some(Self::DyDx),
+            "balancer" => Some(Self::Balancer),
+            _ => None,
+        }
+    }
+
+    /// Returns the contract the loan is taken from and repaid to.
+    pub fn lender(self) -> anyhow::Result<Address> {
+        // ---
+        let address = match self {
+            Self::AaveV3 => AAVE_V3_POOL,
+            Self::DyDx => DYDX_SOLO_MARGIN,
+            Self::Balancer => BALANCER_VAULT,
+        };
+        address
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid {} lender address '{}': {}", self, address, e))
+    }
+
+    /// Returns whether the provider can lend `token`.
+    pub fn lends(self, token: Address) -> bool {
+        // ---
+        match self {
+            Self::DyDx => dydx_market(token).is_some(),
+            Self::AaveV3 | Self::Balancer => true,
+        }
+    }
+
+    /// Returns the fee owed on top of a loan of `amount`, in the token's units.
+    ///
+    /// The Aave premium rounds half up, as the pool's `percentMul` does.
+    pub fn fee(self, amount: U256) -> U256 {
+        // ---
+        match self {
+            Self::AaveV3 => {
+                let denominator = U256::from(BPS_DENOMINATOR);
+                (amount * AAVE_V3_PREMIUM_BPS + denominator / 2) / denominator
+            }
+            Self::DyDx => U256::from(DYDX_REPAY_OVERHEAD_WEI),
+            Self::Balancer => U256::zero(),
+        }
+    }
+
+    /// Returns the `(to, calldata)` borrowing `amount` of `token` for `receiver`,
+    /// the executor contract whose callback runs `callback`, an
+    /// [`encode_callback`] payload.
+    pub fn loan_call(
+        self,
+        token: Address,
+        amount: U256,
+        receiver: Address,
+        callback: Bytes,
+    ) -> anyhow::Result<(Address, Bytes)> {
+        // ---
+        let data = match self {
+            Self::AaveV3 => encode_call(
+                "flashLoanSimple(address,address,uint256,bytes,uint16)",
+                &[
+                    Token::Address(receiver),
+                    Token::Address(token),
+                    Token::Uint(amount),
+                    Token::Bytes(callback.to_vec()),
+                    Token::Uint(U256::zero()), // referralCode
+                ],
+            ),
+            Self::DyDx => {
+                let market = dydx_market(token)
+                    .ok_or_else(|| anyhow::anyhow!("dYdX does not lend {:?}", token))?;
+                let repay = self.repayment(amount);
+                encode_call(
+                    DYDX_OPERATE,
+                    &[
+                        Token::Array(vec![Token::Tuple(vec![
+                            Token::Address(receiver),
+                            Token::Uint(U256::one()),
+                        ])]),
+                        Token::Array(vec![
+                            dydx_action(DYDX_WITHDRAW, false, amount, market, receiver, Vec::new()),
+                            dydx_action(
+                                DYDX_CALL,
+                                false,
+                                U256::zero(),
+                                0,
+                                receiver,
+                                callback.to_vec(),
+                            ),
+                            dydx_action(DYDX_DEPOSIT, true, repay, market, receiver, Vec::new()),
+                        ]),
+                    ],
+                )
+            }
+            Self::Balancer => encode_call(
+                "flashLoan(address,address[],uint256[],bytes)",
+                &[
+                    Token::Address(receiver),
+                    Token::Array(vec![Token::Address(token)]),
+                    Token::Array(vec![Token::Uint(amount)]),
+                    Token::Bytes(callback.to_vec()),
+                ],
+            ),
+        };
+        Ok((self.lender()?, data))
+    }
+
+    /// Returns the callback calls carried by `data`, a loan call built by
+    /// [`loan_call`](Self::loan_call).
+    ///
+    /// # Errors
+    /// Returns an error if `data` isn't this provider's loan call or its
+    /// payload doesn't decode.
+    pub fn callback_calls(self, data: &[u8]) -> anyhow::Result<Vec<(Address, Bytes)>> {
+        // ---
+        let (signature, params, payload_at) = match self {
+            Self::AaveV3 => (
+                "flashLoanSimple(address,address,uint256,bytes,uint16)",
+                vec![
+                    ParamType::Address,
+                    ParamType::Address,
+                    ParamType::Uint(256),
+                    ParamType::Bytes,
+                    ParamType::Uint(16),
+                ],
+                3,
+            ),
+            Self::DyDx => (DYDX_OPERATE, dydx_operate_params(), 1),
+            Self::Balancer => (
+                "flashLoan(address,address[],uint256[],bytes)",
+                vec![
+                    ParamType::Address,
+                    ParamType::Array(Box::new(ParamType::Address)),
+                    ParamType::Array(Box::new(ParamType::Uint(256))),
+                    ParamType::Bytes,
+                ],
+                3,
+            ),
+        };
+
+        let args = decode_call(data, signature, &params)
+            .ok_or_else(|| anyhow::anyhow!("Not a {} flash loan call", self))?;
+        let payload = match (self, args.into_iter().nth(payload_at)) {
+            // The payload rides on the `call` action, the second of three
+            (Self::DyDx, Some(Token::Array(actions))) => actions
+                .into_iter()
+                .nth(1)
+                .and_then(Token::into_tuple)
+                .and_then(|fields| fields.into_iter().nth(7)),
+            (_, payload) => payload,
+        };
+        let payload = payload
+            .and_then(Token::into_bytes)
+            .ok_or_else(|| anyhow::anyhow!("{} flash loan call has no callback", self))?;
+        decode_callback(&payload)
+    }
+
+    /// Returns what settles a loan of `amount`: the principal plus the fee.
+    pub fn repayment(self, amount: U256) -> U256 {
+        // ---
+        amount + self.fee(amount)
+    }
+
+    /// Returns the `(to, calldata)` paying `owed` of `token` back to the lender;
+    /// `owed` is normally [`repayment`](Self::repayment) of the principal.
+    ///
+    /// Aave and dYdX pull the repayment, so the lender is approved for it;
+    /// Balancer expects it transferred back to the vault.
+    pub fn repayment_call(self, token: Address, owed: U256) -> anyhow::Result<(Address, Bytes)> {
+        // ---
+        let lender = self.lender()?;
+        let owed = Token::Uint(owed);
+        let data = match self {
+            Self::AaveV3 | Self::DyDx => {
+                encode_call("approve(address,uint256)", &[Token::Address(lender), owed])
+            }
+            Self::Balancer => {
+                encode_call("transfer(address,uint256)", &[Token::Address(lender), owed])
+            }
+        };
+        Ok((token, data))
+    }
+
+    /// Returns the amount a [`repayment_call`](Self::repayment_call)'s
+    /// calldata `data` returns to the lender, or `None` if `data` isn't this
+    /// provider's repayment.
+    pub fn repaid_by(self, data: &[u8]) -> Option<U256> {
+        // ---
+        let signature = match self {
+            Self::AaveV3 | Self::DyDx => "approve(address,uint256)",
+            Self::Balancer => "transfer(address,uint256)",
+        };
+        let args = decode_call(data, signature, &[ParamType::Address, ParamType::Uint(256)])?;
+        match args.as_slice() {
+            [Token::Address(to), Token::Uint(owed)] if self.lender().ok() == Some(*to) => {
+                Some(*owed)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FlashLoanProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str(match self {
+            Self::AaveV3 => "aave_v3",
+            Self::DyDx => "dydx",
+            Self::Balancer => "balancer",
+        })
+    }
+}
+
+/// Returns the cheapest of `providers` that lends `token` for a loan of
+/// `amount`; ties go to the provider listed first.
+pub fn cheapest_provider(
+    providers: &[FlashLoanProvider],
+    token: Address,
+    amount: U256,
+) -> Option<FlashLoanProvider> {
+    // ---
+    providers
+        .iter()
+        .copied()
+        .filter(|provider| provider.lends(token))
+        .min_by_key(|provider| provider.fee(amount))
+}
+
+/// Encodes the calls the executor makes from the loan callback, in order, as
+/// an ABI `(address,bytes)[]` of `(to, calldata)`.
+pub fn encode_callback(calls: &[(Address, Bytes)]) -> Bytes {
+    // ---
+    let calls = calls
+        .iter()
+        .map(|(to, data)| Token::Tuple(vec![Token::Address(*to), Token::Bytes(data.to_vec())]))
+        .collect();
+    abi::encode(&[Token::Array(calls)]).into()
+}
+
+/// Decodes an [`encode_callback`] payload back into its `(to, calldata)` calls.
+///
+/// # Errors
+/// Returns an error if `payload` isn't an ABI `(address,bytes)[]`.
+pub fn decode_callback(payload: &[u8]) -> anyhow::Result<Vec<(Address, Bytes)>> {
+    // ---
+    let call = ParamType::Tuple(vec![ParamType::Address, ParamType::Bytes]);
+    let decoded = abi::decode(&[ParamType::Array(Box::new(call))], payload)
+        .map_err(|e| anyhow::anyhow!("Invalid flash loan callback payload: {}", e))?;
+
+    decoded
+        .into_iter()
+        .filter_map(Token::into_array)
+        .flatten()
+        .map(|call| match call.into_tuple().as_deref() {
+            Some([Token::Address(to), Token::Bytes(data)]) => Ok((*to, data.clone().into())),
+            _ => anyhow::bail!("Invalid flash loan callback call"),
+        })
+        .collect()
+}
+
+/// Returns the SoloMargin market id of `token`, if dYdX lists it.
+fn dydx_market(token: Address) -> Option<u64> {
+    // ---
+    DYDX_MARKETS
+        .iter()
+        .find(|(address, _)| address.parse::<Address>().ok() == Some(token))
+        .map(|(_, market)| *market)
+}
+
+/// Encodes one dYdX `ActionArgs` tuple on account 0, with a wei-denominated
+/// delta amount.
+fn dydx_action(
+    action: u8,
+    positive: bool,
+    amount: U256,
+    market: u64,
+    other: Address,
+    data: Vec<u8>,
+) -> Token {
+    // ---
+    Token::Tuple(vec![
+        Token::Uint(U256::from(action)),
+        Token::Uint(U256::zero()), // accountId
+        Token::Tuple(vec![
+            Token::Bool(positive),
+            Token::Uint(U256::zero()), // denomination: Wei
+            Token::Uint(U256::zero()), // reference: Delta
+            Token::Uint(amount),
+        ]),
+        Token::Uint(U256::from(market)), // primaryMarketId
+        Token::Uint(U256::zero()),       // secondaryMarketId
+        Token::Address(other),
+        Token::Uint(U256::zero()), // otherAccountId
+        Token::Bytes(data),
+    ])
+}
+
+/// Returns the parameter types of [`DYDX_OPERATE`].
+fn dydx_operate_params() -> Vec<ParamType> {
+    // ---
+    let action = ParamType::Tuple(vec![
+        ParamType::Uint(8),
+        ParamType::Uint(256),
+        ParamType::Tuple(vec![
+            ParamType::Bool,
+            ParamType::Uint(8),
+            ParamType::Uint(8),
+            ParamType::Uint(256),
+        ]),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Bytes,
+    ]);
+    vec![
+        ParamType::Array(Box::new(ParamType::Tuple(vec![
+            ParamType::Address,
+            ParamType::Uint(256),
+        ]))),
+        ParamType::Array(Box::new(action)),
+    ]
+}
+
+/// Decodes `data` as a call to `signature` with `params`, or `None` if the
+/// selector differs or the arguments don't decode.
+fn decode_call(data: &[u8], signature: &str, params: &[ParamType]) -> Option<Vec<Token>> {
+    // ---
+    let (selector, args) = data.split_first_chunk::<4>()?;
+    if *selector != ethers::utils::id(signature) {
+        return None;
+    }
+    abi::decode(params, args).ok()
+}
+
+/// ABI-encodes a call to `signature` with `args`.
+fn encode_call(signature: &str, args: &[Token]) -> Bytes {
+    // ---
+    let mut data = ethers::utils::id(signature).to_vec();
+    data.extend(abi::encode(args));
+    data.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::ParamType;
+
+    fn weth() -> Address {
+        DYDX_MARKETS[0].0.parse().unwrap()
+    }
+
+    fn receiver() -> Address {
+        Address::from_low_u64_be(0xe8ec)
+    }
+
+    /// Calls the executor runs from the loan callback.
+    fn calls() -> Vec<(Address, Bytes)> {
+        vec![
+            (
+                Address::from_low_u64_be(0x9001),
+                Bytes::from(vec![0xaa; 36]),
+            ),
+            (weth(), Bytes::from(vec![0xbb; 68])),
+        ]
+    }
+
+    /// Decodes `data` as a call to `signature` with `params`.
+    fn decode(data: &Bytes, signature: &str, params: &[ParamType]) -> Vec<Token> {
+        assert_eq!(data[..4], ethers::utils::id(signature));
+        abi::decode(params, &data[4..]).unwrap()
+    }
+
+    #[test]
+    fn aave_v3_borrows_with_flash_loan_simple_and_charges_premium() {
+        let amount = U256::exp10(18);
+        let (to, data) = FlashLoanProvider::AaveV3
+            .loan_call(weth(), amount, receiver(), encode_callback(&calls()))
+            .unwrap();
+
+        assert_eq!(to, AAVE_V3_POOL.parse::<Address>().unwrap());
+        let args = decode(
+            &data,
+            "flashLoanSimple(address,address,uint256,bytes,uint16)",
+            &[
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Bytes,
+                ParamType::Uint(16),
+            ],
+        );
+        assert_eq!(args[0], Token::Address(receiver()));
+        assert_eq!(args[1], Token::Address(weth()));
+        assert_eq!(args[2], Token::Uint(amount));
+        assert_eq!(
+            FlashLoanProvider::AaveV3.callback_calls(&data).unwrap(),
+            calls()
+        );
+
+        // 0.05% of 1 WETH, approved for the pool to pull back
+        let fee = FlashLoanProvider::AaveV3.fee(amount);
+        assert_eq!(fee, U256::exp10(14) * 5);
+        let (token, data) = FlashLoanProvider::AaveV3
+            .repayment_call(weth(), FlashLoanProvider::AaveV3.repayment(amount))
+            .unwrap();
+        assert_eq!(token, weth());
+        let args = decode(
+            &data,
+            "approve(address,uint256)",
+            &[ParamType::Address, ParamType::Uint(256)],
+        );
+        assert_eq!(args, vec![Token::Address(to), Token::Uint(amount + fee)]);
+        assert_eq!(
+            FlashLoanProvider::AaveV3.repaid_by(&data),
+            Some(amount + fee)
+        );
+    }
+
+    #[test]
+    fn dydx_withdraws_calls_and_deposits_two_extra_wei() {
+        let amount = U256::exp10(18);
+        let (to, data) = FlashLoanProvider::DyDx
+            .loan_call(weth(), amount, receiver(), encode_callback(&calls()))
+            .unwrap();
+
+        assert_eq!(to, DYDX_SOLO_MARGIN.parse::<Address>().unwrap());
+        let args = decode(&data, DYDX_OPERATE, &dydx_operate_params());
+        assert_eq!(
+            FlashLoanProvider::DyDx.callback_calls(&data).unwrap(),
+            calls()
+        );
+
+        let actions = args[1].clone().into_array().unwrap();
+        let kinds_and_amounts: Vec<(U256, Token)> = actions
+            .into_iter()
+            .map(|action| {
+                let fields = action.into_tuple().unwrap();
+                let amount = fields[2].clone().into_tuple().unwrap()[3].clone();
+                (fields[0].clone().into_uint().unwrap(), amount)
+            })
+            .collect();
+        assert_eq!(
+            kinds_and_amounts,
+            vec![
+                (U256::from(DYDX_WITHDRAW), Token::Uint(amount)),
+                (U256::from(DYDX_CALL), Token::Uint(U256::zero())),
+                (U256::from(DYDX_DEPOSIT), Token::Uint(amount + 2)),
+            ]
+        );
+        assert_eq!(FlashLoanProvider::DyDx.fee(amount), U256::from(2));
+
+        // Only its three markets are lendable
+        let usdt: Address = "0xdAC17F958D2ee523a2206206994597C13D831ec7"
+            .parse()
+            .unwrap();
+        assert!(!FlashLoanProvider::DyDx.lends(usdt));
+        assert!(FlashLoanProvider::DyDx
+            .loan_call(usdt, amount, receiver(), Bytes::new())
+            .is_err());
+    }
+
+    #[test]
+    fn balancer_lends_fee_free_and_is_repaid_by_transfer() {
+        let amount = U256::exp10(18);
+        let (to, data) = FlashLoanProvider::Balancer
+            .loan_call(weth(), amount, receiver(), encode_callback(&calls()))
+            .unwrap();
+
+        assert_eq!(to, BALANCER_VAULT.parse::<Address>().unwrap());
+        let args = decode(
+            &data,
+            "flashLoan(address,address[],uint256[],bytes)",
+            &[
+                ParamType::Address,
+                ParamType::Array(Box::new(ParamType::Address)),
+                ParamType::Array(Box::new(ParamType::Uint(256))),
+                ParamType::Bytes,
+            ],
+        );
+        assert_eq!(args[1], Token::Array(vec![Token::Address(weth())]));
+        assert_eq!(args[2], Token::Array(vec![Token::Uint(amount)]));
+        assert_eq!(
+            FlashLoanProvider::Balancer.callback_calls(&data).unwrap(),
+            calls()
+        );
+
+        // Another provider's loan call isn't mistaken for Balancer's
+        assert!(FlashLoanProvider::AaveV3.callback_calls(&data).is_err());
+
+        assert!(FlashLoanProvider::Balancer.fee(amount).is_zero());
+        let (_, data) = FlashLoanProvider::Balancer
+            .repayment_call(weth(), FlashLoanProvider::Balancer.repayment(amount))
+            .unwrap();
+        let args = decode(
+            &data,
+            "transfer(address,uint256)",
+            &[ParamType::Address, ParamType::Uint(256)],
+        );
+        assert_eq!(args, vec![Token::Address(to), Token::Uint(amount)]);
+        assert_eq!(FlashLoanProvider::Balancer.repaid_by(&data), Some(amount));
+
+        // An approval isn't how Balancer is repaid
+        assert_eq!(FlashLoanProvider::AaveV3.repaid_by(&data), None);
+    }
+
+    #[test]
+    fn cheapest_configured_provider_lending_the_token_wins() {
+        use FlashLoanProvider::*;
+        let usdt: Address = "0xdAC17F958D2ee523a2206206994597C13D831ec7"
+            .parse()
+            .unwrap();
+        let amount = U256::exp10(18);
+
+        assert_eq!(
+            cheapest_provider(&[AaveV3, DyDx, Balancer], weth(), amount),
+            Some(Balancer)
+        );
+        assert_eq!(
+            cheapest_provider(&[AaveV3, DyDx], weth(), amount),
+            Some(DyDx)
+        );
+        assert_eq!(
+            cheapest_provider(&[AaveV3, DyDx], usdt, amount),
+            Some(AaveV3)
+        );
+        assert_eq!(cheapest_provider(&[DyDx], usdt, amount), None);
+    }
+
+    #[test]
+    fn provider_names_parse() {
+        assert_eq!(
+            FlashLoanProvider::from_name("aave"),
+            Some(FlashLoanProvider::AaveV3)
+        );
+        for provider in [
+            FlashLoanProvider::AaveV3,
+            FlashLoanProvider::DyDx,
+            FlashLoanProvider::Balancer,
+        ] {
+            assert_eq!(
+                FlashLoanProvider::from_name(&provider.to_string()),
+                Some(provider)
+            );
+        }
+        assert_eq!(FlashLoanProvider::from_name("maker"), None);
+    }
+}
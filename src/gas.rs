@@ -0,0 +1,451 @@
+//! Base-fee tracking and gas price helpers.
+//!
+//! Follows new block headers (subscribed over WebSocket, polled over HTTP) to
+//! keep the latest EIP-1559 base fee, a short exponential moving average of
+//! recent base fees, and a prediction of the next block's base fee. A single
+//! tracker is shared (via `Arc`) by the searcher and bundler so gas costs and
+//! bundle pricing use the same fresh numbers instead of per-module constants.
+
+use crate::amm::BPS_DENOMINATOR;
+use crate::types::PriorityFeeStrategy;
+use ethers::providers::{JsonRpcClient, Middleware, Provider, PubsubClient, StreamExt};
+use ethers::types::{Block, BlockNumber, H256, U256, U64};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+/// Base fee assumed until the first block header has been observed (20 gwei).
+const DEFAULT_BASE_FEE_WEI: u64 = 20_000_000_000;
+
+/// Number of blocks covered by the base-fee moving average.
+const EMA_WINDOW_BLOCKS: u64 = 5;
+
+/// EIP-1559 maximum base fee change denominator (12.5% per block).
+const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+
+/// EIP-1559 elasticity multiplier (gas target = gas limit / 2).
+const ELASTICITY_MULTIPLIER: u64 = 2;
+
+// ---
+
+/// Shared view of the chain's current and predicted base fee.
+#[derive(Debug)]
+pub struct BaseFeeTracker {
+    // ---
+    state: RwLock<BaseFeeState>,
+}
+
+/// Snapshot of the most recently observed block header.
+#[derive(Debug, Clone)]
+struct BaseFeeState {
+    // ---
+    /// Block number the snapshot was taken from
+    block_number: U64,
+
+    /// Base fee of the latest block in wei
+    base_fee: U256,
+
+    /// Moving average of recent base fees in wei
+    ema: U256,
+
+    /// Gas used by the latest block
+    gas_used: U256,
+
+    /// Gas limit of the latest block
+    gas_limit: U256,
+
+    /// Timestamp of the latest block in seconds since the Unix epoch
+    timestamp: U256,
+}
+
+// ---
+
+impl BaseFeeTracker {
+    // ---
+
+    /// Creates a tracker seeded with `initial_base_fee` and no block history.
+    ///
+    /// The block timestamp starts at the local wall clock until a header is observed.
+    pub fn new(initial_base_fee: U256) -> Self {
+        // ---
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Self {
+            state: RwLock::new(BaseFeeState {
+                block_number: U64::zero(),
+                base_fee: initial_base_fee,
+                ema: initial_base_fee,
+                // Exactly at target, so the prediction equals the seed
+                gas_used: U256::one(),
+                gas_limit: U256::from(ELASTICITY_MULTIPLIER),
+                timestamp: U256::from(now),
+            }),
+        }
+    }
+
+    /// Creates a tracker from the latest block and keeps it fresh from a new-heads subscription.
+    ///
+    /// Falls back to a 20 gwei seed if the latest block cannot be fetched; the
+    /// subscription corrects it as soon as the next header arrives. Subscription
+    /// failures are logged and leave the tracker serving its last known values.
+    pub async fn spawn<P>(provider: Arc<Provider<P>>) -> Arc<Self>
+    where
+        P: PubsubClient + 'static,
+    {
+        // ---
+
+        let tracker = Self::seeded(provider.as_ref()).await;
+        let task_tracker = tracker.clone();
+
+        tokio::spawn(async move {
+            // ---
+            let mut heads = match provider.subscribe_blocks().await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("New-heads subscription failed: {}", e);
+                    return;
+                }
+            };
+
+            while let Some(block) = heads.next().await {
+                task_tracker.observe_block(&block);
+            }
+            warn!("New-heads subscription ended; base fee tracking stopped");
+        });
+
+        info!(
+            "⛽ Tracking base fee (current {} gwei)",
+            format_gwei(tracker.current())
+        );
+        tracker
+    }
+
+    /// Creates a tracker from the latest block and keeps it fresh by polling
+    /// `eth_getBlockByNumber(latest)` every `poll_interval`.
+    ///
+    /// Used with transports that cannot subscribe to new heads (HTTP). Failed
+    /// polls are logged and retried at the next interval.
+    pub async fn spawn_polling<P>(provider: Arc<Provider<P>>, poll_interval: Duration) -> Arc<Self>
+    where
+        P: JsonRpcClient + 'static,
+    {
+        // ---
+
+        let tracker = Self::seeded(provider.as_ref()).await;
+        let task_tracker = tracker.clone();
+
+        tokio::spawn(async move {
+            // ---
+            let mut interval = tokio::time::interval(poll_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                match provider.get_block(BlockNumber::Latest).await {
+                    // Stale or repeated headers are ignored by observe_block
+                    Ok(Some(block)) => task_tracker.observe_block(&block),
+                    Ok(None) => {}
+                    Err(e) => warn!("Latest block poll failed: {}", e),
+                }
+            }
+        });
+
+        info!(
+            "⛽ Tracking base fee by polling every {:?} (current {} gwei)",
+            poll_interval,
+            format_gwei(tracker.current())
+        );
+        tracker
+    }
+
+    /// Creates a tracker seeded from the latest block, falling back to 20 gwei.
+    async fn seeded<P: JsonRpcClient>(provider: &Provider<P>) -> Arc<Self> {
+        // ---
+
+        let tracker = Arc::new(Self::new(U256::from(DEFAULT_BASE_FEE_WEI)));
+
+        match provider.get_block(BlockNumber::Latest).await {
+            Ok(Some(block)) => tracker.observe_block(&block),
+            Ok(None) => warn!("Latest block unavailable; seeding base fee at 20 gwei"),
+            Err(e) => warn!(
+                "Failed to fetch latest block ({}); seeding base fee at 20 gwei",
+                e
+            ),
+        }
+        tracker
+    }
+
+    /// Updates the tracker from a block header. Headers without a base fee
+    /// (pre-London) or older than the latest observed block are ignored.
+    pub fn observe_block(&self, block: &Block<H256>) {
+        // ---
+
+        let Some(base_fee) = block.base_fee_per_gas else {
+            return;
+        };
+        let block_number = block.number.unwrap_or_default();
+
+        let mut state = self.state.write().unwrap();
+        if block_number < state.block_number {
+            return;
+        }
+
+        state.ema = (base_fee * 2 + state.ema * (EMA_WINDOW_BLOCKS - 1)) / (EMA_WINDOW_BLOCKS + 1);
+        state.block_number = block_number;
+        state.base_fee = base_fee;
+        state.gas_used = block.gas_used;
+        state.gas_limit = block.gas_limit;
+        state.timestamp = block.timestamp;
+
+        debug!(
+            block = %block_number,
+            base_fee_gwei = %format_gwei(base_fee),
+            ema_gwei = %format_gwei(state.ema),
+            "⛽ Base fee updated"
+        );
+    }
+
+    /// Returns the number of the latest observed block (zero before the first header).
+    pub fn block_number(&self) -> U64 {
+        // ---
+        self.state.read().unwrap().block_number
+    }
+
+    /// Returns the timestamp of the latest observed block in seconds.
+    pub fn timestamp(&self) -> U256 {
+        // ---
+        self.state.read().unwrap().timestamp
+    }
+
+    /// Returns the base fee of the latest observed block in wei.
+    pub fn current(&self) -> U256 {
+        // ---
+        self.state.read().unwrap().base_fee
+    }
+
+    /// Returns the moving average of recent base fees in wei.
+    pub fn ema(&self) -> U256 {
+        // ---
+        self.state.read().unwrap().ema
+    }
+
+    /// Predicts the next block's base fee in wei from the latest header.
+    pub fn predict_next(&self) -> U256 {
+        // ---
+        let state = self.state.read().unwrap();
+        next_base_fee(state.base_fee, state.gas_used, state.gas_limit)
+    }
+}
+
+// ---
+
+/// Computes the next block's base fee using the EIP-1559 update rule.
+///
+/// The base fee moves by at most 1/8 per block, proportionally to how far the
+/// parent block's gas usage was above or below the gas target (half the limit).
+pub fn next_base_fee(base_fee: U256, gas_used: U256, gas_limit: U256) -> U256 {
+    // ---
+
+    let gas_target = gas_limit / ELASTICITY_MULTIPLIER;
+    if gas_target.is_zero() || gas_used == gas_target {
+        return base_fee;
+    }
+
+    if gas_used > gas_target {
+        let delta =
+            base_fee * (gas_used - gas_target) / gas_target / BASE_FEE_MAX_CHANGE_DENOMINATOR;
+        base_fee + delta.max(U256::one())
+    } else {
+        let delta =
+            base_fee * (gas_target - gas_used) / gas_target / BASE_FEE_MAX_CHANGE_DENOMINATOR;
+        base_fee.saturating_sub(delta)
+    }
+}
+
+/// Returns the priority fee `strategy` tips on top of a base fee that is
+/// `current` now and predicted to be `predicted` in the next block.
+pub fn priority_fee(strategy: &PriorityFeeStrategy, current: U256, predicted: U256) -> U256 {
+    // ---
+
+    let gwei = U256::exp10(9);
+    match *strategy {
+        PriorityFeeStrategy::Fixed(tip_gwei) => U256::from(tip_gwei) * gwei,
+        PriorityFeeStrategy::Dynamic {
+            base_fee_multiplier,
+            min_priority_gwei,
+            max_priority_gwei,
+        } => {
+            let excess_bps = ((base_fee_multiplier - 1.0).max(0.0) * 10_000.0).round() as u64;
+            let tip = predicted.saturating_mul(U256::from(excess_bps)) / U256::from(10_000);
+            let floor = U256::from(min_priority_gwei) * gwei;
+            let ceiling = U256::from(max_priority_gwei) * gwei;
+            tip.clamp(floor, ceiling.max(floor))
+        }
+        PriorityFeeStrategy::Competitive { min_priority_gwei } => {
+            U256::from(min_priority_gwei) * gwei + predicted.saturating_sub(current)
+        }
+    }
+}
+
+/// Returns the gas price bundle transactions bid for the next block: its
+/// predicted base fee plus the tip `strategy` sets, raised by
+/// `price_bump_bps` to outbid competing searchers, then capped at
+/// `max_gas_price_gwei`. Near the cap, the bump and tip give way first.
+///
+/// # Errors
+/// Returns an error if the predicted base fee alone reaches the cap, since no
+/// bid under it could be included.
+pub fn bundle_gas_price(
+    strategy: &PriorityFeeStrategy,
+    price_bump_bps: u16,
+    max_gas_price_gwei: u64,
+    base_fees: &BaseFeeTracker,
+) -> anyhow::Result<U256> {
+    // ---
+
+    let predicted = base_fees.predict_next();
+    let tip = priority_fee(strategy, base_fees.current(), predicted);
+    let cap = U256::from(max_gas_price_gwei) * U256::exp10(9);
+    if predicted >= cap {
+        anyhow::bail!(
+            "Next block's base fee ({} gwei) is at or above max_gas_price_gwei ({})",
+            format_gwei(predicted),
+            max_gas_price_gwei
+        );
+    }
+
+    let computed = predicted.saturating_add(tip);
+    let gas_price = computed.saturating_add(
+        computed.saturating_mul(U256::from(price_bump_bps)) / U256::from(BPS_DENOMINATOR),
+    );
+    if gas_price > cap {
+        debug!(
+            tip_gwei = %format_gwei(tip),
+            bumped_gwei = %format_gwei(gas_price),
+            "⛽ Gas price capped at {} gwei",
+            max_gas_price_gwei
+        );
+    }
+    Ok(gas_price.min(cap))
+}
+
+/// Formats a wei amount as gwei for logs.
+fn format_gwei(wei: U256) -> String {
+    // ---
+    ethers::utils::format_units(wei, "gwei").unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gwei(amount: u64) -> U256 {
+        U256::from(amount) * U256::exp10(9)
+    }
+
+    /// A tracker whose latest block was full at `base_fee_gwei`, so the next
+    /// base fee is predicted 12.5% higher.
+    fn full_block_at(base_fee_gwei: u64) -> BaseFeeTracker {
+        let tracker = BaseFeeTracker::new(gwei(base_fee_gwei));
+        tracker.observe_block(&Block {
+            number: Some(U64::from(1)),
+            base_fee_per_gas: Some(gwei(base_fee_gwei)),
+            gas_used: U256::from(30_000_000),
+            gas_limit: U256::from(30_000_000),
+            ..Default::default()
+        });
+        tracker
+    }
+
+    fn dynamic(multiplier: f64) -> PriorityFeeStrategy {
+        PriorityFeeStrategy::Dynamic {
+            base_fee_multiplier: multiplier,
+            min_priority_gwei: 1,
+            max_priority_gwei: 20,
+        }
+    }
+
+    #[test]
+    fn dynamic_tip_is_clamped_through_a_base_fee_spike() {
+        // Calm: 16 gwei, predicted 18 -> tip 18 * 0.5 = 9 gwei, inside the band
+        let calm = full_block_at(16);
+        assert_eq!(
+            bundle_gas_price(&dynamic(1.5), 0, 300, &calm).unwrap(),
+            gwei(27)
+        );
+
+        // Spike: 160 gwei, predicted 180 -> 90 gwei unclamped; capped at 20
+        let spike = full_block_at(160);
+        assert_eq!(
+            bundle_gas_price(&dynamic(1.5), 0, 300, &spike).unwrap(),
+            gwei(200)
+        );
+
+        // A multiplier of 1 tips nothing, so the floor applies
+        assert_eq!(
+            bundle_gas_price(&dynamic(1.0), 0, 300, &calm).unwrap(),
+            gwei(19)
+        );
+    }
+
+    #[test]
+    fn gas_price_respects_the_configured_ceiling() {
+        let spike = full_block_at(160);
+
+        // Room for only part of the tip: the bid stops at the cap
+        assert_eq!(
+            bundle_gas_price(&dynamic(1.5), 0, 190, &spike).unwrap(),
+            gwei(190)
+        );
+
+        // The base fee alone reaches the cap: no bid can land
+        assert!(bundle_gas_price(&dynamic(1.5), 0, 180, &spike).is_err());
+    }
+
+    #[test]
+    fn price_bump_raises_the_whole_bid() {
+        // Predicted 18 gwei + 9 gwei tip = 27 gwei, bumped 10% to 29.7 gwei
+        let calm = full_block_at(16);
+        assert_eq!(
+            bundle_gas_price(&dynamic(1.5), 1_000, 300, &calm).unwrap(),
+            gwei(297) / 10
+        );
+    }
+
+    #[test]
+    fn ceiling_applies_after_the_price_bump() {
+        // 27 gwei bumped 50% is 40.5 gwei, past a 40 gwei ceiling
+        let calm = full_block_at(16);
+        assert_eq!(
+            bundle_gas_price(&dynamic(1.5), 5_000, 40, &calm).unwrap(),
+            gwei(40)
+        );
+
+        // Under the ceiling the bump is kept whole
+        assert_eq!(
+            bundle_gas_price(&dynamic(1.5), 5_000, 41, &calm).unwrap(),
+            gwei(405) / 10
+        );
+
+        // No bump gets a bid past a base fee already at the ceiling
+        let spike = full_block_at(160);
+        assert!(bundle_gas_price(&dynamic(1.5), 5_000, 180, &spike).is_err());
+    }
+
+    #[test]
+    fn fixed_and_competitive_tips() {
+        let spike = full_block_at(160);
+        let (current, predicted) = (spike.current(), spike.predict_next());
+
+        let fixed = PriorityFeeStrategy::Fixed(3);
+        assert_eq!(priority_fee(&fixed, current, predicted), gwei(3));
+
+        // The floor plus the 20 gwei rise expected into the next block
+        let competitive = PriorityFeeStrategy::Competitive {
+            min_priority_gwei: 2,
+        };
+        assert_eq!(priority_fee(&competitive, current, predicted), gwei(22));
+    }
+}
@@ -0,0 +1,528 @@
+//! Post-hoc analysis of MEV that landed in confirmed blocks.
+//!
+//! `--analyze-blocks` follows the chain head instead of the mempool and runs
+//! each confirmed block's transactions through the same swap decoders the
+//! detectors use, looking for what other searchers captured:
+//!
+//! * a **sandwich**: one sender swaps a pair, others swap it the same way,
+//!   then the first sender swaps it back
+//! * a **round-trip arbitrage**: one sender swaps a pair and back through
+//!   different routers with no one else trading it in between
+//!
+//! Sandwich value is estimated with the detector's AMM math against the
+//! deepest known pool for the pair; arbitrage value is the floor the
+//! searcher's own slippage guards locked in. Both are estimates: calldata
+//! doesn't say what a swap actually returned, and the known reserves aren't
+//! the block's. Swaps routed through searchers' own contracts don't decode
+//! and go unseen. Nothing is ever built or sent in this mode.
+
+use crate::amm;
+use crate::gas::BaseFeeTracker;
+use crate::searcher::{self, TxType};
+use crate::types::{PoolInfo, TokenRegistry};
+use ethers::providers::{JsonRpcClient, Middleware, Provider};
+use ethers::types::{Address, Transaction, TxHash, U256, U64};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant as TokioInstant;
+use tracing::{debug, info};
+
+/// How often the chain head is checked for a newly confirmed block.
+const HEAD_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+// ---
+
+/// MEV another searcher captured in a confirmed block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LandedMev {
+    // ---
+    Sandwich {
+        /// Sender of the frontrun and backrun
+        searcher: Address,
+        frontrun: TxHash,
+        /// Swaps in the same direction between the frontrun and backrun
+        victims: Vec<TxHash>,
+        backrun: TxHash,
+        /// Token sold by the frontrun and recovered by the backrun
+        token: Address,
+        /// Estimated profit in `token` units, or `None` without a known pool
+        extracted: Option<U256>,
+    },
+
+    RoundTripArbitrage {
+        searcher: Address,
+        open: TxHash,
+        close: TxHash,
+        /// Token sold by the first swap and bought back by the second
+        token: Address,
+        /// Least profit in `token` units the swaps' slippage guards allowed
+        extracted: U256,
+    },
+}
+
+/// A decoded swap at its position in the block.
+#[derive(Debug, Clone, Copy)]
+struct Swap {
+    // ---
+    hash: TxHash,
+    from: Address,
+    router: Option<Address>,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    amount_out_min: U256,
+}
+
+/// Limits on a `--analyze-blocks` run, from the listener options.
+#[derive(Debug, Clone, Copy)]
+pub struct FollowLimits {
+    // ---
+    /// Block transactions to analyze before returning
+    pub max_tx: usize,
+
+    /// When to stop, if ever
+    pub deadline: Option<TokioInstant>,
+
+    /// Head silence after which the connection is considered stalled
+    pub idle_timeout: Option<Duration>,
+}
+
+// ---
+
+impl Swap {
+    // ---
+
+    /// Decodes `tx` as a direct Uniswap swap; other calls aren't swaps here.
+    fn of(tx: &Transaction) -> Option<Self> {
+        // ---
+        let (token_in, token_out, amount_in, amount_out_min) =
+            match searcher::decode_transaction_type(tx) {
+                TxType::UniswapV2Swap {
+                    token_in,
+                    token_out,
+                    amount_in,
+                    amount_out_min,
+                }
+                | TxType::UniswapV3Swap {
+                    token_in,
+                    token_out,
+                    amount_in,
+                    amount_out_min,
+                    ..
+                } => (token_in, token_out, amount_in, amount_out_min),
+                _ => return None,
+            };
+        Some(Self {
+            hash: tx.hash,
+            from: tx.from,
+            router: tx.to,
+            token_in,
+            token_out,
+            amount_in,
+            amount_out_min,
+        })
+    }
+
+    /// Whether `other` trades the same pair, in either direction.
+    fn same_pair(&self, other: &Self) -> bool {
+        // ---
+        (self.token_in, self.token_out) == (other.token_in, other.token_out)
+            || (self.token_in, self.token_out) == (other.token_out, other.token_in)
+    }
+}
+
+/// Finds the sandwiches and round-trip arbitrages among a confirmed block's
+/// `transactions`, in block order, valuing sandwiches against `pools`.
+///
+/// Each swap belongs to at most one finding: a searcher's swap opens one at
+/// its next swap reversing it.
+pub fn analyze_block(transactions: &[Transaction], pools: &[PoolInfo]) -> Vec<LandedMev> {
+    // ---
+
+    let swaps: Vec<Swap> = transactions.iter().filter_map(Swap::of).collect();
+    let mut claimed = vec![false; swaps.len()];
+    let mut found = Vec::new();
+
+    for (i, open) in swaps.iter().enumerate() {
+        if claimed[i] {
+            continue;
+        }
+        let Some(j) = (i + 1..swaps.len()).find(|&j| {
+            let close = &swaps[j];
+            !claimed[j]
+                && close.from == open.from
+                && (close.token_in, close.token_out) == (open.token_out, open.token_in)
+        }) else {
+            continue;
+        };
+        let close = &swaps[j];
+
+        let others: Vec<&Swap> = swaps[i + 1..j]
+            .iter()
+            .filter(|swap| swap.from != open.from && swap.same_pair(open))
+            .collect();
+        let victims: Vec<&Swap> = others
+            .iter()
+            .copied()
+            .filter(|swap| swap.token_in == open.token_in)
+            .collect();
+
+        let mev = if !victims.is_empty() {
+            let victim_volume = victims.iter().fold(U256::zero(), |total, swap| {
+                total.saturating_add(swap.amount_in)
+            });
+            LandedMev::Sandwich {
+                searcher: open.from,
+                frontrun: open.hash,
+                victims: victims.iter().map(|swap| swap.hash).collect(),
+                backrun: close.hash,
+                token: open.token_in,
+                extracted: sandwich_profit(open, victim_volume, pools),
+            }
+        } else if others.is_empty() && open.router != close.router {
+            LandedMev::RoundTripArbitrage {
+                searcher: open.from,
+                open: open.hash,
+                close: close.hash,
+                token: open.token_in,
+                extracted: close.amount_out_min.saturating_sub(open.amount_in),
+            }
+        } else {
+            continue;
+        };
+        claimed[i] = true;
+        claimed[j] = true;
+        found.push(mev);
+    }
+    found
+}
+
+/// Estimates what a frontrun of `frontrun.amount_in` ahead of
+/// `victim_volume` returned, in the deepest known pool for the pair.
+fn sandwich_profit(frontrun: &Swap, victim_volume: U256, pools: &[PoolInfo]) -> Option<U256> {
+    // ---
+
+    let pool = pools
+        .iter()
+        .filter(|pool| {
+            (pool.token_a, pool.token_b) == (frontrun.token_in, frontrun.token_out)
+                || (pool.token_b, pool.token_a) == (frontrun.token_in, frontrun.token_out)
+        })
+        .max_by(|a, b| a.liquidity_usd.total_cmp(&b.liquidity_usd))?;
+    let (reserve_in, reserve_out) = if pool.token_a == frontrun.token_in {
+        (pool.reserve_a, pool.reserve_b)
+    } else {
+        (pool.reserve_b, pool.reserve_a)
+    };
+
+    // A frontrun too large for the known reserves can't be priced
+    let frontrun_out =
+        amm::get_amount_out(frontrun.amount_in, reserve_in, reserve_out, pool.fee_bps);
+    if frontrun_out >= reserve_out {
+        return None;
+    }
+    let quote = amm::quote_sandwich(
+        frontrun.amount_in,
+        victim_volume,
+        reserve_in,
+        reserve_out,
+        pool.fee_bps,
+    );
+    Some(quote.backrun_out.saturating_sub(frontrun.amount_in))
+}
+
+/// Follows the chain head through `base_fees` and analyzes every block
+/// confirmed after the first head it reports, logging what landed. Until the
+/// tracker has seen a header (head 0), nothing is fetched.
+///
+/// Returns the number of block transactions analyzed once `limits.max_tx`
+/// is reached, the deadline passes, or the head stays still for
+/// `limits.idle_timeout`.
+///
+/// # Errors
+/// Returns an error if a block can't be fetched.
+pub async fn follow_blocks<P>(
+    provider: Arc<Provider<P>>,
+    base_fees: Arc<BaseFeeTracker>,
+    limits: FollowLimits,
+) -> anyhow::Result<usize>
+where
+    P: JsonRpcClient + 'static,
+{
+    // ---
+
+    info!("🔎 Analyzing confirmed blocks for landed MEV - no bundles will be built or submitted");
+
+    let registry = TokenRegistry::with_defaults();
+    let pools = searcher::default_pools(&registry);
+    let mut next: Option<U64> = None;
+    let mut last_head_at = TokioInstant::now();
+    let (mut analyzed, mut blocks, mut sandwiches, mut arbitrages) = (0, 0, 0, 0);
+
+    let stop = loop {
+        if analyzed >= limits.max_tx {
+            break "max_tx";
+        }
+        let now = TokioInstant::now();
+        if limits.deadline.is_some_and(|deadline| now >= deadline) {
+            break "time budget";
+        }
+
+        let head = base_fees.block_number();
+        if next.is_none() && !head.is_zero() {
+            next = Some(head + 1);
+        }
+        let Some(number) = next.filter(|next| head >= *next) else {
+            if limits
+                .idle_timeout
+                .is_some_and(|idle| now.duration_since(last_head_at) >= idle)
+            {
+                break "stalled head";
+            }
+            tokio::time::sleep(HEAD_CHECK_INTERVAL).await;
+            continue;
+        };
+        last_head_at = now;
+
+        let block = provider
+            .get_block_with_txs(number)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch block {}: {}", number, e))?
+            .ok_or_else(|| anyhow::anyhow!("Block {} not found", number))?;
+
+        let landed = analyze_block(&block.transactions, &pools);
+        for mev in &landed {
+            match mev {
+                LandedMev::Sandwich { .. } => sandwiches += 1,
+                LandedMev::RoundTripArbitrage { .. } => arbitrages += 1,
+            }
+            log_landed(number, mev, &registry);
+        }
+        debug!(
+            "Block {}: {} txs, {} landed MEV",
+            number,
+            block.transactions.len(),
+            landed.len()
+        );
+
+        analyzed += block.transactions.len();
+        blocks += 1;
+        next = Some(number + 1);
+    };
+
+    info!(
+        "🔎 Analyzed {} blocks ({} txs) until {}: {} sandwiches, {} round-trip arbitrages landed",
+        blocks, analyzed, stop, sandwiches, arbitrages
+    );
+    Ok(analyzed)
+}
+
+/// Logs one finding with its estimated value in the profit token's units.
+fn log_landed(block: U64, mev: &LandedMev, registry: &TokenRegistry) {
+    // ---
+    match mev {
+        LandedMev::Sandwich {
+            searcher,
+            frontrun,
+            victims,
+            backrun,
+            token,
+            extracted,
+        } => info!(
+            "🥪 Landed sandwich in block {} by {:?}: frontrun {:?}, {} victim(s), backrun {:?}; extracted ~{}",
+            block,
+            searcher,
+            frontrun,
+            victims.len(),
+            backrun,
+            extracted.map_or("unknown (no known pool)".to_string(), |amount| {
+                format_token_amount(*token, amount, registry)
+            })
+        ),
+        LandedMev::RoundTripArbitrage {
+            searcher,
+            open,
+            close,
+            token,
+            extracted,
+        } => info!(
+            "🔁 Landed round-trip arbitrage in block {} by {:?}: {:?} then {:?}; extracted at least {}",
+            block,
+            searcher,
+            open,
+            close,
+            format_token_amount(*token, *extracted, registry)
+        ),
+    }
+}
+
+/// Formats `amount` base units of `token` with its symbol, or raw units when
+/// the token isn't known.
+fn format_token_amount(token: Address, amount: U256, registry: &TokenRegistry) -> String {
+    // ---
+    match registry.get(&token) {
+        Some(info) => format!(
+            "{} {}",
+            ethers::utils::format_units(amount, u32::from(info.decimals)).unwrap_or_default(),
+            info.symbol
+        ),
+        None => format!("{} units of {:?}", amount, token),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{pair_swap_tx, MockProvider, TEST_BASE_FEE_WEI};
+    use ethers::types::{Block, H256};
+
+    fn at(n: u64, mut tx: Transaction) -> Transaction {
+        tx.hash = TxHash::from_low_u64_be(n);
+        tx
+    }
+
+    #[test]
+    fn replayed_sandwich_is_found_and_valued() {
+        let eth = U256::exp10(18);
+        let sandwicher = Address::repeat_byte(0x5e);
+        let victim = Address::repeat_byte(0x71);
+        let bystander = Address::repeat_byte(0xb7);
+        let registry = TokenRegistry::with_defaults();
+        let pools = searcher::default_pools(&registry);
+
+        let block = vec![
+            at(1, pair_swap_tx(bystander, eth, ["DAI", "USDC"])),
+            at(2, pair_swap_tx(sandwicher, eth * 20, ["WETH", "USDC"])),
+            at(3, pair_swap_tx(victim, eth * 100, ["WETH", "USDC"])),
+            at(4, pair_swap_tx(bystander, eth, ["WETH", "DAI"])),
+            at(
+                5,
+                pair_swap_tx(sandwicher, U256::exp10(6) * 49_000, ["USDC", "WETH"]),
+            ),
+            at(6, Transaction::default()),
+        ];
+
+        let landed = analyze_block(&block, &pools);
+        assert_eq!(landed.len(), 1, "{landed:?}");
+        let LandedMev::Sandwich {
+            searcher: sender,
+            frontrun,
+            victims,
+            backrun,
+            token,
+            extracted,
+        } = &landed[0]
+        else {
+            panic!("expected a sandwich: {landed:?}");
+        };
+        assert_eq!(*sender, sandwicher);
+        assert_eq!(*frontrun, TxHash::from_low_u64_be(2));
+        assert_eq!(*victims, [TxHash::from_low_u64_be(3)]);
+        assert_eq!(*backrun, TxHash::from_low_u64_be(5));
+
+        // The AMM math the sandwich detector uses, on the deepest WETH/USDC pool
+        let (weth, usdc) = (
+            registry.address_of("WETH").unwrap(),
+            registry.address_of("USDC").unwrap(),
+        );
+        assert_eq!(*token, weth);
+        let pool = pools
+            .iter()
+            .filter(|pool| (pool.token_a, pool.token_b) == (weth, usdc))
+            .max_by(|a, b| a.liquidity_usd.total_cmp(&b.liquidity_usd))
+            .unwrap();
+        let quote = amm::quote_sandwich(
+            eth * 20,
+            eth * 100,
+            pool.reserve_a,
+            pool.reserve_b,
+            pool.fee_bps,
+        );
+        assert_eq!(*extracted, Some(quote.backrun_out - eth * 20));
+        assert!(extracted.unwrap() > U256::zero());
+    }
+
+    #[test]
+    fn round_trips_count_only_across_routers_without_interlopers() {
+        let eth = U256::exp10(18);
+        let arbitrageur = Address::repeat_byte(0x5e);
+        let via = |mut tx: Transaction, router: u8| {
+            tx.to = Some(Address::repeat_byte(router));
+            tx
+        };
+
+        let arbitrage = vec![
+            at(
+                1,
+                via(pair_swap_tx(arbitrageur, eth, ["WETH", "USDC"]), 0x01),
+            ),
+            at(
+                2,
+                via(
+                    pair_swap_tx(arbitrageur, U256::exp10(6) * 2_600, ["USDC", "WETH"]),
+                    0x02,
+                ),
+            ),
+        ];
+        let landed = analyze_block(&arbitrage, &[]);
+        assert!(
+            matches!(landed.as_slice(), [LandedMev::RoundTripArbitrage { open, close, .. }]
+                if *open == TxHash::from_low_u64_be(1) && *close == TxHash::from_low_u64_be(2)),
+            "{landed:?}"
+        );
+
+        // Back and forth through one router is just a user changing their mind
+        let same_router = vec![
+            at(
+                1,
+                via(pair_swap_tx(arbitrageur, eth, ["WETH", "USDC"]), 0x01),
+            ),
+            at(
+                2,
+                via(
+                    pair_swap_tx(arbitrageur, U256::exp10(6) * 2_600, ["USDC", "WETH"]),
+                    0x01,
+                ),
+            ),
+        ];
+        assert!(analyze_block(&same_router, &[]).is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn following_starts_after_the_first_seen_head() {
+        let mock = Arc::new(MockProvider::default().with_response(
+            "eth_getBlockByNumber",
+            Block::<Transaction> {
+                number: Some(U64::from(101)),
+                ..Default::default()
+            },
+        ));
+        let base_fees = Arc::new(BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI)));
+        let limits = FollowLimits {
+            max_tx: usize::MAX,
+            deadline: None,
+            idle_timeout: Some(Duration::from_secs(30)),
+        };
+
+        // The tracker's first header arrives after following has started
+        let heads = base_fees.clone();
+        tokio::spawn(async move {
+            for number in [100, 101] {
+                tokio::time::sleep(Duration::from_secs(12)).await;
+                heads.observe_block(&Block::<H256> {
+                    number: Some(U64::from(number)),
+                    base_fee_per_gas: Some(U256::from(TEST_BASE_FEE_WEI)),
+                    ..Default::default()
+                });
+            }
+        });
+        follow_blocks(Arc::new(Provider::new(mock.clone())), base_fees, limits)
+            .await
+            .unwrap();
+
+        // Only the block after the first head is fetched, not block 1
+        let fetched = mock.params("eth_getBlockByNumber");
+        assert_eq!(fetched.len(), 1);
+        assert_eq!(fetched[0][0], "0x65");
+    }
+}
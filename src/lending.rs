@@ -0,0 +1,154 @@
+//! Lending-protocol liquidation math.
+//!
+//! Pure functions for sizing liquidations: how much of a position's debt a
+//! single liquidation may repay (the protocol's close factor), how much of that
+//! cap to actually use, and the bonus earned on the repaid amount. Like
+//! [`crate::amm`], nothing here touches RPC or mock data.
+
+use crate::amm::BPS_DENOMINATOR;
+use crate::searcher::Protocol;
+use crate::types::LiquidationSizing;
+use ethers::types::{U256, U512};
+
+// ---
+
+/// Returns the share of a position's debt one liquidation may repay, in basis points.
+///
+/// Aave V2 and Compound cap a single liquidation at 50% of the borrow; Maker
+/// auctions the whole vault and Euler lets liquidators repay up to the full debt.
+pub fn close_factor_bps(protocol: Protocol) -> u64 {
+    // ---
+    match protocol {
+        Protocol::Aave | Protocol::Compound => 5_000,
+        Protocol::MakerDAO | Protocol::Euler => BPS_DENOMINATOR,
+    }
+}
+
+/// Returns the bonus paid to liquidators on the repaid debt, in basis points.
+///
+/// Protocol-wide defaults; actual incentives vary per collateral asset.
+pub fn liquidation_bonus_bps(protocol: Protocol) -> u64 {
+    // ---
+    match protocol {
+        Protocol::Aave | Protocol::Euler => 500,
+        Protocol::Compound => 800,
+        Protocol::MakerDAO => 1_300,
+    }
+}
+
+/// Returns the largest amount of `debt` a single liquidation may repay.
+pub fn max_liquidatable_debt(protocol: Protocol, debt: U256) -> U256 {
+    // ---
+    share_of(debt, close_factor_bps(protocol))
+}
+
+/// Returns the debt to repay for a liquidation, applying `sizing` to the
+/// protocol's close-factor cap.
+///
+/// `Partial` fractions above 100% are clamped, so the result never exceeds
+/// [`max_liquidatable_debt`].
+pub fn liquidation_repay_amount(protocol: Protocol, debt: U256, sizing: LiquidationSizing) -> U256 {
+    // ---
+
+    let max = max_liquidatable_debt(protocol, debt);
+
+    match sizing {
+        LiquidationSizing::Max => max,
+        LiquidationSizing::Partial { fraction_bps } => {
+            let fraction_bps = u64::from(fraction_bps).min(BPS_DENOMINATOR);
+            share_of(max, fraction_bps)
+        }
+    }
+}
+
+/// Returns the bonus earned by repaying `repay_amount`, in the debt token's units.
+pub fn liquidation_bonus(protocol: Protocol, repay_amount: U256) -> U256 {
+    // ---
+    share_of(repay_amount, liquidation_bonus_bps(protocol))
+}
+
+/// Returns `bps` basis points (at most 100%) of `amount`, rounded down,
+/// multiplying in 512 bits so no amount can overflow.
+fn share_of(amount: U256, bps: u64) -> U256 {
+    // ---
+    let share = amount.full_mul(U256::from(bps)) / U512::from(BPS_DENOMINATOR);
+    U256::try_from(share).unwrap_or(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROTOCOLS: [Protocol; 4] = [
+        Protocol::Aave,
+        Protocol::Compound,
+        Protocol::MakerDAO,
+        Protocol::Euler,
+    ];
+
+    #[test]
+    fn close_factor_caps_each_protocol() {
+        let debt = U256::from(1_000_000);
+        let caps = PROTOCOLS.map(|protocol| max_liquidatable_debt(protocol, debt));
+        assert_eq!(
+            caps,
+            [500_000, 500_000, 1_000_000, 1_000_000].map(U256::from)
+        );
+    }
+
+    #[test]
+    fn bonus_is_paid_at_each_protocols_rate() {
+        let repay = U256::from(1_000_000);
+        let bonuses = PROTOCOLS.map(|protocol| liquidation_bonus(protocol, repay));
+        assert_eq!(bonuses, [50_000, 80_000, 130_000, 50_000].map(U256::from));
+    }
+
+    #[test]
+    fn partial_sizing_scales_the_cap_and_clamps_above_it() {
+        let debt = U256::from(1_000_000);
+        let quarter = LiquidationSizing::Partial {
+            fraction_bps: 2_500,
+        };
+        assert_eq!(
+            liquidation_repay_amount(Protocol::Aave, debt, quarter),
+            U256::from(125_000)
+        );
+        assert_eq!(
+            liquidation_repay_amount(Protocol::Euler, debt, quarter),
+            U256::from(250_000)
+        );
+
+        let over = LiquidationSizing::Partial {
+            fraction_bps: u16::MAX,
+        };
+        for protocol in PROTOCOLS {
+            assert_eq!(
+                liquidation_repay_amount(protocol, debt, over),
+                max_liquidatable_debt(protocol, debt)
+            );
+            assert_eq!(
+                liquidation_repay_amount(protocol, debt, LiquidationSizing::Max),
+                max_liquidatable_debt(protocol, debt)
+            );
+        }
+    }
+
+    #[test]
+    fn amounts_round_down_and_never_overflow() {
+        // 0.05 * 19 wei rounds down to nothing
+        assert_eq!(
+            liquidation_bonus(Protocol::Aave, U256::from(19)),
+            U256::zero()
+        );
+        assert_eq!(
+            max_liquidatable_debt(Protocol::Compound, U256::from(3)),
+            U256::one()
+        );
+
+        assert_eq!(max_liquidatable_debt(Protocol::Euler, U256::MAX), U256::MAX);
+        assert_eq!(
+            liquidation_bonus(Protocol::MakerDAO, U256::MAX),
+            U256::MAX / 10_000 * 1_300 + U256::MAX % 10_000 * 1_300 / 10_000
+        );
+    }
+}
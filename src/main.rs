@@ -4,69 +4,554 @@
 //! Connects to an Ethereum node via WebSocket, listens for pending transactions,
 //! analyzes them for MEV opportunities, and creates/submits bundles for execution.
 
+use clap::builder::TypedValueParser;
 use clap::Parser;
 use dotenv::dotenv;
-use tracing::{debug, info};
+use ethers::signers::Signer;
+use ethers::types::Address;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::fmt::time::{ChronoLocal, ChronoUtc};
+use tracing_subscriber::fmt::{self, writer::BoxMakeWriter, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
 
+mod amm;
+mod audit;
+mod backtest;
+mod bench;
+mod build_info;
 mod bundler;
+mod control;
+mod explain;
+mod flashloan;
+mod gas;
+mod landed;
+mod lending;
 mod mempool;
+#[cfg(test)]
+mod mock;
+mod observe;
+mod oracle;
+mod positions;
+mod ratelimit;
+mod relay_stats;
+#[cfg(unix)]
+mod reload;
+mod replacement;
+mod report;
+mod reserves;
+mod routing;
 mod searcher;
+mod selfcheck;
+mod simulation;
+mod sink;
+mod snapshot;
+mod source;
+mod trace;
+#[allow(dead_code)] // config/metrics scaffolding, wired in incrementally
 mod types;
+mod units;
+mod wallet;
+mod websocket;
 
 // ---
 
-/// Application entry point.
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+/// Builds the log filter: `--log-filter` directives when given, otherwise a
+/// single level from `--verbose` (debug), `--bench` (warn), or the default (info).
+///
+/// # Errors
+///
+/// Returns an error if `--log-filter` is not a valid `RUST_LOG`-style directive list.
+fn log_filter(cli: &Args) -> anyhow::Result<EnvFilter> {
     // ---
 
-    dotenv().ok();
+    if let Some(directives) = &cli.log_filter {
+        return EnvFilter::try_new(directives)
+            .map_err(|e| anyhow::anyhow!("Invalid --log-filter '{}': {}", directives, e));
+    }
 
-    let cli = Args::parse();
-    let log_level = if cli.verbose { "debug" } else { "info" };
+    // Per-opportunity logs would dominate a benchmark or dry run; keep only warnings
+    let log_level = if cli.verbose {
+        "debug"
+    } else if cli.bench.is_some()
+        || cli.explain_config.is_some()
+        || cli.backtest.is_some()
+        || cli.snapshot
+    {
+        "warn"
+    } else {
+        "info"
+    };
+    Ok(EnvFilter::new(log_level))
+}
+
+/// RFC 3339 in UTC with microseconds and a `Z` suffix, e.g. `2025-01-02T03:04:05.678901Z`.
+const UTC_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.6fZ";
 
+/// Timer stamping log lines with [`UTC_TIMESTAMP_FORMAT`].
+fn utc_timer() -> ChronoUtc {
     // ---
+    ChronoUtc::new(UTC_TIMESTAMP_FORMAT.to_string())
+}
+
+/// Builds one log output in the `--log-format` format, stamped per `--timestamp`.
+///
+/// Timestamps are RFC 3339; in JSON they are the `timestamp` field.
+fn log_layer<S, W>(cli: &Args, ansi: bool, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    // ---
+
+    // Color codes would corrupt the JSON
+    let ansi = ansi && cli.log_format == LogFormat::Text;
+    let layer = fmt::layer().with_ansi(ansi).with_writer(writer);
+    match (cli.log_format, cli.timestamp) {
+        (LogFormat::Text, TimestampStyle::Utc) => layer.with_timer(utc_timer()).boxed(),
+        (LogFormat::Text, TimestampStyle::Local) => {
+            layer.with_timer(ChronoLocal::rfc_3339()).boxed()
+        }
+        (LogFormat::Text, TimestampStyle::None) => layer.without_time().boxed(),
+        (LogFormat::Json, TimestampStyle::Utc) => layer.json().with_timer(utc_timer()).boxed(),
+        (LogFormat::Json, TimestampStyle::Local) => {
+            layer.json().with_timer(ChronoLocal::rfc_3339()).boxed()
+        }
+        (LogFormat::Json, TimestampStyle::None) => layer.json().without_time().boxed(),
+    }
+}
 
-    // Initialize tracing with smart colorization
+/// Installs the tracing subscriber: console output, plus a daily-rolling file
+/// when `--log-file` is given.
+///
+/// # Returns
+///
+/// The file writer's guard, if a log file is configured. Buffered file logs are
+/// flushed when it is dropped, so the caller must keep it alive until exit.
+///
+/// # Errors
+///
+/// Returns an error if `--log-file` does not name a file.
+fn init_tracing(cli: &Args, filter: EnvFilter) -> anyhow::Result<Option<WorkerGuard>> {
+    // ---
+
+    // Initialize console output with smart colorization
     let use_color = match cli.color {
         ColorChoice::Always => true,
         ColorChoice::Never => false,
         ColorChoice::Auto => {
-            // Check if stdout is a terminal and not being redirected
-            std::io::IsTerminal::is_terminal(&std::io::stdout())
+            // Check if the log stream is a terminal and not being redirected
+            if cli.emit_opportunities {
+                std::io::IsTerminal::is_terminal(&std::io::stderr())
+            } else {
+                std::io::IsTerminal::is_terminal(&std::io::stdout())
+            }
+        }
+    };
+
+    // Keep stdout clean for the JSON opportunity stream when it is enabled
+    let console_writer = if cli.emit_opportunities {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    let console_layer = log_layer(cli, use_color, console_writer);
+
+    // File output is never colored, and is rolled over daily as `<name>.YYYY-MM-DD`
+    let (file_layer, guard) = match &cli.log_file {
+        Some(path) => {
+            let file_name = path.file_name().ok_or_else(|| {
+                anyhow::anyhow!("--log-file must name a file: {}", path.display())
+            })?;
+            let dir = path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or_else(|| Path::new("."));
+
+            let appender = tracing_appender::rolling::daily(dir, file_name);
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(log_layer(cli, false, writer)), Some(guard))
         }
+        None => (None, None),
     };
 
-    tracing_subscriber::fmt()
-        .with_env_filter(log_level)
-        .with_ansi(use_color)
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(console_layer)
+        .with(file_layer)
         .init();
 
-    info!("🚀 mempool-vortex starting...");
+    Ok(guard)
+}
+
+/// Applies the `--enable-*`/`--disable-*` strategy flags over the config's
+/// `enabled` settings; clap rejects both flags for one strategy.
+fn apply_strategy_flags(cli: &Args, config: &mut types::MEVConfig) {
+    // ---
+    let overrides = [
+        (
+            &mut config.arbitrage.enabled,
+            cli.enable_arbitrage,
+            cli.disable_arbitrage,
+        ),
+        (
+            &mut config.sandwich.enabled,
+            cli.enable_sandwich,
+            cli.disable_sandwich,
+        ),
+        (
+            &mut config.liquidation.enabled,
+            cli.enable_liquidation,
+            cli.disable_liquidation,
+        ),
+    ];
+    for (enabled, enable, disable) in overrides {
+        if enable {
+            *enabled = true;
+        } else if disable {
+            *enabled = false;
+        }
+    }
+}
+
+/// Application entry point.
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // ---
+
+    dotenv().ok();
+
+    let cli = Args::parse();
+    if cli.build_info {
+        println!("{}", build_info::report());
+        return Ok(());
+    }
+
+    // ---
+
+    // Held for the whole run so buffered file logs are flushed on exit
+    let _log_guard = init_tracing(&cli, log_filter(&cli)?)?;
+
+    info!("🚀 mempool-vortex {} starting...", build_info::summary());
+    debug!("🦀 Built with {}", build_info::RUSTC_VERSION);
     debug!("CLI args: {:?}", cli);
 
-    // Final RPC URL, use command line if available else fallback to .env
-    let rpc_url = cli
-        .rpc_url
-        .clone()
-        .or_else(|| std::env::var("ETH_RPC_URL").ok())
-        .ok_or_else(|| {
-            anyhow::anyhow!(
-                "Missing Ethereum RPC URL: provide via --rpc-url or set ETH_RPC_URL in .env"
-            )
-        })?;
+    // Benchmarks run detection on synthetic txs only, so no node is needed
+    if let Some(count) = cli.bench {
+        let mut config = types::Config::from_env()?;
+        apply_strategy_flags(&cli, &mut config.mev_config);
+        let dex_routers = types::DexRegistry::from_config(&config.dex_config)?;
+        let report = bench::run(usize::try_from(count)?, config.mev_config, &dex_routers).await;
+        println!("{}", report.summary_table());
+        return Ok(());
+    }
+
+    // Dry runs compare two configs on sample txs, so no node is needed either
+    if let Some(path) = &cli.explain_config {
+        let config = types::Config::from_env()?;
+        let proposed = types::Config::from_file(path)?;
+        proposed.mev_config.validate()?;
+        let dex_routers = types::DexRegistry::from_config(&config.dex_config)?;
+        let diff = explain::run(
+            explain::EXPLAIN_SAMPLE_TXS,
+            config.mev_config,
+            proposed.mev_config,
+            &dex_routers,
+        )
+        .await;
+        println!("{}", diff.summary_table());
+        return Ok(());
+    }
+
+    // Backtests replay a capture through offline detection, so no node is needed
+    if let Some(path) = &cli.backtest {
+        let mut config = match &cli.backtest_config {
+            Some(config_path) => types::Config::from_file(config_path)?,
+            None => types::Config::from_env()?,
+        };
+        apply_strategy_flags(&cli, &mut config.mev_config);
+        config.mev_config.validate()?;
+        let capture = backtest::load_capture(path)?;
+        let report = backtest::run(
+            &capture,
+            config.mev_config,
+            backtest::inclusion_probability(&config.relay_config),
+        )
+        .await;
+        println!("{}", report.summary_table());
+        return Ok(());
+    }
+
+    // Final RPC URLs, use command line if available else fallback to .env
+    let rpc_urls: Vec<String> = if cli.rpc_url.is_empty() {
+        std::env::var("ETH_RPC_URL")
+            .map(|urls| urls.split(',').map(|url| url.trim().to_string()).collect())
+            .unwrap_or_default()
+    } else {
+        cli.rpc_url.clone()
+    };
+    if rpc_urls.iter().all(|url| url.is_empty()) {
+        anyhow::bail!("Missing Ethereum RPC URL: provide via --rpc-url or set ETH_RPC_URL in .env");
+    }
+    // A bad failover URL should fail now, not when the primary drops
+    for url in &rpc_urls {
+        types::RpcTransport::from_url(url)?;
+    }
+
+    // Snapshots read the pool once from the primary node, without the pipeline
+    if cli.snapshot {
+        let ws = websocket::WsOptions {
+            headers: cli.ws_headers.clone(),
+            connect_timeout: Duration::from_secs(cli.ws_connect_timeout_secs),
+            max_frame_bytes: cli.ws_max_frame_bytes,
+        };
+        let report = snapshot::run(
+            &rpc_urls[0],
+            &ws,
+            Duration::from_secs(cli.snapshot_window_secs),
+        )
+        .await?;
+        println!("{}", report.summary_table());
+        return Ok(());
+    }
+
+    if !cli.filter_min_value_eth.is_finite() || cli.filter_min_value_eth < 0.0 {
+        anyhow::bail!("--filter-min-value-eth must be a non-negative number");
+    }
+    let min_value_wei = units::eth_f64_to_wei(cli.filter_min_value_eth);
+
+    let alert_min_profit_wei = match cli.alert_on_opportunity {
+        Some(eth) if !eth.is_finite() || eth < 0.0 => {
+            anyhow::bail!("--alert-on-opportunity must be a non-negative number")
+        }
+        Some(eth) => Some(units::eth_f64_to_wei(eth)),
+        None => None,
+    };
+
+    let mut config = types::Config::from_env()?;
+    apply_strategy_flags(&cli, &mut config.mev_config);
+    let strategies = config.mev_config.enabled_strategies();
+    if strategies.is_empty() {
+        warn!("🎛️ Every strategy is disabled; transactions will be logged but never traded");
+    } else {
+        info!("🎛️ Strategies enabled: {}", strategies.join(", "));
+    }
+
+    // The key stays inside the wallet, which the bundler keeps to sign bundle transactions
+    match wallet::load_searcher_wallet(cli.keystore.as_deref(), &cli.keystore_password_env)? {
+        Some(wallet) => {
+            let address = wallet.address();
+            info!(
+                "🔑 Searcher account: {}",
+                ethers::utils::to_checksum(&address, None)
+            );
+            bundler::set_searcher_wallet(wallet.with_chain_id(config.dex_config.chain_id));
+        }
+        None => info!("🔑 No searcher key configured; bundles use the zero address"),
+    }
+
+    let dex_routers = types::DexRegistry::from_config(&config.dex_config)?;
+
+    let relays = bundler::resolve_relays(&cli.relays)?;
+    let relay_order: Vec<&str> = relays.iter().map(|relay| relay.name.as_str()).collect();
+    info!("📡 Relay priority: {}", relay_order.join(" → "));
+
+    // Learned rates carry over from earlier runs; new relays start from the config
+    let mut learned_relay_stats = match &cli.relay_stats_file {
+        Some(path) => relay_stats::load(path)?,
+        None => Default::default(),
+    };
+    relay_stats::seed(&mut learned_relay_stats, &config.relay_config, &relay_order);
+    if cli.adaptive_relay_order {
+        info!("📡 Relays will be tried in order of learned inclusion rate");
+    }
+
+    if cli.startup_selfcheck {
+        let timeout = Duration::from_secs(config.relay_config.submission_timeout_secs);
+        let readiness = selfcheck::check_relays(&relays, timeout).await;
+        info!(
+            "🩺 Relay readiness:\n{}",
+            selfcheck::readiness_table(&readiness)
+        );
+
+        let not_ready: Vec<&str> = readiness
+            .iter()
+            .filter(|relay| !relay.is_ready())
+            .map(|relay| relay.relay.as_str())
+            .collect();
+        if !not_ready.is_empty() {
+            // Without submission the relays are never contacted, so only warn
+            if cli.simulate || cli.no_submit || cli.observe_blocks.is_some() || cli.analyze_blocks {
+                warn!("⚠️ Relays not ready: {}", not_ready.join(", "));
+            } else {
+                anyhow::bail!("Startup self-check failed for: {}", not_ready.join(", "));
+            }
+        }
+    }
 
     // ---
 
     // Complete MEV pipeline
     info!("🔗 Starting MEV pipeline...");
 
-    if cli.simulate {
+    // Block observation is observe-only, and the block count, not --max-tx, ends it
+    let observing = cli.observe_blocks.is_some();
+    if let Some(blocks) = cli.observe_blocks {
+        info!(
+            "🧱 Observing {} full blocks - no bundles will be built or submitted",
+            blocks
+        );
+    } else if cli.analyze_blocks {
+        info!("🔎 Following confirmed blocks for landed MEV instead of the mempool");
+    } else if cli.no_submit {
+        info!("🔬 Running in detection-only mode - no bundles will be built or submitted");
+    } else if cli.simulate {
         info!("🧪 Running in simulation mode - no actual bundle submissions");
     }
 
+    let (audit, audit_writer) = match &cli.audit_file {
+        Some(path) => {
+            let (audit, writer) = audit::AuditLog::create(path)?;
+            info!(
+                "🗒️ Recording each transaction's disposition to {}",
+                path.display()
+            );
+            (audit, Some(writer))
+        }
+        None => (audit::AuditLog::default(), None),
+    };
+
+    let mut sinks = sink::OpportunitySinks::default();
+    if cli.emit_opportunities {
+        sinks.push(sink::StdoutSink::new(cli.json_pretty));
+    }
+    if let Some(path) = &cli.opportunity_file {
+        sinks.push(sink::FileSink::open(path)?);
+        info!("📤 Appending detected opportunities to {}", path.display());
+    }
+    // With alerts on, the webhook only hears about opportunities worth alerting on
+    let mut alert_sinks = sink::OpportunitySinks::default();
+    if let Some(url) = &cli.opportunity_webhook {
+        let webhook = sink::WebhookSink::new(url)?;
+        match cli.alert_on_opportunity {
+            Some(eth) => {
+                alert_sinks.push(webhook);
+                info!(
+                    "📤 Posting opportunities netting at least {} ETH to a webhook",
+                    eth
+                );
+            }
+            None => {
+                sinks.push(webhook);
+                info!("📤 Posting detected opportunities to a webhook");
+            }
+        }
+    }
+
+    let options = mempool::ListenerOptions {
+        max_tx: if observing { usize::MAX } else { cli.max_tx },
+        addr_style: cli.addr_style.clone(),
+        value_decimals: cli.value_decimals,
+        simulate: cli.simulate,
+        no_submit: cli.no_submit || observing,
+        min_value_wei,
+        exclude_to: cli.exclude_to.iter().copied().collect(),
+        quiet_excluded: cli.quiet_excluded,
+        quiet: cli.quiet,
+        inclusion_odds: searcher::InclusionOdds::from_config(&config.relay_config, &relay_order),
+        relays,
+        source: cli.source,
+        full_pending: cli.full_pending,
+        txpool_poll_interval: Duration::from_millis(cli.txpool_interval_ms),
+        ws: websocket::WsOptions {
+            headers: cli.ws_headers.clone(),
+            connect_timeout: Duration::from_secs(cli.ws_connect_timeout_secs),
+            max_frame_bytes: cli.ws_max_frame_bytes,
+        },
+        startup_retry: websocket::StartupRetry {
+            retries: cli.startup_retries,
+            delay: Duration::from_secs(cli.startup_retry_delay_secs),
+        },
+        idle_timeout: cli.idle_timeout_secs.map(Duration::from_secs),
+        deadline: cli
+            .shutdown_after_secs
+            .map(|secs| tokio::time::Instant::now() + Duration::from_secs(secs)),
+        gas_config: config.gas_config,
+        sinks,
+        alert_min_profit_wei,
+        alert_sinks,
+        cooldown_blocks: cli.cooldown_blocks,
+        relay_mode: cli.relay_mode,
+        broadcast_wait: cli.broadcast_wait,
+        relay_timeout: Duration::from_secs(config.relay_config.submission_timeout_secs),
+        bundle_limits: bundler::BundleLimits {
+            max_gas: config.relay_config.max_bundle_gas,
+            max_txs: config.relay_config.max_bundle_txs,
+        },
+        rpc_rps: cli.rpc_rps,
+        dex_routers: Arc::new(dex_routers),
+        analysis_timeout: Duration::from_millis(cli.analysis_timeout_ms),
+        report: cli.report.clone(),
+        relay_stats: learned_relay_stats,
+        relay_stats_file: cli.relay_stats_file.clone(),
+        adaptive_relay_order: cli.adaptive_relay_order,
+        max_relay_concurrency: cli.max_relay_concurrency,
+        trace_bundles: cli.trace_bundles,
+        simulate_bundles: cli.simulate_bundles,
+        max_pending_queue: cli.max_pending_queue,
+        max_in_flight_analyses: cli.max_in_flight_analyses,
+        observe_blocks: cli.observe_blocks,
+        analyze_blocks: cli.analyze_blocks,
+        audit,
+    };
+
+    // Detectors and the control API share one live strategy config and the
+    // session's metrics
+    let mev_config = Arc::new(RwLock::new(config.mev_config));
+    let metrics = mempool::session_metrics(&options);
+    if let Some(addr) = cli.control_addr {
+        let token = std::env::var(&cli.control_token_env)
+            .ok()
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "--control-addr needs a bearer token in the {} environment variable",
+                    cli.control_token_env
+                )
+            })?;
+        let api = control::ControlApi::new(mev_config.clone(), metrics.clone(), token);
+        let bound = control::spawn(addr, api)?;
+        info!("🎛️ Control API listening on http://{}", bound);
+    }
+    #[cfg(unix)]
+    {
+        let flags = cli.clone();
+        reload::spawn_on_hangup(
+            mev_config.clone(),
+            types::CONFIG_FILE.into(),
+            move |config| apply_strategy_flags(&flags, config),
+        )?;
+        info!(
+            "🔄 Send SIGHUP to reload strategy settings from {}",
+            types::CONFIG_FILE
+        );
+    }
+
     // Start mempool listener with integrated MEV detection and execution
-    mempool::listen_to_mempool(&rpc_url, cli.max_tx, cli.addr_style, cli.simulate).await?;
+    let result = mempool::listen_to_mempool(&rpc_urls, options, mev_config, metrics).await;
+    if let Some(writer) = audit_writer {
+        if !writer.finish(audit::AUDIT_FLUSH_TIMEOUT) {
+            warn!("🗒️ Audit file may be missing the last records: writer did not finish in time");
+        }
+    }
+    result?;
 
     info!("✅ MEV pipeline completed successfully");
     Ok(())
@@ -75,7 +560,7 @@ async fn main() -> anyhow::Result<()> {
 // ---
 
 /// Command-line arguments for mempool-vortex.
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(
     name = "mempool-vortex",
     version,
@@ -88,6 +573,48 @@ async fn main() -> anyhow::Result<()> {
         mempool-vortex --max-tx 200\n  \
         mempool-vortex --rpc-url wss://eth-sepolia.g.alchemy.com/v2/KEY --verbose\n  \
         mempool-vortex --simulate --addr-style full\n  \
+        mempool-vortex --simulate --filter-min-value-eth 0.5\n  \
+        mempool-vortex --simulate --exclude-to 0x00000000000000ADc04C56Bf30aC9d3c0aAF14dC --quiet-excluded\n  \
+        mempool-vortex --simulate --analysis-timeout-ms 500\n  \
+        mempool-vortex --simulate --quiet\n  \
+        mempool-vortex --no-submit --max-pending-queue 2000\n  \
+        mempool-vortex --no-submit --emit-opportunities > opportunities.jsonl\n  \
+        mempool-vortex --no-submit --alert-on-opportunity 0.5\n  \
+        mempool-vortex --simulate --audit-file audit.jsonl\n  \
+        mempool-vortex --relays flashbots,bloxroute\n  \
+        mempool-vortex --relays flashbots --startup-selfcheck\n  \
+        mempool-vortex --adaptive-relay-order --relay-stats-file relay-stats.json\n  \
+        mempool-vortex --simulate --rpc-url http://127.0.0.1:8545 --trace-bundles\n  \
+        mempool-vortex --simulate --rpc-url http://127.0.0.1:8545 --simulate-bundles\n  \
+        KEYSTORE_PASSWORD=... mempool-vortex --keystore ~/.vortex/searcher.json\n  \
+        CONTROL_API_TOKEN=... mempool-vortex --simulate --control-addr 127.0.0.1:8080\n  \
+        mempool-vortex --relays flashbots,bloxroute,eden --relay-mode broadcast\n  \
+        mempool-vortex --relays flashbots,bloxroute,eden --relay-mode broadcast --broadcast-wait first\n  \
+        mempool-vortex --relays flashbots,bloxroute,eden --relay-mode broadcast --max-relay-concurrency 4\n  \
+        mempool-vortex --source txpool --txpool-interval-ms 500\n  \
+        mempool-vortex --simulate --full-pending\n  \
+        mempool-vortex --ws-header \"X-Api-Key: KEY\" --ws-connect-timeout-secs 5 --ws-max-frame-bytes 67108864\n  \
+        mempool-vortex --startup-retries 10 --startup-retry-delay-secs 3\n  \
+        mempool-vortex --rpc-url https://rpc.example.org --rpc-rps 25\n  \
+        mempool-vortex --rpc-url wss://primary.example.org,wss://backup.example.org --idle-timeout-secs 30\n  \
+        mempool-vortex --bench 10000\n  \
+        mempool-vortex --explain-config proposed_config.json\n  \
+        mempool-vortex --backtest capture.jsonl --backtest-config proposed_config.json\n  \
+        mempool-vortex --snapshot --rpc-url wss://eth-sepolia.g.alchemy.com/v2/KEY --snapshot-window-secs 10\n  \
+        mempool-vortex --emit-opportunities | jq .\n  \
+        mempool-vortex --no-submit --emit-opportunities --json-pretty\n  \
+        mempool-vortex --no-submit --opportunity-file opportunities.jsonl\n  \
+        mempool-vortex --simulate --opportunity-webhook https://hooks.example.com/mev\n  \
+        mempool-vortex --log-file /var/log/mempool-vortex/vortex.log\n  \
+        mempool-vortex --log-format json --timestamp utc > vortex.jsonl\n  \
+        mempool-vortex --simulate --value-decimals 4\n  \
+        mempool-vortex --no-submit --max-tx 100000 --shutdown-after-secs 600\n  \
+        mempool-vortex --no-submit --shutdown-after-secs 3600 --report run-report.json\n  \
+        mempool-vortex --observe-blocks 20 --report blocks.json\n  \
+        mempool-vortex --analyze-blocks --shutdown-after-secs 600\n  \
+        mempool-vortex --simulate --disable-arbitrage --enable-liquidation\n  \
+        mempool-vortex --simulate --log-filter mempool_vortex::bundler=debug,info\n  \
+        mempool-vortex --build-info\n  \
         ETH_RPC_URL=wss://eth-sepolia.g.alchemy.com/v2/KEY mempool-vortex --simulate"
 )]
 pub struct Args {
@@ -96,20 +623,105 @@ pub struct Args {
     #[arg(short, long)]
     pub verbose: bool,
 
+    /// Skip the routine per-transaction log line.
+    ///
+    /// High-value and high-gas alerts, detected opportunities, and the final
+    /// summary are still logged.
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Per-module log filter in RUST_LOG syntax (e.g. "mempool_vortex::bundler=debug,info").
+    ///
+    /// Overrides the level chosen by --verbose (and --bench) when given.
+    #[arg(long, value_name = "DIRECTIVES")]
+    pub log_filter: Option<String>,
+
     /// Run in simulation mode (no real bundle submission)
+    ///
+    /// Bundles are still built (approvals, gas estimates) but never sent to relays.
     #[arg(long)]
     pub simulate: bool,
 
-    /// Ethereum RPC WebSocket URL to connect to.
+    /// Detect and report opportunities only; never build or submit bundles.
     ///
-    /// Optional: can also be provided via the ETH_RPC_URL environment variable
-    /// (dotenv is supported).
+    /// Lighter than --simulate, which still builds bundles: use this for pure
+    /// mempool analytics.
+    #[arg(long, conflicts_with = "simulate")]
+    pub no_submit: bool,
+
+    /// Run arbitrage (direct and multi-hop) this run, overriding the config.
+    #[arg(long, conflicts_with = "disable_arbitrage")]
+    pub enable_arbitrage: bool,
+
+    /// Skip arbitrage (direct and multi-hop) this run, overriding the config.
+    #[arg(long)]
+    pub disable_arbitrage: bool,
+
+    /// Run sandwich detection this run, overriding the config.
+    #[arg(long, conflicts_with = "disable_sandwich")]
+    pub enable_sandwich: bool,
+
+    /// Skip sandwich detection this run, overriding the config.
+    #[arg(long)]
+    pub disable_sandwich: bool,
+
+    /// Run liquidations this run, overriding the config.
+    #[arg(long, conflicts_with = "disable_liquidation")]
+    pub enable_liquidation: bool,
+
+    /// Skip liquidations this run, overriding the config.
+    #[arg(long)]
+    pub disable_liquidation: bool,
+
+    /// Ethereum RPC URL to connect to (wss:// preferred; https:// polls txpool_content).
+    ///
+    /// Repeat the flag or give a comma-separated list for failover: the first
+    /// URL is the primary, and the listener moves to the next one when the
+    /// connection drops or goes idle. Optional: can also be provided via the
+    /// ETH_RPC_URL environment variable (dotenv is supported).
     #[arg(
         long,
-        value_name = "WSS_URL",
+        value_name = "URL",
+        value_delimiter = ',',
         env = "ETH_RPC_URL" // clap reads from env (dotenv already loaded in main)
     )]
-    rpc_url: Option<String>,
+    rpc_url: Vec<String>,
+
+    /// Seconds without a pending transaction before the stream is considered
+    /// stalled and the listener fails over to the next --rpc-url.
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub idle_timeout_secs: Option<u64>,
+
+    /// Extra header for the WebSocket handshake, as NAME:VALUE (repeatable).
+    ///
+    /// For providers that authenticate with a header (e.g. an API key) rather
+    /// than a key in the URL. Values are redacted from logs.
+    #[arg(long = "ws-header", value_name = "NAME:VALUE")]
+    pub ws_headers: Vec<websocket::WsHeader>,
+
+    /// Seconds allowed to open the WebSocket connection before giving up.
+    #[arg(long, value_name = "SECS", default_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    pub ws_connect_timeout_secs: u64,
+
+    /// Largest WebSocket frame accepted from the node, in bytes.
+    ///
+    /// Raise it when large txpool_content responses exceed the default limits;
+    /// the message size limit is raised to match if needed.
+    #[arg(long, value_name = "BYTES")]
+    pub ws_max_frame_bytes: Option<usize>,
+
+    /// Times to retry the first WebSocket connection while the node is not
+    /// reachable yet (refused, reset, timed out, or a 5xx from a proxy).
+    ///
+    /// Lets the bot wait for a node started alongside it instead of exiting.
+    /// A rejected handshake (e.g. 401 for a bad API key) is never retried.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub startup_retries: u32,
+
+    /// Seconds before the first startup retry; doubles after each failed
+    /// attempt, up to a minute.
+    #[arg(long, value_name = "SECS", default_value = "2", value_parser = clap::value_parser!(u64).range(1..))]
+    pub startup_retry_delay_secs: u64,
 
     /// Maximum number of transactions to process before exiting.
     #[arg(
@@ -120,10 +732,349 @@ pub struct Args {
     )]
     pub max_tx: usize,
 
+    /// Stop after this many seconds, draining in-flight transactions, even if
+    /// --max-tx hasn't been reached; whichever limit comes first ends the run.
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub shutdown_after_secs: Option<u64>,
+
+    /// Write a JSON run report (final metrics, opportunities by strategy,
+    /// latency percentiles, top pairs, run duration) to this file on exit,
+    /// however the run ends.
+    #[arg(long, value_name = "PATH")]
+    pub report: Option<PathBuf>,
+
+    /// Record every transaction's final disposition (skipped, no_opportunity,
+    /// detected, submitted, rejected) with its call type and reason to this
+    /// file as JSON lines, for offline analysis. The file is truncated first.
+    #[arg(long, value_name = "PATH")]
+    pub audit_file: Option<PathBuf>,
+
+    /// Observe the mempool for this many full blocks, then print a per-block
+    /// table (transactions, high-value transactions, opportunities by
+    /// strategy, gas price distribution) and stop.
+    ///
+    /// Observe-only: bundles are never built, as with --no-submit. The block
+    /// already latest at startup is only partly seen and is left out; --report
+    /// exports the table as well.
+    #[arg(long, value_name = "N", conflicts_with_all = ["max_tx", "simulate"], value_parser = clap::value_parser!(u64).range(1..))]
+    pub observe_blocks: Option<u64>,
+
+    /// Follow confirmed blocks instead of the mempool and flag sandwiches and
+    /// round-trip arbitrages that landed in them, with an estimate of the
+    /// value each extracted.
+    ///
+    /// Observe-only: bundles are never built. Estimates use the known pool
+    /// reserves, not each block's, and swaps routed through searchers' own
+    /// contracts go unseen. --max-tx counts the blocks' transactions.
+    #[arg(
+        long,
+        conflicts_with_all = ["observe_blocks", "emit_opportunities", "opportunity_file", "opportunity_webhook", "trace_bundles", "simulate_bundles", "simulate", "no_submit"]
+    )]
+    pub analyze_blocks: bool,
+
+    /// Where pending transactions come from.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "SOURCE",
+        default_value = "subscribe",
+        long_help = "Where pending transactions come from.\n\
+                     • subscribe: eth_subscribe to pending transaction hashes\n\
+                     • txpool:    poll Geth's txpool_content (for nodes without pending subscriptions)"
+    )]
+    pub source: TxSource,
+
+    /// Subscribe to full pending transaction bodies instead of hashes, skipping
+    /// the per-hash fetch and its latency; falls back to hashes with a warning
+    /// if the node doesn't support it. Only affects --source subscribe.
+    #[arg(long)]
+    pub full_pending: bool,
+
+    /// Delay between txpool_content polls when --source txpool is selected.
+    #[arg(long, value_name = "MS", default_value = "1000")]
+    pub txpool_interval_ms: u64,
+
+    /// Blocks to suppress repeat bundles for the same token pair and strategy.
+    ///
+    /// A re-announced swap yields the same opportunity again; once a bundle is
+    /// built for a pair, identical opportunities are skipped for this many
+    /// blocks. 0 disables the cooldown.
+    #[arg(long, value_name = "BLOCKS", default_value = "3")]
+    pub cooldown_blocks: u64,
+
+    /// Print each detected opportunity to stdout as one JSON object per line.
+    ///
+    /// Logs move to stderr in this mode so the stream can be piped into other tools.
+    #[arg(long)]
+    pub emit_opportunities: bool,
+
+    /// Pretty-print emitted JSON records instead of one compact line each.
+    ///
+    /// Handy when reading captured output by hand; the stream remains valid
+    /// JSON (a sequence of objects, e.g. for `jq`), but is no longer one per line.
+    #[arg(long, requires = "emit_opportunities")]
+    pub json_pretty: bool,
+
+    /// Append each detected opportunity to this file as one JSON line, in the
+    /// --emit-opportunities format.
+    #[arg(long, value_name = "PATH")]
+    pub opportunity_file: Option<PathBuf>,
+
+    /// POST each detected opportunity as JSON, in the --emit-opportunities
+    /// format, to this http(s) URL.
+    ///
+    /// Deliveries run alongside analysis and never delay a bundle; one that
+    /// fails or takes over 5s is logged and dropped. Can also be provided via
+    /// the OPPORTUNITY_WEBHOOK_URL environment variable, keeping a token in
+    /// the URL out of the process list.
+    #[arg(
+        long,
+        value_name = "URL",
+        env = "OPPORTUNITY_WEBHOOK_URL",
+        hide_env_values = true
+    )]
+    pub opportunity_webhook: Option<String>,
+
+    /// Alert on every opportunity netting at least this much ETH after gas.
+    ///
+    /// Fires whether or not a bundle is built or sent, so profitable trades
+    /// passing by in --no-submit or --simulate runs can be followed up. The
+    /// alert is a warning carrying the --emit-opportunities record, also
+    /// posted to --opportunity-webhook, which then receives only alerts.
+    #[arg(long, value_name = "ETH")]
+    pub alert_on_opportunity: Option<f64>,
+
+    /// Only analyze transactions carrying at least this much ETH.
+    ///
+    /// Transactions below the threshold are still fetched and logged, but skip
+    /// MEV opportunity analysis. The default of 0 analyzes everything.
+    #[arg(long, value_name = "ETH", default_value = "0.0")]
+    pub filter_min_value_eth: f64,
+
+    /// Skip analysis of transactions sent to this address (repeatable).
+    ///
+    /// For noisy contracts such as a busy NFT mint. Takes precedence over the
+    /// other filters; excluded transactions are still logged unless
+    /// --quiet-excluded is given.
+    #[arg(long = "exclude-to", value_name = "ADDR")]
+    pub exclude_to: Vec<Address>,
+
+    /// Don't log transactions skipped by --exclude-to.
+    #[arg(long, requires = "exclude_to")]
+    pub quiet_excluded: bool,
+
+    /// Time budget for analyzing a single transaction, in milliseconds.
+    ///
+    /// A stalled quote or eth_call would otherwise hold the task indefinitely;
+    /// analyses that overrun are logged, counted, and dropped.
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value = "2000",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub analysis_timeout_ms: u64,
+
+    /// Pending transactions queued while analysis is at capacity.
+    ///
+    /// Under a mempool flood, arrivals beyond this depth push out the oldest
+    /// queued ones, which are dropped unanalyzed and counted, so memory stays
+    /// bounded.
+    #[arg(long, value_name = "N", default_value_t = mempool::DEFAULT_PENDING_QUEUE_DEPTH, value_parser = clap::value_parser!(u32).range(1..).map(|n| n as usize))]
+    pub max_pending_queue: usize,
+
+    /// Pending transactions analyzed at once.
+    ///
+    /// Arrivals beyond this wait in the pending queue (--max-pending-queue).
+    /// Higher values use more of the node's request capacity.
+    #[arg(long, value_name = "N", default_value_t = mempool::DEFAULT_MAX_IN_FLIGHT_ANALYSES, value_parser = clap::value_parser!(u32).range(1..).map(|n| n as usize))]
+    pub max_in_flight_analyses: usize,
+
+    /// Relays to submit bundles to, in priority order (comma-separated).
+    ///
+    /// Overrides which relays are enabled for this run: only the listed relays
+    /// are used, tried in the order given. Defaults to the built-in relay set.
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    pub relays: Vec<String>,
+
+    /// Try relays in order of their learned inclusion rate instead of the
+    /// configured priority.
+    ///
+    /// Each relay's acceptance and inclusion rates are tracked as moving
+    /// averages over the session, starting from its configured
+    /// inclusion_probability; the best recent performer is tried first.
+    #[arg(long)]
+    pub adaptive_relay_order: bool,
+
+    /// Load learned relay rates from this file at startup and save them on
+    /// exit, so they survive restarts. A missing file starts fresh.
+    #[arg(long, value_name = "PATH")]
+    pub relay_stats_file: Option<PathBuf>,
+
+    /// Trace each built bundle's transactions with debug_traceCall and log
+    /// the gas each used against its limit, and why any reverted.
+    ///
+    /// Needs a node with the debug namespace (a fork or a tracing node) and
+    /// costs a call per transaction. Each transaction is traced alone against
+    /// the latest state, so legs that depend on earlier ones may show reverts
+    /// the bundle wouldn't hit.
+    #[arg(long, conflicts_with_all = ["no_submit", "observe_blocks"])]
+    pub trace_bundles: bool,
+
+    /// Simulate each built bundle with eth_callBundle and reject it when the
+    /// builder would receive less than the intended builder payment plus the
+    /// profit margin.
+    ///
+    /// Needs a node serving eth_callBundle (a Flashbots-style builder or a
+    /// fork) and costs a call per bundle. A bundle the node can't simulate is
+    /// logged and sent anyway.
+    #[arg(long, conflicts_with_all = ["no_submit", "observe_blocks"])]
+    pub simulate_bundles: bool,
+
+    /// How bundles are sent to the selected relays.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        default_value = "sequential",
+        long_help = "How bundles are sent to the selected relays.\n\
+                     • sequential: try relays in priority order until one accepts\n\
+                     • broadcast:  send to all relays at once and log each relay's response"
+    )]
+    pub relay_mode: RelayMode,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WAIT",
+        default_value = "all",
+        long_help = "When a --relay-mode broadcast reports its result.\n\
+                     • first: as soon as one relay accepts; slower relays keep submitting\n\
+                     • all:   once every relay has answered or timed out\n\
+                     Each relay gets submission_timeout_secs (relay config) to answer."
+    )]
+    pub broadcast_wait: BroadcastWait,
+
+    /// Maximum relay requests in flight at once, across every bundle.
+    ///
+    /// Bounds the connections opened when many bundles fan out to many relays.
+    /// A submission finding every slot busy waits until its target block is
+    /// due, then fails.
+    #[arg(long, value_name = "N", default_value_t = bundler::DEFAULT_RELAY_CONCURRENCY, value_parser = clap::value_parser!(u16).range(1..).map(usize::from))]
+    pub max_relay_concurrency: usize,
+
+    /// Maximum RPC requests per second sent to the node (default: unlimited).
+    ///
+    /// Keeps the pipeline under provider quotas instead of hitting 429s. Calls
+    /// over the limit queue for up to 2 seconds; beyond that they are dropped
+    /// with a warning.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub rpc_rps: Option<u32>,
+
+    /// Also write logs to this file, rolled over daily (no ANSI colors).
+    ///
+    /// Console output is kept; the file receives the same log level.
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
     /// Control colored log output for terminal compatibility.
     #[arg(long, value_enum, value_name = "MODE", default_value = "auto")]
     pub color: ColorChoice,
 
+    /// Log line timestamps: RFC 3339 in UTC or local time, or none.
+    #[arg(long, value_enum, value_name = "STYLE", default_value = "utc")]
+    pub timestamp: TimestampStyle,
+
+    /// Log output format for the console and --log-file.
+    ///
+    /// `json` writes one JSON object per line, never colored, with the
+    /// timestamp in a `timestamp` field.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// Print build metadata (version, git commit, build time, rustc) and exit.
+    #[arg(long)]
+    pub build_info: bool,
+
+    /// Benchmark detection on N synthetic transactions, print a summary, and exit.
+    ///
+    /// Generates seeded V2/V3 swaps and transfers and runs them through opportunity
+    /// analysis back to back, without a node; reports txs/sec and a latency
+    /// histogram. Only warnings are logged unless --verbose is given.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub bench: Option<u64>,
+
+    /// Compare a proposed config file against the current one, print the diff, and exit.
+    ///
+    /// Runs a seeded sample of synthetic transactions through opportunity analysis
+    /// under both configs' strategy settings (the file uses the mev_config.json
+    /// format) and reports opportunities per strategy before and after, without
+    /// a node. Only warnings are logged unless --verbose is given.
+    #[arg(long, value_name = "PATH", conflicts_with = "bench")]
+    pub explain_config: Option<PathBuf>,
+
+    /// Replay a capture of pending transactions, print the profit they would have made, and exit.
+    ///
+    /// The capture holds one transaction per line in the eth_getTransactionByHash
+    /// JSON format. Reports opportunities, hit rate, and gross, net, and expected
+    /// profit (net scaled by the first relay's inclusion probability) per
+    /// strategy, without a node: pools are priced from the reserve snapshot
+    /// lines the capture records, or from built-in reserves where it has none.
+    /// Only warnings are logged unless --verbose is given.
+    #[arg(long, value_name = "CAPTURE", conflicts_with_all = ["bench", "explain_config"])]
+    pub backtest: Option<PathBuf>,
+
+    /// Config file (mev_config.json format) whose strategies and relay inclusion
+    /// probabilities --backtest uses, instead of the current config.
+    #[arg(long, value_name = "PATH", requires = "backtest")]
+    pub backtest_config: Option<PathBuf>,
+
+    /// Summarize the node's pending pool, print it, and exit.
+    ///
+    /// Reads txpool_content and reports the pending transactions by call type,
+    /// their total value, the distribution of their gas prices, and the
+    /// busiest senders. Over WebSocket, a node without txpool_content is
+    /// watched for --snapshot-window-secs instead. Only warnings are logged
+    /// unless --verbose is given.
+    #[arg(long, conflicts_with_all = ["bench", "explain_config", "backtest", "observe_blocks", "analyze_blocks"])]
+    pub snapshot: bool,
+
+    /// How long --snapshot watches pending transactions on a node without txpool_content.
+    #[arg(long, value_name = "SECS", default_value_t = 5, requires = "snapshot", value_parser = clap::value_parser!(u64).range(1..))]
+    pub snapshot_window_secs: u64,
+
+    /// Check each enabled relay's credentials and endpoint before listening.
+    ///
+    /// Prints a readiness table and exits non-zero if any relay is
+    /// misconfigured (only warns under --simulate or --no-submit).
+    #[arg(long)]
+    pub startup_selfcheck: bool,
+
+    /// Encrypted JSON keystore holding the searcher's signing key.
+    ///
+    /// Takes precedence over the plaintext PRIVATE_KEY environment variable.
+    /// The password is read from the variable named by --keystore-password-env.
+    #[arg(long, value_name = "PATH")]
+    pub keystore: Option<PathBuf>,
+
+    /// Environment variable holding the --keystore password.
+    #[arg(long, value_name = "VAR", default_value = "KEYSTORE_PASSWORD")]
+    pub keystore_password_env: String,
+
+    /// Serve the runtime control API on this address (e.g. 127.0.0.1:8080).
+    ///
+    /// GET /config returns the strategy config; PATCH /config adjusts min_profit,
+    /// max_gas_price_gwei, and arbitrage/sandwich/liquidation.enabled live;
+    /// GET /metrics serves the session metrics for Prometheus to scrape.
+    /// Requests must send the token from --control-token-env as
+    /// `Authorization: Bearer <token>`.
+    #[arg(long, value_name = "ADDR")]
+    pub control_addr: Option<SocketAddr>,
+
+    /// Environment variable holding the control API bearer token.
+    #[arg(long, value_name = "VAR", default_value = "CONTROL_API_TOKEN")]
+    pub control_token_env: String,
+
     /// Controls how Ethereum addresses are rendered in logs.
     ///
     /// Use `short` for compact logs or `full` when debugging exact addresses.
@@ -137,6 +1088,13 @@ pub struct Args {
                      • full:  full EIP-55 checksummed address"
     )]
     pub addr_style: AddrStyle,
+
+    /// Decimals shown for ETH amounts in log messages (rounded, 0-18).
+    ///
+    /// Tiny nonzero amounts show as e.g. <0.000001 rather than zero. JSON
+    /// output and structured log fields keep full precision.
+    #[arg(long, value_name = "N", default_value_t = units::DEFAULT_VALUE_DECIMALS, value_parser = clap::value_parser!(u8).range(0..=18).map(usize::from))]
+    pub value_decimals: usize,
 }
 
 // ---
@@ -149,6 +1107,31 @@ pub enum ColorChoice {
     Never,
 }
 
+/// How log lines are timestamped.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimestampStyle {
+    // ---
+    /// RFC 3339 in the local time zone, with its UTC offset.
+    Local,
+
+    /// RFC 3339 in UTC (`Z`).
+    Utc,
+
+    /// No timestamp, e.g. when a log collector adds its own.
+    None,
+}
+
+/// Log line format.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    // ---
+    /// Human-readable lines.
+    Text,
+
+    /// One JSON object per line.
+    Json,
+}
+
 /// How to render Ethereum addresses in logs.
 #[derive(clap::ValueEnum, Clone, Debug)]
 pub enum AddrStyle {
@@ -159,3 +1142,146 @@ pub enum AddrStyle {
     /// Full EIP-55 checksummed address with no elision.
     Full,
 }
+
+/// Where the listener reads pending transactions from.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum TxSource {
+    // ---
+    /// `eth_subscribe` to pending transaction hashes and fetch each body.
+    Subscribe,
+
+    /// Poll Geth's `txpool_content` and diff against the previous poll.
+    Txpool,
+}
+
+/// How bundles are sent to the selected relays.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum RelayMode {
+    // ---
+    /// Try relays in priority order, stopping at the first that accepts.
+    Sequential,
+
+    /// Send to every relay concurrently and report each relay's response.
+    Broadcast,
+}
+
+/// When a relay broadcast reports its result.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BroadcastWait {
+    // ---
+    /// At the first acceptance, without waiting on slower relays.
+    First,
+
+    /// Once every relay has answered or timed out.
+    All,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::Level;
+
+    #[test]
+    fn log_filter_targets_one_module() {
+        let cli = Args::try_parse_from([
+            "mempool-vortex",
+            "--verbose",
+            "--log-filter",
+            "mempool_vortex::bundler=debug,info",
+        ])
+        .unwrap();
+        let subscriber = tracing_subscriber::registry().with(log_filter(&cli).unwrap());
+
+        // Bundler debug gets through; everything else stays at info despite --verbose
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(tracing::enabled!(target: "mempool_vortex::bundler", Level::DEBUG));
+            assert!(!tracing::enabled!(target: "mempool_vortex::mempool", Level::DEBUG));
+            assert!(tracing::enabled!(target: "mempool_vortex::mempool", Level::INFO));
+        });
+    }
+
+    /// Log output collected in memory.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Logs one line through the layer `args` configure and returns it.
+    fn log_line(args: &[&str]) -> String {
+        let cli = Args::try_parse_from(["mempool-vortex"].iter().chain(args)).unwrap();
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber =
+            tracing_subscriber::registry().with(log_layer(&cli, false, move || writer.clone()));
+        tracing::subscriber::with_default(subscriber, || info!("🚀 ready"));
+
+        let line = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        line.trim_end().to_string()
+    }
+
+    #[test]
+    fn log_lines_carry_rfc3339_timestamps() {
+        // UTC is the default
+        let line = log_line(&[]);
+        let stamp = line.split_whitespace().next().unwrap();
+        let time = chrono::DateTime::parse_from_rfc3339(stamp).unwrap();
+        assert!(stamp.ends_with('Z'));
+        assert_eq!(time.offset().local_minus_utc(), 0);
+        assert!(line.ends_with("INFO mempool_vortex::tests: 🚀 ready"));
+
+        let line = log_line(&["--timestamp", "local"]);
+        let stamp = line.split_whitespace().next().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(stamp).is_ok());
+
+        assert!(log_line(&["--timestamp", "none"])
+            .trim_start()
+            .starts_with("INFO"));
+    }
+
+    #[test]
+    fn json_logs_carry_a_timestamp_field() {
+        let line = log_line(&["--log-format", "json"]);
+        let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+        let stamp = record["timestamp"].as_str().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(stamp).is_ok());
+        assert_eq!(record["level"], "INFO");
+        assert_eq!(record["fields"]["message"], "🚀 ready");
+
+        let line = log_line(&["--log-format", "json", "--timestamp", "none"]);
+        let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert!(record.get("timestamp").is_none());
+    }
+
+    #[test]
+    fn malformed_log_filter_is_an_error() {
+        let cli = Args::try_parse_from(["mempool-vortex", "--log-filter", "bundler=loud"]).unwrap();
+        assert!(log_filter(&cli).is_err());
+    }
+
+    #[test]
+    fn strategy_flags_override_the_config() {
+        let cli =
+            Args::try_parse_from(["mempool-vortex", "--disable-arbitrage", "--enable-sandwich"])
+                .unwrap();
+        let mut config = types::MEVConfig::default();
+
+        apply_strategy_flags(&cli, &mut config);
+
+        assert_eq!(config.enabled_strategies(), ["sandwich", "liquidation"]);
+        assert!(Args::try_parse_from([
+            "mempool-vortex",
+            "--enable-liquidation",
+            "--disable-liquidation"
+        ])
+        .is_err());
+    }
+}
@@ -0,0 +1,338 @@
+//! Scripted JSON-RPC transport and fixtures for tests.
+//!
+//! [`MockProvider`] answers each JSON-RPC method with a canned response, so the
+//! pipeline, detectors, and bundler run against `Provider<MockProvider>` exactly
+//! as they do against WebSocket or HTTP, without a node. Responses are keyed by
+//! method rather than queued, so concurrently spawned pipeline tasks get the
+//! same answers regardless of scheduling. Subscriptions replay a scripted list
+//! of notifications, then end.
+
+use crate::gas::BaseFeeTracker;
+use crate::oracle::StaticPriceOracle;
+use crate::reserves::{PoolReserveCache, POOL_RESERVE_TTL};
+use crate::searcher::{self, AnalysisContext};
+use crate::types::{MEVConfig, MEVMetrics, TokenRegistry};
+use async_trait::async_trait;
+use ethers::abi::{self, Token};
+use ethers::providers::{JsonRpcClient, JsonRpcError, MockError, Provider, PubsubClient};
+use ethers::types::{Address, Bytes, Transaction, H256, U256, U64};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// Base fee the fixtures' trackers are seeded with (20 gwei).
+pub const TEST_BASE_FEE_WEI: u64 = 20_000_000_000;
+
+// ---
+
+/// JSON-RPC transport with a fixed response per method.
+///
+/// Unscripted methods fail with a "method not found" error, as a node without
+/// the method would. Every request is recorded for [`MockProvider::call_count`]
+/// and [`MockProvider::params`].
+#[derive(Debug, Default)]
+pub struct MockProvider {
+    // ---
+    /// Response returned for every call to each method
+    responses: Mutex<HashMap<String, Result<Value, JsonRpcError>>>,
+
+    /// Latency added before answering each method
+    delays: Mutex<HashMap<String, Duration>>,
+
+    /// Methods called so far with their params, in order
+    calls: Mutex<Vec<(String, Value)>>,
+
+    /// Notifications every subscription delivers, in order
+    notifications: Mutex<Vec<Value>>,
+}
+
+// ---
+
+impl MockProvider {
+    // ---
+
+    /// Answers every `method` call with `response`, serialized to JSON.
+    pub fn with_response(self, method: &str, response: impl Serialize) -> Self {
+        // ---
+        let response = serde_json::to_value(response).expect("mock response must serialize");
+        self.script(method, Ok(response))
+    }
+
+    /// Fails every `method` call with a JSON-RPC error carrying `message`.
+    pub fn with_error(self, method: &str, message: &str) -> Self {
+        // ---
+        self.script(
+            method,
+            Err(JsonRpcError {
+                code: -32000,
+                message: message.to_string(),
+                data: None,
+            }),
+        )
+    }
+
+    /// Holds every `method` call for `delay` before answering, as a stalled node would.
+    pub fn with_delay(self, method: &str, delay: Duration) -> Self {
+        // ---
+        self.delays
+            .lock()
+            .expect("mock delays poisoned")
+            .insert(method.to_string(), delay);
+        self
+    }
+
+    /// Delivers `notifications` on every subscription, then ends it. The
+    /// subscription id still has to be scripted as the `eth_subscribe` response.
+    pub fn with_notifications<T: Serialize>(
+        self,
+        notifications: impl IntoIterator<Item = T>,
+    ) -> Self {
+        // ---
+        *self
+            .notifications
+            .lock()
+            .expect("mock notifications poisoned") = notifications
+            .into_iter()
+            .map(|n| serde_json::to_value(n).expect("mock notification must serialize"))
+            .collect();
+        self
+    }
+
+    /// Returns how many times `method` has been requested.
+    pub fn call_count(&self, method: &str) -> usize {
+        // ---
+        let calls = self.calls.lock().expect("mock call log poisoned");
+        calls.iter().filter(|(called, _)| called == method).count()
+    }
+
+    /// Returns the params of every `method` request so far, in order.
+    pub fn params(&self, method: &str) -> Vec<Value> {
+        // ---
+        let calls = self.calls.lock().expect("mock call log poisoned");
+        calls
+            .iter()
+            .filter(|(called, _)| called == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+
+    fn script(self, method: &str, response: Result<Value, JsonRpcError>) -> Self {
+        // ---
+        self.responses
+            .lock()
+            .expect("mock responses poisoned")
+            .insert(method.to_string(), response);
+        self
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for MockProvider {
+    type Error = MockError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, MockError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        // ---
+
+        let params = serde_json::to_value(params)?;
+        self.calls
+            .lock()
+            .expect("mock call log poisoned")
+            .push((method.to_string(), params));
+
+        let delay = self
+            .delays
+            .lock()
+            .expect("mock delays poisoned")
+            .get(method)
+            .copied();
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        let response = self
+            .responses
+            .lock()
+            .expect("mock responses poisoned")
+            .get(method)
+            .cloned()
+            .unwrap_or_else(|| {
+                Err(JsonRpcError {
+                    code: -32601,
+                    message: format!("method not found: {method}"),
+                    data: None,
+                })
+            });
+
+        match response {
+            Ok(value) => Ok(serde_json::from_value(value)?),
+            Err(e) => Err(MockError::JsonRpcError(e)),
+        }
+    }
+}
+
+impl PubsubClient for MockProvider {
+    type NotificationStream = futures::stream::Iter<std::vec::IntoIter<Box<RawValue>>>;
+
+    fn subscribe<T: Into<U256>>(&self, _id: T) -> Result<Self::NotificationStream, MockError> {
+        // ---
+        let notifications = self
+            .notifications
+            .lock()
+            .expect("mock notifications poisoned");
+        let raw: Vec<Box<RawValue>> = notifications
+            .iter()
+            .map(|n| serde_json::value::to_raw_value(n).expect("mock notification must serialize"))
+            .collect();
+        Ok(futures::stream::iter(raw))
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, _id: T) -> Result<(), MockError> {
+        Ok(())
+    }
+}
+
+// ---
+
+/// Builds an analysis context over `mock` with default config, registry,
+/// pools, and oracle, and a base fee fixed at [`TEST_BASE_FEE_WEI`].
+pub fn analysis_context(mock: Arc<MockProvider>) -> AnalysisContext<Arc<MockProvider>> {
+    // ---
+
+    let token_registry = TokenRegistry::with_defaults();
+
+    // Stablecoins at $1, as the listener prices them
+    let oracle = ["USDC", "USDT", "DAI"]
+        .iter()
+        .filter_map(|symbol| token_registry.address_of(symbol))
+        .fold(StaticPriceOracle::default(), |oracle, stablecoin| {
+            oracle.with_token_price(stablecoin, 1.0)
+        });
+    let base_fees = Arc::new(BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI)));
+    AnalysisContext {
+        provider: Arc::new(Provider::new(mock)),
+        oracle: Arc::new(oracle),
+        config: Arc::new(RwLock::new(MEVConfig::default())),
+        pools: Arc::new(searcher::default_pools(&token_registry)),
+        token_registry: Arc::new(token_registry),
+        base_fees: base_fees.clone(),
+        victim_simulations: searcher::VictimSimulationCache::new(searcher::VICTIM_SIMULATION_TTL),
+        pool_reserves: PoolReserveCache::new(POOL_RESERVE_TTL, base_fees.clone()),
+        tx_types: searcher::TxTypeCache::new(searcher::TX_TYPE_CACHE_CAPACITY, base_fees),
+        metrics: Arc::new(Mutex::new(MEVMetrics::default())),
+        inclusion_odds: searcher::InclusionOdds::default(),
+    }
+}
+
+/// Returns a pending Uniswap V2 `swapExactTokensForTokens` selling `amount_in`
+/// WETH for USDC, priced at 10 gwei.
+pub fn swap_tx(amount_in: U256) -> Transaction {
+    // ---
+    guarded_swap_tx(amount_in, U256::zero())
+}
+
+/// Returns [`swap_tx`] with its `amountOutMin` slippage guard set to
+/// `amount_out_min`.
+pub fn guarded_swap_tx(amount_in: U256, amount_out_min: U256) -> Transaction {
+    // ---
+    Transaction {
+        hash: H256::from_low_u64_be(0x5a1b),
+        input: v2_swap_input(amount_in, amount_out_min, ["WETH", "USDC"]),
+        gas_price: Some(U256::from(10_000_000_000u64)),
+        ..Default::default()
+    }
+}
+
+/// Returns a Uniswap V2 `swapExactTokensForTokens` sent by `from`, selling
+/// `amount_in` of one default-registry token for another (by symbol).
+pub fn pair_swap_tx(from: Address, amount_in: U256, path: [&str; 2]) -> Transaction {
+    // ---
+    Transaction {
+        from,
+        input: v2_swap_input(amount_in, U256::zero(), path),
+        gas_price: Some(U256::from(10_000_000_000u64)),
+        ..Default::default()
+    }
+}
+
+/// Encodes `swapExactTokensForTokens` calldata along `path`, with no deadline.
+fn v2_swap_input(amount_in: U256, amount_out_min: U256, path: [&str; 2]) -> Bytes {
+    // ---
+
+    let registry = TokenRegistry::with_defaults();
+    let token = |symbol| Token::Address(registry.address_of(symbol).expect("default token"));
+
+    let mut input = vec![0x38, 0xed, 0x17, 0x39];
+    input.extend(abi::encode(&[
+        Token::Uint(amount_in),
+        Token::Uint(amount_out_min),
+        Token::Array(path.into_iter().map(token).collect()),
+        Token::Address(Address::zero()),
+        Token::Uint(U256::MAX), // deadline
+    ]));
+    input.into()
+}
+
+/// Returns a pending Uniswap V3 `exactInputSingle` selling `amount_in` WETH
+/// for USDC in the pool of fee tier `fee` (hundredths of a basis point).
+pub fn v3_swap_tx(amount_in: U256, fee: u32) -> Transaction {
+    // ---
+
+    let registry = TokenRegistry::with_defaults();
+    let token = |symbol| Token::Address(registry.address_of(symbol).expect("default token"));
+
+    let mut input = vec![0x41, 0x4b, 0xf3, 0x89];
+    input.extend(abi::encode(&[Token::Tuple(vec![
+        token("WETH"),
+        token("USDC"),
+        Token::Uint(U256::from(fee)),
+        Token::Address(Address::zero()), // recipient
+        Token::Uint(U256::MAX),          // deadline
+        Token::Uint(amount_in),
+        Token::Uint(U256::zero()), // amountOutMinimum
+        Token::Uint(U256::zero()), // sqrtPriceLimitX96
+    ])]));
+
+    Transaction {
+        hash: H256::from_low_u64_be(0x5a1b + u64::from(fee)),
+        input: input.into(),
+        gas_price: Some(U256::from(10_000_000_000u64)),
+        ..Default::default()
+    }
+}
+
+/// Returns [`swap_tx`] wrapped in an EIP-4844 envelope carrying two blobs, with
+/// a 1 gwei blob gas fee cap.
+pub fn blob_tx(amount_in: U256) -> Transaction {
+    // ---
+
+    let mut tx = swap_tx(amount_in);
+    tx.transaction_type = Some(U64::from(3));
+    tx.max_fee_per_gas = Some(U256::from(30_000_000_000u64));
+    tx.max_priority_fee_per_gas = Some(U256::from(2_000_000_000u64));
+    tx.other
+        .insert("maxFeePerBlobGas".into(), serde_json::json!("0x3b9aca00"));
+    tx.other.insert(
+        "blobVersionedHashes".into(),
+        serde_json::json!([H256::repeat_byte(1), H256::repeat_byte(2)]),
+    );
+    tx
+}
+
+/// Returns Aave's `getUserAccountData` answer for a position at
+/// `health_factor`, to script as the `eth_call` response.
+pub fn account_data(health_factor: f64) -> Bytes {
+    // ---
+    let health_factor = ethers::utils::parse_ether(health_factor).unwrap();
+    let mut words = vec![Token::Uint(U256::exp10(12)); 5];
+    words.push(Token::Uint(health_factor));
+    abi::encode(&words).into()
+}
@@ -0,0 +1,269 @@
+//! Block-scoped mempool analytics.
+//!
+//! `--observe-blocks N` watches the mempool for N full blocks without building
+//! bundles. Each pending transaction is bucketed under the head that was
+//! latest when it arrived, so a block's bucket is the flow seen between its
+//! header and the next one. The block that is already latest at startup was
+//! only partly observed and is left out. When the session ends, the buckets
+//! are printed as a per-block table and exported in the `--report` file.
+
+use ethers::types::{Transaction, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Value at or above which a pending transaction counts as high-value (10 ETH).
+const HIGH_VALUE_WEI: u128 = 10_000_000_000_000_000_000;
+
+// ---
+
+/// Observations bucketed by block, over a window of full blocks.
+#[derive(Debug, Clone, Default)]
+pub struct BlockObserver {
+    // ---
+    /// Full blocks to observe
+    blocks: u64,
+
+    /// First full block of the window, once the starting head is known
+    first: Option<u64>,
+
+    /// Latest head seen
+    head: u64,
+
+    /// Observations of each block in the window that saw any
+    stats: BTreeMap<u64, BlockStats>,
+}
+
+/// What was observed while one block was the latest.
+#[derive(Debug, Clone, Default)]
+struct BlockStats {
+    // ---
+    transactions: u64,
+    high_value: u64,
+    opportunities: BTreeMap<&'static str, u64>,
+    gas_prices_gwei: Vec<f64>,
+}
+
+/// One row of the per-block table, as exported in the run report.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BlockReport {
+    // ---
+    /// Block that was latest while these transactions arrived
+    pub block: u64,
+
+    /// Pending transactions observed
+    pub transactions: u64,
+
+    /// Transactions carrying at least 10 ETH
+    pub high_value: u64,
+
+    /// Opportunities detected, by strategy
+    pub opportunities: BTreeMap<String, u64>,
+
+    /// Distribution of the transactions' gas price bids
+    pub gas_price_gwei: GasPriceDistribution,
+}
+
+/// Gas price bids in gwei (the fee cap for EIP-1559 transactions); `None`
+/// when the block saw no transactions.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GasPriceDistribution {
+    // ---
+    pub min: Option<f64>,
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub max: Option<f64>,
+}
+
+// ---
+
+impl BlockObserver {
+    // ---
+
+    /// Creates an observer of `blocks` full blocks, starting after the first head seen.
+    pub fn new(blocks: u64) -> Self {
+        // ---
+        Self {
+            blocks,
+            ..Default::default()
+        }
+    }
+
+    /// Moves the observer to `head`. The first head seen starts the window
+    /// at the block after it. Head 0 is what the base-fee tracker reports
+    /// before its first header, so it is ignored.
+    pub fn observe_head(&mut self, head: u64) {
+        // ---
+        if head == 0 {
+            return;
+        }
+        self.first.get_or_insert(head + 1);
+        self.head = self.head.max(head);
+    }
+
+    /// Returns whether every block in the window has been followed by the next.
+    pub fn is_complete(&self) -> bool {
+        // ---
+        self.first
+            .is_some_and(|first| self.head >= first + self.blocks)
+    }
+
+    /// Records a pending transaction that arrived while `head` was the latest block.
+    pub fn record_transaction(&mut self, head: u64, tx: &Transaction) {
+        // ---
+        self.observe_head(head);
+        let Some(stats) = self.bucket(head) else {
+            return;
+        };
+        stats.transactions += 1;
+        if tx.value >= U256::from(HIGH_VALUE_WEI) {
+            stats.high_value += 1;
+        }
+        if let Some(bid) = tx.max_fee_per_gas.or(tx.gas_price) {
+            let gwei = ethers::utils::format_units(bid, "gwei").unwrap_or_default();
+            stats.gas_prices_gwei.push(gwei.parse().unwrap_or_default());
+        }
+    }
+
+    /// Records an opportunity found in a transaction that arrived while `head`
+    /// was the latest block.
+    pub fn record_opportunity(&mut self, head: u64, strategy: &'static str) {
+        // ---
+        if let Some(stats) = self.bucket(head) {
+            *stats.opportunities.entry(strategy).or_default() += 1;
+        }
+    }
+
+    /// Returns one row per block of the window observed so far, including
+    /// blocks that saw no transactions.
+    pub fn reports(&self) -> Vec<BlockReport> {
+        // ---
+        let Some(first) = self.first else {
+            return Vec::new();
+        };
+        let last = self.head.min(first + self.blocks);
+        (first..last)
+            .map(|block| {
+                let stats = self.stats.get(&block).cloned().unwrap_or_default();
+                BlockReport {
+                    block,
+                    transactions: stats.transactions,
+                    high_value: stats.high_value,
+                    opportunities: stats
+                        .opportunities
+                        .iter()
+                        .map(|(strategy, count)| (strategy.to_string(), *count))
+                        .collect(),
+                    gas_price_gwei: stats.gas_price_distribution(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the per-block table printed when the session ends.
+    pub fn summary_table(&self) -> String {
+        // ---
+
+        let reports = self.reports();
+        let mut table = format!(
+            "🧱 Observed {} of {} full blocks\n{:<10} {:>6} {:>10} {:>6} {:>10} {:>10} {:>10}  {}\n",
+            reports.len(),
+            self.blocks,
+            "block",
+            "txs",
+            "high-value",
+            "opps",
+            "gas p50",
+            "gas p90",
+            "gas max",
+            "by strategy"
+        );
+
+        let gwei = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{v:.2}"));
+        for report in &reports {
+            let strategies: Vec<String> = report
+                .opportunities
+                .iter()
+                .map(|(strategy, count)| format!("{strategy}={count}"))
+                .collect();
+            let _ = writeln!(
+                table,
+                "{:<10} {:>6} {:>10} {:>6} {:>10} {:>10} {:>10}  {}",
+                report.block,
+                report.transactions,
+                report.high_value,
+                report.opportunities.values().sum::<u64>(),
+                gwei(report.gas_price_gwei.p50),
+                gwei(report.gas_price_gwei.p90),
+                gwei(report.gas_price_gwei.max),
+                strategies.join(" ")
+            );
+        }
+        table.trim_end().to_string()
+    }
+
+    /// Returns the bucket of `head`, or `None` outside the window.
+    fn bucket(&mut self, head: u64) -> Option<&mut BlockStats> {
+        // ---
+        let first = self.first?;
+        (first..first + self.blocks)
+            .contains(&head)
+            .then(|| self.stats.entry(head).or_default())
+    }
+}
+
+impl BlockStats {
+    // ---
+
+    /// Returns the distribution of the block's gas price bids.
+    fn gas_price_distribution(&self) -> GasPriceDistribution {
+        // ---
+        GasPriceDistribution::of(self.gas_prices_gwei.clone())
+    }
+}
+
+impl GasPriceDistribution {
+    // ---
+
+    /// Returns the distribution of `bids_gwei`, in any order.
+    pub fn of(mut bids_gwei: Vec<f64>) -> Self {
+        // ---
+        bids_gwei.sort_by(f64::total_cmp);
+        let percentile = |quantile: f64| {
+            let last = bids_gwei.len().checked_sub(1)?;
+            Some(bids_gwei[((last as f64 * quantile).round() as usize).min(last)])
+        };
+        Self {
+            min: percentile(0.0),
+            p50: percentile(0.50),
+            p90: percentile(0.90),
+            max: percentile(1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_starts_after_the_first_seeded_head() {
+        let mut observer = BlockObserver::new(2);
+
+        // Transactions seen before the tracker has a head don't start the window
+        observer.record_transaction(0, &Transaction::default());
+        assert!(observer.reports().is_empty());
+
+        observer.record_transaction(100, &Transaction::default());
+        observer.record_transaction(101, &Transaction::default());
+        observer.observe_head(103);
+        assert!(observer.is_complete());
+        let blocks: Vec<u64> = observer
+            .reports()
+            .iter()
+            .map(|report| report.block)
+            .collect();
+        assert_eq!(blocks, [101, 102]);
+        assert_eq!(observer.reports()[0].transactions, 1);
+    }
+}
@@ -0,0 +1,210 @@
+//! Price oracle abstraction for valuing opportunities.
+//!
+//! Detectors and reporting need market prices (e.g. ETH/USD) that don't come
+//! from the transaction being analyzed. The [`PriceOracle`] trait decouples them
+//! from where those prices come from; [`StaticPriceOracle`] serves fixed prices
+//! for simulation until a live feed (Chainlink, DEX TWAP) is wired in.
+
+use crate::types::{ProfitDenomination, TokenRegistry};
+use crate::units;
+use ethers::types::{Address, U256};
+use std::collections::HashMap;
+use std::fmt;
+
+// ---
+
+/// Source of market prices used by the searcher.
+///
+/// Implementations must be cheap to query: detectors call them on the hot path
+/// for every analyzed transaction, so live oracles should cache and refresh in
+/// the background rather than issue an RPC per call.
+pub trait PriceOracle: Send + Sync + fmt::Debug {
+    /// Returns the current ETH price in USD, if known.
+    fn eth_price_usd(&self) -> Option<f64>;
+
+    /// Returns the current price of an ERC-20 token in USD, if known.
+    fn token_price_usd(&self, _token: Address) -> Option<f64> {
+        None
+    }
+}
+
+/// Oracle serving fixed prices, for simulation and offline analysis.
+#[derive(Debug, Clone)]
+pub struct StaticPriceOracle {
+    // ---
+    /// ETH price in USD
+    pub eth_price_usd: f64,
+
+    /// ERC-20 token prices in USD
+    pub token_prices_usd: HashMap<Address, f64>,
+}
+
+// ---
+
+impl StaticPriceOracle {
+    /// Returns the oracle with a fixed USD price for `token`.
+    pub fn with_token_price(mut self, token: Address, price_usd: f64) -> Self {
+        self.token_prices_usd.insert(token, price_usd);
+        self
+    }
+}
+
+/// Returns the oracle the pipeline prices with: the default ETH/USD price,
+/// plus USDC, USDT and DAI from `tokens` at $1.
+pub fn pipeline_oracle(tokens: &TokenRegistry) -> StaticPriceOracle {
+    // ---
+    ["USDC", "USDT", "DAI"]
+        .iter()
+        .filter_map(|symbol| tokens.address_of(symbol))
+        .fold(StaticPriceOracle::default(), |oracle, stablecoin| {
+            oracle.with_token_price(stablecoin, 1.0)
+        })
+}
+
+impl Default for StaticPriceOracle {
+    fn default() -> Self {
+        Self {
+            eth_price_usd: 2_500.0, // Mock ETH/USD price
+            token_prices_usd: HashMap::new(),
+        }
+    }
+}
+
+impl PriceOracle for StaticPriceOracle {
+    fn eth_price_usd(&self) -> Option<f64> {
+        Some(self.eth_price_usd)
+    }
+
+    fn token_price_usd(&self, token: Address) -> Option<f64> {
+        self.token_prices_usd.get(&token).copied()
+    }
+}
+
+/// Converts a wei amount into `denomination` units using `oracle` prices.
+///
+/// Returns `None` when the oracle lacks a price the conversion needs, or when a
+/// token price is not positive.
+pub fn denominate(
+    oracle: &dyn PriceOracle,
+    wei: U256,
+    denomination: ProfitDenomination,
+) -> Option<f64> {
+    // ---
+
+    let eth = units::wei_to_eth_f64(wei);
+
+    match denomination {
+        ProfitDenomination::Eth => Some(eth),
+        ProfitDenomination::Usd => Some(eth * oracle.eth_price_usd()?),
+        ProfitDenomination::Token(token) => {
+            let token_price_usd = oracle.token_price_usd(token).filter(|price| *price > 0.0)?;
+            Some(eth * oracle.eth_price_usd()? / token_price_usd)
+        }
+    }
+}
+
+/// Checks that `oracle` can convert profits into `denomination`.
+///
+/// # Errors
+/// Returns an error if the oracle lacks a price the conversion needs, since
+/// no profit could then be compared against `min_profit`.
+pub fn ensure_priced(
+    oracle: &dyn PriceOracle,
+    denomination: ProfitDenomination,
+) -> anyhow::Result<()> {
+    // ---
+    if denominate(oracle, U256::exp10(18), denomination).is_none() {
+        anyhow::bail!(
+            "profit_denomination {:?} has no oracle price; use Eth, Usd or a priced token",
+            denomination
+        );
+    }
+    Ok(())
+}
+
+/// Values `amount` base units of `token` in wei, using `oracle` prices and the
+/// token's decimals from `tokens`. WETH is worth its amount in wei.
+///
+/// Returns `None` when `tokens` doesn't know the token or the oracle lacks a
+/// positive price for it or for ETH.
+pub fn token_value_wei(
+    oracle: &dyn PriceOracle,
+    tokens: &TokenRegistry,
+    token: Address,
+    amount: U256,
+) -> Option<U256> {
+    // ---
+
+    if tokens.address_of("WETH") == Some(token) {
+        return Some(amount);
+    }
+    let decimals = tokens.get(&token)?.decimals;
+    let token_price_usd = oracle.token_price_usd(token).filter(|price| *price > 0.0)?;
+    let eth_price_usd = oracle.eth_price_usd().filter(|price| *price > 0.0)?;
+    let units: f64 = ethers::utils::format_units(amount, u32::from(decimals))
+        .ok()?
+        .parse()
+        .ok()?;
+    Some(units::eth_f64_to_wei(
+        units * token_price_usd / eth_price_usd,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profits_are_denominated_through_oracle_prices() {
+        let dai = Address::repeat_byte(0xda);
+        let oracle = StaticPriceOracle::default().with_token_price(dai, 0.5);
+        let one_eth = U256::exp10(18);
+
+        assert_eq!(
+            denominate(&oracle, one_eth, ProfitDenomination::Eth),
+            Some(1.0)
+        );
+        assert_eq!(
+            denominate(&oracle, one_eth, ProfitDenomination::Usd),
+            Some(2_500.0)
+        );
+        assert_eq!(
+            denominate(&oracle, one_eth / 2, ProfitDenomination::Token(dai)),
+            Some(2_500.0)
+        );
+    }
+
+    #[test]
+    fn unpriced_denominations_are_not_converted() {
+        let unknown = Address::repeat_byte(0x77);
+        let free = Address::repeat_byte(0xfe);
+        let oracle = StaticPriceOracle::default().with_token_price(free, 0.0);
+        let one_eth = U256::exp10(18);
+
+        assert_eq!(
+            denominate(&oracle, one_eth, ProfitDenomination::Token(unknown)),
+            None
+        );
+        assert_eq!(
+            denominate(&oracle, one_eth, ProfitDenomination::Token(free)),
+            None
+        );
+
+        /// An oracle that has lost its ETH feed.
+        #[derive(Debug)]
+        struct NoEthPrice;
+        impl PriceOracle for NoEthPrice {
+            fn eth_price_usd(&self) -> Option<f64> {
+                None
+            }
+        }
+        assert_eq!(
+            denominate(&NoEthPrice, one_eth, ProfitDenomination::Usd),
+            None
+        );
+        assert_eq!(
+            denominate(&NoEthPrice, one_eth, ProfitDenomination::Eth),
+            Some(1.0)
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! On-chain reads of lending positions.
+//!
+//! A position that looks liquidatable at the chain head may be an artefact of a
+//! block that gets reorged out, so the liquidation detector reads account data
+//! `RiskParameters.min_confirmations` blocks behind the head. By the time the
+//! bundle is built the borrower may have repaid or topped up collateral, so
+//! the bundler reads the health factor again at the head just before sending.
+//! Only Aave exposes a single `getUserAccountData` view; other protocols have
+//! no reader here and keep their detector data.
+
+use crate::searcher::Protocol;
+use crate::units;
+use ethers::abi::{self, ParamType, Token};
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, BlockId, BlockNumber, TransactionRequest, U64};
+
+/// Aave V3 pool (mainnet): read for account data, called to liquidate, and
+/// borrowed from for flash loans.
+pub const AAVE_V3_POOL: &str = "0x87870Bca3F3fD6335C3F4ce8392D69350B4fA4E2";
+
+/// Selector of Aave's `getUserAccountData(address)`.
+const GET_USER_ACCOUNT_DATA: [u8; 4] = [0xbf, 0x92, 0x85, 0x7c];
+
+/// Health factor at or above which a position can't be liquidated.
+pub const LIQUIDATABLE_BELOW: f64 = 1.0;
+
+// ---
+
+/// Returns the block position data is read at: `confirmations` blocks behind
+/// `latest`, or the node's latest block when no head has been seen yet.
+pub fn confirmed_block(latest: U64, confirmations: u8) -> Option<BlockId> {
+    // ---
+    if latest.is_zero() {
+        return None;
+    }
+    let block = latest.saturating_sub(U64::from(confirmations));
+    Some(BlockId::Number(BlockNumber::Number(block)))
+}
+
+/// Reads the health factor of `owner`'s position on `protocol` as of `block`
+/// (the latest block when `None`).
+///
+/// # Returns
+/// * `Ok(Some(health_factor))` once the protocol's pool answers
+/// * `Ok(None)` for protocols without an on-chain reader
+/// * `Err` if the call fails or returns something other than account data
+pub async fn fetch_health_factor<M: Middleware>(
+    provider: &M,
+    protocol: Protocol,
+    owner: Address,
+    block: Option<BlockId>,
+) -> anyhow::Result<Option<f64>> {
+    // ---
+
+    let Protocol::Aave = protocol else {
+        return Ok(None);
+    };
+
+    let mut data = GET_USER_ACCOUNT_DATA.to_vec();
+    data.extend(abi::encode(&[Token::Address(owner)]));
+    let pool: Address = AAVE_V3_POOL.parse().expect("valid pool address");
+    let call: TypedTransaction = TransactionRequest::new().to(pool).data(data).into();
+    let output = provider
+        .call(&call, block)
+        .await
+        .map_err(|e| anyhow::anyhow!("getUserAccountData({:?}) failed: {}", owner, e))?;
+
+    // (totalCollateralBase, totalDebtBase, availableBorrowsBase,
+    //  currentLiquidationThreshold, ltv, healthFactor)
+    let tokens = abi::decode(&vec![ParamType::Uint(256); 6], &output)
+        .map_err(|e| anyhow::anyhow!("Malformed getUserAccountData({:?}): {}", owner, e))?;
+    let health_factor = tokens[5]
+        .clone()
+        .into_uint()
+        .ok_or_else(|| anyhow::anyhow!("Malformed getUserAccountData({:?})", owner))?;
+
+    // Scaled by 1e18; a debt-free account reports uint256 max, which stays healthy
+    let health_factor = units::wei_to_eth_f64(health_factor);
+    Ok(Some(health_factor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{account_data, MockProvider};
+    use ethers::providers::Provider;
+    use std::sync::Arc;
+
+    #[test]
+    fn positions_are_read_behind_the_head() {
+        let at = |block: u64| Some(BlockId::Number(BlockNumber::Number(block.into())));
+        assert_eq!(confirmed_block(100.into(), 0), at(100));
+        assert_eq!(confirmed_block(100.into(), 3), at(97));
+        assert_eq!(confirmed_block(2.into(), 3), at(0));
+        assert_eq!(confirmed_block(U64::zero(), 3), None);
+    }
+
+    #[tokio::test]
+    async fn aave_health_factor_is_decoded() {
+        let mock = MockProvider::default().with_response("eth_call", account_data(0.95));
+        let provider = Provider::new(Arc::new(mock));
+        let owner = Address::repeat_byte(0x12);
+
+        let health = fetch_health_factor(&provider, Protocol::Aave, owner, None).await;
+        assert_eq!(health.unwrap(), Some(0.95));
+
+        // No reader: the detector's data stands
+        let health = fetch_health_factor(&provider, Protocol::Compound, owner, None).await;
+        assert_eq!(health.unwrap(), None);
+    }
+}
@@ -0,0 +1,359 @@
+//! Client-side rate limiting for JSON-RPC providers.
+//!
+//! Free-tier node providers cap requests per second and answer excess calls
+//! with HTTP 429, which the pipeline would otherwise see as dropped
+//! transactions. [`RateLimitedClient`] wraps any transport so every request
+//! (body fetches, `eth_call`, gas estimates, block polls) first takes a token
+//! from one shared [`RateLimiter`], and the pipeline throttles itself instead.
+//! Calls beyond the limit wait in a bounded queue; once the queue is full,
+//! further calls are shed with an error rather than piling up unboundedly.
+
+use crate::types::MEVMetrics;
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, JsonRpcError, ProviderError, PubsubClient, RpcError};
+use ethers::types::U256;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// Longest a request may wait for a token before it is shed instead of queued.
+pub const MAX_QUEUE_WAIT: Duration = Duration::from_secs(2);
+
+/// Window over which the reported RPC call rate is measured.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+// ---
+
+/// Token bucket shared by every request of a session.
+///
+/// The bucket holds up to one second of tokens (`rps`) and refills
+/// continuously. Requests that find it empty reserve a future token and sleep
+/// until it is due, so queued requests are released at exactly `rps`. The
+/// queue is bounded by [`MAX_QUEUE_WAIT`]: a request that would wait longer is
+/// shed. Without a limit, requests are only counted.
+#[derive(Debug)]
+pub struct RateLimiter {
+    // ---
+    /// Requests per second, or `None` to count without limiting
+    rps: Option<u32>,
+
+    /// Bucket and rate-window state
+    state: Mutex<LimiterState>,
+
+    /// Session metrics receiving call counts and the measured call rate
+    metrics: Arc<Mutex<MEVMetrics>>,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    // ---
+    /// Available tokens; negative while requests are queued for future tokens
+    tokens: f64,
+
+    /// When `tokens` was last refilled
+    refilled_at: Instant,
+
+    /// Consecutive shed requests, so saturation is warned about once
+    shed_streak: u64,
+
+    /// Start of the current call-rate window
+    window_start: Instant,
+
+    /// Requests sent in the current call-rate window
+    window_calls: u64,
+}
+
+/// JSON-RPC transport that takes a [`RateLimiter`] token before every request.
+#[derive(Debug)]
+pub struct RateLimitedClient<C> {
+    // ---
+    inner: C,
+    limiter: Arc<RateLimiter>,
+}
+
+/// Error from a [`RateLimitedClient`]: the wrapped transport's, or a shed request.
+#[derive(Debug)]
+pub enum RateLimitError<E> {
+    // ---
+    /// The request was sent and the transport failed
+    Transport(E),
+
+    /// The request queue was full, so the request was never sent
+    Shed {
+        /// JSON-RPC method of the shed request
+        method: String,
+    },
+}
+
+// ---
+
+impl RateLimiter {
+    // ---
+
+    /// Creates a limiter admitting `rps` requests per second (`None` for no
+    /// limit) that reports into `metrics`.
+    pub fn new(rps: Option<u32>, metrics: Arc<Mutex<MEVMetrics>>) -> Self {
+        // ---
+        let now = Instant::now();
+        Self {
+            rps: rps.filter(|rps| *rps > 0),
+            state: Mutex::new(LimiterState {
+                tokens: f64::from(rps.unwrap_or_default()),
+                refilled_at: now,
+                shed_streak: 0,
+                window_start: now,
+                window_calls: 0,
+            }),
+            metrics,
+        }
+    }
+
+    /// Waits for a request token.
+    ///
+    /// Returns `false` without waiting if the queue is full and the request
+    /// must be shed.
+    pub async fn acquire(&self, method: &str) -> bool {
+        // ---
+
+        let wait = match self.reserve() {
+            Some(wait) => wait,
+            None => {
+                self.record(MEVMetrics::record_rpc_shed);
+                return false;
+            }
+        };
+
+        if !wait.is_zero() {
+            debug!("RPC rate limit: {} queued for {:?}", method, wait);
+            tokio::time::sleep(wait).await;
+        }
+        self.record_send();
+        true
+    }
+
+    /// Takes a token, returning how long to wait until it is due, or `None`
+    /// if the wait would exceed [`MAX_QUEUE_WAIT`].
+    fn reserve(&self) -> Option<Duration> {
+        // ---
+
+        let now = Instant::now();
+        let mut state = self.state.lock().expect("rate limiter poisoned");
+
+        match self.rps {
+            None => Some(Duration::ZERO),
+            Some(rps) => {
+                let rate = f64::from(rps);
+                let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+                state.tokens = (state.tokens + elapsed * rate).min(rate);
+                state.refilled_at = now;
+
+                // A non-negative balance after taking our token means it is available now
+                let wait = Duration::from_secs_f64((1.0 - state.tokens).max(0.0) / rate);
+                if wait > MAX_QUEUE_WAIT {
+                    if state.shed_streak == 0 {
+                        warn!(
+                            "🚦 RPC rate limit saturated ({} req/s); shedding requests",
+                            rps
+                        );
+                    }
+                    state.shed_streak += 1;
+                    return None;
+                }
+                state.tokens -= 1.0;
+                state.shed_streak = 0;
+                Some(wait)
+            }
+        }
+    }
+
+    /// Counts a request as sent and, at the end of each window, publishes the
+    /// measured call rate.
+    fn record_send(&self) {
+        // ---
+
+        let now = Instant::now();
+        let mut state = self.state.lock().expect("rate limiter poisoned");
+
+        // Rate is measured over whole windows so it reads as a stable req/s
+        state.window_calls += 1;
+        let window = now.duration_since(state.window_start);
+        let rate = (window >= RATE_WINDOW).then(|| {
+            let rate = state.window_calls as f64 / window.as_secs_f64();
+            state.window_start = now;
+            state.window_calls = 0;
+            rate
+        });
+        drop(state);
+
+        self.record(|m| {
+            m.record_rpc_call();
+            if let Some(rate) = rate {
+                m.rpc_call_rate = rate;
+            }
+        });
+    }
+
+    fn record(&self, update: impl FnOnce(&mut MEVMetrics)) {
+        // ---
+        update(&mut self.metrics.lock().expect("metrics poisoned"));
+    }
+}
+
+impl<C> RateLimitedClient<C> {
+    /// Wraps `inner` so its requests draw from `limiter`.
+    pub fn new(inner: C, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+#[async_trait]
+impl<C> JsonRpcClient for RateLimitedClient<C>
+where
+    C: JsonRpcClient + 'static,
+    C::Error: 'static,
+{
+    type Error = RateLimitError<C::Error>;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        // ---
+
+        if !self.limiter.acquire(method).await {
+            return Err(RateLimitError::Shed {
+                method: method.to_string(),
+            });
+        }
+        self.inner
+            .request(method, params)
+            .await
+            .map_err(RateLimitError::Transport)
+    }
+}
+
+/// Subscriptions pass straight through: notifications are pushed by the node
+/// and cost no requests, while `eth_subscribe` itself goes through `request`.
+impl<C> PubsubClient for RateLimitedClient<C>
+where
+    C: PubsubClient + 'static,
+    C::Error: 'static,
+{
+    type NotificationStream = C::NotificationStream;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        self.inner.subscribe(id).map_err(RateLimitError::Transport)
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        self.inner
+            .unsubscribe(id)
+            .map_err(RateLimitError::Transport)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for RateLimitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitError::Transport(e) => e.fmt(f),
+            RateLimitError::Shed { method } => {
+                write!(f, "{method} shed: RPC rate limit queue is full")
+            }
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for RateLimitError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RateLimitError::Transport(e) => Some(e),
+            RateLimitError::Shed { .. } => None,
+        }
+    }
+}
+
+impl<E: RpcError + 'static> RpcError for RateLimitError<E> {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            RateLimitError::Transport(e) => e.as_error_response(),
+            RateLimitError::Shed { .. } => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            RateLimitError::Transport(e) => e.as_serde_error(),
+            RateLimitError::Shed { .. } => None,
+        }
+    }
+}
+
+impl<E: RpcError + 'static> From<RateLimitError<E>> for ProviderError {
+    fn from(e: RateLimitError<E>) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockProvider;
+    use ethers::providers::{Middleware, Provider};
+
+    fn limited_provider(
+        rps: u32,
+    ) -> (
+        Provider<RateLimitedClient<MockProvider>>,
+        Arc<Mutex<MEVMetrics>>,
+    ) {
+        let metrics = Arc::new(Mutex::new(MEVMetrics::default()));
+        let limiter = Arc::new(RateLimiter::new(Some(rps), metrics.clone()));
+        let mock = MockProvider::default().with_response("eth_blockNumber", "0x1");
+        (
+            Provider::new(RateLimitedClient::new(mock, limiter)),
+            metrics,
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn call_rate_stays_under_limit() {
+        let (provider, metrics) = limited_provider(10);
+        let start = Instant::now();
+
+        // A one-second burst, then a full queue (2s) released at 10 req/s
+        let calls = (0..30).map(|_| async {
+            provider.get_block_number().await.unwrap();
+            start.elapsed().as_secs_f64()
+        });
+        let mut sent_at = futures::future::join_all(calls).await;
+        sent_at.sort_by(f64::total_cmp);
+
+        // By time t, at most the burst plus t seconds of refill have been sent
+        for (i, t) in sent_at.iter().enumerate() {
+            assert!((i + 1) as f64 <= 10.0 + 10.0 * t + 1e-6, "call {i} at {t}s");
+        }
+        assert!(sent_at[29] >= 2.0);
+
+        let metrics = metrics.lock().unwrap();
+        assert_eq!(metrics.rpc_calls, 30);
+        assert!(metrics.rpc_call_rate <= 10.0 + 1e-6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn saturated_queue_sheds_requests() {
+        let (provider, metrics) = limited_provider(10);
+
+        let calls = (0..50).map(|_| provider.get_block_number());
+        let results = futures::future::join_all(calls).await;
+
+        // 10 immediate + 20 queued within the 2s bound; the rest are shed
+        let shed = results.iter().filter(|r| r.is_err()).count();
+        assert_eq!(shed, 20);
+        assert_eq!(metrics.lock().unwrap().rpc_calls_shed, 20);
+    }
+}
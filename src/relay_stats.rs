@@ -0,0 +1,182 @@
+//! Relay acceptance and inclusion rates learned from submissions.
+//!
+//! The `inclusion_probability` configured per relay is a guess. [`RelayStats`]
+//! tracks what each relay actually does: how often it accepts a bundle, and
+//! how often the bundles it reports on land. Both are exponentially weighted
+//! moving averages, so recent behavior dominates, seeded from the configured
+//! probability. `--adaptive-relay-order` ranks relays by the product of the
+//! two, and `--relay-stats-file` carries the rates across restarts.
+
+use crate::types::RelayConfiguration;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Weight of each new observation in the moving averages.
+pub const RELAY_EWMA_ALPHA: f64 = 0.1;
+
+/// Inclusion rate assumed for a relay the configuration doesn't describe.
+const UNKNOWN_RELAY_PRIOR: f64 = 0.5;
+
+// ---
+
+/// Observed behavior of one relay.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RelayStats {
+    // ---
+    /// Bundles sent to the relay
+    pub submissions: u64,
+
+    /// Submissions the relay accepted
+    pub accepted: u64,
+
+    /// Bundles whose fate (landed or not) the relay reported
+    pub outcomes: u64,
+
+    /// Reported bundles that landed on-chain
+    pub included: u64,
+
+    /// Recent share of submissions accepted (EWMA, 0.0-1.0)
+    pub acceptance_rate: f64,
+
+    /// Recent share of reported bundles that landed (EWMA, 0.0-1.0)
+    pub inclusion_rate: f64,
+}
+
+// ---
+
+impl RelayStats {
+    // ---
+
+    /// Starts tracking a relay expected to land bundles with `inclusion_probability`.
+    pub fn with_prior(inclusion_probability: f64) -> Self {
+        // ---
+        Self {
+            submissions: 0,
+            accepted: 0,
+            outcomes: 0,
+            included: 0,
+            acceptance_rate: 1.0,
+            inclusion_rate: inclusion_probability.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Records whether the relay accepted a submission.
+    pub fn record_response(&mut self, accepted: bool) {
+        // ---
+        self.submissions += 1;
+        self.accepted += u64::from(accepted);
+        self.acceptance_rate = ewma(self.acceptance_rate, accepted);
+    }
+
+    /// Records whether a bundle the relay reported on landed.
+    pub fn record_inclusion(&mut self, included: bool) {
+        // ---
+        self.outcomes += 1;
+        self.included += u64::from(included);
+        self.inclusion_rate = ewma(self.inclusion_rate, included);
+    }
+
+    /// Returns the chance a bundle sent to the relay lands: accepted, then included.
+    pub fn score(&self) -> f64 {
+        // ---
+        self.acceptance_rate * self.inclusion_rate
+    }
+}
+
+impl Default for RelayStats {
+    fn default() -> Self {
+        Self::with_prior(UNKNOWN_RELAY_PRIOR)
+    }
+}
+
+/// Starts tracking each `selected` relay not already in `stats`, from the
+/// `inclusion_probability` configured for it (matched by name, ignoring case).
+pub fn seed(
+    stats: &mut BTreeMap<String, RelayStats>,
+    config: &RelayConfiguration,
+    selected: &[&str],
+) {
+    // ---
+    for name in selected {
+        let prior = config
+            .relays
+            .iter()
+            .find(|(configured, _)| configured.eq_ignore_ascii_case(name))
+            .map_or(UNKNOWN_RELAY_PRIOR, |(_, relay)| {
+                relay.inclusion_probability
+            });
+        stats
+            .entry(name.to_string())
+            .or_insert_with(|| RelayStats::with_prior(prior));
+    }
+}
+
+/// Moves `rate` toward `observed` by [`RELAY_EWMA_ALPHA`].
+fn ewma(rate: f64, observed: bool) -> f64 {
+    // ---
+    rate + RELAY_EWMA_ALPHA * (f64::from(u8::from(observed)) - rate)
+}
+
+/// Reads relay stats saved by [`save`]; a missing file means none yet.
+///
+/// # Errors
+/// Returns an error if the file exists but can't be read or parsed.
+pub fn load(path: &Path) -> anyhow::Result<BTreeMap<String, RelayStats>> {
+    // ---
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read relay stats {}: {}", path.display(), e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| anyhow::anyhow!("Malformed relay stats {}: {}", path.display(), e))
+}
+
+/// Writes relay stats as pretty-printed JSON, keyed by relay name.
+///
+/// # Errors
+/// Returns an error if the file can't be written.
+pub fn save(path: &Path, stats: &BTreeMap<String, RelayStats>) -> anyhow::Result<()> {
+    // ---
+    let json = serde_json::to_string_pretty(stats)
+        .map_err(|e| anyhow::anyhow!("Cannot serialize relay stats: {}", e))?;
+    std::fs::write(path, json + "\n")
+        .map_err(|e| anyhow::anyhow!("Cannot write relay stats {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_follow_recent_observations() {
+        let mut stats = RelayStats::with_prior(0.8);
+        assert_eq!(stats.score(), 0.8);
+
+        stats.record_response(false);
+        stats.record_inclusion(true);
+        assert!((stats.acceptance_rate - 0.9).abs() < 1e-12);
+        assert!((stats.inclusion_rate - 0.82).abs() < 1e-12);
+        assert_eq!((stats.submissions, stats.accepted), (1, 0));
+        assert_eq!((stats.outcomes, stats.included), (1, 1));
+
+        // A long run of misses drags the rate toward zero
+        (0..50).for_each(|_| stats.record_inclusion(false));
+        assert!(stats.inclusion_rate < 0.01);
+    }
+
+    #[test]
+    fn stats_survive_a_save_and_load() {
+        let path = std::env::temp_dir().join(format!("vortex-relays-{}.json", std::process::id()));
+        assert!(load(&path).unwrap().is_empty());
+
+        let mut flashbots = RelayStats::with_prior(0.85);
+        flashbots.record_response(true);
+        let stats = BTreeMap::from([("flashbots".to_string(), flashbots)]);
+        save(&path, &stats).unwrap();
+        let loaded = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), stats);
+    }
+}
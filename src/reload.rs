@@ -0,0 +1,194 @@
+//! SIGHUP reload of the strategy config.
+//!
+//! Operators who'd rather not run the control API can edit the config file
+//! and send the process SIGHUP. The file is re-read and validated, and its
+//! strategy settings replace the live shared config, so detectors and the
+//! bundler pick them up from the next transaction; a file that doesn't load
+//! or validate is rejected and the live config kept. Unix only: the module is
+//! compiled out elsewhere.
+
+use crate::oracle;
+use crate::types::{Config, MEVConfig, SharedConfig, TokenRegistry};
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{info, warn};
+
+// ---
+
+/// Reloads `config` from the config file at `path` whenever the process
+/// receives SIGHUP, applying `overrides` (the command line's strategy flags)
+/// on top of each reload.
+///
+/// # Errors
+/// Returns an error if the signal handler can't be installed.
+pub fn spawn_on_hangup(
+    config: SharedConfig,
+    path: PathBuf,
+    overrides: impl Fn(&mut MEVConfig) + Send + 'static,
+) -> anyhow::Result<()> {
+    // ---
+    let mut hangup = signal(SignalKind::hangup())
+        .map_err(|e| anyhow::anyhow!("Cannot install SIGHUP handler: {}", e))?;
+    let hangups = futures::stream::poll_fn(move |cx| hangup.poll_recv(cx));
+    tokio::spawn(reload_on(hangups, config, path, overrides));
+    Ok(())
+}
+
+/// Reloads `config` from `path` each time `hangups` yields, logging what
+/// changed or why the reload was rejected. Runs until `hangups` ends.
+async fn reload_on(
+    mut hangups: impl Stream<Item = ()> + Unpin,
+    config: SharedConfig,
+    path: PathBuf,
+    overrides: impl Fn(&mut MEVConfig),
+) {
+    // ---
+    while hangups.next().await.is_some() {
+        match reload(&config, &path, &overrides) {
+            Ok(changes) if changes.is_empty() => {
+                info!(
+                    "🔄 Reloaded {}: no strategy settings changed",
+                    path.display()
+                );
+            }
+            Ok(changes) => info!("🔄 Reloaded {}: {}", path.display(), changes.join(", ")),
+            Err(e) => warn!("⚠️ Rejected config reload, keeping the live config: {}", e),
+        }
+    }
+}
+
+/// Replaces the live strategy settings with those in the config file at
+/// `path`, with `overrides` applied, and returns the settings that changed
+/// as `name: old → new`.
+///
+/// The whole `mev_config` section is swapped, so control API changes made
+/// since startup are dropped unless the file carries them too. Other sections
+/// (relays, gas, routers) still only take effect on restart.
+///
+/// # Errors
+/// Returns an error if the file can't be read or parsed, its strategy
+/// settings don't validate, or its profit denomination can't be priced; the
+/// live config is left unchanged.
+fn reload(
+    config: &SharedConfig,
+    path: &Path,
+    overrides: impl Fn(&mut MEVConfig),
+) -> anyhow::Result<Vec<String>> {
+    // ---
+    let mut reloaded = Config::from_file(path)?.mev_config;
+    overrides(&mut reloaded);
+    reloaded.validate()?;
+    oracle::ensure_priced(
+        &oracle::pipeline_oracle(&TokenRegistry::with_defaults()),
+        reloaded.profit_denomination,
+    )?;
+
+    let mut live = config.write().expect("config poisoned");
+    let changes = changed_settings(&live, &reloaded);
+    *live = reloaded;
+    Ok(changes)
+}
+
+/// Lists the settings that differ between `old` and `new`, by dotted name.
+fn changed_settings(old: &MEVConfig, new: &MEVConfig) -> Vec<String> {
+    // ---
+
+    let settings = |config: &MEVConfig| {
+        let mut settings = BTreeMap::new();
+        let value = serde_json::to_value(config).expect("config serializes to JSON");
+        flatten(String::new(), value, &mut settings);
+        settings
+    };
+    let (old, new) = (settings(old), settings(new));
+    let show = |value: Option<&Value>| value.map_or("unset".to_string(), Value::to_string);
+
+    old.keys()
+        .chain(new.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|name| old.get(*name) != new.get(*name))
+        .map(|name| {
+            format!(
+                "{}: {} → {}",
+                name,
+                show(old.get(name)),
+                show(new.get(name))
+            )
+        })
+        .collect()
+}
+
+/// Collects the leaves of `value` into `settings`, keyed by dotted path.
+fn flatten(path: String, value: Value, settings: &mut BTreeMap<String, Value>) {
+    // ---
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                let path = if path.is_empty() {
+                    name
+                } else {
+                    format!("{path}.{name}")
+                };
+                flatten(path, field, settings);
+            }
+        }
+        leaf => {
+            settings.insert(path, leaf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, MockProvider};
+    use crate::types::ProfitDenomination;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn hangup_reloads_the_config_file() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let path = std::env::temp_dir().join(format!("vortex-reload-{}.json", std::process::id()));
+        let write = |min_profit: f64| {
+            let mut config = Config::default();
+            config.mev_config.min_profit = min_profit;
+            std::fs::write(&path, serde_json::to_string(&config).unwrap()).unwrap();
+        };
+        // One simulated SIGHUP per call
+        let hangup = || {
+            let sighup = futures::stream::iter([()]);
+            reload_on(
+                sighup,
+                ctx.config.clone(),
+                path.clone(),
+                |_: &mut MEVConfig| {},
+            )
+        };
+
+        write(0.5);
+        hangup().await;
+        assert_eq!(ctx.config().min_profit, 0.5);
+        assert_eq!(
+            changed_settings(&MEVConfig::default(), &ctx.config()),
+            ["min_profit: 0.01 → 0.5"]
+        );
+
+        // An invalid file is rejected and the live config kept
+        write(-1.0);
+        hangup().await;
+        assert_eq!(ctx.config().min_profit, 0.5);
+
+        // So is a profit denomination the oracle can't price
+        let mut config = Config::default();
+        config.mev_config.profit_denomination =
+            ProfitDenomination::Token(ethers::types::Address::repeat_byte(0x77));
+        std::fs::write(&path, serde_json::to_string(&config).unwrap()).unwrap();
+        hangup().await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(ctx.config().profit_denomination, ProfitDenomination::Eth);
+        assert_eq!(ctx.config().min_profit, 0.5);
+    }
+}
@@ -0,0 +1,217 @@
+//! Replacement-transaction detection.
+//!
+//! A sender replaces a pending transaction by re-sending its nonce with a
+//! higher gas bid: to speed it up, to cancel it, or to outbid a competitor.
+//! [`ReplacementTracker`] remembers the latest bid seen for each pending
+//! `(from, nonce)` and reports a [`Replacement`] when a higher bid reuses it.
+//! Entries expire a few blocks after they were seen, once the nonce has most
+//! likely been mined, and the map is bounded so a flood of senders can't grow
+//! it without limit.
+
+use crate::gas::BaseFeeTracker;
+use ethers::types::{Address, Transaction, TxHash, U256, U64};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Pending nonces tracked at once.
+pub const REPLACEMENT_TRACKER_CAPACITY: usize = 50_000;
+
+/// Blocks a pending nonce is tracked after its latest transaction was seen.
+pub const REPLACEMENT_WINDOW_BLOCKS: u64 = 3;
+
+// ---
+
+/// Latest bid seen for each pending `(from, nonce)`.
+#[derive(Debug)]
+pub struct ReplacementTracker {
+    // ---
+    /// Most pending nonces tracked at once
+    capacity: usize,
+
+    /// Chain head, which scopes each entry's lifetime
+    heads: Arc<BaseFeeTracker>,
+
+    /// Latest bid on each pending `(from, nonce)`
+    pending: Mutex<HashMap<(Address, U256), PendingBid>>,
+}
+
+/// The latest transaction seen for a pending nonce.
+#[derive(Debug, Clone, Copy)]
+struct PendingBid {
+    // ---
+    hash: TxHash,
+
+    /// Gas bid in wei
+    bid: U256,
+
+    /// Block that was latest when the transaction was seen
+    seen: U64,
+}
+
+/// A pending transaction re-sent with a higher gas bid under the same nonce.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Replacement {
+    // ---
+    /// Sender of both transactions
+    pub from: Address,
+
+    /// Nonce both transactions use
+    pub nonce: U256,
+
+    /// The transaction being replaced
+    pub old_hash: TxHash,
+
+    /// The replacing transaction
+    pub new_hash: TxHash,
+
+    /// How much higher the new gas bid is, in wei
+    pub gas_delta: U256,
+}
+
+// ---
+
+impl ReplacementTracker {
+    // ---
+
+    /// Creates a tracker of up to `capacity` pending nonces, aged by `heads`.
+    pub fn new(capacity: usize, heads: Arc<BaseFeeTracker>) -> Self {
+        // ---
+        Self {
+            capacity,
+            heads,
+            pending: Mutex::default(),
+        }
+    }
+
+    /// Records `tx` and returns the replacement it makes, if it re-sends a
+    /// tracked nonce with a higher gas bid (the fee cap for EIP-1559).
+    ///
+    /// A re-send at the same or a lower bid is not a replacement nodes would
+    /// accept, and leaves the tracked transaction as it was.
+    pub fn observe(&self, tx: &Transaction) -> Option<Replacement> {
+        // ---
+
+        let bid = tx.max_fee_per_gas.or(tx.gas_price)?;
+        let head = self.heads.block_number();
+        let key = (tx.from, tx.nonce);
+
+        let latest = PendingBid {
+            hash: tx.hash,
+            bid,
+            seen: head,
+        };
+
+        let mut pending = self.pending.lock().expect("replacement tracker poisoned");
+        let previous = pending
+            .get(&key)
+            .filter(|previous| !expired(previous.seen, head))
+            .copied();
+
+        match previous {
+            Some(previous) if previous.hash == tx.hash || bid <= previous.bid => None,
+            Some(previous) => {
+                pending.insert(key, latest);
+                Some(Replacement {
+                    from: tx.from,
+                    nonce: tx.nonce,
+                    old_hash: previous.hash,
+                    new_hash: tx.hash,
+                    gas_delta: bid - previous.bid,
+                })
+            }
+            None => {
+                // Make room from expired nonces; skip tracking if none free up
+                if pending.len() >= self.capacity {
+                    pending.retain(|_, tracked| !expired(tracked.seen, head));
+                }
+                if pending.len() < self.capacity {
+                    pending.insert(key, latest);
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Returns whether an entry seen at block `seen` has outlived the window at `head`.
+fn expired(seen: U64, head: U64) -> bool {
+    // ---
+    head > seen + REPLACEMENT_WINDOW_BLOCKS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Block;
+
+    fn tx(hash: u64, nonce: u64, gas_gwei: u64) -> Transaction {
+        Transaction {
+            hash: TxHash::from_low_u64_be(hash),
+            from: Address::repeat_byte(0xaa),
+            nonce: nonce.into(),
+            gas_price: Some(U256::exp10(9) * gas_gwei),
+            ..Default::default()
+        }
+    }
+
+    fn tracker(capacity: usize) -> (ReplacementTracker, Arc<BaseFeeTracker>) {
+        let heads = Arc::new(BaseFeeTracker::new(U256::exp10(10)));
+        (ReplacementTracker::new(capacity, heads.clone()), heads)
+    }
+
+    fn advance(heads: &BaseFeeTracker, number: u64) {
+        heads.observe_block(&Block {
+            number: Some(number.into()),
+            base_fee_per_gas: Some(U256::exp10(10)),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn higher_bid_on_a_pending_nonce_is_a_replacement() {
+        let (tracker, _) = tracker(16);
+        assert_eq!(tracker.observe(&tx(1, 7, 10)), None);
+
+        // Re-announced, or re-sent without a higher bid: nothing to report
+        assert_eq!(tracker.observe(&tx(1, 7, 10)), None);
+        assert_eq!(tracker.observe(&tx(2, 7, 10)), None);
+        assert_eq!(tracker.observe(&tx(3, 8, 50)), None);
+
+        let bumped = tracker.observe(&tx(4, 7, 12)).unwrap();
+        assert_eq!(bumped.old_hash, TxHash::from_low_u64_be(1));
+        assert_eq!(bumped.new_hash, TxHash::from_low_u64_be(4));
+        assert_eq!(bumped.nonce, U256::from(7));
+        assert_eq!(bumped.gas_delta, U256::exp10(9) * 2);
+
+        // The replacement is now the one to beat
+        let again = tracker.observe(&tx(5, 7, 15)).unwrap();
+        assert_eq!(again.old_hash, TxHash::from_low_u64_be(4));
+        assert_eq!(again.gas_delta, U256::exp10(9) * 3);
+    }
+
+    #[test]
+    fn nonces_expire_and_the_map_stays_bounded() {
+        let (tracker, heads) = tracker(2);
+        advance(&heads, 100);
+        tracker.observe(&tx(1, 1, 10));
+        tracker.observe(&tx(2, 2, 10));
+
+        // Full: a third nonce isn't tracked, so its bump goes unreported
+        tracker.observe(&tx(3, 3, 10));
+        assert_eq!(tracker.observe(&tx(4, 3, 20)), None);
+
+        // Still inside the window
+        advance(&heads, 100 + REPLACEMENT_WINDOW_BLOCKS);
+        assert!(tracker.observe(&tx(5, 1, 20)).is_some());
+
+        // Past it, nonce 2 has most likely been mined: a new transaction
+        // under it starts over in the freed slot
+        advance(&heads, 101 + REPLACEMENT_WINDOW_BLOCKS);
+        assert_eq!(tracker.observe(&tx(6, 2, 20)), None);
+        assert_eq!(
+            tracker.observe(&tx(7, 2, 25)).unwrap().old_hash,
+            TxHash::from_low_u64_be(6)
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Machine-readable run report.
+//!
+//! `--report <PATH>` writes a JSON summary of a listening session when it
+//! ends, whether by `--max-tx`, `--shutdown-after-secs`, Ctrl-C, or an error:
+//! the final [`MEVMetrics`] plus what the session summary logs only as text
+//! (opportunities by strategy, latency percentiles, top pairs, rejections)
+//! and how long the run took. `--observe-blocks` sessions add their per-block
+//! table.
+
+use crate::observe::BlockReport;
+use crate::types::{MEVMetrics, PairStats, TokenRegistry};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Token pairs listed in the report.
+const TOP_PAIRS_IN_REPORT: usize = 10;
+
+// ---
+
+/// Summary of one listening session, written by `--report`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunReport {
+    // ---
+    /// Session start in Unix seconds
+    pub started_at: u64,
+
+    /// Wall-clock run time in seconds
+    pub duration_secs: f64,
+
+    /// Why the session ended: `max_tx`, `time_budget`, `interrupted`,
+    /// `blocks_observed`, or `error`
+    pub stop_reason: String,
+
+    /// The error that ended the session, if one did
+    pub error: Option<String>,
+
+    /// Final session metrics
+    pub metrics: MEVMetrics,
+
+    /// Opportunities detected, by strategy
+    pub strategies: BTreeMap<String, u64>,
+
+    /// Processing latency percentiles over the most recent transactions
+    pub latency_ms: LatencyPercentiles,
+
+    /// Most active token pairs, by opportunities detected
+    pub top_pairs: Vec<PairReport>,
+
+    /// Detector rejections, by strategy and reason
+    pub rejections: Vec<RejectionCount>,
+
+    /// Per-block observations, for `--observe-blocks` sessions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<BlockReport>,
+}
+
+/// Processing latency percentiles in milliseconds; `None` when no
+/// transaction was analyzed.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct LatencyPercentiles {
+    // ---
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p99: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Activity on one token pair.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PairReport {
+    // ---
+    /// Symbol (or address) of the pair's first token in address order
+    pub token_a: String,
+
+    /// Symbol (or address) of the pair's second token
+    pub token_b: String,
+
+    /// Address of `token_a`
+    pub token_a_address: Address,
+
+    /// Address of `token_b`
+    pub token_b_address: Address,
+
+    /// The pair's counts and realized profit
+    #[serde(flatten)]
+    pub stats: PairStats,
+}
+
+/// How often one strategy passed on a transaction for one reason.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RejectionCount {
+    // ---
+    pub strategy: String,
+    pub reason: String,
+    pub count: u64,
+}
+
+// ---
+
+impl RunReport {
+    // ---
+
+    /// Builds the report for a session that started at `started_at`, ran for
+    /// `duration`, and ended for `stop_reason` (with `error` if it failed).
+    pub fn new(
+        metrics: &MEVMetrics,
+        started_at: SystemTime,
+        duration: Duration,
+        stop_reason: &str,
+        error: Option<String>,
+    ) -> Self {
+        // ---
+
+        let strategies = [
+            ("arbitrage", metrics.arbitrage_count),
+            ("sandwich", metrics.sandwich_count),
+            ("liquidation", metrics.liquidation_count),
+        ]
+        .into_iter()
+        .map(|(strategy, count)| (strategy.to_string(), count))
+        .collect();
+
+        let registry = TokenRegistry::with_defaults();
+        let top_pairs = metrics
+            .top_pairs(TOP_PAIRS_IN_REPORT)
+            .into_iter()
+            .map(|((token_a, token_b), stats)| PairReport {
+                token_a: registry.symbol(&token_a),
+                token_b: registry.symbol(&token_b),
+                token_a_address: token_a,
+                token_b_address: token_b,
+                stats: stats.clone(),
+            })
+            .collect();
+
+        let rejections = metrics
+            .rejection_counts()
+            .into_iter()
+            .map(|((strategy, reason), count)| RejectionCount {
+                strategy: strategy.to_string(),
+                reason: reason.to_string(),
+                count,
+            })
+            .collect();
+
+        Self {
+            started_at: started_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            duration_secs: duration.as_secs_f64(),
+            stop_reason: stop_reason.to_string(),
+            error,
+            metrics: metrics.clone(),
+            strategies,
+            latency_ms: LatencyPercentiles {
+                p50: metrics.latency_percentile(0.50),
+                p90: metrics.latency_percentile(0.90),
+                p99: metrics.latency_percentile(0.99),
+                max: metrics.latency_percentile(1.0),
+            },
+            top_pairs,
+            rejections,
+            blocks: metrics
+                .observed_blocks
+                .as_ref()
+                .map(|observer| observer.reports())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Writes the report to `path` as pretty-printed JSON.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        // ---
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| anyhow::anyhow!("Cannot serialize run report: {}", e))?;
+        std::fs::write(path, json + "\n")
+            .map_err(|e| anyhow::anyhow!("Cannot write run report {}: {}", path.display(), e))
+    }
+}
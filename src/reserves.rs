@@ -0,0 +1,292 @@
+//! Short-lived cache of on-chain pool reserves.
+//!
+//! Every pending swap in a block quotes against the same pools, so reserves are
+//! fetched once per pool and reused until the chain moves on. Entries are tied
+//! to the head block seen by the shared [`BaseFeeTracker`], which follows the
+//! node's new-heads subscription: a new block invalidates every entry, and the
+//! TTL bounds reuse if heads stop arriving. Lookups that miss on the same pool
+//! at once wait for a single fetch.
+
+use crate::gas::BaseFeeTracker;
+use ethers::abi::{self, ParamType};
+use ethers::providers::{JsonRpcClient, Middleware, Provider};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, TransactionRequest, U256, U64};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How long fetched reserves are reused at most (one mainnet slot).
+pub const POOL_RESERVE_TTL: Duration = Duration::from_secs(12);
+
+// ---
+
+/// Reserves of V2-style pools, reused within a block.
+#[derive(Debug)]
+pub struct PoolReserveCache {
+    // ---
+    /// How long an entry stays valid within its block
+    ttl: Duration,
+
+    /// Head tracker whose block number scopes every entry
+    heads: Arc<BaseFeeTracker>,
+
+    /// `(reserve0, reserve1)` per pool, with the block and time fetched
+    entries: Mutex<HashMap<Address, CachedReserves>>,
+
+    /// Per-pool lock held while its reserves are fetched
+    fetches: Mutex<HashMap<Address, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+/// One pool's reserves as of a block.
+#[derive(Debug, Clone, Copy)]
+struct CachedReserves {
+    // ---
+    reserves: (U256, U256),
+    block: U64,
+    fetched_at: Instant,
+}
+
+/// Reserves returned by [`PoolReserveCache::get_or_fetch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReserveLookup {
+    // ---
+    /// `(reserve0, reserve1)`, ordered by token address as the pair stores them
+    pub reserves: (U256, U256),
+
+    /// Whether the reserves came from the cache rather than the node
+    pub cached: bool,
+}
+
+// ---
+
+impl PoolReserveCache {
+    // ---
+
+    /// Creates an empty cache scoped to the blocks `heads` observes.
+    pub fn new(ttl: Duration, heads: Arc<BaseFeeTracker>) -> Self {
+        // ---
+        Self {
+            ttl,
+            heads,
+            entries: Mutex::new(HashMap::new()),
+            fetches: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns `pool`'s reserves if they were fetched in the current block
+    /// and within the TTL.
+    pub fn get(&self, pool: Address) -> Option<(U256, U256)> {
+        // ---
+        let block = self.heads.block_number();
+        let entries = self.entries.lock().expect("pool reserve cache poisoned");
+        entries
+            .get(&pool)
+            .filter(|entry| entry.block == block && entry.fetched_at.elapsed() < self.ttl)
+            .map(|entry| entry.reserves)
+    }
+
+    /// Records `pool`'s reserves as of the current block, as if just fetched.
+    pub fn insert(&self, pool: Address, reserves: (U256, U256)) {
+        // ---
+        let block = self.heads.block_number();
+        let mut entries = self.entries.lock().expect("pool reserve cache poisoned");
+        entries.insert(
+            pool,
+            CachedReserves {
+                reserves,
+                block,
+                fetched_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Returns `pool`'s reserves, calling the pair's `getReserves()` when the
+    /// cache has none fresh. A lookup that misses while another is fetching
+    /// the same pool waits for that fetch and is answered from the cache.
+    ///
+    /// # Errors
+    /// Returns an error if the call fails or returns something other than
+    /// `(uint112, uint112, uint32)`.
+    pub async fn get_or_fetch<P: JsonRpcClient>(
+        &self,
+        provider: &Provider<P>,
+        pool: Address,
+    ) -> anyhow::Result<ReserveLookup> {
+        // ---
+
+        if let Some(reserves) = self.get(pool) {
+            return Ok(ReserveLookup {
+                reserves,
+                cached: true,
+            });
+        }
+
+        let fetch = self
+            .fetches
+            .lock()
+            .expect("pool reserve cache poisoned")
+            .entry(pool)
+            .or_default()
+            .clone();
+        let _fetching = fetch.lock().await;
+        if let Some(reserves) = self.get(pool) {
+            return Ok(ReserveLookup {
+                reserves,
+                cached: true,
+            });
+        }
+
+        // Scope the entry to the block the reserves were read at
+        let block = self.heads.block_number();
+        let reserves = fetch_reserves(provider, pool).await?;
+
+        let mut entries = self.entries.lock().expect("pool reserve cache poisoned");
+        entries.retain(|_, entry| entry.block == block && entry.fetched_at.elapsed() < self.ttl);
+        entries.insert(
+            pool,
+            CachedReserves {
+                reserves,
+                block,
+                fetched_at: Instant::now(),
+            },
+        );
+        Ok(ReserveLookup {
+            reserves,
+            cached: false,
+        })
+    }
+}
+
+/// Calls `getReserves()` on a Uniswap V2-style pair.
+async fn fetch_reserves<P: JsonRpcClient>(
+    provider: &Provider<P>,
+    pool: Address,
+) -> anyhow::Result<(U256, U256)> {
+    // ---
+
+    // getReserves() = 0x0902f1ac
+    let call: TypedTransaction = TransactionRequest::new()
+        .to(pool)
+        .data(vec![0x09, 0x02, 0xf1, 0xac])
+        .into();
+    let output = provider
+        .call(&call, None)
+        .await
+        .map_err(|e| anyhow::anyhow!("getReserves() on {:?} failed: {}", pool, e))?;
+
+    let params = [
+        ParamType::Uint(112),
+        ParamType::Uint(112),
+        ParamType::Uint(32),
+    ];
+    let tokens = abi::decode(&params, &output)
+        .map_err(|e| anyhow::anyhow!("Malformed getReserves() from {:?}: {}", pool, e))?;
+    match (tokens[0].clone().into_uint(), tokens[1].clone().into_uint()) {
+        (Some(reserve0), Some(reserve1)) => Ok((reserve0, reserve1)),
+        _ => anyhow::bail!("Malformed getReserves() from {:?}", pool),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockProvider, TEST_BASE_FEE_WEI};
+    use ethers::abi::Token;
+    use ethers::types::{Block, Bytes, H256};
+
+    fn pool() -> Address {
+        Address::from_low_u64_be(0xb4e16d01)
+    }
+
+    /// Node answering every `getReserves()` with 1,000 WETH / 2.5M USDC.
+    fn reserves_provider() -> MockProvider {
+        let output = abi::encode(&[
+            Token::Uint(U256::exp10(18) * 1_000),
+            Token::Uint(U256::exp10(6) * 2_500_000),
+            Token::Uint(U256::from(1_700_000_000u64)),
+        ]);
+        MockProvider::default().with_response("eth_call", Bytes::from(output))
+    }
+
+    fn head(number: u64) -> Block<H256> {
+        Block {
+            number: Some(U64::from(number)),
+            base_fee_per_gas: Some(U256::from(TEST_BASE_FEE_WEI)),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reserves_expire_after_ttl() {
+        let mock = Arc::new(reserves_provider());
+        let provider = Provider::new(mock.clone());
+        let heads = Arc::new(BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI)));
+        let cache = PoolReserveCache::new(POOL_RESERVE_TTL, heads);
+
+        let first = cache.get_or_fetch(&provider, pool()).await.unwrap();
+        assert!(!first.cached);
+        assert_eq!(first.reserves.0, U256::exp10(18) * 1_000);
+
+        let second = cache.get_or_fetch(&provider, pool()).await.unwrap();
+        assert_eq!(
+            second,
+            ReserveLookup {
+                cached: true,
+                ..first
+            }
+        );
+        assert_eq!(mock.call_count("eth_call"), 1);
+
+        tokio::time::advance(POOL_RESERVE_TTL).await;
+        assert_eq!(cache.get(pool()), None);
+        assert!(!cache.get_or_fetch(&provider, pool()).await.unwrap().cached);
+        assert_eq!(mock.call_count("eth_call"), 2);
+    }
+
+    #[tokio::test]
+    async fn new_block_invalidates_reserves() {
+        let mock = Arc::new(reserves_provider());
+        let provider = Provider::new(mock.clone());
+        let heads = Arc::new(BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI)));
+        heads.observe_block(&head(100));
+        let cache = PoolReserveCache::new(POOL_RESERVE_TTL, heads.clone());
+
+        cache.get_or_fetch(&provider, pool()).await.unwrap();
+        assert!(cache.get(pool()).is_some());
+
+        heads.observe_block(&head(101));
+        assert_eq!(cache.get(pool()), None);
+        assert!(!cache.get_or_fetch(&provider, pool()).await.unwrap().cached);
+        assert_eq!(mock.call_count("eth_call"), 2);
+    }
+
+    #[tokio::test]
+    async fn concurrent_misses_share_one_fetch() {
+        let mock = Arc::new(reserves_provider().with_delay("eth_call", Duration::from_millis(50)));
+        let provider = Provider::new(mock.clone());
+        let heads = Arc::new(BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI)));
+        let cache = PoolReserveCache::new(POOL_RESERVE_TTL, heads);
+
+        let lookups =
+            futures::future::join_all((0..4).map(|_| cache.get_or_fetch(&provider, pool()))).await;
+        assert_eq!(mock.call_count("eth_call"), 1);
+        let cached: Vec<bool> = lookups
+            .into_iter()
+            .map(|lookup| lookup.unwrap().cached)
+            .collect();
+        assert_eq!(cached, [false, true, true, true]);
+    }
+
+    #[tokio::test]
+    async fn failed_fetch_is_an_error_and_not_cached() {
+        let mock = Arc::new(MockProvider::default().with_error("eth_call", "execution reverted"));
+        let provider = Provider::new(mock.clone());
+        let heads = Arc::new(BaseFeeTracker::new(U256::from(TEST_BASE_FEE_WEI)));
+        let cache = PoolReserveCache::new(POOL_RESERVE_TTL, heads);
+
+        assert!(cache.get_or_fetch(&provider, pool()).await.is_err());
+        assert_eq!(cache.get(pool()), None);
+    }
+}
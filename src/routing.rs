@@ -0,0 +1,333 @@
+//! Multi-hop arbitrage path search over constant-product pools.
+//!
+//! Finds cycles that start and end in a base token (e.g. WETH → USDC → DAI →
+//! WETH) and return more than they take in. The search is bounded in both
+//! depth ([`MAX_HOPS`]) and number of candidate cycles
+//! ([`MAX_CANDIDATE_CYCLES`]) so per-transaction latency stays predictable.
+//! Each hop is quoted with [`crate::amm`]; nothing here touches RPC.
+
+use crate::amm;
+use crate::types::PoolInfo;
+use ethers::types::{Address, U256};
+
+/// Longest cycle considered, in swaps.
+pub const MAX_HOPS: usize = 3;
+
+/// Upper bound on cycles sized and quoted per search.
+pub const MAX_CANDIDATE_CYCLES: usize = 256;
+
+// ---
+
+/// One swap through a pool of the searched set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hop {
+    // ---
+    /// Index of the pool in the searched slice
+    pub pool: usize,
+
+    /// Token sold into the pool
+    pub token_in: Address,
+
+    /// Token bought from the pool
+    pub token_out: Address,
+}
+
+/// A profitable cycle, sized for maximum profit.
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    // ---
+    /// Swaps in execution order; the first sells and the last buys the base token
+    pub hops: Vec<Hop>,
+
+    /// Input amount of each hop, in that hop's `token_in` units
+    pub hop_amounts_in: Vec<U256>,
+
+    /// Base token returned by the final hop
+    pub amount_out: U256,
+}
+
+// ---
+
+impl Cycle {
+    /// Returns the base-token profit (output minus input).
+    pub fn profit(&self) -> U256 {
+        // ---
+        let amount_in = self.hop_amounts_in.first().copied().unwrap_or_default();
+        self.amount_out.saturating_sub(amount_in)
+    }
+}
+
+/// Returns the most profitable cycle through `pools` that starts and ends in
+/// `base_token`, using at most `max_hops` swaps (capped at [`MAX_HOPS`]) and at
+/// most `max_amount_in` of the base token.
+///
+/// Cycles visit each pool at most once and each intermediate token at most
+/// once. Cycles whose marginal exchange rate does not exceed 1 are discarded
+/// before sizing, since no input size can make them profitable.
+pub fn find_best_cycle(
+    pools: &[PoolInfo],
+    base_token: Address,
+    max_amount_in: U256,
+    max_hops: usize,
+) -> Option<Cycle> {
+    // ---
+
+    let max_hops = max_hops.min(MAX_HOPS);
+    let mut candidates = Vec::new();
+    let mut path = Vec::with_capacity(max_hops);
+    collect_cycles(
+        pools,
+        base_token,
+        base_token,
+        max_hops,
+        &mut path,
+        &mut candidates,
+    );
+
+    candidates
+        .into_iter()
+        .filter(|hops| marginal_rate(pools, hops) > 1.0)
+        .filter_map(|hops| {
+            let amount_in = optimal_amount_in(pools, &hops, max_amount_in);
+            let hop_amounts_in = quote_hops(pools, &hops, amount_in);
+            let amount_out = quote_path(pools, &hops, amount_in);
+
+            (amount_out > amount_in).then_some(Cycle {
+                hops,
+                hop_amounts_in,
+                amount_out,
+            })
+        })
+        .max_by_key(Cycle::profit)
+}
+
+/// Returns the output of swapping `amount_in` along `hops`.
+pub fn quote_path(pools: &[PoolInfo], hops: &[Hop], amount_in: U256) -> U256 {
+    // ---
+    hops.iter().fold(amount_in, |amount, hop| {
+        let (reserve_in, reserve_out) = reserves(&pools[hop.pool], hop.token_in);
+        amm::get_amount_out(amount, reserve_in, reserve_out, pools[hop.pool].fee_bps)
+    })
+}
+
+/// Returns the input amount of every hop when `amount_in` enters the path.
+fn quote_hops(pools: &[PoolInfo], hops: &[Hop], amount_in: U256) -> Vec<U256> {
+    // ---
+
+    let mut amounts = Vec::with_capacity(hops.len());
+    let mut amount = amount_in;
+    for hop in hops {
+        amounts.push(amount);
+        let (reserve_in, reserve_out) = reserves(&pools[hop.pool], hop.token_in);
+        amount = amm::get_amount_out(amount, reserve_in, reserve_out, pools[hop.pool].fee_bps);
+    }
+    amounts
+}
+
+/// Depth-first enumeration of cycles from `base_token`, stopping at
+/// [`MAX_CANDIDATE_CYCLES`].
+fn collect_cycles(
+    pools: &[PoolInfo],
+    base_token: Address,
+    current: Address,
+    hops_left: usize,
+    path: &mut Vec<Hop>,
+    out: &mut Vec<Vec<Hop>>,
+) {
+    // ---
+
+    if hops_left == 0 || out.len() >= MAX_CANDIDATE_CYCLES {
+        return;
+    }
+
+    for (index, pool) in pools.iter().enumerate() {
+        let token_out = if pool.token_a == current {
+            pool.token_b
+        } else if pool.token_b == current {
+            pool.token_a
+        } else {
+            continue;
+        };
+
+        // Never reuse a pool or revisit an intermediate token
+        if path.iter().any(|hop| hop.pool == index)
+            || (token_out != base_token && path.iter().any(|hop| hop.token_in == token_out))
+        {
+            continue;
+        }
+
+        path.push(Hop {
+            pool: index,
+            token_in: current,
+            token_out,
+        });
+
+        if token_out == base_token {
+            // A single hop back to the base token is a round trip, not a cycle
+            if path.len() >= 2 {
+                out.push(path.clone());
+            }
+        } else {
+            collect_cycles(pools, base_token, token_out, hops_left - 1, path, out);
+        }
+
+        path.pop();
+        if out.len() >= MAX_CANDIDATE_CYCLES {
+            return;
+        }
+    }
+}
+
+/// Returns the fee-adjusted exchange rate of an infinitesimal trade along `hops`.
+///
+/// Only used to discard unprofitable cycles, so `f64` precision is sufficient.
+fn marginal_rate(pools: &[PoolInfo], hops: &[Hop]) -> f64 {
+    // ---
+    hops.iter().fold(1.0, |rate, hop| {
+        let pool = &pools[hop.pool];
+        let (reserve_in, reserve_out) = reserves(pool, hop.token_in);
+        if reserve_in.is_zero() {
+            return 0.0;
+        }
+        let fee_factor = 1.0 - f64::from(pool.fee_bps) / amm::BPS_DENOMINATOR as f64;
+        rate * u256_to_f64(reserve_out) / u256_to_f64(reserve_in) * fee_factor
+    })
+}
+
+/// Finds the input (at most `max_amount_in`) maximizing the cycle's profit.
+///
+/// Profit along a chain of constant-product swaps is concave in the input, so
+/// ternary search converges on the optimum in a bounded number of quotes.
+fn optimal_amount_in(pools: &[PoolInfo], hops: &[Hop], max_amount_in: U256) -> U256 {
+    // ---
+
+    let profit = |amount_in: U256| quote_path(pools, hops, amount_in).saturating_sub(amount_in);
+
+    let (mut low, mut high) = (U256::zero(), max_amount_in);
+    while high - low > U256::from(2) {
+        let third = (high - low) / 3;
+        let (m1, m2) = (low + third, high - third);
+        if profit(m1) < profit(m2) {
+            low = m1;
+        } else {
+            high = m2;
+        }
+    }
+
+    // Pick the best of the (at most three) remaining candidates
+    let mut best = low;
+    let mut amount_in = low + 1;
+    while amount_in <= high {
+        if profit(amount_in) > profit(best) {
+            best = amount_in;
+        }
+        amount_in += U256::one();
+    }
+    best
+}
+
+/// Returns `(reserve_in, reserve_out)` of `pool` when selling `token_in`.
+fn reserves(pool: &PoolInfo, token_in: Address) -> (U256, U256) {
+    // ---
+    if pool.token_a == token_in {
+        (pool.reserve_a, pool.reserve_b)
+    } else {
+        (pool.reserve_b, pool.reserve_a)
+    }
+}
+
+/// Converts a U256 to `f64`, losing precision beyond 53 bits.
+fn u256_to_f64(value: U256) -> f64 {
+    // ---
+    value.0.iter().rev().fold(0.0, |acc, limb| {
+        acc * 18_446_744_073_709_551_616.0 + *limb as f64
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(n: u64) -> Address {
+        Address::from_low_u64_be(n)
+    }
+
+    /// A 0.3% pool holding `reserve_a` of `token_a` against `reserve_b` of
+    /// `token_b`, in whole tokens of 18 decimals.
+    fn pool(token_a: Address, token_b: Address, reserve_a: u64, reserve_b: u64) -> PoolInfo {
+        PoolInfo {
+            address: Address::random(),
+            dex: "UniswapV2".to_string(),
+            token_a,
+            token_b,
+            reserve_a: U256::exp10(18) * reserve_a,
+            reserve_b: U256::exp10(18) * reserve_b,
+            fee_bps: 30,
+            liquidity_usd: 0.0,
+            last_updated: 0,
+        }
+    }
+
+    #[test]
+    fn mispriced_triangle_is_found_and_sized() {
+        let (weth, usdc, dai) = (token(1), token(2), token(3));
+        // WETH sells for 2,000 USDC, but 1,900 DAI buys it back
+        let pools = [
+            pool(weth, usdc, 1_000, 2_000_000),
+            pool(usdc, dai, 2_000_000, 2_000_000),
+            pool(dai, weth, 1_900_000, 1_000),
+        ];
+        let max_amount_in = U256::exp10(18) * 100;
+
+        let cycle = find_best_cycle(&pools, weth, max_amount_in, MAX_HOPS).unwrap();
+        assert_eq!(cycle.hops.len(), 3);
+        assert_eq!(cycle.hops[0].token_in, weth);
+        assert_eq!(cycle.hops[2].token_out, weth);
+        assert!(cycle.profit() > U256::zero());
+        assert!(cycle.hop_amounts_in[0] <= max_amount_in);
+        assert_eq!(
+            cycle.amount_out,
+            quote_path(&pools, &cycle.hops, cycle.hop_amounts_in[0])
+        );
+    }
+
+    #[test]
+    fn fairly_priced_pools_hold_no_cycle() {
+        let (weth, usdc, dai) = (token(1), token(2), token(3));
+        let pools = [
+            pool(weth, usdc, 1_000, 2_000_000),
+            pool(usdc, dai, 2_000_000, 2_000_000),
+            pool(dai, weth, 2_000_000, 1_000),
+        ];
+        assert!(find_best_cycle(&pools, weth, U256::exp10(20), MAX_HOPS).is_none());
+
+        // Nor do pools that never lead back to the base token
+        let pools = [
+            pool(weth, usdc, 1_000, 2_000_000),
+            pool(usdc, dai, 2_000_000, 1_000_000),
+        ];
+        assert!(find_best_cycle(&pools, weth, U256::exp10(20), MAX_HOPS).is_none());
+    }
+
+    #[test]
+    fn cycles_longer_than_max_hops_are_not_searched() {
+        let (weth, a, b, c) = (token(1), token(2), token(3), token(4));
+        // Only the four-hop loop is mispriced
+        let pools = [
+            pool(weth, a, 1_000, 1_000),
+            pool(a, b, 1_000, 1_000),
+            pool(b, c, 1_000, 1_000),
+            pool(c, weth, 1_000, 900),
+        ];
+        assert!(find_best_cycle(&pools, weth, U256::exp10(20), MAX_HOPS + 1).is_none());
+
+        // And a caller's lower limit is honored too
+        let (usdc, dai) = (a, b);
+        let triangle = [
+            pool(weth, usdc, 1_000, 2_000_000),
+            pool(usdc, dai, 2_000_000, 2_000_000),
+            pool(dai, weth, 1_900_000, 1_000),
+        ];
+        assert!(find_best_cycle(&triangle, weth, U256::exp10(20), 2).is_none());
+    }
+}
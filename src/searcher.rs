@@ -4,12 +4,148 @@
 //! from pending Ethereum transactions. It analyzes transaction patterns to detect
 //! arbitrage, sandwich attacks, and liquidation opportunities.
 
-use ethers::types::{Address, Transaction, TxHash, U256};
+use crate::amm;
+use crate::gas::BaseFeeTracker;
+use crate::lending;
+use crate::oracle::{self, PriceOracle};
+use crate::positions;
+use crate::reserves::PoolReserveCache;
+use crate::routing;
+use crate::types::{
+    MEVConfig, MEVMetrics, PoolInfo, ProfitDenomination, ProfitFloorMode, RelayConfiguration,
+    SharedConfig, TokenRegistry,
+};
+use crate::units;
+use ethers::abi::{self, ParamType, Token};
+use ethers::providers::{JsonRpcClient, Middleware, Provider, RpcError, Ws};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Transaction, TxHash, U256, U64};
 use serde::{Deserialize, Serialize};
-use tracing::{debug, info};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLockReadGuard};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Most WETH committed to a single multi-hop arbitrage cycle.
+const MAX_CYCLE_INPUT_ETH: f64 = 50.0;
+
+/// How long a victim simulation result is reused for re-announced transactions
+/// (about one block).
+pub const VICTIM_SIMULATION_TTL: Duration = Duration::from_secs(12);
+
+/// Most decoded transaction types kept for the current block.
+pub const TX_TYPE_CACHE_CAPACITY: usize = 10_000;
+
+/// Uniswap router calls a multicall makes ahead of its swap to approve the
+/// router by ERC-2612 or DAI-style permit: `selfPermit`,
+/// `selfPermitIfNecessary`, `selfPermitAllowed`, `selfPermitAllowedIfNecessary`.
+const SELF_PERMIT_SELECTORS: [[u8; 4]; 4] = [
+    [0xf3, 0x99, 0x5c, 0x67],
+    [0xc2, 0xe3, 0x14, 0x0a],
+    [0x46, 0x59, 0xa4, 0x94],
+    [0xa4, 0xa7, 0x8f, 0x0c],
+];
+
+/// Universal Router `V3_SWAP_EXACT_IN` command.
+const UR_V3_SWAP_EXACT_IN: u8 = 0x00;
+
+/// Universal Router `V2_SWAP_EXACT_IN` command.
+const UR_V2_SWAP_EXACT_IN: u8 = 0x08;
+
+/// Bits of a Universal Router command byte naming the command; the top bit
+/// only lets it revert without failing the rest.
+const UR_COMMAND_MASK: u8 = 0x3f;
 
 // ---
 
+/// Shared state every detector needs, created once by the mempool listener.
+///
+/// Detectors read chain state, market prices, and strategy thresholds from here
+/// instead of setting up their own clients or relying on global state. The
+/// transport `P` is WebSocket for live runs and HTTP for polling-only observation.
+pub struct AnalysisContext<P = Ws> {
+    // ---
+    /// Chain client for on-chain reads (reserves, positions, simulations)
+    pub provider: Arc<Provider<P>>,
+
+    /// Market price source
+    pub oracle: Arc<dyn PriceOracle>,
+
+    /// Strategy thresholds and toggles, adjustable at runtime via the control API
+    pub config: SharedConfig,
+
+    /// Known token metadata
+    pub token_registry: Arc<TokenRegistry>,
+
+    /// Shared base-fee tracker used to price gas costs
+    pub base_fees: Arc<BaseFeeTracker>,
+
+    /// Known pools searched for multi-hop arbitrage cycles
+    pub pools: Arc<Vec<PoolInfo>>,
+
+    /// Recent victim `eth_call` results, so re-announced txs aren't re-simulated
+    pub victim_simulations: VictimSimulationCache,
+
+    /// On-chain reserves of known pools, reused within a block
+    pub pool_reserves: PoolReserveCache,
+
+    /// Decoded calldata of recently seen transactions, reused within a block
+    pub tx_types: TxTypeCache,
+
+    /// Session metrics receiving rejection reason counts
+    pub metrics: Arc<Mutex<MEVMetrics>>,
+
+    /// Inclusion odds of the relays bundles are submitted to
+    pub inclusion_odds: InclusionOdds,
+}
+
+/// Chance a bundle lands on each relay the run submits to, so opportunities
+/// can be ranked by expected value rather than raw profit.
+///
+/// Without any relays (offline runs) every strategy lands with certainty,
+/// which leaves the ranking on net profit.
+#[derive(Debug, Clone, Default)]
+pub struct InclusionOdds {
+    // ---
+    /// Inclusion probability and refused strategies of each relay
+    relays: Vec<(f64, Vec<String>)>,
+}
+
+/// Short-lived cache of whether pending transactions revert when simulated.
+///
+/// Pending transactions are often re-announced; the state they execute against
+/// rarely changes within a block, so a result is reused until `ttl` elapses.
+#[derive(Debug)]
+pub struct VictimSimulationCache {
+    // ---
+    /// How long a result stays valid
+    ttl: Duration,
+
+    /// Whether each simulated transaction reverted, and when it was simulated
+    results: Mutex<HashMap<TxHash, (bool, Instant)>>,
+}
+
+/// Transaction types already decoded in the current block.
+///
+/// Re-announced transactions carry the same calldata, so the ABI decode is
+/// done once per hash and block. Entries from earlier blocks are dropped, and
+/// once `capacity` is reached new transactions are decoded without caching.
+#[derive(Debug)]
+pub struct TxTypeCache {
+    // ---
+    /// Most entries kept at once
+    capacity: usize,
+
+    /// Head tracker whose block number scopes every entry
+    heads: Arc<BaseFeeTracker>,
+
+    /// Decoded type of each transaction, with the block it was decoded in
+    entries: Mutex<HashMap<TxHash, (TxType, U64)>>,
+}
+
 /// Represents different types of MEV opportunities that can be detected.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MEVOpportunity {
@@ -20,21 +156,46 @@ pub enum MEVOpportunity {
         token_b: Address,
         buy_dex: DEX,
         sell_dex: DEX,
+        /// `token_a` sold on `buy_dex`
+        amount_in: U256,
+        /// `token_b` bought on `buy_dex` and sold back on `sell_dex`
+        token_b_amount: U256,
+        profit_eth: U256,
+        gas_cost_eth: U256,
+        net_profit_eth: U256,
+        /// Latest block when the opportunity was detected
+        detected_block: U64,
+    },
+
+    /// Cyclic arbitrage through up to three pools, starting and ending in WETH
+    MultiHopArbitrage {
+        /// Swaps in execution order as (dex, token_in, token_out)
+        path: Vec<(DEX, Address, Address)>,
+        /// Input amount of each swap, in its `token_in` units
+        hop_amounts_in: Vec<U256>,
         profit_eth: U256,
         gas_cost_eth: U256,
         net_profit_eth: U256,
+        /// Latest block when the opportunity was detected
+        detected_block: U64,
     },
 
     /// Sandwich attack opportunity on a large swap
     Sandwich {
-        _victim_tx_hash: TxHash,
+        victim_tx_hash: TxHash,
         token_in: Address,
         token_out: Address,
         victim_amount_in: U256,
         frontrun_amount: U256,
+        /// `token_out` the backrun sells: everything the frontrun bought
         backrun_amount: U256,
+        /// Least `token_in` the backrun accepts. At break-even, it reverts
+        /// rather than sell into a pool the victim never moved
+        backrun_min_out: U256,
         estimated_profit_eth: U256,
         gas_cost_eth: U256,
+        /// Latest block when the opportunity was detected
+        detected_block: U64,
     },
 
     /// Liquidation opportunity in lending protocols
@@ -45,13 +206,47 @@ pub enum MEVOpportunity {
         debt_token: Address,
         collateral_amount: U256,
         debt_amount: U256,
+        /// Debt repaid by this liquidation (capped by the protocol's close factor)
+        repay_amount: U256,
         liquidation_bonus_eth: U256,
         health_factor: f64,
+        /// Latest block when the opportunity was detected
+        detected_block: U64,
     },
 }
 
+/// Why a detector passed on a transaction it could have traded around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    /// The trade is smaller than the strategy's configured minimum
+    BelowMinSize,
+
+    /// Gas costs (or the victim's gas price) eat the whole edge
+    GasTooHigh,
+
+    /// No profitable price difference to capture
+    NoPriceEdge,
+
+    /// The pool involved is below the configured liquidity floor
+    InsufficientLiquidity,
+
+    /// A token involved is not allowed by the configured token list
+    Blacklisted,
+
+    /// The victim transaction reverts on its own
+    WouldRevert,
+
+    /// The victim's slippage guard leaves no room to move the price first
+    SlippageProtected,
+
+    /// Net profit (or a liquidation's bonus) is below the strategy's own floor
+    BelowProfitFloor,
+}
+
 /// Supported DEX protocols for arbitrage detection
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum DEX {
     UniswapV2,
     UniswapV3,
@@ -61,7 +256,7 @@ pub enum DEX {
 }
 
 /// Supported DeFi lending protocols for liquidation detection
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Protocol {
     Aave,
     Compound,
@@ -69,8 +264,23 @@ pub enum Protocol {
     Euler,
 }
 
+/// DEX aggregators whose swap entrypoints are decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregator {
+    // ---
+    /// 1inch AggregationRouter `swap` (v4 and v5)
+    OneInch,
+
+    /// 0x Exchange Proxy `transformERC20`, `fillRfqOrder`, and `fillLimitOrder`
+    ZeroEx,
+
+    /// CoW Protocol settlement contract `settle`
+    CoW,
+}
+
 /// Transaction type classification based on function signatures
-#[derive(Debug, Clone)]
+#[allow(dead_code)] // lending variants are decoded once liquidation monitoring is live
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TxType {
     // ---
     ERC20Transfer {
@@ -82,12 +292,30 @@ pub enum TxType {
         token_in: Address,
         token_out: Address,
         amount_in: U256,
+        /// Least `token_out` the swap accepts before reverting (its slippage guard)
+        amount_out_min: U256,
     },
 
     UniswapV3Swap {
         token_in: Address,
         token_out: Address,
         amount_in: U256,
+        /// Least `token_out` the swap accepts before reverting (its slippage guard)
+        amount_out_min: U256,
+        /// Pool fee tier in hundredths of a basis point (500 = 0.05%, 3000 = 0.3%)
+        fee: u32,
+    },
+
+    /// Swap routed by a DEX aggregator; the pools it trades in aren't in the calldata
+    AggregatorSwap {
+        aggregator: Aggregator,
+        /// Token sold; routers take native ETH as `0xEeee…EEeE`
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        /// Whether the fill trades through AMM pools (0x order fills settle
+        /// against a market maker and move none)
+        via_pools: bool,
     },
 
     CompoundSupply {
@@ -99,58 +327,868 @@ pub enum TxType {
         token: Address,
         amount: U256,
     },
+
+    /// EIP-4844 blob-carrying transaction (rollup data; never analyzed)
+    Blob {
+        blob_count: usize,
+    },
     Unknown,
 }
 
+/// EIP-2718 envelope of a transaction, which determines its fee fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxEnvelope {
+    // ---
+    /// Untyped transaction priced by `gas_price`
+    Legacy,
+
+    /// EIP-2930 (type 1): `gas_price` plus an access list
+    AccessList,
+
+    /// EIP-1559 (type 2): `max_fee_per_gas` and `max_priority_fee_per_gas`
+    DynamicFee,
+
+    /// EIP-4844 (type 3): EIP-1559 fees plus `max_fee_per_blob_gas` for its blobs
+    Blob,
+}
+
 // ---
 
+impl TxType {
+    // ---
+
+    /// Snake-case name of the call type, as written to the audit file (e.g. `uniswap_v2_swap`).
+    pub fn label(&self) -> &'static str {
+        // ---
+        match self {
+            TxType::ERC20Transfer { .. } => "erc20_transfer",
+            TxType::UniswapV2Swap { .. } => "uniswap_v2_swap",
+            TxType::UniswapV3Swap { .. } => "uniswap_v3_swap",
+            TxType::AggregatorSwap { .. } => "aggregator_swap",
+            TxType::CompoundSupply { .. } => "compound_supply",
+            TxType::AaveBorrow { .. } => "aave_borrow",
+            TxType::Blob { .. } => "blob",
+            TxType::Unknown => "unknown",
+        }
+    }
+}
+
+impl TxEnvelope {
+    // ---
+
+    /// EIP-2718 type byte of EIP-2930 access-list transactions.
+    const ACCESS_LIST_TYPE: u64 = 1;
+
+    /// EIP-2718 type byte of EIP-1559 dynamic-fee transactions.
+    const DYNAMIC_FEE_TYPE: u64 = 2;
+
+    /// EIP-2718 type byte of EIP-4844 blob transactions.
+    const BLOB_TYPE: u64 = 3;
+
+    /// Classifies `tx` by its `type` field; untyped and unrecognized types are
+    /// treated as legacy.
+    pub fn of(tx: &Transaction) -> Self {
+        // ---
+        match tx.transaction_type.map(|t| t.as_u64()) {
+            Some(Self::ACCESS_LIST_TYPE) => Self::AccessList,
+            Some(Self::DYNAMIC_FEE_TYPE) => Self::DynamicFee,
+            Some(Self::BLOB_TYPE) => Self::Blob,
+            _ => Self::Legacy,
+        }
+    }
+}
+
+/// Returns the gas price `tx` offers: its legacy `gas_price`, else its
+/// EIP-1559 `max_fee_per_gas`.
+///
+/// `None` when neither is set to a non-zero amount, as in some providers'
+/// partial responses. Such a transaction can't be priced, and treating its
+/// gas as free would make it look endlessly profitable to trade around.
+pub fn offered_gas_price(tx: &Transaction) -> Option<U256> {
+    // ---
+    [tx.gas_price, tx.max_fee_per_gas]
+        .into_iter()
+        .flatten()
+        .find(|price| !price.is_zero())
+}
+
+/// Returns a blob transaction's `maxFeePerBlobGas` in wei.
+///
+/// ethers' `Transaction` predates EIP-4844, so blob fields land in `other`.
+pub fn max_fee_per_blob_gas(tx: &Transaction) -> Option<U256> {
+    // ---
+    tx.other
+        .get_deserialized::<U256>("maxFeePerBlobGas")
+        .and_then(Result::ok)
+}
+
+/// Returns the number of blobs a transaction carries (from `blobVersionedHashes`).
+pub fn blob_count(tx: &Transaction) -> usize {
+    // ---
+    tx.other
+        .get("blobVersionedHashes")
+        .and_then(serde_json::Value::as_array)
+        .map_or(0, Vec::len)
+}
+
+impl<P> AnalysisContext<P> {
+    /// Returns the current strategy config.
+    ///
+    /// The guard blocks control API updates while held: copy out what's needed
+    /// and drop it before calling back into the context or awaiting.
+    pub fn config(&self) -> RwLockReadGuard<'_, MEVConfig> {
+        // ---
+        self.config.read().expect("config poisoned")
+    }
+
+    /// Returns the base fee used to cost gas for opportunities.
+    ///
+    /// Our bundles land in the next block; cost gas at its predicted base fee, but
+    /// never below the recent average so a momentary dip doesn't inflate profits.
+    pub fn pricing_base_fee(&self) -> U256 {
+        // ---
+        self.base_fees.predict_next().max(self.base_fees.ema())
+    }
+
+    /// Classifies `tx`, reusing the type decoded for its hash earlier in the block.
+    pub fn classify(&self, tx: &Transaction) -> TxType {
+        // ---
+        let cached = self.tx_types.get(tx.hash);
+        self.metrics
+            .lock()
+            .expect("metrics poisoned")
+            .record_decode_lookup(cached.is_some());
+        cached.unwrap_or_else(|| {
+            let tx_type = decode_transaction_type(tx);
+            self.tx_types.insert(tx.hash, tx_type.clone());
+            tx_type
+        })
+    }
+
+    /// Returns `pool` with its cached on-chain reserves, if fetched this block.
+    pub fn with_current_reserves(&self, pool: &PoolInfo) -> PoolInfo {
+        // ---
+        let mut pool = pool.clone();
+        if let Some((reserve0, reserve1)) = self.pool_reserves.get(pool.address) {
+            // Pairs order reserves by token address
+            (pool.reserve_a, pool.reserve_b) = if pool.token_a < pool.token_b {
+                (reserve0, reserve1)
+            } else {
+                (reserve1, reserve0)
+            };
+        }
+        pool
+    }
+
+    /// Returns `opportunity`'s trade size valued in wei, or `None` if its
+    /// token can't be priced.
+    pub fn trade_size_wei(&self, opportunity: &MEVOpportunity) -> Option<U256> {
+        // ---
+        oracle::token_value_wei(
+            self.oracle.as_ref(),
+            &self.token_registry,
+            opportunity.trade_token(),
+            opportunity.trade_size(),
+        )
+    }
+
+    /// Returns a pool's liquidity in USD.
+    ///
+    /// Uses the pool registry's figure when it has one; otherwise values the
+    /// pool at twice an oracle-priced side, since a constant-product pool holds
+    /// equal value in each token. Returns `None` if neither token can be priced.
+    pub fn pool_liquidity_usd(&self, pool: &PoolInfo) -> Option<f64> {
+        // ---
+
+        if pool.liquidity_usd > 0.0 {
+            return Some(pool.liquidity_usd);
+        }
+        [
+            (pool.token_a, pool.reserve_a),
+            (pool.token_b, pool.reserve_b),
+        ]
+        .into_iter()
+        .find_map(|(token, reserve)| self.token_value_usd(token, reserve))
+        .map(|side_usd| side_usd * 2.0)
+    }
+
+    /// Values `amount` base units of `token` in USD via the oracle.
+    fn token_value_usd(&self, token: Address, amount: U256) -> Option<f64> {
+        // ---
+
+        let price_usd = if self.token_registry.address_of("WETH") == Some(token) {
+            self.oracle.eth_price_usd()
+        } else {
+            self.oracle.token_price_usd(token)
+        }?;
+        let decimals = self.token_registry.get(&token)?.decimals;
+        let units: f64 = ethers::utils::format_units(amount, u32::from(decimals))
+            .ok()?
+            .parse()
+            .ok()?;
+        Some(units * price_usd)
+    }
+
+    /// Records why `strategy` passed on the current transaction and returns
+    /// `None`, so detectors can `return ctx.reject(..)`.
+    ///
+    /// Emits a structured `rejected` debug event (shown with `--verbose`) and
+    /// counts the reason in the session metrics.
+    pub fn reject<T>(&self, strategy: &'static str, reason: RejectReason) -> Option<T> {
+        // ---
+        debug!(event = "rejected", strategy, %reason, "🚫 {} rejected: {}", strategy, reason);
+        self.metrics
+            .lock()
+            .expect("metrics poisoned")
+            .record_rejection(strategy, reason);
+        None
+    }
+
+    /// Converts a wei amount into the configured profit denomination.
+    pub fn denominate_profit(&self, wei: U256) -> Option<f64> {
+        // ---
+        oracle::denominate(self.oracle.as_ref(), wei, self.config().profit_denomination)
+    }
+
+    /// Returns the unit label of the configured profit denomination
+    /// (`ETH`, `USD`, or the token's symbol).
+    pub fn profit_unit(&self) -> String {
+        // ---
+        match self.config().profit_denomination {
+            ProfitDenomination::Eth => "ETH".to_string(),
+            ProfitDenomination::Usd => "USD".to_string(),
+            ProfitDenomination::Token(token) => self.token_registry.symbol(&token),
+        }
+    }
+
+    /// Formats a wei amount for logs in the configured profit denomination,
+    /// falling back to ETH when the oracle cannot convert it.
+    pub fn format_profit(&self, wei: U256) -> String {
+        // ---
+
+        if self.config().profit_denomination == ProfitDenomination::Eth {
+            return format!(
+                "{} ETH{}",
+                ethers::utils::format_ether(wei),
+                format_usd_estimate(self, wei)
+            );
+        }
+
+        match self.denominate_profit(wei) {
+            Some(amount) => format!("{:.2} {}", amount, self.profit_unit()),
+            None => format!("{} ETH", ethers::utils::format_ether(wei)),
+        }
+    }
+}
+
+impl InclusionOdds {
+    // ---
+
+    /// Collects the odds of the `selected` relays (matched by name, ignoring
+    /// case) from the relay configuration; relays it doesn't list are left out.
+    pub fn from_config(config: &RelayConfiguration, selected: &[&str]) -> Self {
+        // ---
+        let relays = selected
+            .iter()
+            .filter_map(|name| {
+                config
+                    .relays
+                    .iter()
+                    .find(|(configured, _)| configured.eq_ignore_ascii_case(name))
+            })
+            .map(|(_, relay)| {
+                (
+                    relay.inclusion_probability.clamp(0.0, 1.0),
+                    relay.refused_strategies.clone(),
+                )
+            })
+            .collect();
+        Self { relays }
+    }
+
+    /// Returns the best inclusion probability among relays accepting
+    /// `strategy`: 0.0 if every relay refuses it, 1.0 if no relay is known.
+    pub fn best_for(&self, strategy: &str) -> f64 {
+        // ---
+        if self.relays.is_empty() {
+            return 1.0;
+        }
+        self.relays
+            .iter()
+            .filter(|(_, refused)| !refused.iter().any(|refused| refused == strategy))
+            .map(|(probability, _)| *probability)
+            .fold(0.0, f64::max)
+    }
+}
+
+impl VictimSimulationCache {
+    /// Creates an empty cache whose results expire after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        // ---
+        Self {
+            ttl,
+            results: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached revert result for `tx_hash`, if still fresh.
+    fn get(&self, tx_hash: TxHash) -> Option<bool> {
+        // ---
+        let results = self
+            .results
+            .lock()
+            .expect("victim simulation cache poisoned");
+        results
+            .get(&tx_hash)
+            .filter(|(_, simulated_at)| simulated_at.elapsed() < self.ttl)
+            .map(|(reverts, _)| *reverts)
+    }
+
+    /// Records a simulation result, dropping expired entries.
+    fn insert(&self, tx_hash: TxHash, reverts: bool) {
+        // ---
+        let mut results = self
+            .results
+            .lock()
+            .expect("victim simulation cache poisoned");
+        results.retain(|_, (_, simulated_at)| simulated_at.elapsed() < self.ttl);
+        results.insert(tx_hash, (reverts, Instant::now()));
+    }
+}
+
+impl TxTypeCache {
+    /// Creates an empty cache of at most `capacity` entries, scoped to the
+    /// blocks `heads` observes.
+    pub fn new(capacity: usize, heads: Arc<BaseFeeTracker>) -> Self {
+        // ---
+        Self {
+            capacity,
+            heads,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the type decoded for `tx_hash` in the current block, if any.
+    pub fn get(&self, tx_hash: TxHash) -> Option<TxType> {
+        // ---
+        let block = self.heads.block_number();
+        let entries = self.entries.lock().expect("tx type cache poisoned");
+        entries
+            .get(&tx_hash)
+            .filter(|(_, decoded_in)| *decoded_in == block)
+            .map(|(tx_type, _)| tx_type.clone())
+    }
+
+    /// Records the type decoded for `tx_hash`, dropping entries from earlier blocks.
+    fn insert(&self, tx_hash: TxHash, tx_type: TxType) {
+        // ---
+        let block = self.heads.block_number();
+        let mut entries = self.entries.lock().expect("tx type cache poisoned");
+        entries.retain(|_, (_, decoded_in)| *decoded_in == block);
+        if entries.len() < self.capacity {
+            entries.insert(tx_hash, (tx_type, block));
+        }
+    }
+}
+
+impl DEX {
+    /// Parses a config/pool DEX name such as `uniswap_v2` or `SushiSwap`
+    /// (case-insensitive, `_`/`-` ignored), or a common alias (`univ2`, `sushi`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match normalize_name(name).as_str() {
+            "uniswapv2" | "univ2" => Some(DEX::UniswapV2),
+            "uniswapv3" | "univ3" => Some(DEX::UniswapV3),
+            "sushiswap" | "sushi" => Some(DEX::SushiSwap),
+            "pancakeswap" | "pancake" => Some(DEX::PancakeSwap),
+            "balancer" | "balancerv2" => Some(DEX::Balancer),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for DEX {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // ---
+        DEX::from_name(s).ok_or_else(|| {
+            format!(
+                "unknown DEX '{s}' (expected uniswap_v2, uniswap_v3, sushiswap, pancakeswap, or balancer)"
+            )
+        })
+    }
+}
+
+impl TryFrom<&str> for DEX {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl Protocol {
+    /// Parses a config protocol name such as `aave` or `MakerDAO`
+    /// (case-insensitive, `_`/`-` ignored), or a common alias (`aave_v3`, `maker`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match normalize_name(name).as_str() {
+            "aave" | "aavev2" | "aavev3" => Some(Protocol::Aave),
+            "compound" | "compoundv2" | "comp" => Some(Protocol::Compound),
+            "makerdao" | "maker" => Some(Protocol::MakerDAO),
+            "euler" => Some(Protocol::Euler),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // ---
+        Protocol::from_name(s).ok_or_else(|| {
+            format!("unknown lending protocol '{s}' (expected aave, compound, makerdao, or euler)")
+        })
+    }
+}
+
+impl TryFrom<&str> for Protocol {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+/// Lowercases `name` and drops `_`, `-`, and spaces, so `Uniswap-V2` matches `uniswap_v2`.
+fn normalize_name(name: &str) -> String {
+    // ---
+    name.chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' '))
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+impl MEVOpportunity {
+    /// Returns the strategy name (`arbitrage`, `sandwich`, or `liquidation`).
+    pub fn strategy(&self) -> &'static str {
+        match self {
+            MEVOpportunity::Arbitrage { .. } => "arbitrage",
+            MEVOpportunity::MultiHopArbitrage { .. } => "multi_hop_arbitrage",
+            MEVOpportunity::Sandwich { .. } => "sandwich",
+            MEVOpportunity::Liquidation { .. } => "liquidation",
+        }
+    }
+
+    /// Returns the latest block number when the opportunity was detected.
+    pub fn detected_block(&self) -> U64 {
+        // ---
+        match self {
+            MEVOpportunity::Arbitrage { detected_block, .. }
+            | MEVOpportunity::MultiHopArbitrage { detected_block, .. }
+            | MEVOpportunity::Sandwich { detected_block, .. }
+            | MEVOpportunity::Liquidation { detected_block, .. } => *detected_block,
+        }
+    }
+
+    /// Returns the capital the opportunity puts to work, in
+    /// [`trade_token`](Self::trade_token) units: the arbitrage input, the
+    /// first hop of a cycle, the frontrun, or the debt repaid.
+    pub fn trade_size(&self) -> U256 {
+        // ---
+        match self {
+            MEVOpportunity::Arbitrage { amount_in, .. } => *amount_in,
+            MEVOpportunity::MultiHopArbitrage { hop_amounts_in, .. } => {
+                hop_amounts_in.first().copied().unwrap_or_default()
+            }
+            MEVOpportunity::Sandwich {
+                frontrun_amount, ..
+            } => *frontrun_amount,
+            MEVOpportunity::Liquidation { repay_amount, .. } => *repay_amount,
+        }
+    }
+
+    /// Returns the token [`trade_size`](Self::trade_size) is counted in.
+    pub fn trade_token(&self) -> Address {
+        // ---
+        match self {
+            MEVOpportunity::Arbitrage { token_a, .. } => *token_a,
+            MEVOpportunity::MultiHopArbitrage { path, .. } => path
+                .first()
+                .map(|(_, token_in, _)| *token_in)
+                .unwrap_or_default(),
+            MEVOpportunity::Sandwich { token_in, .. } => *token_in,
+            MEVOpportunity::Liquidation { debt_token, .. } => *debt_token,
+        }
+    }
+
+    /// Returns the token pair the opportunity trades, in trade direction.
+    ///
+    /// Multi-hop cycles report their first swap; liquidations report
+    /// `(collateral_token, debt_token)`.
+    pub fn token_pair(&self) -> (Address, Address) {
+        match self {
+            MEVOpportunity::Arbitrage {
+                token_a, token_b, ..
+            } => (*token_a, *token_b),
+            MEVOpportunity::MultiHopArbitrage { path, .. } => path
+                .first()
+                .map(|(_, token_in, token_out)| (*token_in, *token_out))
+                .unwrap_or_default(),
+            MEVOpportunity::Sandwich {
+                token_in,
+                token_out,
+                ..
+            } => (*token_in, *token_out),
+            MEVOpportunity::Liquidation {
+                collateral_token,
+                debt_token,
+                ..
+            } => (*collateral_token, *debt_token),
+        }
+    }
+}
+
+/// Concise one-line summary for logs: strategy, tokens, and profit.
+///
+/// Tokens render as abbreviated addresses (`0xc02a…6cc2`). Liquidations show
+/// the gross bonus, since their gas cost depends on the base fee.
+impl fmt::Display for MEVOpportunity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        let eth = ethers::utils::format_ether;
+        match self {
+            MEVOpportunity::Arbitrage {
+                token_a,
+                token_b,
+                buy_dex,
+                sell_dex,
+                net_profit_eth,
+                ..
+            } => write!(
+                f,
+                "arbitrage {}/{} (buy {}, sell {}): net {} ETH",
+                token_a,
+                token_b,
+                buy_dex,
+                sell_dex,
+                eth(*net_profit_eth)
+            ),
+            MEVOpportunity::MultiHopArbitrage {
+                path,
+                net_profit_eth,
+                ..
+            } => {
+                write!(f, "multi_hop_arbitrage ")?;
+                for (_, token_in, _) in path {
+                    write!(f, "{} → ", token_in)?;
+                }
+                match path.first() {
+                    Some((_, start, _)) => write!(f, "{}", start)?,
+                    None => write!(f, "(empty)")?,
+                }
+                write!(
+                    f,
+                    " ({} hops): net {} ETH",
+                    path.len(),
+                    eth(*net_profit_eth)
+                )
+            }
+            MEVOpportunity::Sandwich {
+                victim_tx_hash,
+                token_in,
+                token_out,
+                estimated_profit_eth,
+                gas_cost_eth,
+                ..
+            } => write!(
+                f,
+                "sandwich {}/{} around {}: net {} ETH",
+                token_in,
+                token_out,
+                victim_tx_hash,
+                eth(estimated_profit_eth.saturating_sub(*gas_cost_eth))
+            ),
+            MEVOpportunity::Liquidation {
+                protocol,
+                collateral_token,
+                debt_token,
+                liquidation_bonus_eth,
+                health_factor,
+                ..
+            } => write!(
+                f,
+                "liquidation on {} {}/{} (health {:.2}): bonus {} ETH",
+                protocol,
+                collateral_token,
+                debt_token,
+                health_factor,
+                eth(*liquidation_bonus_eth)
+            ),
+        }
+    }
+}
+
+/// Snake-case reason label, as used in logs and metrics (e.g. `below_min_size`).
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str(match self {
+            RejectReason::BelowMinSize => "below_min_size",
+            RejectReason::GasTooHigh => "gas_too_high",
+            RejectReason::NoPriceEdge => "no_price_edge",
+            RejectReason::InsufficientLiquidity => "insufficient_liquidity",
+            RejectReason::Blacklisted => "blacklisted",
+            RejectReason::WouldRevert => "would_revert",
+            RejectReason::SlippageProtected => "slippage_protected",
+            RejectReason::BelowProfitFloor => "below_profit_floor",
+        })
+    }
+}
+
+/// Canonical config name, as accepted by [`DEX::from_name`] (e.g. `uniswap_v2`).
+impl fmt::Display for DEX {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str(match self {
+            DEX::UniswapV2 => "uniswap_v2",
+            DEX::UniswapV3 => "uniswap_v3",
+            DEX::SushiSwap => "sushiswap",
+            DEX::PancakeSwap => "pancakeswap",
+            DEX::Balancer => "balancer",
+        })
+    }
+}
+
+/// Canonical lowercase protocol name (e.g. `aave`).
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str(match self {
+            Protocol::Aave => "aave",
+            Protocol::Compound => "compound",
+            Protocol::MakerDAO => "makerdao",
+            Protocol::Euler => "euler",
+        })
+    }
+}
+
 /// Main entry point for MEV opportunity evaluation.
 ///
 /// Analyzes a pending transaction to determine if it presents any profitable
 /// MEV opportunities. Returns the most profitable opportunity if found.
 ///
 /// # Arguments
+/// * `ctx` - Shared analysis context (provider, oracle, config, registries)
 /// * `tx` - The pending transaction to analyze
 ///
 /// # Returns
 /// * `Some(MEVOpportunity)` if a profitable opportunity is detected
 /// * `None` if no opportunities are found
-pub async fn evaluate_opportunity(tx: &Transaction) -> Option<MEVOpportunity> {
+pub async fn evaluate_opportunity<P: JsonRpcClient>(
+    ctx: &AnalysisContext<P>,
+    tx: &Transaction,
+) -> Option<MEVOpportunity> {
     // ---
 
     debug!("🔍 Analyzing tx {} for MEV opportunities", tx.hash);
 
+    let base_fee = ctx.pricing_base_fee();
+
+    // Read the settings the control API can patch once, so a PATCH landing
+    // mid-tx can't gate and threshold it differently; each detector still
+    // reads its own strategy settings (changed only by a reload) as it runs
+    let (max_gas_price_gwei, profit_margin_bps, min_profit, fee_bps, floors, rank_by_ev, enabled) = {
+        let config = ctx.config();
+        (
+            config.max_gas_price_gwei,
+            config.profit_margin_bps,
+            config.min_profit,
+            config.fee_bps,
+            ProfitFloors::from_config(&config),
+            config.rank_by_expected_value,
+            (
+                config.arbitrage.enabled,
+                config.sandwich.enabled,
+                config.liquidation.enabled,
+            ),
+        )
+    };
+    let (arbitrage_enabled, sandwich_enabled, liquidation_enabled) = enabled;
+
+    // Above the gas ceiling no bundle is worth landing
+    if base_fee > U256::from(max_gas_price_gwei) * U256::exp10(9) {
+        debug!(
+            "Skipping tx {}: base fee {} gwei above the {} gwei ceiling",
+            tx.hash,
+            ethers::utils::format_units(base_fee, "gwei").unwrap_or_default(),
+            max_gas_price_gwei
+        );
+        return None;
+    }
+
+    // Without fee data every gas cost would come out as zero
+    if offered_gas_price(tx).is_none() {
+        debug!(
+            "Skipping tx {}: no gas price or EIP-1559 fee fields",
+            tx.hash
+        );
+        return None;
+    }
+
     // Decode transaction type and extract relevant data
-    let tx_type = decode_transaction_type(tx);
+    let tx_type = ctx.classify(tx);
     debug!("Transaction type: {:?}", tx_type);
 
+    // Blob txs post rollup data; they move no pool state we can trade around
+    if let TxType::Blob { blob_count } = tx_type {
+        debug!("Skipping blob tx {} ({} blobs)", tx.hash, blob_count);
+        return None;
+    }
+
+    // Swaps are quoted against the pools' current reserves
+    if observed_swap(ctx, &tx_type).is_some() {
+        refresh_pool_reserves(ctx).await;
+    }
+
     // Check for different opportunity types
     let mut opportunities = Vec::new();
 
     // 1. Check for arbitrage opportunities
-    if let Some(arb) = detect_arbitrage(tx, &tx_type).await {
-        opportunities.push(arb);
+    if arbitrage_enabled {
+        if let Some(arb) = detect_arbitrage(ctx, &tx_type, base_fee) {
+            opportunities.push(arb);
+        }
+
+        // 2. Check for multi-hop (triangular) arbitrage the swap opens up
+        if let Some(cycle) = detect_multi_hop_arbitrage(ctx, &tx_type, base_fee) {
+            opportunities.push(cycle);
+        }
+    }
+
+    // 3. Check for sandwich attack opportunities
+    if sandwich_enabled {
+        if let Some(sandwich) = detect_sandwich_opportunity(ctx, tx, &tx_type).await {
+            opportunities.push(sandwich);
+        }
     }
 
-    // 2. Check for sandwich attack opportunities
-    if let Some(sandwich) = detect_sandwich_opportunity(tx, &tx_type) {
-        opportunities.push(sandwich);
+    // 4. Check for liquidation opportunities (independent of current tx)
+    if liquidation_enabled {
+        if let Some(liq) = detect_liquidation_opportunity(ctx, base_fee).await {
+            opportunities.push(liq);
+        }
     }
 
-    // 3. Check for liquidation opportunities (independent of current tx)
-    if let Some(liq) = detect_liquidation_opportunity().await {
-        opportunities.push(liq);
+    // Strategies with a floor of their own answer to it, not the global minimum;
+    // either way, only the profit kept after the fee share counts
+    opportunities.retain(|opportunity| {
+        let net_profit = after_fee_share(calculate_net_profit(opportunity, base_fee), fee_bps);
+        let trade_size = ctx.trade_size_wei(opportunity);
+        let Some(clears) = floors.clear(opportunity, trade_size, net_profit) else {
+            return true;
+        };
+        if !clears {
+            ctx.reject::<()>(opportunity.strategy(), RejectReason::BelowProfitFloor);
+        }
+        clears
+    });
+
+    // Return the most profitable opportunity, if it clears the profit threshold
+    let odds = rank_by_ev.then_some(&ctx.inclusion_odds);
+    let best = select_best_opportunity(opportunities, base_fee, profit_margin_bps, odds)?;
+    let net_profit = after_fee_share(calculate_net_profit(&best, base_fee), fee_bps);
+    if floors
+        .clear(&best, ctx.trade_size_wei(&best), net_profit)
+        .is_some()
+    {
+        return Some(best);
     }
 
-    // Return the most profitable opportunity
-    select_best_opportunity(opportunities)
+    match ctx.denominate_profit(net_profit) {
+        Some(profit) if profit >= min_profit => Some(best),
+        Some(profit) => {
+            debug!(
+                "Best {} opportunity below minimum profit: {:.4} < {} {}",
+                best.strategy(),
+                profit,
+                min_profit,
+                ctx.profit_unit()
+            );
+            None
+        }
+        None => {
+            debug!(
+                "Best {} opportunity has no {} price to compare",
+                best.strategy(),
+                ctx.profit_unit()
+            );
+            None
+        }
+    }
+}
+
+/// Loads the on-chain reserves of every known V2-style pool into the cache.
+///
+/// Pools without an address are left at their registry reserves, as are pools
+/// whose fetch fails.
+async fn refresh_pool_reserves<P: JsonRpcClient>(ctx: &AnalysisContext<P>) {
+    // ---
+
+    let lookups = ctx
+        .pools
+        .iter()
+        .filter(|pool| !pool.address.is_zero() && has_reserves(pool))
+        .map(|pool| async move {
+            (
+                pool.address,
+                ctx.pool_reserves
+                    .get_or_fetch(&ctx.provider, pool.address)
+                    .await,
+            )
+        });
+
+    for (address, lookup) in futures::future::join_all(lookups).await {
+        match lookup {
+            Ok(lookup) => ctx
+                .metrics
+                .lock()
+                .expect("metrics poisoned")
+                .record_reserve_lookup(lookup.cached),
+            Err(e) => debug!("Quoting {:?} at registry reserves: {}", address, e),
+        }
+    }
+}
+
+/// Returns whether `pool` is a V2-style pair exposing `getReserves()`.
+fn has_reserves(pool: &PoolInfo) -> bool {
+    // ---
+    matches!(
+        DEX::from_name(&pool.dex),
+        Some(DEX::UniswapV2 | DEX::SushiSwap | DEX::PancakeSwap)
+    )
 }
 
 /// Decodes transaction input data to classify the transaction type.
-fn decode_transaction_type(tx: &Transaction) -> TxType {
+///
+/// Blob transactions are classified by envelope alone; access-list
+/// transactions are decoded like any other call.
+pub fn decode_transaction_type(tx: &Transaction) -> TxType {
     // ---
 
-    let input = &tx.input;
+    if TxEnvelope::of(tx) == TxEnvelope::Blob {
+        return TxType::Blob {
+            blob_count: blob_count(tx),
+        };
+    }
+
+    decode_call(tx, &tx.input)
+}
+
+/// Classifies a call to `tx.to` with `input` as its calldata: the
+/// transaction's own, or one a router multicall makes to itself.
+fn decode_call(tx: &Transaction, input: &[u8]) -> TxType {
+    // ---
 
     if input.len() < 4 {
         return TxType::Unknown;
@@ -174,155 +1212,885 @@ fn decode_transaction_type(tx: &Transaction) -> TxType {
             }
         }
 
-        // Uniswap V2 swapExactTokensForTokens = 0x38ed1739
-        [0x38, 0xed, 0x17, 0x39] => {
-            if input.len() >= 68 {
-                let amount_in = U256::from_big_endian(&input[4..36]);
-                // Simplified - would need full ABI decoding for token addresses
-                TxType::UniswapV2Swap {
-                    token_in: Address::zero(),  // Would decode from path
-                    token_out: Address::zero(), // Would decode from path
+        // Uniswap V2 swapExactTokensForTokens(uint256,uint256,address[],address,uint256) = 0x38ed1739
+        // Uniswap V2 swapExactTokensForETH(uint256,uint256,address[],address,uint256) = 0x18cbafe5
+        [0x38, 0xed, 0x17, 0x39] | [0x18, 0xcb, 0xaf, 0xe5] => {
+            match decode_v2_path_swap(&input[4..]) {
+                Some(([amount_in, amount_out_min], token_in, token_out)) => TxType::UniswapV2Swap {
+                    token_in,
+                    token_out,
                     amount_in,
-                }
-            } else {
-                TxType::Unknown
+                    amount_out_min,
+                },
+                None => TxType::Unknown,
             }
         }
 
-        // Uniswap V3 exactInputSingle = 0x414bf389
+        // Uniswap V2 swapExactETHForTokens(uint256,address[],address,uint256) = 0x7ff36ab5
+        [0x7f, 0xf3, 0x6a, 0xb5] => match decode_v2_path_swap(&input[4..]) {
+            // The ETH sold is the call's value; the path starts at WETH
+            Some(([amount_out_min], token_in, token_out)) => TxType::UniswapV2Swap {
+                token_in,
+                token_out,
+                amount_in: tx.value,
+                amount_out_min,
+            },
+            None => TxType::Unknown,
+        },
+
+        // Uniswap V2 swapTokensForExactTokens(uint256,uint256,address[],address,uint256) = 0x8803dbee
+        [0x88, 0x03, 0xdb, 0xee] => match decode_v2_path_swap(&input[4..]) {
+            // Exact output: the victim spends at most amountInMax for exactly
+            // amountOut, so size the trade by the cap (within the victim's
+            // slippage tolerance of the real input) and guard it by the output
+            Some(([amount_out, amount_in_max], token_in, token_out)) => TxType::UniswapV2Swap {
+                token_in,
+                token_out,
+                amount_in: amount_in_max,
+                amount_out_min: amount_out,
+            },
+            None => TxType::Unknown,
+        },
+
+        // Uniswap V3 exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160)) = 0x414bf389
         [0x41, 0x4b, 0xf3, 0x89] => {
-            TxType::UniswapV3Swap {
-                token_in: Address::zero(),  // Would decode from params
-                token_out: Address::zero(), // Would decode from params
-                amount_in: U256::zero(),    // Would decode from params
+            let params = ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Uint(24),
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(160),
+            ]);
+            let fields = abi::decode(&[params], &input[4..])
+                .ok()
+                .and_then(|mut tokens| tokens.pop()?.into_tuple());
+            match fields.as_deref() {
+                Some([token_in, token_out, fee, _, _, amount_in, amount_out_min, _]) => {
+                    TxType::UniswapV3Swap {
+                        token_in: token_in.clone().into_address().unwrap_or_default(),
+                        token_out: token_out.clone().into_address().unwrap_or_default(),
+                        amount_in: amount_in.clone().into_uint().unwrap_or_default(),
+                        amount_out_min: amount_out_min.clone().into_uint().unwrap_or_default(),
+                        // uint24 on the wire, so the low word holds it whole
+                        fee: fee.clone().into_uint().unwrap_or_default().low_u32(),
+                    }
+                }
+                _ => TxType::Unknown,
+            }
+        }
+
+        // Uniswap SwapRouter02 exactInputSingle((address,address,uint24,address,uint256,uint256,uint160)) = 0x04e45aaf
+        [0x04, 0xe4, 0x5a, 0xaf] => {
+            let params = ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Uint(24),
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(160),
+            ]);
+            let fields = abi::decode(&[params], &input[4..])
+                .ok()
+                .and_then(|mut tokens| tokens.pop()?.into_tuple());
+            match fields.as_deref() {
+                Some([token_in, token_out, fee, _, amount_in, amount_out_min, _]) => {
+                    TxType::UniswapV3Swap {
+                        token_in: token_in.clone().into_address().unwrap_or_default(),
+                        token_out: token_out.clone().into_address().unwrap_or_default(),
+                        amount_in: amount_in.clone().into_uint().unwrap_or_default(),
+                        amount_out_min: amount_out_min.clone().into_uint().unwrap_or_default(),
+                        fee: fee.clone().into_uint().unwrap_or_default().low_u32(),
+                    }
+                }
+                _ => TxType::Unknown,
             }
         }
 
-        _ => TxType::Unknown,
+        // Uniswap router multicall(bytes[]) = 0xac9650d8
+        // Uniswap SwapRouter02 multicall(uint256,bytes[]) = 0x5ae401dc
+        // Uniswap SwapRouter02 multicall(bytes32,bytes[]) = 0x1f0464d1
+        [0xac, 0x96, 0x50, 0xd8] | [0x5a, 0xe4, 0x01, 0xdc] | [0x1f, 0x04, 0x64, 0xd1] => {
+            decode_multicall_swap(tx, selector, &input[4..]).unwrap_or(TxType::Unknown)
+        }
+
+        // Universal Router execute(bytes,bytes[],uint256) = 0x3593564c
+        // Universal Router execute(bytes,bytes[]) = 0x24856bc3
+        [0x35, 0x93, 0x56, 0x4c] | [0x24, 0x85, 0x6b, 0xc3] => {
+            decode_universal_router_swap(tx, &input[4..]).unwrap_or(TxType::Unknown)
+        }
+
+        _ => decode_aggregator_swap(selector, &input[4..]).unwrap_or(TxType::Unknown),
     }
 }
 
-/// Detects arbitrage opportunities based on transaction analysis.
-async fn detect_arbitrage(_tx: &Transaction, tx_type: &TxType) -> Option<MEVOpportunity> {
+/// Decodes the swap a router `multicall` makes, skipping the `selfPermit`
+/// calls that approve the router ahead of it.
+///
+/// Returns the first call that decodes as a swap, or `None` if none does.
+fn decode_multicall_swap(tx: &Transaction, selector: &[u8], args: &[u8]) -> Option<TxType> {
     // ---
 
+    let calls = ParamType::Array(Box::new(ParamType::Bytes));
+    let params = match selector {
+        [0x5a, 0xe4, 0x01, 0xdc] => vec![ParamType::Uint(256), calls],
+        [0x1f, 0x04, 0x64, 0xd1] => vec![ParamType::FixedBytes(32), calls],
+        _ => vec![calls],
+    };
+    let calls = abi::decode(&params, args).ok()?.pop()?.into_array()?;
+
+    calls
+        .into_iter()
+        .filter_map(Token::into_bytes)
+        .filter(|call| {
+            call.get(..4)
+                .is_some_and(|selector| !SELF_PERMIT_SELECTORS.iter().any(|p| p == selector))
+        })
+        .map(|call| decode_call(tx, &call))
+        .find(|tx_type| {
+            matches!(
+                tx_type,
+                TxType::UniswapV2Swap { .. }
+                    | TxType::UniswapV3Swap { .. }
+                    | TxType::AggregatorSwap { .. }
+            )
+        })
+}
+
+/// Decodes the first exact-input swap among a Universal Router `execute`'s
+/// commands.
+///
+/// Commands ahead of it (a `PERMIT2_PERMIT` signing the router an allowance,
+/// a `PERMIT2_TRANSFER_FROM`, or wrapping ETH) are skipped. A swap of the
+/// router's whole balance sells the ETH the transaction sent. V3 paths are
+/// packed `token, fee, token, ...`; the first hop is the pool traded first.
+/// Returns `None` if the calldata doesn't decode or carries no such swap.
+fn decode_universal_router_swap(tx: &Transaction, args: &[u8]) -> Option<TxType> {
+    // ---
+
+    // The contract-balance sentinel: the amount is whatever the router holds
+    let contract_balance = U256::one() << 255;
+    let amount = |token: &Token| {
+        let amount = token.clone().into_uint()?;
+        Some(if amount == contract_balance {
+            tx.value
+        } else {
+            amount
+        })
+    };
+
+    let params = [
+        ParamType::Bytes,
+        ParamType::Array(Box::new(ParamType::Bytes)),
+    ];
+    let mut tokens = abi::decode(&params, args).ok()?.into_iter();
+    let commands = tokens.next()?.into_bytes()?;
+    let inputs = tokens.next()?.into_array()?;
+
+    commands.iter().zip(inputs).find_map(|(command, input)| {
+        let input = input.into_bytes()?;
+        match command & UR_COMMAND_MASK {
+            UR_V2_SWAP_EXACT_IN => {
+                let params = [
+                    ParamType::Address,
+                    ParamType::Uint(256),
+                    ParamType::Uint(256),
+                    ParamType::Array(Box::new(ParamType::Address)),
+                    ParamType::Bool,
+                ];
+                let swap = abi::decode(&params, &input).ok()?;
+                match swap[3].clone().into_array()?.as_slice() {
+                    [token_in, token_out, ..] => Some(TxType::UniswapV2Swap {
+                        token_in: token_in.clone().into_address()?,
+                        token_out: token_out.clone().into_address()?,
+                        amount_in: amount(&swap[1])?,
+                        amount_out_min: swap[2].clone().into_uint()?,
+                    }),
+                    _ => None,
+                }
+            }
+            UR_V3_SWAP_EXACT_IN => {
+                let params = [
+                    ParamType::Address,
+                    ParamType::Uint(256),
+                    ParamType::Uint(256),
+                    ParamType::Bytes,
+                    ParamType::Bool,
+                ];
+                let swap = abi::decode(&params, &input).ok()?;
+                let path = swap[3].clone().into_bytes()?;
+                let first_hop = path.get(..43)?;
+                Some(TxType::UniswapV3Swap {
+                    token_in: Address::from_slice(&first_hop[..20]),
+                    token_out: Address::from_slice(&first_hop[23..]),
+                    amount_in: amount(&swap[1])?,
+                    amount_out_min: swap[2].clone().into_uint()?,
+                    fee: u32::from_be_bytes([0, first_hop[20], first_hop[21], first_hop[22]]),
+                })
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Decodes the arguments of a Uniswap V2 router swap: `N` leading amounts,
+/// then `(address[] path, address to, uint256 deadline)`.
+///
+/// Returns the amounts and the first hop of the path, which is the pool the
+/// swap trades in first, or `None` if the calldata doesn't decode or the path
+/// is shorter than two tokens.
+fn decode_v2_path_swap<const N: usize>(args: &[u8]) -> Option<([U256; N], Address, Address)> {
+    // ---
+
+    let mut params = vec![ParamType::Uint(256); N];
+    params.extend([
+        ParamType::Array(Box::new(ParamType::Address)),
+        ParamType::Address,
+        ParamType::Uint(256),
+    ]);
+    let tokens = abi::decode(&params, args).ok()?;
+
+    let mut amounts = [U256::zero(); N];
+    for (amount, token) in amounts.iter_mut().zip(&tokens) {
+        *amount = token.clone().into_uint()?;
+    }
+    match tokens[N].clone().into_array()?.as_slice() {
+        [token_in, token_out, ..] => Some((
+            amounts,
+            token_in.clone().into_address()?,
+            token_out.clone().into_address()?,
+        )),
+        _ => None,
+    }
+}
+
+/// Decodes a DEX-aggregator swap entrypoint into [`TxType::AggregatorSwap`].
+///
+/// A CoW settlement batches many trades; it is classified by its first one.
+/// Returns `None` for other selectors, or calldata that doesn't decode.
+fn decode_aggregator_swap(selector: &[u8], args: &[u8]) -> Option<TxType> {
+    // ---
+
+    let uint = ParamType::Uint(256);
+    let zero_ex_signature = ParamType::Tuple(vec![
+        ParamType::Uint(8),
+        ParamType::Uint(8),
+        ParamType::FixedBytes(32),
+        ParamType::FixedBytes(32),
+    ]);
+
+    match selector {
+        // 1inch v5 swap(address,(address,address,address,address,uint256,uint256,uint256),bytes,bytes) = 0x12aa3caf
+        [0x12, 0xaa, 0x3c, 0xaf] => {
+            let desc = ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Address,
+                uint.clone(),
+                uint.clone(),
+                uint,
+            ]);
+            let params = [ParamType::Address, desc, ParamType::Bytes, ParamType::Bytes];
+            let tokens = abi::decode(&params, args).ok()?;
+            let desc = tokens[1].clone().into_tuple()?;
+            aggregator_swap(Aggregator::OneInch, &desc[0], &desc[1], &desc[4], true)
+        }
+
+        // 1inch v4 swap(address,(address,address,address,address,uint256,uint256,uint256,bytes),bytes) = 0x7c025200
+        [0x7c, 0x02, 0x52, 0x00] => {
+            let desc = ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Address,
+                uint.clone(),
+                uint.clone(),
+                uint,
+                ParamType::Bytes,
+            ]);
+            let params = [ParamType::Address, desc, ParamType::Bytes];
+            let tokens = abi::decode(&params, args).ok()?;
+            let desc = tokens[1].clone().into_tuple()?;
+            aggregator_swap(Aggregator::OneInch, &desc[0], &desc[1], &desc[4], true)
+        }
+
+        // 0x transformERC20(address,address,uint256,uint256,(uint32,bytes)[]) = 0x415565b0
+        [0x41, 0x55, 0x65, 0xb0] => {
+            let transformation = ParamType::Tuple(vec![ParamType::Uint(32), ParamType::Bytes]);
+            let params = [
+                ParamType::Address,
+                ParamType::Address,
+                uint.clone(),
+                uint,
+                ParamType::Array(Box::new(transformation)),
+            ];
+            let tokens = abi::decode(&params, args).ok()?;
+            aggregator_swap(Aggregator::ZeroEx, &tokens[0], &tokens[1], &tokens[2], true)
+        }
+
+        // 0x fillRfqOrder((address,address,uint128,uint128,address,address,address,bytes32,uint64,uint256),(uint8,uint8,bytes32,bytes32),uint128) = 0xaa77476c
+        [0xaa, 0x77, 0x47, 0x6c] => {
+            let order = ParamType::Tuple(vec![
+                ParamType::Address, // makerToken
+                ParamType::Address, // takerToken
+                ParamType::Uint(128),
+                ParamType::Uint(128),
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::FixedBytes(32),
+                ParamType::Uint(64),
+                uint,
+            ]);
+            let params = [order, zero_ex_signature, ParamType::Uint(128)];
+            let tokens = abi::decode(&params, args).ok()?;
+            let order = tokens[0].clone().into_tuple()?;
+
+            // The taker sells takerToken for the maker's makerToken
+            aggregator_swap(Aggregator::ZeroEx, &order[1], &order[0], &tokens[2], false)
+        }
+
+        // 0x fillLimitOrder((address,address,uint128,uint128,uint128,address,address,address,address,bytes32,uint64,uint256),(uint8,uint8,bytes32,bytes32),uint128) = 0xf6274f66
+        [0xf6, 0x27, 0x4f, 0x66] => {
+            let order = ParamType::Tuple(vec![
+                ParamType::Address, // makerToken
+                ParamType::Address, // takerToken
+                ParamType::Uint(128),
+                ParamType::Uint(128),
+                ParamType::Uint(128),
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::FixedBytes(32),
+                ParamType::Uint(64),
+                uint,
+            ]);
+            let params = [order, zero_ex_signature, ParamType::Uint(128)];
+            let tokens = abi::decode(&params, args).ok()?;
+            let order = tokens[0].clone().into_tuple()?;
+            aggregator_swap(Aggregator::ZeroEx, &order[1], &order[0], &tokens[2], false)
+        }
+
+        // CoW settle(address[],uint256[],(uint256,uint256,address,uint256,uint256,uint32,bytes32,uint256,uint256,uint256,bytes)[],(address,uint256,bytes)[][3]) = 0x13d79a0b
+        [0x13, 0xd7, 0x9a, 0x0b] => {
+            let trade = ParamType::Tuple(vec![
+                uint.clone(), // sellTokenIndex
+                uint.clone(), // buyTokenIndex
+                ParamType::Address,
+                uint.clone(), // sellAmount
+                uint.clone(),
+                ParamType::Uint(32),
+                ParamType::FixedBytes(32),
+                uint.clone(),
+                uint.clone(),
+                uint.clone(),
+                ParamType::Bytes,
+            ]);
+            let interaction =
+                ParamType::Tuple(vec![ParamType::Address, uint.clone(), ParamType::Bytes]);
+            let params = [
+                ParamType::Array(Box::new(ParamType::Address)),
+                ParamType::Array(Box::new(uint)),
+                ParamType::Array(Box::new(trade)),
+                ParamType::FixedArray(Box::new(ParamType::Array(Box::new(interaction))), 3),
+            ];
+            let tokens = abi::decode(&params, args).ok()?;
+            let addresses = tokens[0].clone().into_array()?;
+            let trade = tokens[2]
+                .clone()
+                .into_array()?
+                .into_iter()
+                .next()?
+                .into_tuple()?;
+            let token_at = |index: &Token| {
+                let index = index.clone().into_uint()?;
+                addresses.get(usize::try_from(index).ok()?)
+            };
+            aggregator_swap(
+                Aggregator::CoW,
+                token_at(&trade[0])?,
+                token_at(&trade[1])?,
+                &trade[3],
+                true,
+            )
+        }
+
+        _ => None,
+    }
+}
+
+/// Builds a [`TxType::AggregatorSwap`] from decoded address and amount tokens.
+fn aggregator_swap(
+    aggregator: Aggregator,
+    token_in: &Token,
+    token_out: &Token,
+    amount_in: &Token,
+    via_pools: bool,
+) -> Option<TxType> {
+    // ---
+    Some(TxType::AggregatorSwap {
+        aggregator,
+        token_in: token_in.clone().into_address()?,
+        token_out: token_out.clone().into_address()?,
+        amount_in: amount_in.clone().into_uint()?,
+        via_pools,
+    })
+}
+
+/// Detects cross-DEX arbitrage opened up by a pending swap.
+///
+/// The pending swap is applied to its pool first: the price it moves there,
+/// relative to the same pair on other DEXs, is the backrun opportunity. The
+/// arbitrage is a WETH-based round trip through the victim's pool and one
+/// counterpart pool, sized by [`routing::find_best_cycle`] against the
+/// post-swap reserves.
+fn detect_arbitrage<P>(
+    ctx: &AnalysisContext<P>,
+    tx_type: &TxType,
+    base_fee: U256,
+) -> Option<MEVOpportunity> {
+    // ---
+
+    const STRATEGY: &str = "arbitrage";
+    let swap = observed_swap(ctx, tx_type)?;
+    let ObservedSwap {
+        token_in,
+        token_out,
+        amount_in,
+        ..
+    } = swap;
+
+    // Only analyze large swaps to avoid high gas cost ratio
+    if amount_in < units::eth_f64_to_wei(ctx.config().arbitrage.min_trade_size_eth) {
+        return ctx.reject(STRATEGY, RejectReason::BelowMinSize);
+    }
+
+    let whitelist = ctx.config().arbitrage.token_whitelist.clone();
+    let allowed = |token| whitelist.is_empty() || whitelist.contains(token);
+    if !(allowed(&token_in) && allowed(&token_out)) {
+        return ctx.reject(STRATEGY, RejectReason::Blacklisted);
+    }
+
+    debug!(
+        "🔄 Checking arbitrage for large swap: {} -> {}",
+        ctx.token_registry.symbol(&token_in),
+        ctx.token_registry.symbol(&token_out)
+    );
+
+    let weth = ctx.token_registry.address_of("WETH")?;
+    let (pools, victim) = match post_swap_pools(ctx, &swap) {
+        Ok(quotes) => quotes,
+        Err(reason) => return ctx.reject(STRATEGY, reason),
+    };
+    let Some((cycle, buy_dex, sell_dex)) = best_cross_dex_cycle(
+        &pools,
+        victim,
+        weth,
+        units::eth_f64_to_wei(MAX_CYCLE_INPUT_ETH),
+    ) else {
+        return ctx.reject(STRATEGY, RejectReason::NoPriceEdge);
+    };
+
+    let profit = cycle.profit();
+    let estimated_gas_cost = estimate_arbitrage_gas_cost(base_fee);
+    if profit <= estimated_gas_cost {
+        return ctx.reject(STRATEGY, RejectReason::GasTooHigh);
+    }
+    let net_profit = profit - estimated_gas_cost;
+
+    let token_b = cycle.hops[0].token_out;
+    info!(
+        "💎 Arbitrage detected: {} profit after gas ({} WETH → {} on {:?} → WETH on {:?})",
+        ctx.format_profit(net_profit),
+        ethers::utils::format_ether(cycle.hop_amounts_in[0]),
+        ctx.token_registry.symbol(&token_b),
+        buy_dex,
+        sell_dex
+    );
+
+    Some(MEVOpportunity::Arbitrage {
+        token_a: weth,
+        token_b,
+        buy_dex,
+        sell_dex,
+        amount_in: cycle.hop_amounts_in[0],
+        token_b_amount: cycle.hop_amounts_in[1],
+        profit_eth: profit,
+        gas_cost_eth: estimated_gas_cost,
+        net_profit_eth: net_profit,
+        detected_block: ctx.base_fees.block_number(),
+    })
+}
+
+/// A decoded pending swap, as the arbitrage detectors see it.
+#[derive(Debug, Clone, Copy)]
+struct ObservedSwap {
+    // ---
+    /// DEX the swap trades on; `None` for aggregator swaps, whose route
+    /// isn't in the calldata
+    dex: Option<DEX>,
+
+    /// Fee of the pool traded in (basis points), when the call names a tier.
+    /// `None` for V2-style routers, which have one pool per pair
+    fee_bps: Option<u16>,
+
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+}
+
+/// Returns the swap a decoded transaction makes, if it is one.
+///
+/// Aggregator swaps count only when they trade through pools; native ETH is
+/// read as WETH, which is what the pools hold.
+fn observed_swap<P>(ctx: &AnalysisContext<P>, tx_type: &TxType) -> Option<ObservedSwap> {
+    // ---
     match tx_type {
         TxType::UniswapV2Swap {
             token_in,
             token_out,
             amount_in,
-        }
-        | TxType::UniswapV3Swap {
+            ..
+        } => Some(ObservedSwap {
+            dex: Some(DEX::UniswapV2),
+            fee_bps: None,
+            token_in: *token_in,
+            token_out: *token_out,
+            amount_in: *amount_in,
+        }),
+        TxType::UniswapV3Swap {
             token_in,
             token_out,
             amount_in,
+            fee,
+            ..
+        } => Some(ObservedSwap {
+            dex: Some(DEX::UniswapV3),
+            // V3 tiers are in hundredths of a basis point
+            fee_bps: u16::try_from(fee / 100).ok(),
+            token_in: *token_in,
+            token_out: *token_out,
+            amount_in: *amount_in,
+        }),
+        TxType::AggregatorSwap {
+            token_in,
+            token_out,
+            amount_in,
+            via_pools: true,
+            ..
         } => {
-            // Only analyze large swaps to avoid high gas cost ratio
-            if *amount_in < U256::from(10).pow(18.into()) {
-                // < 1 ETH equivalent
-                return None;
-            }
-
-            debug!(
-                "🔄 Checking arbitrage for large swap: {} -> {}",
-                token_in, token_out
-            );
-
-            // Simulate prices across different DEXs
-            let prices = simulate_dex_prices(*token_in, *token_out, *amount_in).await;
+            let wrapped = |token: Address| match ctx.token_registry.address_of("WETH") {
+                Some(weth) if token == Address::repeat_byte(0xee) => weth,
+                _ => token,
+            };
+            Some(ObservedSwap {
+                dex: None,
+                fee_bps: None,
+                token_in: wrapped(*token_in),
+                token_out: wrapped(*token_out),
+                amount_in: *amount_in,
+            })
+        }
+        _ => None,
+    }
+}
 
-            // Find best buy and sell prices
-            let (best_buy_dex, best_buy_price) = prices.iter().min_by(|a, b| a.1.cmp(&b.1))?;
-            let (best_sell_dex, best_sell_price) = prices.iter().max_by(|a, b| a.1.cmp(&b.1))?;
+/// Returns the enabled-DEX pools as they will be right after the pending
+/// `swap`, with the index of the pool it trades in.
+///
+/// A V3 swap trades in the pool of its fee tier; the pair's other tiers are
+/// separate pools with their own prices. An aggregator swap is taken to fill in
+/// the pair's deepest pool, where routers send most of the volume. Pools below `min_pool_liquidity_usd`
+/// (or whose liquidity can't be valued) are left out: their quotes evaporate
+/// under the price impact of a real fill.
+///
+/// # Errors
+/// [`RejectReason::NoPriceEdge`] if the swap's pool is not a known enabled-DEX
+/// pool, or [`RejectReason::InsufficientLiquidity`] if it is below the floor.
+fn post_swap_pools<P>(
+    ctx: &AnalysisContext<P>,
+    swap: &ObservedSwap,
+) -> Result<(Vec<PoolInfo>, usize), RejectReason> {
+    // ---
 
-            let price_diff = *best_sell_price - *best_buy_price;
-            let estimated_gas_cost = estimate_arbitrage_gas_cost();
+    let (enabled, min_liquidity_usd) = {
+        let config = ctx.config();
+        (
+            config.arbitrage.enabled_dexs.clone(),
+            config.arbitrage.min_pool_liquidity_usd,
+        )
+    };
+
+    let is_victim = |pool: &PoolInfo| {
+        swap.dex
+            .is_none_or(|dex| DEX::from_name(&pool.dex) == Some(dex))
+            && pool_trades(pool, swap.token_in, swap.token_out)
+            && swap.fee_bps.is_none_or(|fee_bps| pool.fee_bps == fee_bps)
+    };
+
+    let enabled_pools: Vec<&PoolInfo> = ctx
+        .pools
+        .iter()
+        .filter(|pool| DEX::from_name(&pool.dex).is_some_and(|dex| enabled.contains(&dex)))
+        .collect();
+    if !enabled_pools.iter().any(|pool| is_victim(pool)) {
+        return Err(RejectReason::NoPriceEdge);
+    }
 
-            if price_diff > estimated_gas_cost {
-                let net_profit = price_diff - estimated_gas_cost;
+    let mut pools: Vec<PoolInfo> = enabled_pools
+        .into_iter()
+        .map(|pool| ctx.with_current_reserves(pool))
+        .filter(|pool| {
+            min_liquidity_usd <= 0.0
+                || ctx
+                    .pool_liquidity_usd(pool)
+                    .is_some_and(|usd| usd >= min_liquidity_usd)
+        })
+        .collect();
+    let liquidity_usd = |pool: &PoolInfo| ctx.pool_liquidity_usd(pool).unwrap_or_default();
+    let victim = pools
+        .iter()
+        .enumerate()
+        .filter(|(_, pool)| is_victim(pool))
+        .max_by(|(_, a), (_, b)| liquidity_usd(a).total_cmp(&liquidity_usd(b)))
+        .map(|(i, _)| i)
+        .ok_or(RejectReason::InsufficientLiquidity)?;
+    apply_swap(&mut pools[victim], swap.token_in, swap.amount_in);
+
+    Ok((pools, victim))
+}
 
-                info!(
-                    "💎 Arbitrage detected: {} profit after gas",
-                    ethers::utils::format_ether(net_profit)
-                );
+/// Returns whether `pool` trades the `token_a`/`token_b` pair, in either order.
+fn pool_trades(pool: &PoolInfo, token_a: Address, token_b: Address) -> bool {
+    // ---
+    (pool.token_a, pool.token_b) == (token_a, token_b)
+        || (pool.token_b, pool.token_a) == (token_a, token_b)
+}
 
-                return Some(MEVOpportunity::Arbitrage {
-                    token_a: *token_in,
-                    token_b: *token_out,
-                    buy_dex: *best_buy_dex,
-                    sell_dex: *best_sell_dex,
-                    profit_eth: price_diff,
-                    gas_cost_eth: estimated_gas_cost,
-                    net_profit_eth: net_profit,
-                });
-            }
-        }
-        _ => {}
+/// Finds the most profitable WETH round trip between `pools[victim]` and
+/// another DEX's pool for the same pair, using at most `max_amount_in` WETH.
+///
+/// Returns the cycle with the DEX of its first (buy) and second (sell) swap.
+fn best_cross_dex_cycle(
+    pools: &[PoolInfo],
+    victim: usize,
+    weth: Address,
+    max_amount_in: U256,
+) -> Option<(routing::Cycle, DEX, DEX)> {
+    // ---
+
+    let victim_pool = &pools[victim];
+    if victim_pool.token_a != weth && victim_pool.token_b != weth {
+        return None;
     }
 
-    None
+    pools
+        .iter()
+        .enumerate()
+        .filter(|(i, pool)| {
+            *i != victim
+                && pool.dex != victim_pool.dex
+                && pool_trades(pool, victim_pool.token_a, victim_pool.token_b)
+        })
+        .filter_map(|(_, counterpart)| {
+            let pair = [victim_pool.clone(), counterpart.clone()];
+            let cycle = routing::find_best_cycle(&pair, weth, max_amount_in, 2)?;
+            let buy_dex = DEX::from_name(&pair[cycle.hops[0].pool].dex)?;
+            let sell_dex = DEX::from_name(&pair[cycle.hops[1].pool].dex)?;
+            Some((cycle, buy_dex, sell_dex))
+        })
+        .max_by_key(|(cycle, _, _)| cycle.profit())
+}
+
+/// Detects triangular arbitrage cycles opened up by a pending swap.
+///
+/// The pending swap is applied to its pool first, since the price move it
+/// causes is what makes a backrun cycle profitable. Cycles start and end in
+/// WETH, span at most [`routing::MAX_HOPS`] enabled-DEX pools, and are sized by
+/// [`routing::find_best_cycle`].
+fn detect_multi_hop_arbitrage<P>(
+    ctx: &AnalysisContext<P>,
+    tx_type: &TxType,
+    base_fee: U256,
+) -> Option<MEVOpportunity> {
+    // ---
+
+    const STRATEGY: &str = "multi_hop_arbitrage";
+    let swap = observed_swap(ctx, tx_type)?;
+    let weth = ctx.token_registry.address_of("WETH")?;
+
+    // Search the pool state as it will be right after the pending swap
+    let (pools, _) = match post_swap_pools(ctx, &swap) {
+        Ok(quotes) => quotes,
+        Err(reason) => return ctx.reject(STRATEGY, reason),
+    };
+
+    let max_amount_in = units::eth_f64_to_wei(MAX_CYCLE_INPUT_ETH);
+    let Some(cycle) = routing::find_best_cycle(&pools, weth, max_amount_in, routing::MAX_HOPS)
+    else {
+        return ctx.reject(STRATEGY, RejectReason::NoPriceEdge);
+    };
+
+    let profit = cycle.profit();
+    let gas_cost = estimate_multi_hop_gas_cost(base_fee, cycle.hops.len());
+    if profit <= gas_cost {
+        return ctx.reject(STRATEGY, RejectReason::GasTooHigh);
+    }
+    let net_profit = profit - gas_cost;
+
+    let path: Vec<(DEX, Address, Address)> = cycle
+        .hops
+        .iter()
+        .filter_map(|hop| {
+            DEX::from_name(&pools[hop.pool].dex).map(|dex| (dex, hop.token_in, hop.token_out))
+        })
+        .collect();
+
+    info!(
+        "🔺 Multi-hop arbitrage detected: {} ({} hops), {} profit after gas",
+        path.iter()
+            .map(|(_, token_in, _)| ctx.token_registry.symbol(token_in))
+            .chain(std::iter::once(ctx.token_registry.symbol(&weth)))
+            .collect::<Vec<_>>()
+            .join(" → "),
+        path.len(),
+        ctx.format_profit(net_profit)
+    );
+
+    Some(MEVOpportunity::MultiHopArbitrage {
+        path,
+        hop_amounts_in: cycle.hop_amounts_in,
+        profit_eth: profit,
+        gas_cost_eth: gas_cost,
+        net_profit_eth: net_profit,
+        detected_block: ctx.base_fees.block_number(),
+    })
 }
 
 /// Detects sandwich attack opportunities on large swaps.
-fn detect_sandwich_opportunity(tx: &Transaction, tx_type: &TxType) -> Option<MEVOpportunity> {
+///
+/// A victim swap that would revert on its own (e.g. its slippage guard trips)
+/// makes the sandwich pure loss, so otherwise profitable candidates are
+/// simulated with `eth_call` first and dropped if the victim reverts.
+async fn detect_sandwich_opportunity<P: JsonRpcClient>(
+    ctx: &AnalysisContext<P>,
+    tx: &Transaction,
+    tx_type: &TxType,
+) -> Option<MEVOpportunity> {
     // ---
 
+    const STRATEGY: &str = "sandwich";
     match tx_type {
         TxType::UniswapV2Swap {
             token_in,
             token_out,
             amount_in,
+            amount_out_min,
         }
         | TxType::UniswapV3Swap {
             token_in,
             token_out,
             amount_in,
+            amount_out_min,
+            ..
         } => {
             // Only sandwich large swaps that will move price significantly
-            let min_sandwich_amount = U256::from(5).pow(18.into()); // 5 ETH equivalent
+            let min_sandwich_amount =
+                units::eth_f64_to_wei(ctx.config().sandwich.min_victim_size_eth);
 
             if *amount_in < min_sandwich_amount {
-                return None;
+                return ctx.reject(STRATEGY, RejectReason::BelowMinSize);
             }
 
             // Check gas price - sandwich only profitable with reasonable gas
-            let gas_price = tx.gas_price.unwrap_or_default();
-            let max_profitable_gas = U256::from(50).pow(9.into()); // 50 gwei
+            let gas_price = offered_gas_price(tx).unwrap_or_default();
+            let max_profitable_gas = U256::from(50) * U256::exp10(9); // 50 gwei
 
             if gas_price > max_profitable_gas {
                 debug!(
                     "❌ Gas price too high for sandwich: {} gwei",
                     ethers::utils::format_units(gas_price, "gwei").unwrap_or_default()
                 );
-                return None;
+                return ctx.reject(STRATEGY, RejectReason::GasTooHigh);
             }
 
-            // Calculate optimal frontrun amount (typically 10-20% of victim trade)
-            let frontrun_amount = *amount_in / 10; // 10% of victim amount
-            let backrun_amount = frontrun_amount * 105 / 100; // Sell 5% more due to price impact
+            let (reserve_in, reserve_out, fee_bps) = get_mock_pool_reserves(*token_in, *token_out);
+            debug!(
+                "Victim price impact: {} bps",
+                amm::price_impact_bps(*amount_in, reserve_in, fee_bps)
+            );
+
+            // Largest frontrun the victim's slippage guard tolerates, capped at the
+            // configured share of their trade
+            let max_frontrun_bps = (ctx.config().sandwich.max_frontrun_percent * 100.0) as u64;
+            let max_frontrun = *amount_in * max_frontrun_bps / amm::BPS_DENOMINATOR;
+            let frontrun_amount = amm::optimal_sandwich_frontrun(
+                *amount_in,
+                *amount_out_min,
+                reserve_in,
+                reserve_out,
+                fee_bps,
+                max_frontrun,
+            );
 
-            // Estimate profit (simplified calculation)
-            let estimated_profit = calculate_sandwich_profit(*amount_in, frontrun_amount);
+            // The backrun sells what the frontrun bought into the pool as the
+            // frontrun and victim leave it
+            let quote = amm::quote_sandwich(
+                frontrun_amount,
+                *amount_in,
+                reserve_in,
+                reserve_out,
+                fee_bps,
+            );
+            let estimated_profit = quote.backrun_out.saturating_sub(frontrun_amount);
             let gas_cost = estimate_sandwich_gas_cost(gas_price);
 
             if estimated_profit > gas_cost {
+                // Simulate last: it's the only step here that costs an RPC round-trip
+                if victim_reverts(ctx, tx).await {
+                    debug!("❌ Victim tx {} reverts; skipping sandwich", tx.hash);
+                    return ctx.reject(STRATEGY, RejectReason::WouldRevert);
+                }
+
                 info!(
-                    "🥪 Sandwich opportunity: {} ETH profit on {} ETH trade",
-                    ethers::utils::format_ether(estimated_profit),
+                    "🥪 Sandwich opportunity: {} profit on {} ETH trade",
+                    ctx.format_profit(estimated_profit),
                     ethers::utils::format_ether(*amount_in)
                 );
 
                 return Some(MEVOpportunity::Sandwich {
-                    _victim_tx_hash: tx.hash,
+                    victim_tx_hash: tx.hash,
                     token_in: *token_in,
                     token_out: *token_out,
                     victim_amount_in: *amount_in,
                     frontrun_amount,
-                    backrun_amount,
+                    backrun_amount: quote.frontrun_out,
+                    backrun_min_out: frontrun_amount,
                     estimated_profit_eth: estimated_profit,
                     gas_cost_eth: gas_cost,
+                    detected_block: ctx.base_fees.block_number(),
                 });
             }
+
+            // When the victim's min-out, not our cap, bounds the frontrun, the
+            // trade is protected: any larger move makes it revert
+            let reason = if frontrun_amount < max_frontrun {
+                debug!(
+                    "❌ Victim tx {} is slippage-protected: min out {} allows a frontrun of only {}",
+                    tx.hash, amount_out_min, frontrun_amount
+                );
+                RejectReason::SlippageProtected
+            } else if estimated_profit.is_zero() {
+                RejectReason::NoPriceEdge
+            } else {
+                RejectReason::GasTooHigh
+            };
+            return ctx.reject(STRATEGY, reason);
         }
         _ => {}
     }
@@ -330,8 +2098,40 @@ fn detect_sandwich_opportunity(tx: &Transaction, tx_type: &TxType) -> Option<MEV
     None
 }
 
+/// Returns whether `tx` reverts when executed against the latest state.
+///
+/// Results are cached per transaction hash (see [`VictimSimulationCache`]).
+/// Only a JSON-RPC revert counts; transport failures are logged and treated as
+/// non-reverting, so a flaky node doesn't suppress every sandwich.
+async fn victim_reverts<P: JsonRpcClient>(ctx: &AnalysisContext<P>, tx: &Transaction) -> bool {
+    // ---
+
+    if let Some(reverts) = ctx.victim_simulations.get(tx.hash) {
+        return reverts;
+    }
+
+    let call: TypedTransaction = tx.into();
+    let reverts = match ctx.provider.call(&call, None).await {
+        Ok(_) => false,
+        Err(e) if e.as_error_response().is_some_and(|e| e.is_revert()) => true,
+        Err(e) => {
+            warn!("Failed to simulate victim tx {}: {}", tx.hash, e);
+            return false;
+        }
+    };
+
+    ctx.victim_simulations.insert(tx.hash, reverts);
+    reverts
+}
+
 /// Detects liquidation opportunities in lending protocols.
-async fn detect_liquidation_opportunity() -> Option<MEVOpportunity> {
+///
+/// Health factors are read on chain `min_confirmations` blocks behind the
+/// head; positions whose read fails keep their mock health factor.
+async fn detect_liquidation_opportunity<P: JsonRpcClient>(
+    ctx: &AnalysisContext<P>,
+    base_fee: U256,
+) -> Option<MEVOpportunity> {
     // ---
 
     // In a real implementation, this would:
@@ -342,15 +2142,48 @@ async fn detect_liquidation_opportunity() -> Option<MEVOpportunity> {
     // Mock liquidation opportunity for demonstration
     let mock_positions = get_mock_liquidation_positions();
 
-    for position in mock_positions {
-        if position.health_factor < 1.0 {
-            let liquidation_bonus = position.collateral_amount / 20; // 5% bonus
-            let gas_cost = estimate_liquidation_gas_cost();
+    let enabled_protocols = ctx.config().liquidation.enabled_protocols.clone();
+    let block = positions::confirmed_block(
+        ctx.base_fees.block_number(),
+        ctx.config().risk.min_confirmations,
+    );
+    for mut position in mock_positions {
+        if !enabled_protocols.contains(&position.protocol) {
+            continue;
+        }
+        match positions::fetch_health_factor(
+            &*ctx.provider,
+            position.protocol,
+            position.owner,
+            block,
+        )
+        .await
+        {
+            Ok(Some(health_factor)) => position.health_factor = health_factor,
+            Ok(None) => {}
+            Err(e) => debug!("Using mock health factor for {:?}: {}", position.owner, e),
+        }
+        if position.health_factor < ctx.config().liquidation.health_factor_threshold {
+            // A single liquidation may only repay up to the protocol's close factor
+            let repay_amount = lending::liquidation_repay_amount(
+                position.protocol,
+                position.debt_amount,
+                ctx.config().liquidation.sizing,
+            );
+            // Mock debt is WETH, so the bonus is already ETH-denominated
+            let liquidation_bonus = lending::liquidation_bonus(position.protocol, repay_amount);
+            let gas_cost = estimate_liquidation_gas_cost(base_fee);
+
+            let min_bonus = units::eth_f64_to_wei(ctx.config().liquidation.min_bonus_eth);
+            if liquidation_bonus < min_bonus {
+                ctx.reject::<()>("liquidation", RejectReason::BelowProfitFloor);
+                continue;
+            }
 
             if liquidation_bonus > gas_cost {
                 info!(
-                    "⚡ Liquidation opportunity: {} ETH bonus",
-                    ethers::utils::format_ether(liquidation_bonus)
+                    "⚡ Liquidation opportunity: {} bonus",
+                    ctx.format_profit(liquidation_bonus)
                 );
 
                 return Some(MEVOpportunity::Liquidation {
@@ -360,8 +2193,10 @@ async fn detect_liquidation_opportunity() -> Option<MEVOpportunity> {
                     debt_token: position.debt_token,
                     collateral_amount: position.collateral_amount,
                     debt_amount: position.debt_amount,
+                    repay_amount,
                     liquidation_bonus_eth: liquidation_bonus,
                     health_factor: position.health_factor,
+                    detected_block: ctx.base_fees.block_number(),
                 });
             }
         }
@@ -370,31 +2205,190 @@ async fn detect_liquidation_opportunity() -> Option<MEVOpportunity> {
     None
 }
 
-/// Selects the most profitable opportunity from a list of candidates.
-fn select_best_opportunity(opportunities: Vec<MEVOpportunity>) -> Option<MEVOpportunity> {
+/// Per-strategy minimum net profits, read from the config once per
+/// transaction. Strategies without a floor answer to the global `min_profit`.
+#[derive(Debug, Clone, Copy)]
+struct ProfitFloors {
     // ---
+    /// `ArbitrageConfig` floors, covering multi-hop cycles too
+    arbitrage: StrategyFloor,
 
-    if opportunities.is_empty() {
-        return None;
+    /// `SandwichConfig` floors
+    sandwich: StrategyFloor,
+
+    /// Whether a strategy must clear both of its floors or either one
+    mode: ProfitFloorMode,
+}
+
+/// One strategy's absolute and relative profit floors.
+#[derive(Debug, Clone, Copy)]
+struct StrategyFloor {
+    // ---
+    /// `min_net_profit_eth`, in wei
+    absolute: Option<U256>,
+
+    /// `min_net_profit_bps`, of the trade size valued in ETH
+    relative_bps: Option<u16>,
+}
+
+impl ProfitFloors {
+    // ---
+
+    fn from_config(config: &MEVConfig) -> Self {
+        // ---
+        let wei = |eth: Option<f64>| eth.map(units::eth_f64_to_wei);
+        Self {
+            arbitrage: StrategyFloor {
+                absolute: wei(config.arbitrage.min_net_profit_eth),
+                relative_bps: config.arbitrage.min_net_profit_bps,
+            },
+            sandwich: StrategyFloor {
+                absolute: wei(config.sandwich.min_net_profit_eth),
+                relative_bps: config.sandwich.min_net_profit_bps,
+            },
+            mode: config.profit_floor_mode,
+        }
     }
 
-    // Sort by net profit and return the best one
-    let mut sorted_opps = opportunities;
-    sorted_opps.sort_by(|a, b| {
-        let profit_a = calculate_net_profit(a);
-        let profit_b = calculate_net_profit(b);
-        profit_b.cmp(&profit_a) // Descending order
-    });
+    /// Returns whether `opportunity`, trading `trade_size_wei` worth, clears
+    /// its strategy's floors at `net_profit`, or `None` if the strategy has
+    /// none of its own. A trade that can't be valued doesn't clear a relative
+    /// floor.
+    fn clear(
+        &self,
+        opportunity: &MEVOpportunity,
+        trade_size_wei: Option<U256>,
+        net_profit: U256,
+    ) -> Option<bool> {
+        // ---
+
+        let floor = match opportunity {
+            MEVOpportunity::Arbitrage { .. } | MEVOpportunity::MultiHopArbitrage { .. } => {
+                self.arbitrage
+            }
+            MEVOpportunity::Sandwich { .. } => self.sandwich,
+            MEVOpportunity::Liquidation { .. } => return None,
+        };
+
+        let absolute = floor.absolute.map(|floor| net_profit >= floor);
+        let relative = floor.relative_bps.map(|bps| {
+            trade_size_wei.is_some_and(|trade_size| {
+                net_profit.saturating_mul(U256::from(amm::BPS_DENOMINATOR))
+                    >= trade_size.saturating_mul(U256::from(bps))
+            })
+        });
+        match (absolute, relative, self.mode) {
+            (None, None, _) => None,
+            (Some(clears), None, _) | (None, Some(clears), _) => Some(clears),
+            (Some(absolute), Some(relative), ProfitFloorMode::All) => Some(absolute && relative),
+            (Some(absolute), Some(relative), ProfitFloorMode::Any) => Some(absolute || relative),
+        }
+    }
+}
+
+/// Selects the most profitable opportunity from a list of candidates.
+///
+/// Candidates whose net profit doesn't clear break-even by `margin_bps` of
+/// their gas cost are dropped first. With `odds`, the rest are ranked on
+/// expected value (net profit times the best inclusion probability among
+/// relays accepting the strategy), and any no relay accepts are dropped;
+/// without, on net profit alone.
+fn select_best_opportunity(
+    opportunities: Vec<MEVOpportunity>,
+    base_fee: U256,
+    margin_bps: u16,
+    odds: Option<&InclusionOdds>,
+) -> Option<MEVOpportunity> {
+    // ---
+
+    let rank = |opportunity: &MEVOpportunity| {
+        let net_profit = calculate_net_profit(opportunity, base_fee);
+        match odds {
+            Some(odds) => expected_value(net_profit, odds.best_for(opportunity.strategy())),
+            None => net_profit,
+        }
+    };
+
+    // Highest rank wins; ties go to the earliest detector
+    opportunities
+        .into_iter()
+        .filter(|opportunity| {
+            let net_profit = calculate_net_profit(opportunity, base_fee);
+            let cost = calculate_gas_cost(opportunity, base_fee);
+            let accepted = meets_profit_margin(net_profit, cost, margin_bps);
+            debug!(
+                "📐 {} margin over break-even: {} bps (required {}){}",
+                opportunity.strategy(),
+                profit_margin_bps(net_profit, cost)
+                    .map_or_else(|| "∞".to_string(), |bps| bps.to_string()),
+                margin_bps,
+                if accepted { "" } else { "; rejected" }
+            );
+            accepted
+        })
+        .map(|opportunity| (rank(&opportunity), opportunity))
+        .filter(|(rank, opportunity)| {
+            let landable = odds.is_none() || !rank.is_zero();
+            if !landable {
+                debug!(
+                    "📭 No selected relay accepts {} bundles; dropping",
+                    opportunity.strategy()
+                );
+            }
+            landable
+        })
+        .min_by_key(|(rank, _)| Reverse(*rank))
+        .map(|(_, opportunity)| opportunity)
+}
+
+/// Returns `net_profit` weighted by `probability` (0.0-1.0), to the nearest
+/// millionth of the probability.
+fn expected_value(net_profit: U256, probability: f64) -> U256 {
+    // ---
+    let ppm = (probability.clamp(0.0, 1.0) * 1e6).round() as u64;
+    net_profit.saturating_mul(U256::from(ppm)) / U256::from(1_000_000)
+}
+
+/// Returns `net_profit` in basis points of `cost`: the headroom above
+/// break-even. `None` when there is no cost to measure against.
+pub fn profit_margin_bps(net_profit: U256, cost: U256) -> Option<U256> {
+    // ---
+    (!cost.is_zero()).then(|| net_profit.saturating_mul(U256::from(amm::BPS_DENOMINATOR)) / cost)
+}
+
+/// Returns whether `net_profit` is positive and at least `margin_bps` of `cost`.
+pub fn meets_profit_margin(net_profit: U256, cost: U256, margin_bps: u16) -> bool {
+    // ---
+    !net_profit.is_zero()
+        && net_profit.saturating_mul(U256::from(amm::BPS_DENOMINATOR))
+            >= cost.saturating_mul(U256::from(margin_bps))
+}
+
+/// Returns what is left of `net_profit` once `fee_bps` of it is shared with
+/// the configured fee recipient.
+pub fn after_fee_share(net_profit: U256, fee_bps: u16) -> U256 {
+    // ---
+    net_profit - net_profit * U256::from(fee_bps) / U256::from(amm::BPS_DENOMINATOR)
+}
 
-    Some(sorted_opps.into_iter().next().unwrap())
+/// Returns an opportunity's estimated gas cost at `base_fee`.
+pub fn calculate_gas_cost(opportunity: &MEVOpportunity, base_fee: U256) -> U256 {
+    // ---
+    match opportunity {
+        MEVOpportunity::Arbitrage { gas_cost_eth, .. }
+        | MEVOpportunity::MultiHopArbitrage { gas_cost_eth, .. }
+        | MEVOpportunity::Sandwich { gas_cost_eth, .. } => *gas_cost_eth,
+        MEVOpportunity::Liquidation { .. } => estimate_liquidation_gas_cost(base_fee),
+    }
 }
 
-/// Calculates net profit for an opportunity after gas costs.
-fn calculate_net_profit(opportunity: &MEVOpportunity) -> U256 {
+/// Calculates net profit for an opportunity after gas costs at `base_fee`.
+pub fn calculate_net_profit(opportunity: &MEVOpportunity, base_fee: U256) -> U256 {
     // ---
 
     match opportunity {
         MEVOpportunity::Arbitrage { net_profit_eth, .. } => *net_profit_eth,
+        MEVOpportunity::MultiHopArbitrage { net_profit_eth, .. } => *net_profit_eth,
         MEVOpportunity::Sandwich {
             estimated_profit_eth,
             gas_cost_eth,
@@ -410,7 +2404,7 @@ fn calculate_net_profit(opportunity: &MEVOpportunity) -> U256 {
             liquidation_bonus_eth,
             ..
         } => {
-            let gas_cost = estimate_liquidation_gas_cost();
+            let gas_cost = estimate_liquidation_gas_cost(base_fee);
             if *liquidation_bonus_eth > gas_cost {
                 *liquidation_bonus_eth - gas_cost
             } else {
@@ -424,30 +2418,96 @@ fn calculate_net_profit(opportunity: &MEVOpportunity) -> U256 {
 // Helper functions and mock data for simulation
 // ---
 
-/// Simulates DEX prices for arbitrage detection (mock implementation).
-async fn simulate_dex_prices(
-    _token_in: Address,
-    _token_out: Address,
-    _amount: U256,
-) -> Vec<(DEX, U256)> {
+/// Returns the pools searched for multi-hop arbitrage.
+///
+/// Mock mainnet-like Uniswap V2 and SushiSwap pools between WETH, USDC, and DAI,
+/// priced consistently (1 ETH = 2,500 USD) so cycles only become profitable once
+/// a pending swap moves one of them. Each pool carries its mainnet pair
+/// address, so its live reserves replace the mock ones once fetched. Pools
+/// involving tokens missing from `registry` are skipped.
+pub fn default_pools(registry: &TokenRegistry) -> Vec<PoolInfo> {
     // ---
 
-    // In reality, this would query multiple DEX contracts
-    // Mock different prices across DEXs
-    vec![
+    let eth = U256::exp10(18);
+    let usdc = U256::exp10(6);
+    let dai = U256::exp10(18);
+
+    let pairs = [
         (
-            DEX::UniswapV2,
-            U256::from(1000) * U256::from(10).pow(18.into()),
-        ), // 1000 ETH
+            "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc",
+            "uniswap_v2",
+            "WETH",
+            "USDC",
+            eth * 4_000,
+            usdc * 10_000_000u64,
+        ),
         (
-            DEX::SushiSwap,
-            U256::from(1002) * U256::from(10).pow(18.into()),
-        ), // 1002 ETH (2 ETH arbitrage)
+            "0x397FF1542f962076d0BFE58eA045FfA2d347ACa0",
+            "sushiswap",
+            "WETH",
+            "USDC",
+            eth * 2_000,
+            usdc * 5_000_000u64,
+        ),
         (
-            DEX::UniswapV3,
-            U256::from(999) * U256::from(10).pow(18.into()),
-        ), // 999 ETH
-    ]
+            "0xA478c2975Ab1Ea89e8196811F51A7B7Ade33eB11",
+            "uniswap_v2",
+            "WETH",
+            "DAI",
+            eth * 2_000,
+            dai * 5_000_000u64,
+        ),
+        (
+            "0xC3D03e4F041Fd4cD388c549Ee2A29a9E5075882f",
+            "sushiswap",
+            "WETH",
+            "DAI",
+            eth * 1_000,
+            dai * 2_500_000u64,
+        ),
+        (
+            "0xAE461cA67B15dc8dc81CE7615e0320dA1A9aB8D5",
+            "uniswap_v2",
+            "USDC",
+            "DAI",
+            usdc * 5_000_000u64,
+            dai * 5_000_000u64,
+        ),
+    ];
+
+    pairs
+        .into_iter()
+        .filter_map(|(pair, dex, symbol_a, symbol_b, reserve_a, reserve_b)| {
+            Some(PoolInfo {
+                address: pair.parse().ok()?,
+                dex: dex.to_string(),
+                token_a: registry.address_of(symbol_a)?,
+                token_b: registry.address_of(symbol_b)?,
+                reserve_a,
+                reserve_b,
+                fee_bps: 30,
+                liquidity_usd: 0.0,
+                last_updated: 0,
+            })
+        })
+        .collect()
+}
+
+/// Applies a swap of `amount_in` of `token_in` to `pool`'s reserves.
+fn apply_swap(pool: &mut PoolInfo, token_in: Address, amount_in: U256) {
+    // ---
+
+    if pool.token_a == token_in {
+        let amount_out =
+            amm::get_amount_out(amount_in, pool.reserve_a, pool.reserve_b, pool.fee_bps);
+        pool.reserve_a += amount_in;
+        pool.reserve_b -= amount_out;
+    } else {
+        let amount_out =
+            amm::get_amount_out(amount_in, pool.reserve_b, pool.reserve_a, pool.fee_bps);
+        pool.reserve_b += amount_in;
+        pool.reserve_a -= amount_out;
+    }
 }
 
 /// Mock liquidation positions for testing.
@@ -461,24 +2521,50 @@ struct MockPosition {
     health_factor: f64,
 }
 
+/// Mock pool state for sandwich sizing: (reserve of token_in, reserve of token_out, fee bps).
+fn get_mock_pool_reserves(_token_in: Address, _token_out: Address) -> (U256, U256, u16) {
+    // ---
+    // In reality, this would read `getReserves()` from the pair contract
+    let reserve = U256::from(10_000) * U256::exp10(18); // 10,000 ETH-equivalent per side
+    (reserve, reserve, 30) // 0.3% Uniswap V2 fee
+}
+
 fn get_mock_liquidation_positions() -> Vec<MockPosition> {
     // ---
 
     vec![MockPosition {
         protocol: Protocol::Aave,
         owner: Address::from_low_u64_be(0x1234567890abcdef),
-        collateral_token: Address::from_low_u64_be(0xa0b86a33), // Mock USDC
-        debt_token: Address::from_low_u64_be(0xc02aaa39),       // Mock WETH
+        collateral_token: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48" // USDC
+            .parse()
+            .unwrap_or_default(),
+        debt_token: "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2" // WETH
+            .parse()
+            .unwrap_or_default(),
         collateral_amount: U256::from(10000) * U256::from(10).pow(6.into()), // 10,000 USDC
-        debt_amount: U256::from(4) * U256::from(10).pow(18.into()), // 4 ETH
-        health_factor: 0.95,                                    // Below 1.0, ready for liquidation
+        debt_amount: U256::from(4) * U256::from(10).pow(18.into()),          // 4 ETH
+        health_factor: 0.95, // Below 1.0, ready for liquidation
     }]
 }
 
+/// Formats ` (~$X)` for a wei amount using the oracle's ETH price, or nothing if unknown.
+fn format_usd_estimate<P>(ctx: &AnalysisContext<P>, wei: U256) -> String {
+    // ---
+    let Some(eth_price_usd) = ctx.oracle.eth_price_usd() else {
+        return String::new();
+    };
+    format!(" (~${:.2})", units::wei_to_eth_f64(wei) * eth_price_usd)
+}
+
 // Gas cost estimation functions
-fn estimate_arbitrage_gas_cost() -> U256 {
+fn estimate_arbitrage_gas_cost(base_fee: U256) -> U256 {
+    // ---
+    U256::from(300_000) * base_fee // 300k gas at the predicted base fee
+}
+
+fn estimate_multi_hop_gas_cost(base_fee: U256, hops: usize) -> U256 {
     // ---
-    U256::from(300_000) * U256::from(20).pow(9.into()) // 300k gas * 20 gwei
+    U256::from(50_000 + 120_000 * hops) * base_fee // overhead + ~120k gas per swap
 }
 
 fn estimate_sandwich_gas_cost(gas_price: U256) -> U256 {
@@ -486,15 +2572,1509 @@ fn estimate_sandwich_gas_cost(gas_price: U256) -> U256 {
     U256::from(400_000) * gas_price // 400k gas for frontrun + backrun
 }
 
-fn estimate_liquidation_gas_cost() -> U256 {
+fn estimate_liquidation_gas_cost(base_fee: U256) -> U256 {
     // ---
-    U256::from(500_000) * U256::from(25).pow(9.into()) // 500k gas * 25 gwei
+    U256::from(500_000) * base_fee // 500k gas at the predicted base fee
 }
 
-fn calculate_sandwich_profit(_victim_amount: U256, frontrun_amount: U256) -> U256 {
-    // ---
-    // Simplified profit calculation based on price impact
-    // Real implementation would simulate AMM price curves
-    let price_impact_basis_points = 50; // 0.5% price impact
-    frontrun_amount * price_impact_basis_points / 10000
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, MockProvider};
+    use crate::types::RelaySettings;
+    use std::collections::BTreeSet;
+
+    /// A swap large enough for every detector's size threshold.
+    fn large_swap() -> Transaction {
+        mock::swap_tx(U256::exp10(18) * 100)
+    }
+
+    /// A Uniswap V2 swap of `amount_in` from `token_in` to `token_out`.
+    fn v2_swap(token_in: Address, token_out: Address, amount_in: U256) -> ObservedSwap {
+        ObservedSwap {
+            dex: Some(DEX::UniswapV2),
+            fee_bps: None,
+            token_in,
+            token_out,
+            amount_in,
+        }
+    }
+
+    /// Sizes the WETH/USDC backrun against the default pools after a V2 swap
+    /// selling `victim_eth` WETH, or against the untouched pools if zero.
+    fn backrun_cycle(victim_eth: u64) -> Option<(routing::Cycle, DEX, DEX)> {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let weth = ctx.token_registry.address_of("WETH").unwrap();
+        let usdc = ctx.token_registry.address_of("USDC").unwrap();
+        let amount_in = U256::exp10(18) * victim_eth;
+
+        let (pools, victim) = post_swap_pools(&ctx, &v2_swap(weth, usdc, amount_in)).unwrap();
+        best_cross_dex_cycle(
+            &pools,
+            victim,
+            weth,
+            units::eth_f64_to_wei(MAX_CYCLE_INPUT_ETH),
+        )
+    }
+
+    #[tokio::test]
+    async fn large_swap_yields_arbitrage() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let tx_type = decode_transaction_type(&large_swap());
+
+        let opportunity = detect_arbitrage(&ctx, &tx_type, ctx.pricing_base_fee());
+
+        // The victim sold WETH into Uniswap V2, so WETH is bought back there
+        assert!(matches!(
+            opportunity,
+            Some(MEVOpportunity::Arbitrage {
+                buy_dex: DEX::SushiSwap,
+                sell_dex: DEX::UniswapV2,
+                ..
+            })
+        ));
+    }
+
+    /// A pending call to `signature` with `args`, as an aggregator router receives it.
+    fn aggregator_tx(signature: &str, args: &[Token]) -> Transaction {
+        let mut input = ethers::utils::id(signature).to_vec();
+        input.extend(abi::encode(args));
+        Transaction {
+            input: input.into(),
+            gas_price: Some(U256::from(10_000_000_000u64)),
+            ..Default::default()
+        }
+    }
+
+    /// A 1inch v5 `swap` selling `amount_in` of `src_token` for USDC.
+    fn one_inch_swap_tx(src_token: Address, amount_in: U256) -> Transaction {
+        let registry = TokenRegistry::with_defaults();
+        aggregator_tx(
+            "swap(address,(address,address,address,address,uint256,uint256,uint256),bytes,bytes)",
+            &[
+                Token::Address(Address::from_low_u64_be(0x1e)), // executor
+                Token::Tuple(vec![
+                    Token::Address(src_token),
+                    Token::Address(registry.address_of("USDC").unwrap()),
+                    Token::Address(Address::from_low_u64_be(0x1e)),
+                    Token::Address(Address::from_low_u64_be(0xb0b)),
+                    Token::Uint(amount_in),
+                    Token::Uint(U256::one()),  // minReturnAmount
+                    Token::Uint(U256::zero()), // flags
+                ]),
+                Token::Bytes(Vec::new()), // permit
+                Token::Bytes(vec![0xc0, 0xde]),
+            ],
+        )
+    }
+
+    #[test]
+    fn aggregator_entrypoints_are_decoded() {
+        let registry = TokenRegistry::with_defaults();
+        let weth = registry.address_of("WETH").unwrap();
+        let usdc = registry.address_of("USDC").unwrap();
+        let amount = U256::exp10(20);
+        let address = Token::Address;
+        let uint = |n: u64| Token::Uint(U256::from(n));
+        let signature = Token::Tuple(vec![
+            uint(2),
+            uint(27),
+            Token::FixedBytes(vec![1; 32]),
+            Token::FixedBytes(vec![2; 32]),
+        ]);
+        let other = Address::from_low_u64_be(0xa11ce);
+
+        let cases = [
+            (one_inch_swap_tx(weth, amount), Aggregator::OneInch, true),
+            (
+                aggregator_tx(
+                    "swap(address,(address,address,address,address,uint256,uint256,uint256,bytes),bytes)",
+                    &[
+                        address(other),
+                        Token::Tuple(vec![
+                            address(weth),
+                            address(usdc),
+                            address(other),
+                            address(other),
+                            Token::Uint(amount),
+                            uint(1),
+                            uint(0),
+                            Token::Bytes(Vec::new()),
+                        ]),
+                        Token::Bytes(Vec::new()),
+                    ],
+                ),
+                Aggregator::OneInch,
+                true,
+            ),
+            (
+                aggregator_tx(
+                    "transformERC20(address,address,uint256,uint256,(uint32,bytes)[])",
+                    &[
+                        address(weth),
+                        address(usdc),
+                        Token::Uint(amount),
+                        uint(1),
+                        Token::Array(vec![Token::Tuple(vec![
+                            uint(7),
+                            Token::Bytes(vec![0xab; 40]),
+                        ])]),
+                    ],
+                ),
+                Aggregator::ZeroEx,
+                true,
+            ),
+            (
+                aggregator_tx(
+                    "fillRfqOrder((address,address,uint128,uint128,address,address,address,bytes32,uint64,uint256),(uint8,uint8,bytes32,bytes32),uint128)",
+                    &[
+                        Token::Tuple(vec![
+                            address(usdc), // makerToken
+                            address(weth), // takerToken
+                            uint(250_000_000_000),
+                            Token::Uint(amount),
+                            address(other),
+                            address(Address::zero()),
+                            address(other),
+                            Token::FixedBytes(vec![0; 32]),
+                            uint(u64::MAX),
+                            uint(1),
+                        ]),
+                        signature.clone(),
+                        Token::Uint(amount),
+                    ],
+                ),
+                Aggregator::ZeroEx,
+                false,
+            ),
+            (
+                aggregator_tx(
+                    "fillLimitOrder((address,address,uint128,uint128,uint128,address,address,address,address,bytes32,uint64,uint256),(uint8,uint8,bytes32,bytes32),uint128)",
+                    &[
+                        Token::Tuple(vec![
+                            address(usdc), // makerToken
+                            address(weth), // takerToken
+                            uint(250_000_000_000),
+                            Token::Uint(amount),
+                            uint(0),
+                            address(other),
+                            address(Address::zero()),
+                            address(Address::zero()),
+                            address(other),
+                            Token::FixedBytes(vec![0; 32]),
+                            uint(u64::MAX),
+                            uint(1),
+                        ]),
+                        signature,
+                        Token::Uint(amount),
+                    ],
+                ),
+                Aggregator::ZeroEx,
+                false,
+            ),
+            (
+                aggregator_tx(
+                    "settle(address[],uint256[],(uint256,uint256,address,uint256,uint256,uint32,bytes32,uint256,uint256,uint256,bytes)[],(address,uint256,bytes)[][3])",
+                    &[
+                        Token::Array(vec![address(usdc), address(weth)]),
+                        Token::Array(vec![uint(1), uint(2_500)]),
+                        Token::Array(vec![Token::Tuple(vec![
+                            uint(1), // sellTokenIndex: WETH
+                            uint(0), // buyTokenIndex: USDC
+                            address(other),
+                            Token::Uint(amount),
+                            uint(250_000_000_000),
+                            uint(u64::from(u32::MAX)),
+                            Token::FixedBytes(vec![0; 32]),
+                            uint(0),
+                            uint(0),
+                            uint(0),
+                            Token::Bytes(vec![0x5e; 65]),
+                        ])]),
+                        Token::FixedArray(vec![
+                            Token::Array(Vec::new()),
+                            Token::Array(vec![Token::Tuple(vec![
+                                address(other),
+                                uint(0),
+                                Token::Bytes(vec![0x38, 0xed, 0x17, 0x39]),
+                            ])]),
+                            Token::Array(Vec::new()),
+                        ]),
+                    ],
+                ),
+                Aggregator::CoW,
+                true,
+            ),
+        ];
+
+        for (tx, expected_aggregator, expected_via_pools) in cases {
+            let tx_type = decode_transaction_type(&tx);
+            assert!(
+                matches!(
+                    tx_type,
+                    TxType::AggregatorSwap {
+                        aggregator,
+                        token_in,
+                        token_out,
+                        amount_in,
+                        via_pools,
+                    } if aggregator == expected_aggregator
+                        && token_in == weth
+                        && token_out == usdc
+                        && amount_in == amount
+                        && via_pools == expected_via_pools
+                ),
+                "{expected_aggregator:?} decoded as {tx_type:?}"
+            );
+        }
+
+        // A truncated call is still unknown
+        let mut truncated = one_inch_swap_tx(weth, amount);
+        truncated.input = truncated.input[..100].to_vec().into();
+        assert!(matches!(
+            decode_transaction_type(&truncated),
+            TxType::Unknown
+        ));
+    }
+
+    #[test]
+    fn v2_router_entrypoints_are_decoded() {
+        let registry = TokenRegistry::with_defaults();
+        let cases = [
+            // 1 ETH for at least 2,400 USDC
+            (
+                "swapExactETHForTokens(uint256,address[],address,uint256)",
+                concat!(
+                    "7ff36ab5",
+                    "000000000000000000000000000000000000000000000000000000008f0d1800",
+                    "0000000000000000000000000000000000000000000000000000000000000080",
+                    "000000000000000000000000000000000000000000000000000000000000a11c",
+                    "000000000000000000000000000000000000000000000000000000006553f100",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                    "000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                ),
+                ethers::utils::parse_ether(1).unwrap(),
+                "WETH",
+                "USDC",
+                ethers::utils::parse_ether(1).unwrap(),
+                U256::from(2_400_000_000u64),
+            ),
+            // 2,500 USDC for at least 0.9 ETH
+            (
+                "swapExactTokensForETH(uint256,uint256,address[],address,uint256)",
+                concat!(
+                    "18cbafe5",
+                    "000000000000000000000000000000000000000000000000000000009502f900",
+                    "0000000000000000000000000000000000000000000000000c7d713b49da0000",
+                    "00000000000000000000000000000000000000000000000000000000000000a0",
+                    "000000000000000000000000000000000000000000000000000000000000a11c",
+                    "000000000000000000000000000000000000000000000000000000006553f100",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                    "000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                ),
+                U256::zero(),
+                "USDC",
+                "WETH",
+                U256::from(2_500_000_000u64),
+                ethers::utils::parse_ether("0.9").unwrap(),
+            ),
+            // Exactly 1,000 DAI for at most 1,010 USDC, routed USDC -> WETH -> DAI
+            (
+                "swapTokensForExactTokens(uint256,uint256,address[],address,uint256)",
+                concat!(
+                    "8803dbee",
+                    "00000000000000000000000000000000000000000000003635c9adc5dea00000",
+                    "000000000000000000000000000000000000000000000000000000003c336080",
+                    "00000000000000000000000000000000000000000000000000000000000000a0",
+                    "000000000000000000000000000000000000000000000000000000000000a11c",
+                    "000000000000000000000000000000000000000000000000000000006553f100",
+                    "0000000000000000000000000000000000000000000000000000000000000003",
+                    "000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                    "000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                    "0000000000000000000000006b175474e89094c44da98b954eedeac495271d0f",
+                ),
+                U256::zero(),
+                "USDC",
+                "WETH",
+                U256::from(1_010_000_000u64),
+                ethers::utils::parse_ether(1_000).unwrap(),
+            ),
+        ];
+
+        for (signature, calldata, value, token_in, token_out, amount_in, amount_out_min) in cases {
+            let tx = Transaction {
+                input: ethers::utils::hex::decode(calldata).unwrap().into(),
+                value,
+                gas_price: Some(U256::from(10_000_000_000u64)),
+                ..Default::default()
+            };
+            assert_eq!(
+                tx.input[..4],
+                ethers::utils::id(signature)[..],
+                "{signature}"
+            );
+
+            let (token_in, token_out) = (
+                registry.address_of(token_in).unwrap(),
+                registry.address_of(token_out).unwrap(),
+            );
+            assert!(
+                matches!(
+                    decode_transaction_type(&tx),
+                    TxType::UniswapV2Swap { token_in: t_in, token_out: t_out, amount_in: a_in, amount_out_min: a_out }
+                        if t_in == token_in && t_out == token_out && a_in == amount_in && a_out == amount_out_min
+                ),
+                "{signature}"
+            );
+        }
+    }
+
+    /// Key of the account whose permits the fixtures below carry.
+    const PERMIT_OWNER_KEY: &str =
+        "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    /// Signs EIP-712 typed data as a wallet does, returning `r || s || v` in hex.
+    ///
+    /// `domain` and `message` are the encoded fields of the domain and the
+    /// signed struct, each led by its type hash.
+    fn eip712_signature(domain: &[Token], message: &[Token]) -> String {
+        let (domain, message) = (
+            ethers::utils::keccak256(abi::encode(domain)),
+            ethers::utils::keccak256(abi::encode(message)),
+        );
+        let digest = ethers::utils::keccak256([&[0x19, 0x01], &domain[..], &message[..]].concat());
+        let owner: ethers::signers::LocalWallet = PERMIT_OWNER_KEY.parse().unwrap();
+        let signature = owner.sign_hash(digest.into()).unwrap();
+        ethers::utils::hex::encode(signature.to_vec())
+    }
+
+    /// The EIP-712 type hash of `signature`, as an ABI token.
+    fn type_hash(signature: &str) -> Token {
+        Token::FixedBytes(ethers::utils::keccak256(signature).to_vec())
+    }
+
+    /// The owner's Permit2 `PermitSingle` letting the Universal Router spend
+    /// any amount of USDC, as the first `execute` fixtures carry it.
+    fn permit2_signature() -> String {
+        let permit2: Address = "0x000000000022D473030F116dDEE9F6B43aC78BA3"
+            .parse()
+            .unwrap();
+        let router: Address = "0x3fC91A3afd70395Cd496C647d5a6cC9D4B2b7FAD"
+            .parse()
+            .unwrap();
+        let usdc = TokenRegistry::with_defaults().address_of("USDC").unwrap();
+        let details = abi::encode(&[
+            type_hash("PermitDetails(address token,uint160 amount,uint48 expiration,uint48 nonce)"),
+            Token::Address(usdc),
+            Token::Uint((U256::one() << 160) - 1),
+            Token::Uint(U256::from(0x657b7e00u64)),
+            Token::Uint(U256::zero()),
+        ]);
+        eip712_signature(
+            &[
+                type_hash("EIP712Domain(string name,uint256 chainId,address verifyingContract)"),
+                Token::FixedBytes(ethers::utils::keccak256("Permit2").to_vec()),
+                Token::Uint(U256::one()),
+                Token::Address(permit2),
+            ],
+            &[
+                type_hash("PermitSingle(PermitDetails details,address spender,uint256 sigDeadline)PermitDetails(address token,uint160 amount,uint48 expiration,uint48 nonce)"),
+                Token::FixedBytes(ethers::utils::keccak256(details).to_vec()),
+                Token::Address(router),
+                Token::Uint(U256::from(0x6553f808u64)),
+            ],
+        )
+    }
+
+    /// The owner's ERC-2612 permit letting SwapRouter02 spend 2,500 USDC, as
+    /// the `selfPermit` fixtures carry it.
+    fn usdc_permit_signature() -> String {
+        let router: Address = "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45"
+            .parse()
+            .unwrap();
+        let usdc = TokenRegistry::with_defaults().address_of("USDC").unwrap();
+        let owner: ethers::signers::LocalWallet = PERMIT_OWNER_KEY.parse().unwrap();
+        eip712_signature(
+            &[
+                type_hash("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)"),
+                Token::FixedBytes(ethers::utils::keccak256("USD Coin").to_vec()),
+                Token::FixedBytes(ethers::utils::keccak256("2").to_vec()),
+                Token::Uint(U256::one()),
+                Token::Address(usdc),
+            ],
+            &[
+                type_hash("Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)"),
+                Token::Address(ethers::signers::Signer::address(&owner)),
+                Token::Address(router),
+                Token::Uint(U256::from(2_500_000_000u64)),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::from(0x6553f808u64)),
+            ],
+        )
+    }
+
+    #[test]
+    fn permit_wrapped_swaps_are_decoded() {
+        let registry = TokenRegistry::with_defaults();
+        let (usdc, weth) = (
+            registry.address_of("USDC").unwrap(),
+            registry.address_of("WETH").unwrap(),
+        );
+        let cases = [
+            // PERMIT2_PERMIT, then V2_SWAP_EXACT_IN: 2,500 USDC for at least 0.9 ETH
+            (
+                "execute(bytes,bytes[],uint256)",
+                concat!(
+                    "3593564c",
+                    "0000000000000000000000000000000000000000000000000000000000000060",
+                    "00000000000000000000000000000000000000000000000000000000000000a0",
+                    "000000000000000000000000000000000000000000000000000000006553f808",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "0a08000000000000000000000000000000000000000000000000000000000000",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "0000000000000000000000000000000000000000000000000000000000000040",
+                    "00000000000000000000000000000000000000000000000000000000000001c0",
+                    "0000000000000000000000000000000000000000000000000000000000000160",
+                    "000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                    "000000000000000000000000ffffffffffffffffffffffffffffffffffffffff",
+                    "00000000000000000000000000000000000000000000000000000000657b7e00",
+                    "0000000000000000000000000000000000000000000000000000000000000000",
+                    "0000000000000000000000003fc91a3afd70395cd496c647d5a6cc9d4b2b7fad",
+                    "000000000000000000000000000000000000000000000000000000006553f808",
+                    "00000000000000000000000000000000000000000000000000000000000000e0",
+                    "0000000000000000000000000000000000000000000000000000000000000041",
+                    "684115f1354875dc09389c341086a4ac16a0ee6113c6559645c544b43056612e",
+                    "2cfc7e10884711f598aaa994db7a3f04d33a1ed1b3de6374c4b8a41bad78639f",
+                    "1b00000000000000000000000000000000000000000000000000000000000000",
+                    "0000000000000000000000000000000000000000000000000000000000000100",
+                    "0000000000000000000000000000000000000000000000000000000000000001",
+                    "000000000000000000000000000000000000000000000000000000009502f900",
+                    "0000000000000000000000000000000000000000000000000c7d713b49da0000",
+                    "00000000000000000000000000000000000000000000000000000000000000a0",
+                    "0000000000000000000000000000000000000000000000000000000000000001",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                    "000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                ),
+                U256::zero(),
+                TxType::UniswapV2Swap {
+                    token_in: usdc,
+                    token_out: weth,
+                    amount_in: U256::from(2_500_000_000u64),
+                    amount_out_min: ethers::utils::parse_ether("0.9").unwrap(),
+                },
+            ),
+            // PERMIT2_PERMIT, then V3_SWAP_EXACT_IN through the 0.05% pool
+            (
+                "execute(bytes,bytes[],uint256)",
+                concat!(
+                    "3593564c",
+                    "0000000000000000000000000000000000000000000000000000000000000060",
+                    "00000000000000000000000000000000000000000000000000000000000000a0",
+                    "000000000000000000000000000000000000000000000000000000006553f808",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "0a00000000000000000000000000000000000000000000000000000000000000",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "0000000000000000000000000000000000000000000000000000000000000040",
+                    "00000000000000000000000000000000000000000000000000000000000001c0",
+                    "0000000000000000000000000000000000000000000000000000000000000160",
+                    "000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                    "000000000000000000000000ffffffffffffffffffffffffffffffffffffffff",
+                    "00000000000000000000000000000000000000000000000000000000657b7e00",
+                    "0000000000000000000000000000000000000000000000000000000000000000",
+                    "0000000000000000000000003fc91a3afd70395cd496c647d5a6cc9d4b2b7fad",
+                    "000000000000000000000000000000000000000000000000000000006553f808",
+                    "00000000000000000000000000000000000000000000000000000000000000e0",
+                    "0000000000000000000000000000000000000000000000000000000000000041",
+                    "684115f1354875dc09389c341086a4ac16a0ee6113c6559645c544b43056612e",
+                    "2cfc7e10884711f598aaa994db7a3f04d33a1ed1b3de6374c4b8a41bad78639f",
+                    "1b00000000000000000000000000000000000000000000000000000000000000",
+                    "0000000000000000000000000000000000000000000000000000000000000100",
+                    "0000000000000000000000000000000000000000000000000000000000000001",
+                    "000000000000000000000000000000000000000000000000000000009502f900",
+                    "0000000000000000000000000000000000000000000000000c7d713b49da0000",
+                    "00000000000000000000000000000000000000000000000000000000000000a0",
+                    "0000000000000000000000000000000000000000000000000000000000000001",
+                    "000000000000000000000000000000000000000000000000000000000000002b",
+                    "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb480001f4c02aaa39b223fe8d0a",
+                    "0e5c4f27ead9083c756cc2000000000000000000000000000000000000000000",
+                ),
+                U256::zero(),
+                TxType::UniswapV3Swap {
+                    token_in: usdc,
+                    token_out: weth,
+                    amount_in: U256::from(2_500_000_000u64),
+                    amount_out_min: ethers::utils::parse_ether("0.9").unwrap(),
+                    fee: 500,
+                },
+            ),
+            // WRAP_ETH, then V2_SWAP_EXACT_IN of the router's whole balance: the 1 ETH sent
+            (
+                "execute(bytes,bytes[],uint256)",
+                concat!(
+                    "3593564c",
+                    "0000000000000000000000000000000000000000000000000000000000000060",
+                    "00000000000000000000000000000000000000000000000000000000000000a0",
+                    "000000000000000000000000000000000000000000000000000000006553f808",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "0b08000000000000000000000000000000000000000000000000000000000000",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "0000000000000000000000000000000000000000000000000000000000000040",
+                    "00000000000000000000000000000000000000000000000000000000000000a0",
+                    "0000000000000000000000000000000000000000000000000000000000000040",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "8000000000000000000000000000000000000000000000000000000000000000",
+                    "0000000000000000000000000000000000000000000000000000000000000100",
+                    "0000000000000000000000000000000000000000000000000000000000000001",
+                    "8000000000000000000000000000000000000000000000000000000000000000",
+                    "000000000000000000000000000000000000000000000000000000008f0d1800",
+                    "00000000000000000000000000000000000000000000000000000000000000a0",
+                    "0000000000000000000000000000000000000000000000000000000000000000",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                    "000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                ),
+                ethers::utils::parse_ether(1).unwrap(),
+                TxType::UniswapV2Swap {
+                    token_in: weth,
+                    token_out: usdc,
+                    amount_in: ethers::utils::parse_ether(1).unwrap(),
+                    amount_out_min: U256::from(2_400_000_000u64),
+                },
+            ),
+            // SwapRouter02: selfPermit, then exactInputSingle
+            (
+                "multicall(uint256,bytes[])",
+                concat!(
+                    "5ae401dc",
+                    "000000000000000000000000000000000000000000000000000000006553f808",
+                    "0000000000000000000000000000000000000000000000000000000000000040",
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    "0000000000000000000000000000000000000000000000000000000000000040",
+                    "0000000000000000000000000000000000000000000000000000000000000140",
+                    "00000000000000000000000000000000000000000000000000000000000000c4",
+                    "f3995c67000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce",
+                    "3606eb4800000000000000000000000000000000000000000000000000000000",
+                    "9502f90000000000000000000000000000000000000000000000000000000000",
+                    "6553f80800000000000000000000000000000000000000000000000000000000",
+                    "0000001b3fa9ad68ef13cf653ccaf40e25d87ae842c67786d9c8a7e9bdd2070d",
+                    "d5442b154be9b1395b2305382fd41867462e393fbe71f701cbbbf51e8b716694",
+                    "e57c9f6400000000000000000000000000000000000000000000000000000000",
+                    "00000000000000000000000000000000000000000000000000000000000000e4",
+                    "04e45aaf000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce",
+                    "3606eb48000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead908",
+                    "3c756cc200000000000000000000000000000000000000000000000000000000",
+                    "000001f400000000000000000000000000000000000000000000000000000000",
+                    "0000a11c00000000000000000000000000000000000000000000000000000000",
+                    "9502f9000000000000000000000000000000000000000000000000000c7d713b",
+                    "49da000000000000000000000000000000000000000000000000000000000000",
+                    "0000000000000000000000000000000000000000000000000000000000000000",
+                ),
+                U256::zero(),
+                TxType::UniswapV3Swap {
+                    token_in: usdc,
+                    token_out: weth,
+                    amount_in: U256::from(2_500_000_000u64),
+                    amount_out_min: ethers::utils::parse_ether("0.9").unwrap(),
+                    fee: 500,
+                },
+            ),
+        ];
+
+        // The permits are the owner's, signed as their wallet signs them
+        let (permit2, usdc_permit) = (permit2_signature(), usdc_permit_signature());
+        assert!(cases[0].1.contains(&permit2) && cases[1].1.contains(&permit2));
+        // selfPermit takes v ahead of r and s
+        assert!(cases[3].1.contains(&usdc_permit[..128]));
+
+        for (signature, calldata, value, expected) in cases {
+            let tx = Transaction {
+                input: ethers::utils::hex::decode(calldata).unwrap().into(),
+                value,
+                gas_price: Some(U256::from(10_000_000_000u64)),
+                ..Default::default()
+            };
+            assert_eq!(
+                tx.input[..4],
+                ethers::utils::id(signature)[..],
+                "{signature}"
+            );
+            assert_eq!(decode_transaction_type(&tx), expected, "{signature}");
+        }
+
+        // A multicall of permits alone carries no trade
+        let permit_only = Transaction {
+            input: ethers::utils::hex::decode(concat!(
+                "5ae401dc",
+                "000000000000000000000000000000000000000000000000000000006553f808",
+                "0000000000000000000000000000000000000000000000000000000000000040",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "00000000000000000000000000000000000000000000000000000000000000c4",
+                "f3995c67000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce",
+                "3606eb4800000000000000000000000000000000000000000000000000000000",
+                "9502f90000000000000000000000000000000000000000000000000000000000",
+                "6553f80800000000000000000000000000000000000000000000000000000000",
+                "0000001b3fa9ad68ef13cf653ccaf40e25d87ae842c67786d9c8a7e9bdd2070d",
+                "d5442b154be9b1395b2305382fd41867462e393fbe71f701cbbbf51e8b716694",
+                "e57c9f6400000000000000000000000000000000000000000000000000000000",
+            ))
+            .unwrap()
+            .into(),
+            ..Default::default()
+        };
+        assert_eq!(decode_transaction_type(&permit_only), TxType::Unknown);
+    }
+
+    #[tokio::test]
+    async fn aggregator_swap_is_backrun_in_the_deepest_pool() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+
+        // Native ETH in, routed mostly through Uniswap V2, the deepest WETH/USDC pool
+        let tx = one_inch_swap_tx(Address::repeat_byte(0xee), U256::exp10(20));
+        let tx_type = decode_transaction_type(&tx);
+
+        assert!(matches!(
+            detect_arbitrage(&ctx, &tx_type, ctx.pricing_base_fee()),
+            Some(MEVOpportunity::Arbitrage {
+                buy_dex: DEX::SushiSwap,
+                sell_dex: DEX::UniswapV2,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn swaps_are_quoted_at_cached_on_chain_reserves() {
+        // getReserves() reports 2.5M USDC / 1,000 WETH for any pair; USDC
+        // sorts before WETH, so reserve0 is USDC's
+        let output = abi::encode(&[
+            Token::Uint(U256::exp10(6) * 2_500_000),
+            Token::Uint(U256::exp10(18) * 1_000),
+            Token::Uint(U256::zero()),
+        ]);
+        let mock = Arc::new(
+            MockProvider::default().with_response("eth_call", ethers::types::Bytes::from(output)),
+        );
+        let mut ctx = mock::analysis_context(mock.clone());
+        let weth = ctx.token_registry.address_of("WETH").unwrap();
+        let usdc = ctx.token_registry.address_of("USDC").unwrap();
+
+        // Only the Uniswap WETH/USDC pair, whose reserves are fetched once
+        ctx.pools = Arc::new(default_pools(&ctx.token_registry)[..1].to_vec());
+
+        refresh_pool_reserves(&ctx).await;
+        refresh_pool_reserves(&ctx).await;
+        assert_eq!(mock.call_count("eth_call"), 1);
+        assert_eq!(
+            mock.params("eth_call")[0][0]["to"],
+            "0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc"
+        );
+        let metrics = ctx.metrics.lock().unwrap().clone();
+        assert_eq!(
+            (metrics.reserve_cache_hits, metrics.reserve_cache_misses),
+            (1, 1)
+        );
+        assert_eq!(metrics.reserve_cache_hit_rate(), Some(0.5));
+
+        let (quoted, victim) = post_swap_pools(&ctx, &v2_swap(weth, usdc, U256::zero())).unwrap();
+        assert_eq!(quoted[victim].token_a, weth);
+        assert_eq!(quoted[victim].reserve_a, U256::exp10(18) * 1_000);
+        assert_eq!(quoted[victim].reserve_b, U256::exp10(6) * 2_500_000);
+    }
+
+    #[test]
+    fn repeated_hash_is_classified_from_the_cache() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let mut tx = mock::swap_tx(U256::exp10(18));
+
+        assert!(matches!(ctx.classify(&tx), TxType::UniswapV2Swap { .. }));
+        assert!(ctx.tx_types.get(tx.hash).is_some());
+
+        // Garbled calldata under the same hash still classifies as the swap,
+        // so the second lookup never reached the decoder
+        tx.input = ethers::types::Bytes::from(vec![0xde, 0xad, 0xbe, 0xef]);
+        assert!(matches!(ctx.classify(&tx), TxType::UniswapV2Swap { .. }));
+        let metrics = ctx.metrics.lock().unwrap().clone();
+        assert_eq!(
+            (metrics.decode_cache_hits, metrics.decode_cache_misses),
+            (1, 1)
+        );
+        assert_eq!(metrics.decode_cache_hit_rate(), Some(0.5));
+
+        // A new block drops the entry and the calldata is decoded afresh
+        ctx.base_fees.observe_block(&ethers::types::Block {
+            number: Some(U64::from(101)),
+            base_fee_per_gas: Some(U256::from(mock::TEST_BASE_FEE_WEI)),
+            ..Default::default()
+        });
+        assert!(ctx.tx_types.get(tx.hash).is_none());
+        assert!(!matches!(ctx.classify(&tx), TxType::UniswapV2Swap { .. }));
+    }
+
+    #[test]
+    fn v3_fee_tier_is_decoded() {
+        for tier in [500, 3_000, 10_000] {
+            let tx_type = decode_transaction_type(&mock::v3_swap_tx(U256::exp10(20), tier));
+
+            assert!(
+                matches!(tx_type, TxType::UniswapV3Swap { fee, .. } if fee == tier),
+                "tier {tier} decoded as {tx_type:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn v3_swap_quotes_the_pool_of_its_fee_tier() {
+        let mut ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        ctx.config.write().unwrap().arbitrage.min_pool_liquidity_usd = 0.0;
+
+        // One WETH/USDC V3 pool per tier, each priced differently
+        let mut pools = default_pools(&ctx.token_registry);
+        for (fee_bps, usdc_per_weth) in [(5u16, 2_500u64), (30, 2_450), (100, 2_400)] {
+            pools.push(PoolInfo {
+                dex: "uniswap_v3".to_string(),
+                fee_bps,
+                reserve_a: U256::exp10(18) * 1_000,
+                reserve_b: U256::exp10(6) * 1_000 * usdc_per_weth,
+                ..pools[0].clone()
+            });
+        }
+        ctx.pools = Arc::new(pools);
+
+        for tier in [500, 3_000, 10_000] {
+            let tx_type = decode_transaction_type(&mock::v3_swap_tx(U256::exp10(20), tier));
+            let swap = observed_swap(&ctx, &tx_type).unwrap();
+
+            let (quoted, victim) = post_swap_pools(&ctx, &swap).unwrap();
+            assert_eq!(quoted[victim].dex, "uniswap_v3");
+            assert_eq!(u32::from(quoted[victim].fee_bps) * 100, tier);
+            // Only the traded tier moves
+            let moved = quoted
+                .iter()
+                .filter(|pool| {
+                    pool.reserve_a != U256::exp10(18) * 1_000 && pool.dex == "uniswap_v3"
+                })
+                .count();
+            assert_eq!(moved, 1);
+        }
+
+        // No 0.01% pool is known, so that tier has nothing to quote
+        let tx_type = decode_transaction_type(&mock::v3_swap_tx(U256::exp10(20), 100));
+        assert!(matches!(
+            post_swap_pools(&ctx, &observed_swap(&ctx, &tx_type).unwrap()),
+            Err(RejectReason::NoPriceEdge)
+        ));
+    }
+
+    #[tokio::test]
+    async fn thin_pool_is_excluded_from_quotes() {
+        let mut ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let weth = ctx.token_registry.address_of("WETH").unwrap();
+        let usdc = ctx.token_registry.address_of("USDC").unwrap();
+
+        // 10 WETH quoting 5,000 USDC each: double the market, but only ~$50k deep
+        let mut pools = default_pools(&ctx.token_registry);
+        pools.push(PoolInfo {
+            dex: "uniswap_v3".to_string(),
+            reserve_a: U256::exp10(18) * 10,
+            reserve_b: U256::exp10(6) * 50_000,
+            ..pools[0].clone()
+        });
+        ctx.pools = Arc::new(pools);
+        let tx_type = decode_transaction_type(&large_swap());
+
+        ctx.config.write().unwrap().arbitrage.min_pool_liquidity_usd = 0.0;
+        let (quoted, _) = post_swap_pools(&ctx, &v2_swap(weth, usdc, U256::exp10(20))).unwrap();
+        assert!(quoted.iter().any(|pool| pool.dex == "uniswap_v3"));
+        assert!(matches!(
+            detect_arbitrage(&ctx, &tx_type, ctx.pricing_base_fee()),
+            Some(MEVOpportunity::Arbitrage {
+                buy_dex: DEX::UniswapV3,
+                ..
+            })
+        ));
+
+        ctx.config.write().unwrap().arbitrage.min_pool_liquidity_usd = 1_000_000.0;
+        let (quoted, _) = post_swap_pools(&ctx, &v2_swap(weth, usdc, U256::exp10(20))).unwrap();
+        assert!(quoted.iter().all(|pool| pool.dex != "uniswap_v3"));
+        assert!(matches!(
+            detect_arbitrage(&ctx, &tx_type, ctx.pricing_base_fee()),
+            Some(MEVOpportunity::Arbitrage {
+                buy_dex: DEX::SushiSwap,
+                sell_dex: DEX::UniswapV2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn opportunities_display_concise_summaries() {
+        let weth: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+            .parse()
+            .unwrap();
+        let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            .parse()
+            .unwrap();
+
+        let arbitrage = MEVOpportunity::Arbitrage {
+            token_a: weth,
+            token_b: usdc,
+            buy_dex: DEX::SushiSwap,
+            sell_dex: DEX::UniswapV2,
+            amount_in: U256::exp10(19),
+            token_b_amount: U256::exp10(10),
+            profit_eth: U256::exp10(18),
+            gas_cost_eth: U256::exp10(17) * 5,
+            net_profit_eth: U256::exp10(17) * 5,
+            detected_block: U64::zero(),
+        };
+        assert_eq!(
+            arbitrage.to_string(),
+            "arbitrage 0xc02a…6cc2/0xa0b8…eb48 (buy sushiswap, sell uniswap_v2): net 0.500000000000000000 ETH"
+        );
+
+        let multi_hop = MEVOpportunity::MultiHopArbitrage {
+            path: vec![(DEX::UniswapV2, weth, usdc), (DEX::SushiSwap, usdc, weth)],
+            hop_amounts_in: vec![U256::exp10(18), U256::exp10(9)],
+            profit_eth: U256::exp10(17),
+            gas_cost_eth: U256::zero(),
+            net_profit_eth: U256::exp10(17),
+            detected_block: U64::zero(),
+        };
+        assert_eq!(
+            multi_hop.to_string(),
+            "multi_hop_arbitrage 0xc02a…6cc2 → 0xa0b8…eb48 → 0xc02a…6cc2 (2 hops): net 0.100000000000000000 ETH"
+        );
+
+        let liquidation = MEVOpportunity::Liquidation {
+            protocol: Protocol::Aave,
+            position_owner: Address::zero(),
+            collateral_token: usdc,
+            debt_token: weth,
+            collateral_amount: U256::exp10(10),
+            debt_amount: U256::exp10(18),
+            repay_amount: U256::exp10(17) * 5,
+            liquidation_bonus_eth: U256::exp10(16),
+            health_factor: 0.95,
+            detected_block: U64::zero(),
+        };
+        assert_eq!(
+            liquidation.to_string(),
+            "liquidation on aave 0xa0b8…eb48/0xc02a…6cc2 (health 0.95): bonus 0.010000000000000000 ETH"
+        );
+    }
+
+    /// An arbitrage costing 0.01 ETH in gas with the given net profit.
+    fn arbitrage_netting(net_profit: U256) -> MEVOpportunity {
+        let gas_cost = U256::exp10(16);
+        MEVOpportunity::Arbitrage {
+            token_a: Address::from_low_u64_be(1),
+            token_b: Address::from_low_u64_be(2),
+            buy_dex: DEX::SushiSwap,
+            sell_dex: DEX::UniswapV2,
+            amount_in: U256::exp10(19),
+            token_b_amount: U256::exp10(10),
+            profit_eth: gas_cost + net_profit,
+            gas_cost_eth: gas_cost,
+            net_profit_eth: net_profit,
+            detected_block: U64::zero(),
+        }
+    }
+
+    #[test]
+    fn opportunities_must_clear_profit_margin() {
+        let base_fee = U256::from(mock::TEST_BASE_FEE_WEI);
+        let select = |net_profit| {
+            select_best_opportunity(vec![arbitrage_netting(net_profit)], base_fee, 50, None)
+        };
+
+        // 50 bps of the 0.01 ETH gas cost is 5e13 wei of headroom
+        assert!(select(U256::zero()).is_none());
+        assert!(select(U256::from(50_000_000_000_000u64) - 1).is_none());
+        assert!(select(U256::from(50_000_000_000_000u64)).is_some());
+
+        // Even without a margin, break-even is never worth submitting
+        assert!(
+            select_best_opportunity(vec![arbitrage_netting(U256::zero())], base_fee, 0, None)
+                .is_none()
+        );
+    }
+
+    /// A liquidation whose bonus nets `net_profit` at `base_fee`.
+    fn liquidation_netting(net_profit: U256, base_fee: U256) -> MEVOpportunity {
+        MEVOpportunity::Liquidation {
+            protocol: Protocol::Aave,
+            position_owner: Address::from_low_u64_be(3),
+            collateral_token: Address::from_low_u64_be(1),
+            debt_token: Address::from_low_u64_be(2),
+            collateral_amount: U256::exp10(19),
+            debt_amount: U256::exp10(19),
+            repay_amount: U256::exp10(18),
+            liquidation_bonus_eth: net_profit + estimate_liquidation_gas_cost(base_fee),
+            health_factor: 0.9,
+            detected_block: U64::zero(),
+        }
+    }
+
+    /// Relay configuration with one relay per `(name, probability, refused)`.
+    fn relay_odds(relays: &[(&str, f64, &[&str])]) -> InclusionOdds {
+        let config = RelayConfiguration {
+            relays: relays
+                .iter()
+                .map(|(name, probability, refused)| {
+                    let settings = RelaySettings {
+                        endpoint: format!("https://{name}.example.org"),
+                        auth_key: None,
+                        enabled: true,
+                        inclusion_probability: *probability,
+                        avg_latency_ms: 100,
+                        refused_strategies: refused.iter().map(|s| s.to_string()).collect(),
+                    };
+                    (name.to_string(), settings)
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let names: Vec<&str> = relays.iter().map(|(name, ..)| *name).collect();
+        InclusionOdds::from_config(&config, &names)
+    }
+
+    #[test]
+    fn expected_value_prefers_likelier_opportunities() {
+        let base_fee = U256::from(mock::TEST_BASE_FEE_WEI);
+        // Only the long-shot relay takes arbitrage
+        let odds = relay_odds(&[("steady", 0.9, &["arbitrage"]), ("longshot", 0.3, &[])]);
+        let candidates = || {
+            vec![
+                arbitrage_netting(U256::exp10(18)),
+                liquidation_netting(U256::exp10(17) * 6, base_fee),
+            ]
+        };
+
+        // 1 ETH at 30% is worth less than 0.6 ETH at 90%
+        let by_ev = select_best_opportunity(candidates(), base_fee, 50, Some(&odds));
+        assert_eq!(by_ev.map(|o| o.strategy()), Some("liquidation"));
+
+        // Without the weighting, raw profit wins
+        let by_profit = select_best_opportunity(candidates(), base_fee, 50, None);
+        assert_eq!(by_profit.map(|o| o.strategy()), Some("arbitrage"));
+
+        // Nothing accepts arbitrage here, so it is never selected
+        let odds = relay_odds(&[("steady", 0.9, &["arbitrage"])]);
+        let selected = select_best_opportunity(
+            vec![arbitrage_netting(U256::exp10(18))],
+            base_fee,
+            50,
+            Some(&odds),
+        );
+        assert!(selected.is_none());
+    }
+
+    #[test]
+    fn inclusion_odds_follow_the_selected_relays() {
+        let odds = relay_odds(&[("flashbots", 0.85, &[]), ("ethical", 0.95, &["sandwich"])]);
+        assert_eq!(odds.best_for("arbitrage"), 0.95);
+        assert_eq!(odds.best_for("sandwich"), 0.85);
+
+        // Unselected relays don't count; no relays at all means no weighting
+        let config = RelayConfiguration::default();
+        assert_eq!(
+            InclusionOdds::from_config(&config, &["BLOXROUTE"]).best_for("sandwich"),
+            0.75
+        );
+        assert_eq!(InclusionOdds::default().best_for("sandwich"), 1.0);
+    }
+
+    #[test]
+    fn dex_names_round_trip() {
+        for dex in [
+            DEX::UniswapV2,
+            DEX::UniswapV3,
+            DEX::SushiSwap,
+            DEX::PancakeSwap,
+            DEX::Balancer,
+        ] {
+            assert_eq!(DEX::from_name(&dex.to_string()), Some(dex));
+        }
+        for protocol in [
+            Protocol::Aave,
+            Protocol::Compound,
+            Protocol::MakerDAO,
+            Protocol::Euler,
+        ] {
+            assert_eq!(protocol.to_string().parse(), Ok(protocol));
+        }
+        assert_eq!(Protocol::MakerDAO.to_string(), "makerdao");
+    }
+
+    #[test]
+    fn dex_and_protocol_aliases_parse_and_typos_do_not() {
+        assert_eq!("UniswapV2".parse(), Ok(DEX::UniswapV2));
+        assert_eq!(DEX::try_from("uni-v3"), Ok(DEX::UniswapV3));
+        assert_eq!(DEX::try_from("Sushi"), Ok(DEX::SushiSwap));
+        assert_eq!(DEX::try_from("pancake"), Ok(DEX::PancakeSwap));
+        assert_eq!(Protocol::try_from("AAVE_V3"), Ok(Protocol::Aave));
+        assert_eq!(Protocol::try_from("comp"), Ok(Protocol::Compound));
+        assert_eq!(Protocol::try_from("Maker"), Ok(Protocol::MakerDAO));
+
+        for typo in ["uniswap_v4", "sushiswapp", "uniswap", ""] {
+            let err = DEX::try_from(typo).unwrap_err();
+            assert!(err.contains(&format!("'{typo}'")), "{err}");
+        }
+        assert!(Protocol::try_from("aavee").is_err());
+    }
+
+    #[test]
+    fn pre_swap_pools_have_no_arbitrage() {
+        // Uniswap V2 and SushiSwap quote the same WETH/USDC price until the swap lands
+        assert!(backrun_cycle(0).is_none());
+    }
+
+    #[test]
+    fn arbitrage_is_sized_to_victim_impact() {
+        let (small, _, _) = backrun_cycle(20).unwrap();
+        let (large, _, _) = backrun_cycle(100).unwrap();
+
+        assert!(large.hop_amounts_in[0] > small.hop_amounts_in[0]);
+        assert!(large.profit() > small.profit());
+    }
+
+    #[tokio::test]
+    async fn non_swap_yields_no_arbitrage() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let tx = Transaction::default();
+
+        let opportunity = evaluate_opportunity(&ctx, &tx).await;
+
+        assert!(!matches!(
+            opportunity,
+            Some(MEVOpportunity::Arbitrage { .. })
+        ));
+    }
+
+    /// Runs `detect_arbitrage` on `tx` under a tweaked config and base fee,
+    /// returning the reasons it recorded.
+    fn arbitrage_rejections(
+        tx: &Transaction,
+        base_fee: U256,
+        tweak: impl FnOnce(&mut MEVConfig),
+    ) -> Vec<((&'static str, RejectReason), u64)> {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        tweak(&mut ctx.config.write().unwrap());
+
+        assert!(detect_arbitrage(&ctx, &decode_transaction_type(tx), base_fee).is_none());
+        let counts = ctx.metrics.lock().unwrap().rejection_counts();
+        counts
+    }
+
+    #[test]
+    fn arbitrage_rejections_carry_reasons() {
+        let base_fee = U256::from(mock::TEST_BASE_FEE_WEI);
+        let only = |reason| vec![(("arbitrage", reason), 1)];
+
+        let small = mock::swap_tx(U256::exp10(17));
+        assert_eq!(
+            arbitrage_rejections(&small, base_fee, |_| {}),
+            only(RejectReason::BelowMinSize)
+        );
+
+        let large = large_swap();
+        assert_eq!(
+            arbitrage_rejections(&large, base_fee, |config| {
+                config.arbitrage.token_whitelist = vec![Address::from_low_u64_be(9)];
+            }),
+            only(RejectReason::Blacklisted)
+        );
+        assert_eq!(
+            arbitrage_rejections(&large, base_fee, |config| {
+                config.arbitrage.min_pool_liquidity_usd = 1e12;
+            }),
+            only(RejectReason::InsufficientLiquidity)
+        );
+        assert_eq!(
+            arbitrage_rejections(&large, base_fee, |config| {
+                config.arbitrage.enabled_dexs = BTreeSet::from([DEX::UniswapV2]);
+            }),
+            only(RejectReason::NoPriceEdge)
+        );
+
+        // At 100k gwei, 300k gas outweighs the backrun profit
+        assert_eq!(
+            arbitrage_rejections(&large, U256::exp10(14), |_| {}),
+            only(RejectReason::GasTooHigh)
+        );
+    }
+
+    #[tokio::test]
+    async fn sandwich_rejections_carry_reasons() {
+        let revert = MockProvider::default().with_error("eth_call", "execution reverted");
+        let ctx = mock::analysis_context(Arc::new(revert));
+
+        let small = mock::swap_tx(U256::exp10(17));
+        let mut pricey = large_swap();
+        pricey.gas_price = Some(U256::from(100_000_000_000u64));
+        let reverting = large_swap();
+
+        for tx in [small, pricey, reverting] {
+            let tx_type = decode_transaction_type(&tx);
+            assert!(detect_sandwich_opportunity(&ctx, &tx, &tx_type)
+                .await
+                .is_none());
+        }
+
+        assert_eq!(
+            ctx.metrics.lock().unwrap().rejection_counts(),
+            vec![
+                (("sandwich", RejectReason::BelowMinSize), 1),
+                (("sandwich", RejectReason::GasTooHigh), 1),
+                (("sandwich", RejectReason::WouldRevert), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn disabled_strategies_yield_no_opportunities() {
+        let mock = Arc::new(MockProvider::default().with_response("eth_call", "0x"));
+        let ctx = mock::analysis_context(mock);
+        let tx = large_swap();
+        let strategy = || async { evaluate_opportunity(&ctx, &tx).await.map(|o| o.strategy()) };
+        ctx.config.write().unwrap().sandwich.enabled = true;
+
+        assert!(matches!(
+            strategy().await,
+            Some("arbitrage" | "multi_hop_arbitrage")
+        ));
+
+        ctx.config.write().unwrap().arbitrage.enabled = false;
+        assert_eq!(strategy().await, Some("sandwich"));
+
+        ctx.config.write().unwrap().sandwich.enabled = false;
+        ctx.config.write().unwrap().liquidation.enabled = false;
+        assert_eq!(strategy().await, None);
+    }
+
+    #[tokio::test]
+    async fn strategies_answer_to_their_own_profit_floors() {
+        let mock = Arc::new(MockProvider::default().with_response("eth_call", "0x"));
+        let ctx = mock::analysis_context(mock);
+        let tx = large_swap();
+        let strategy = || async { evaluate_opportunity(&ctx, &tx).await.map(|o| o.strategy()) };
+        let configure = |update: fn(&mut MEVConfig)| update(&mut ctx.config.write().unwrap());
+        configure(|config| {
+            config.sandwich.enabled = true;
+            config.liquidation.enabled = false;
+        });
+
+        // An arbitrage floor out of reach leaves the sandwich standing
+        configure(|config| config.arbitrage.min_net_profit_eth = Some(1_000.0));
+        assert_eq!(strategy().await, Some("sandwich"));
+
+        // A sandwich floor out of reach as well leaves nothing
+        configure(|config| config.sandwich.min_net_profit_eth = Some(1_000.0));
+        assert_eq!(strategy().await, None);
+
+        // Floors replace the global minimum rather than adding to it
+        configure(|config| {
+            config.min_profit = 1_000.0;
+            config.sandwich.min_net_profit_eth = Some(0.0);
+        });
+        assert_eq!(strategy().await, Some("sandwich"));
+        configure(|config| config.arbitrage.min_net_profit_eth = Some(0.0));
+        assert!(matches!(
+            strategy().await,
+            Some("arbitrage" | "multi_hop_arbitrage")
+        ));
+
+        let rejections = ctx.metrics.lock().unwrap().rejection_counts();
+        assert!(rejections.contains(&(("sandwich", RejectReason::BelowProfitFloor), 1)));
+        assert!(rejections.iter().any(|((strategy, reason), _)| {
+            strategy.ends_with("arbitrage") && *reason == RejectReason::BelowProfitFloor
+        }));
+    }
+
+    #[test]
+    fn relative_floors_scale_with_trade_size() {
+        let eth = |milli: u64| U256::exp10(15) * milli;
+        let arbitrage = arbitrage_netting(U256::zero());
+        let (small, large) = (Some(eth(1_000)), Some(eth(100_000)));
+        let floors = |absolute_eth: Option<f64>, bps: Option<u16>, mode| {
+            let mut config = MEVConfig::default();
+            config.arbitrage.min_net_profit_eth = absolute_eth;
+            config.arbitrage.min_net_profit_bps = bps;
+            config.profit_floor_mode = mode;
+            ProfitFloors::from_config(&config)
+        };
+
+        // 0.01 ETH absolute: 0.05 ETH clears it on any trade size
+        let absolute = floors(Some(0.01), None, ProfitFloorMode::All);
+        assert_eq!(absolute.clear(&arbitrage, small, eth(50)), Some(true));
+        assert_eq!(absolute.clear(&arbitrage, large, eth(50)), Some(true));
+
+        // 10 bps relative: 0.05 ETH is plenty on 1 ETH, too thin on 100 ETH
+        let relative = floors(None, Some(10), ProfitFloorMode::All);
+        assert_eq!(relative.clear(&arbitrage, small, eth(50)), Some(true));
+        assert_eq!(relative.clear(&arbitrage, large, eth(50)), Some(false));
+        assert_eq!(relative.clear(&arbitrage, large, eth(100)), Some(true));
+
+        // Both must hold by default; with Any, either one will do
+        let both = floors(Some(0.01), Some(10), ProfitFloorMode::All);
+        let either = floors(Some(0.01), Some(10), ProfitFloorMode::Any);
+        assert_eq!(both.clear(&arbitrage, large, eth(50)), Some(false));
+        assert_eq!(either.clear(&arbitrage, large, eth(50)), Some(true));
+        assert_eq!(both.clear(&arbitrage, small, eth(5)), Some(false));
+        assert_eq!(either.clear(&arbitrage, small, eth(5)), Some(true));
+        assert_eq!(either.clear(&arbitrage, small, eth(0)), Some(false));
+
+        // Without floors of its own, the strategy answers to the global minimum
+        let none = floors(None, None, ProfitFloorMode::All);
+        assert_eq!(none.clear(&arbitrage, small, eth(50)), None);
+
+        // A trade that can't be valued doesn't clear a relative floor
+        assert_eq!(relative.clear(&arbitrage, None, eth(50)), Some(false));
+    }
+
+    #[test]
+    fn trade_sizes_are_valued_in_eth() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let dai = ctx.token_registry.address_of("DAI").unwrap();
+        let usdc = ctx.token_registry.address_of("USDC").unwrap();
+        let mut trade = arbitrage_netting(U256::zero());
+
+        // 250,000 DAI and 250,000 USDC are both 100 ETH at $2,500
+        for (token, decimals) in [(dai, 18), (usdc, 6)] {
+            if let MEVOpportunity::Arbitrage {
+                token_a, amount_in, ..
+            } = &mut trade
+            {
+                (*token_a, *amount_in) = (token, U256::exp10(decimals) * 250_000);
+            }
+            assert_eq!(ctx.trade_size_wei(&trade), Some(U256::exp10(18) * 100));
+        }
+
+        // Tokens the oracle can't price have no ETH size
+        if let MEVOpportunity::Arbitrage { token_a, .. } = &mut trade {
+            *token_a = Address::repeat_byte(0x77);
+        }
+        assert_eq!(ctx.trade_size_wei(&trade), None);
+    }
+
+    #[tokio::test]
+    async fn fee_share_counts_against_the_profit_threshold() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let swap = mock::swap_tx(U256::exp10(18) * 100);
+
+        let best = evaluate_opportunity(&ctx, &swap).await.unwrap();
+        let net_profit: f64 =
+            ethers::utils::format_ether(calculate_net_profit(&best, ctx.pricing_base_fee()))
+                .parse()
+                .unwrap();
+        {
+            let mut config = ctx.config.write().unwrap();
+            config.min_profit = net_profit * 0.9;
+            config.fee_recipient = Some(Address::repeat_byte(0xfe));
+            config.fee_bps = 500;
+        }
+        assert!(evaluate_opportunity(&ctx, &swap).await.is_some());
+
+        // A 20% share leaves less than the minimum
+        ctx.config.write().unwrap().fee_bps = 2_000;
+        assert!(evaluate_opportunity(&ctx, &swap).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn liquidations_only_watch_enabled_protocols() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let base_fee = ctx.pricing_base_fee();
+
+        // The mock position is on Aave
+        ctx.config.write().unwrap().liquidation.enabled_protocols =
+            BTreeSet::from([Protocol::Compound]);
+        assert!(detect_liquidation_opportunity(&ctx, base_fee)
+            .await
+            .is_none());
+
+        ctx.config.write().unwrap().liquidation.enabled_protocols =
+            BTreeSet::from([Protocol::Aave]);
+        assert!(detect_liquidation_opportunity(&ctx, base_fee)
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn liquidations_answer_to_their_minimum_bonus() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let base_fee = ctx.pricing_base_fee();
+
+        let found = detect_liquidation_opportunity(&ctx, base_fee).await;
+        let Some(MEVOpportunity::Liquidation {
+            liquidation_bonus_eth,
+            ..
+        }) = found
+        else {
+            panic!("mock position should be liquidatable");
+        };
+
+        let bonus_eth: f64 = ethers::utils::format_ether(liquidation_bonus_eth)
+            .parse()
+            .unwrap();
+        ctx.config.write().unwrap().liquidation.min_bonus_eth = bonus_eth * 2.0;
+        assert!(detect_liquidation_opportunity(&ctx, base_fee)
+            .await
+            .is_none());
+        assert_eq!(
+            ctx.metrics.lock().unwrap().rejection_counts(),
+            vec![(("liquidation", RejectReason::BelowProfitFloor), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn succeeding_victim_is_sandwiched() {
+        let mock = Arc::new(MockProvider::default().with_response("eth_call", "0x"));
+        let ctx = mock::analysis_context(mock);
+        let tx = large_swap();
+
+        let sandwich = detect_sandwich_opportunity(&ctx, &tx, &decode_transaction_type(&tx)).await;
+
+        assert!(matches!(sandwich, Some(MEVOpportunity::Sandwich { .. })));
+    }
+
+    #[tokio::test]
+    async fn backrun_only_pays_off_if_the_victim_executed() {
+        let mock = Arc::new(MockProvider::default().with_response("eth_call", "0x"));
+        let ctx = mock::analysis_context(mock);
+        let tx = large_swap();
+        let TxType::UniswapV2Swap {
+            token_in,
+            token_out,
+            amount_in,
+            ..
+        } = decode_transaction_type(&tx)
+        else {
+            panic!("large swap should decode as a V2 swap");
+        };
+        let Some(MEVOpportunity::Sandwich {
+            frontrun_amount,
+            backrun_amount,
+            backrun_min_out,
+            estimated_profit_eth,
+            ..
+        }) = detect_sandwich_opportunity(&ctx, &tx, &decode_transaction_type(&tx)).await
+        else {
+            panic!("large swap should be sandwiched");
+        };
+        let (reserve_in, reserve_out, fee_bps) = get_mock_pool_reserves(token_in, token_out);
+        let quote = |victim_amount_in| {
+            amm::quote_sandwich(
+                frontrun_amount,
+                victim_amount_in,
+                reserve_in,
+                reserve_out,
+                fee_bps,
+            )
+        };
+
+        // The backrun sells exactly what the frontrun bought, and clears its
+        // break-even guard once the victim has moved the price
+        let executed = quote(amount_in);
+        assert_eq!(backrun_amount, executed.frontrun_out);
+        assert_eq!(backrun_min_out, frontrun_amount);
+        assert_eq!(executed.backrun_out - backrun_min_out, estimated_profit_eth);
+
+        // Dropped victim: selling into the unmoved pool returns less than the
+        // frontrun paid, so the guarded backrun reverts instead
+        let dropped = quote(U256::zero());
+        assert_eq!(dropped.frontrun_out, backrun_amount);
+        assert!(dropped.backrun_out < backrun_min_out);
+    }
+
+    #[tokio::test]
+    async fn tight_slippage_victim_is_not_sandwiched() {
+        let mock = Arc::new(MockProvider::default().with_response("eth_call", "0x"));
+        let ctx = mock::analysis_context(mock);
+        let amount_in = U256::exp10(18) * 100;
+        let (reserve_in, reserve_out, fee_bps) = get_mock_pool_reserves(
+            ctx.token_registry.address_of("WETH").unwrap(),
+            ctx.token_registry.address_of("USDC").unwrap(),
+        );
+        let quote = amm::get_amount_out(amount_in, reserve_in, reserve_out, fee_bps);
+        let sandwich = |min_out| {
+            let tx = mock::guarded_swap_tx(amount_in, min_out);
+            let ctx = &ctx;
+            async move { detect_sandwich_opportunity(ctx, &tx, &decode_transaction_type(&tx)).await }
+        };
+
+        // 0.0001% slippage leaves room for no profitable frontrun; at the exact
+        // quote there is no room at all
+        assert!(sandwich(quote - quote / 1_000_000).await.is_none());
+        assert!(sandwich(quote).await.is_none());
+        assert_eq!(
+            ctx.metrics.lock().unwrap().rejection_counts(),
+            vec![(("sandwich", RejectReason::SlippageProtected), 2)]
+        );
+
+        // 5% slippage is enough to trade around
+        assert!(matches!(
+            sandwich(quote * 95 / 100).await,
+            Some(MEVOpportunity::Sandwich { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn reverting_victim_is_not_sandwiched() {
+        let mock = Arc::new(
+            MockProvider::default()
+                .with_error("eth_call", "execution reverted: INSUFFICIENT_OUTPUT_AMOUNT"),
+        );
+        let ctx = mock::analysis_context(mock.clone());
+        let tx = large_swap();
+        let tx_type = decode_transaction_type(&tx);
+
+        assert!(detect_sandwich_opportunity(&ctx, &tx, &tx_type)
+            .await
+            .is_none());
+
+        // A re-announcement reuses the cached result instead of re-simulating
+        assert!(detect_sandwich_opportunity(&ctx, &tx, &tx_type)
+            .await
+            .is_none());
+        assert_eq!(mock.call_count("eth_call"), 1);
+    }
+
+    #[tokio::test]
+    async fn unreachable_node_does_not_block_sandwich() {
+        // No eth_call scripted: the call fails without a revert
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let tx = large_swap();
+
+        let sandwich = detect_sandwich_opportunity(&ctx, &tx, &decode_transaction_type(&tx)).await;
+
+        assert!(sandwich.is_some());
+    }
+
+    #[tokio::test]
+    async fn blob_tx_is_classified_and_skipped() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let tx = mock::blob_tx(U256::exp10(18) * 100);
+
+        assert_eq!(TxEnvelope::of(&tx), TxEnvelope::Blob);
+        assert!(matches!(
+            decode_transaction_type(&tx),
+            TxType::Blob { blob_count: 2 }
+        ));
+        assert_eq!(max_fee_per_blob_gas(&tx), Some(U256::exp10(9)));
+        assert!(evaluate_opportunity(&ctx, &tx).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn access_list_swap_is_analyzed() {
+        let ctx = mock::analysis_context(Arc::new(MockProvider::default()));
+        let mut tx = large_swap();
+        tx.transaction_type = Some(1u64.into());
+
+        assert_eq!(TxEnvelope::of(&tx), TxEnvelope::AccessList);
+        assert!(evaluate_opportunity(&ctx, &tx).await.is_some());
+    }
 }
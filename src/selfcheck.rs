@@ -0,0 +1,259 @@
+//! Startup self-check of relay credentials and connectivity.
+//!
+//! A missing or malformed relay key otherwise surfaces only when the first
+//! bundle fails to submit, possibly hours into a run. `--startup-selfcheck`
+//! runs these checks at boot for every enabled relay and reports a readiness
+//! table before the listener starts.
+
+use crate::bundler::{self, RelayConfig};
+use ethers::providers::{Http, HttpClientError, JsonRpcClient};
+use ethers::signers::LocalWallet;
+use ethers::types::U64;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Relay whose requests are authenticated by an `X-Flashbots-Signature` header.
+const FLASHBOTS: &str = "flashbots";
+
+// ---
+
+/// Result of one readiness check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckStatus {
+    // ---
+    /// The check succeeded
+    Pass,
+
+    /// The check failed, with the reason
+    Fail(String),
+
+    /// The check does not apply to this relay
+    NotApplicable,
+}
+
+/// Readiness of one relay.
+#[derive(Debug, Clone)]
+pub struct RelayReadiness {
+    // ---
+    /// Relay name
+    pub relay: String,
+
+    /// Signing key or auth header present and well-formed
+    pub key: CheckStatus,
+
+    /// Endpoint answered a JSON-RPC request
+    pub endpoint: CheckStatus,
+
+    /// Request signature header could be built (Flashbots only)
+    pub signature: CheckStatus,
+}
+
+// ---
+
+impl CheckStatus {
+    /// Returns whether the check did not fail.
+    pub fn is_ok(&self) -> bool {
+        !matches!(self, CheckStatus::Fail(_))
+    }
+
+    /// Table cell label.
+    fn label(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "ok",
+            CheckStatus::Fail(_) => "FAIL",
+            CheckStatus::NotApplicable => "-",
+        }
+    }
+}
+
+impl RelayReadiness {
+    /// Returns whether every check passed or did not apply.
+    pub fn is_ready(&self) -> bool {
+        [&self.key, &self.endpoint, &self.signature]
+            .iter()
+            .all(|status| status.is_ok())
+    }
+
+    /// Returns the reasons of the failed checks.
+    pub fn failures(&self) -> Vec<&str> {
+        [&self.key, &self.endpoint, &self.signature]
+            .into_iter()
+            .filter_map(|status| match status {
+                CheckStatus::Fail(reason) => Some(reason.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Checks every enabled relay, pinging endpoints concurrently with `timeout` each.
+pub async fn check_relays(relays: &[RelayConfig], timeout: Duration) -> Vec<RelayReadiness> {
+    // ---
+    let checks = relays
+        .iter()
+        .filter(|relay| relay.enabled)
+        .map(|relay| check_relay(relay, timeout));
+    futures::future::join_all(checks).await
+}
+
+/// Checks one relay's key, endpoint, and (for Flashbots) request signing.
+pub async fn check_relay(relay: &RelayConfig, timeout: Duration) -> RelayReadiness {
+    // ---
+
+    let is_flashbots = relay.name.eq_ignore_ascii_case(FLASHBOTS);
+    let key = relay
+        .signing_key
+        .as_deref()
+        .map(str::trim)
+        .filter(|key| !key.is_empty());
+
+    // Flashbots keys are secp256k1 private keys; other relays take opaque auth headers
+    let (key_status, wallet) = match key {
+        None => (
+            CheckStatus::Fail(format!("{} is not set", relay.key_var)),
+            None,
+        ),
+        Some(key) if is_flashbots => match key.parse::<LocalWallet>() {
+            Ok(wallet) => (CheckStatus::Pass, Some(wallet)),
+            Err(e) => (
+                CheckStatus::Fail(format!(
+                    "{} is not a valid private key: {}",
+                    relay.key_var, e
+                )),
+                None,
+            ),
+        },
+        Some(_) => (CheckStatus::Pass, None),
+    };
+
+    let signature = if !is_flashbots {
+        CheckStatus::NotApplicable
+    } else if let Some(wallet) = wallet {
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber","params":[]}"#;
+        match bundler::flashbots_signature_header(&wallet, body).await {
+            Ok(_) => CheckStatus::Pass,
+            Err(e) => CheckStatus::Fail(format!("cannot sign Flashbots requests: {}", e)),
+        }
+    } else {
+        CheckStatus::Fail("no usable signing key".to_string())
+    };
+
+    RelayReadiness {
+        relay: relay.name.clone(),
+        key: key_status,
+        endpoint: ping_endpoint(&relay.endpoint, timeout).await,
+        signature,
+    }
+}
+
+/// Sends `eth_blockNumber` to a relay endpoint.
+///
+/// Relays need not implement the method: any JSON-RPC or HTTP reply proves the
+/// endpoint is reachable. Only connection failures and timeouts fail.
+async fn ping_endpoint(endpoint: &str, timeout: Duration) -> CheckStatus {
+    // ---
+
+    let client = match Http::from_str(endpoint) {
+        Ok(client) => client,
+        Err(e) => return CheckStatus::Fail(format!("invalid endpoint '{}': {}", endpoint, e)),
+    };
+
+    let request = client.request::<_, U64>("eth_blockNumber", ());
+    match tokio::time::timeout(timeout, request).await {
+        Err(_) => CheckStatus::Fail(format!("{} timed out after {:?}", endpoint, timeout)),
+        Ok(Err(HttpClientError::ReqwestError(e))) => {
+            CheckStatus::Fail(format!("{} unreachable: {}", endpoint, e))
+        }
+        Ok(_) => CheckStatus::Pass,
+    }
+}
+
+/// Renders the readiness table, followed by the reason for each failed check.
+pub fn readiness_table(rows: &[RelayReadiness]) -> String {
+    // ---
+
+    let mut table = format!(
+        "{:<12} {:<6} {:<10} {}\n",
+        "relay", "key", "endpoint", "signature"
+    );
+    for row in rows {
+        let _ = writeln!(
+            table,
+            "{:<12} {:<6} {:<10} {}",
+            row.relay,
+            row.key.label(),
+            row.endpoint.label(),
+            row.signature.label()
+        );
+    }
+    for row in rows {
+        for reason in row.failures() {
+            let _ = writeln!(table, "  {}: {}", row.relay, reason);
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Nothing listens on port 1, so connections are refused immediately.
+    const CLOSED_ENDPOINT: &str = "http://127.0.0.1:1";
+
+    fn relay(name: &str, signing_key: Option<&str>) -> RelayConfig {
+        RelayConfig {
+            name: name.to_string(),
+            endpoint: CLOSED_ENDPOINT.to_string(),
+            signing_key: signing_key.map(str::to_string),
+            key_var: "TEST_RELAY_KEY",
+            enabled: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn flashbots_key_is_parsed_and_signs() {
+        let key = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+        let readiness = check_relay(&relay("flashbots", Some(key)), Duration::from_secs(5)).await;
+
+        assert_eq!(readiness.key, CheckStatus::Pass);
+        assert_eq!(readiness.signature, CheckStatus::Pass);
+        assert!(matches!(readiness.endpoint, CheckStatus::Fail(_)));
+        assert!(!readiness.is_ready());
+    }
+
+    #[tokio::test]
+    async fn missing_or_malformed_keys_fail() {
+        let timeout = Duration::from_secs(5);
+
+        let missing = check_relay(&relay("flashbots", None), timeout).await;
+        assert_eq!(
+            missing.key,
+            CheckStatus::Fail("TEST_RELAY_KEY is not set".to_string())
+        );
+        assert!(matches!(missing.signature, CheckStatus::Fail(_)));
+
+        let malformed = check_relay(&relay("flashbots", Some("0x1234")), timeout).await;
+        assert!(matches!(malformed.key, CheckStatus::Fail(_)));
+
+        // Auth headers are opaque: presence is all that can be checked offline
+        let header = check_relay(&relay("bloXroute", Some("token")), timeout).await;
+        assert_eq!(header.key, CheckStatus::Pass);
+        assert_eq!(header.signature, CheckStatus::NotApplicable);
+    }
+
+    #[test]
+    fn table_lists_failure_reasons() {
+        let rows = [RelayReadiness {
+            relay: "eden".to_string(),
+            key: CheckStatus::Fail("EDEN_API_KEY is not set".to_string()),
+            endpoint: CheckStatus::Pass,
+            signature: CheckStatus::NotApplicable,
+        }];
+
+        let table = readiness_table(&rows);
+        assert!(table.lines().nth(1).unwrap().starts_with("eden"));
+        assert!(table.contains("eden: EDEN_API_KEY is not set"));
+    }
+}
@@ -0,0 +1,104 @@
+//! Bundle simulation with `eth_callBundle`.
+//!
+//! `--simulate-bundles` executes each built bundle with `eth_callBundle` on
+//! top of the latest state before it is sent. The node reports the bundle's
+//! coinbase diff: everything the block builder would earn from it, priority
+//! fees and the coinbase payment alike. A diff short of the builder payment
+//! plus the profit margin means the bundle doesn't do on-chain what its
+//! estimates say (a trade returns less and the payment is capped, or the
+//! payment reverts), so the bundle is rejected rather than sent. Simulation
+//! needs a node serving `eth_callBundle` (a Flashbots-style builder or a
+//! fork) and costs a round trip per bundle, so it is off by default.
+
+use crate::amm::BPS_DENOMINATOR;
+use crate::bundler::{self, MEVBundle};
+use ethers::providers::Middleware;
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+// ---
+
+/// What `eth_callBundle` reports for a simulated bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleSimulation {
+    // ---
+    /// Wei the block builder's balance grows by
+    pub coinbase_diff: U256,
+
+    /// Gas used by all the bundle's transactions
+    pub total_gas_used: U256,
+}
+
+/// `eth_callBundle` result; amounts are decimal strings.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CallBundleResponse {
+    // ---
+    coinbase_diff: String,
+    total_gas_used: u64,
+}
+
+// ---
+
+/// Simulates `bundle` with `eth_callBundle` against the latest state.
+///
+/// # Errors
+/// Returns an error if the bundle's transactions can't be signed, the node
+/// can't simulate the bundle, or its answer isn't a bundle result.
+pub async fn simulate_bundle<M: Middleware>(
+    provider: &M,
+    bundle: &MEVBundle,
+) -> anyhow::Result<BundleSimulation> {
+    // ---
+
+    let mut params = bundler::signed_bundle_params(bundle)?;
+    params["stateBlockNumber"] = "latest".into();
+
+    let response: CallBundleResponse = provider
+        .provider()
+        .request("eth_callBundle", [params])
+        .await
+        .map_err(|e| anyhow::anyhow!("eth_callBundle failed: {}", e))?;
+    debug!("eth_callBundle result: {:?}", response);
+
+    let coinbase_diff = U256::from_dec_str(&response.coinbase_diff).map_err(|e| {
+        anyhow::anyhow!("Malformed coinbaseDiff {:?}: {}", response.coinbase_diff, e)
+    })?;
+    Ok(BundleSimulation {
+        coinbase_diff,
+        total_gas_used: response.total_gas_used.into(),
+    })
+}
+
+/// Checks that the simulated coinbase diff pays the builder at least the
+/// bundle's intended builder payment plus `margin_bps` of it.
+///
+/// # Errors
+/// Returns an error naming both amounts when the diff falls short.
+pub fn verify_builder_payment(
+    simulation: &BundleSimulation,
+    bundle: &MEVBundle,
+    margin_bps: u16,
+) -> anyhow::Result<()> {
+    // ---
+
+    let margin = bundle.builder_payment * U256::from(margin_bps) / U256::from(BPS_DENOMINATOR);
+    let required = bundle.builder_payment.saturating_add(margin);
+    if simulation.coinbase_diff < required {
+        anyhow::bail!(
+            "Simulated coinbase diff of {} ETH is below the intended builder payment of {} ETH plus {} bps margin",
+            ethers::utils::format_ether(simulation.coinbase_diff),
+            ethers::utils::format_ether(bundle.builder_payment),
+            margin_bps
+        );
+    }
+
+    info!(
+        "🧪 Bundle {} simulated: {} gas, builder receives {} ETH",
+        bundle.bundle_id,
+        simulation.total_gas_used,
+        ethers::utils::format_ether(simulation.coinbase_diff)
+    );
+    Ok(())
+}
@@ -0,0 +1,441 @@
+//! Destinations for detected opportunities.
+//!
+//! Every opportunity the listener detects is wrapped in an
+//! [`OpportunityEnvelope`] and handed to each configured [`OpportunitySink`]:
+//! stdout as NDJSON (`--emit-opportunities`), an append-only NDJSON file
+//! (`--opportunity-file`), or a webhook receiving each envelope as a JSON
+//! `POST` (`--opportunity-webhook`). Integrations of their own (a database, a
+//! queue) implement the trait and are added to [`OpportunitySinks`].
+//!
+//! Envelopes travel to the sinks over a bounded channel drained by one task,
+//! so analysis never waits on a slow webhook, and each sink sees envelopes in
+//! detection order. A failing sink is logged and doesn't hold up the others.
+//! Once [`SINK_QUEUE_CAPACITY`] envelopes are waiting, new ones are dropped
+//! and counted rather than queued without limit, and at shutdown the sinks
+//! get [`SINK_DRAIN_TIMEOUT`] to deliver what is left.
+
+use crate::searcher::{self, AnalysisContext, MEVOpportunity};
+use crate::units;
+use async_trait::async_trait;
+use ethers::types::{TxHash, U256};
+use serde::Serialize;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+/// Longest a webhook may take to accept an envelope.
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Envelopes waiting for the sinks at most; further ones are dropped.
+pub const SINK_QUEUE_CAPACITY: usize = 1_024;
+
+/// Longest shutdown waits for the sinks to deliver the queued envelopes.
+pub const SINK_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// ---
+
+/// A detected opportunity tied to its source transaction, as every sink
+/// receives it.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpportunityEnvelope {
+    // ---
+    /// Pending transaction the opportunity was found in
+    pub tx_hash: TxHash,
+
+    /// Detection time in Unix milliseconds
+    pub detected_at: u64,
+
+    /// The detected opportunity
+    pub opportunity: MEVOpportunity,
+
+    /// Profit after gas costs, in ETH
+    pub net_profit_eth: f64,
+
+    /// Profit after gas costs in `profit_unit`, if the oracle can convert it
+    pub net_profit: Option<f64>,
+
+    /// Configured profit denomination (`ETH`, `USD`, or a token symbol)
+    pub profit_unit: String,
+}
+
+/// Somewhere detected opportunities are sent.
+#[async_trait]
+pub trait OpportunitySink: Send + Sync {
+    /// Delivers one envelope.
+    ///
+    /// # Errors
+    /// Returns an error if the envelope couldn't be delivered; it is logged
+    /// and not retried.
+    async fn emit(&self, env: &OpportunityEnvelope) -> anyhow::Result<()>;
+}
+
+/// The configured sinks; none by default.
+#[derive(Clone, Default)]
+pub struct OpportunitySinks {
+    // ---
+    sinks: Vec<Arc<dyn OpportunitySink>>,
+}
+
+/// Sending end of a running sink task.
+#[derive(Debug, Clone)]
+pub struct OpportunityFeed {
+    // ---
+    envelopes: mpsc::Sender<OpportunityEnvelope>,
+    dropped: Arc<AtomicU64>,
+}
+
+/// Writes each envelope to stdout as one JSON line, or indented with `pretty`.
+#[derive(Debug)]
+pub struct StdoutSink {
+    // ---
+    pretty: bool,
+}
+
+/// Appends each envelope to a file as one JSON line.
+#[derive(Debug)]
+pub struct FileSink {
+    // ---
+    file: Arc<Mutex<File>>,
+}
+
+/// `POST`s each envelope as JSON to a URL.
+#[derive(Debug)]
+pub struct WebhookSink {
+    // ---
+    client: reqwest::Client,
+    url: reqwest::Url,
+}
+
+// ---
+
+impl OpportunityEnvelope {
+    // ---
+
+    /// Wraps `opportunity`, found in `tx_hash` and netting `net_profit` wei,
+    /// stamped with the current time.
+    pub fn new<P>(
+        ctx: &AnalysisContext<P>,
+        tx_hash: TxHash,
+        opportunity: &MEVOpportunity,
+        net_profit: U256,
+    ) -> Self {
+        // ---
+        Self {
+            tx_hash,
+            detected_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+            opportunity: opportunity.clone(),
+            net_profit_eth: units::wei_to_eth_f64(net_profit),
+            net_profit: ctx.denominate_profit(net_profit),
+            profit_unit: ctx.profit_unit(),
+        }
+    }
+
+    /// Wraps `opportunity` with its net profit at the context's pricing base fee.
+    pub fn detected<P>(
+        ctx: &AnalysisContext<P>,
+        tx_hash: TxHash,
+        opportunity: &MEVOpportunity,
+    ) -> Self {
+        // ---
+        let net_profit = searcher::calculate_net_profit(opportunity, ctx.pricing_base_fee());
+        Self::new(ctx, tx_hash, opportunity, net_profit)
+    }
+}
+
+impl OpportunitySinks {
+    // ---
+
+    /// Adds `sink` to those every opportunity is sent to.
+    pub fn push(&mut self, sink: impl OpportunitySink + 'static) {
+        // ---
+        self.sinks.push(Arc::new(sink));
+    }
+
+    /// Whether any sink is configured.
+    pub fn is_empty(&self) -> bool {
+        // ---
+        self.sinks.is_empty()
+    }
+
+    /// Starts the task delivering envelopes sent to the returned feed, or
+    /// returns `None` without sinks. The task finishes once every clone of
+    /// the feed is dropped and the envelopes sent to it are delivered; see
+    /// [`finish`] for waiting on it with a deadline.
+    pub fn start(&self) -> Option<(OpportunityFeed, JoinHandle<()>)> {
+        // ---
+
+        if self.is_empty() {
+            return None;
+        }
+        let (envelopes, mut incoming) = mpsc::channel::<OpportunityEnvelope>(SINK_QUEUE_CAPACITY);
+        let sinks = self.sinks.clone();
+        let delivery = tokio::spawn(async move {
+            while let Some(env) = incoming.recv().await {
+                let results =
+                    futures::future::join_all(sinks.iter().map(|sink| sink.emit(&env))).await;
+                for e in results.into_iter().filter_map(Result::err) {
+                    warn!("📤 Opportunity from {:?} not delivered: {}", env.tx_hash, e);
+                }
+            }
+        });
+        let feed = OpportunityFeed {
+            envelopes,
+            dropped: Arc::default(),
+        };
+        Some((feed, delivery))
+    }
+}
+
+impl fmt::Debug for OpportunitySinks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(f, "OpportunitySinks({})", self.sinks.len())
+    }
+}
+
+impl OpportunityFeed {
+    // ---
+
+    /// Queues `env` for every sink, or drops and counts it if
+    /// [`SINK_QUEUE_CAPACITY`] envelopes are already waiting.
+    pub fn send(&self, env: OpportunityEnvelope) {
+        // ---
+        // The task only stops once every feed is dropped, so the queue is
+        // never closed while a feed can send
+        if let Err(mpsc::error::TrySendError::Full(env)) = self.envelopes.try_send(env) {
+            if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                warn!(
+                    "📤 Opportunity queue full; dropping {:?} and any more until the sinks catch up",
+                    env.tx_hash
+                );
+            }
+        }
+    }
+
+    /// Envelopes dropped so far because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        // ---
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Waits up to `timeout` for `delivery` to deliver the envelopes still
+/// queued, abandoning the rest once it passes. Returns whether every queued
+/// envelope was handed to the sinks.
+pub async fn finish(mut delivery: JoinHandle<()>, timeout: Duration) -> bool {
+    // ---
+
+    match tokio::time::timeout(timeout, &mut delivery).await {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            error!("💥 Opportunity delivery task failed: {}", e);
+            false
+        }
+        Err(_) => {
+            warn!(
+                "📤 Sinks still delivering after {:?}; abandoning the queued opportunities",
+                timeout
+            );
+            delivery.abort();
+            false
+        }
+    }
+}
+
+impl StdoutSink {
+    // ---
+
+    /// Writes compact JSON lines, or indented objects with `pretty`.
+    pub fn new(pretty: bool) -> Self {
+        // ---
+        Self { pretty }
+    }
+}
+
+#[async_trait]
+impl OpportunitySink for StdoutSink {
+    async fn emit(&self, env: &OpportunityEnvelope) -> anyhow::Result<()> {
+        // ---
+        let json = if self.pretty {
+            serde_json::to_string_pretty(env)?
+        } else {
+            serde_json::to_string(env)?
+        };
+        // One locked println!, so envelopes never interleave with other output
+        println!("{json}");
+        Ok(())
+    }
+}
+
+impl FileSink {
+    // ---
+
+    /// Opens `path` for appending, creating it if needed.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be opened.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        // ---
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                anyhow::anyhow!("Cannot open opportunity file {}: {}", path.display(), e)
+            })?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+}
+
+#[async_trait]
+impl OpportunitySink for FileSink {
+    async fn emit(&self, env: &OpportunityEnvelope) -> anyhow::Result<()> {
+        // ---
+        let mut line = serde_json::to_vec(env)?;
+        line.push(b'\n');
+        // A slow disk blocks a pool thread, not the runtime
+        let file = self.file.clone();
+        tokio::task::spawn_blocking(move || {
+            file.lock()
+                .expect("opportunity file poisoned")
+                .write_all(&line)
+        })
+        .await?
+        .map_err(|e| anyhow::anyhow!("Opportunity file write failed: {}", e))
+    }
+}
+
+impl WebhookSink {
+    // ---
+
+    /// Posts to `url`, giving up on a request after [`WEBHOOK_TIMEOUT`].
+    ///
+    /// # Errors
+    /// Returns an error if `url` isn't an http(s) URL or the HTTP client
+    /// can't be built.
+    pub fn new(url: &str) -> anyhow::Result<Self> {
+        // ---
+        let url = reqwest::Url::parse(url)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .ok_or_else(|| anyhow::anyhow!("Opportunity webhook must be an http(s) URL"))?;
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .map_err(|e| anyhow::anyhow!("Cannot build webhook client: {}", e))?;
+        Ok(Self { client, url })
+    }
+}
+
+#[async_trait]
+impl OpportunitySink for WebhookSink {
+    async fn emit(&self, env: &OpportunityEnvelope) -> anyhow::Result<()> {
+        // ---
+        // The URL may carry a token, so errors name only its host
+        let host = self.url.host_str().unwrap_or_default();
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(env)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("Webhook {} unreachable: {}", host, e.without_url()))?;
+        if !response.status().is_success() {
+            anyhow::bail!("Webhook {} answered {}", host, response.status());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::searcher::DEX;
+    use ethers::types::{Address, U64};
+
+    /// A sink that never finishes delivering, standing in for a hung webhook.
+    struct StuckSink;
+
+    #[async_trait]
+    impl OpportunitySink for StuckSink {
+        async fn emit(&self, _env: &OpportunityEnvelope) -> anyhow::Result<()> {
+            std::future::pending().await
+        }
+    }
+
+    fn envelope(n: u64) -> OpportunityEnvelope {
+        OpportunityEnvelope {
+            tx_hash: TxHash::from_low_u64_be(n),
+            detected_at: 0,
+            opportunity: MEVOpportunity::Arbitrage {
+                token_a: Address::from_low_u64_be(1),
+                token_b: Address::from_low_u64_be(2),
+                buy_dex: DEX::UniswapV2,
+                sell_dex: DEX::SushiSwap,
+                amount_in: U256::exp10(18),
+                token_b_amount: U256::exp10(9),
+                profit_eth: U256::exp10(16),
+                gas_cost_eth: U256::exp10(15),
+                net_profit_eth: U256::exp10(16) - U256::exp10(15),
+                detected_block: U64::zero(),
+            },
+            net_profit_eth: 0.009,
+            net_profit: Some(0.009),
+            profit_unit: "ETH".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn full_queue_drops_and_counts_envelopes() {
+        let mut sinks = OpportunitySinks::default();
+        sinks.push(StuckSink);
+        let (feed, delivery) = sinks.start().unwrap();
+
+        // Nothing is taken off the queue before the test yields
+        for n in 0..SINK_QUEUE_CAPACITY as u64 + 3 {
+            feed.send(envelope(n));
+        }
+        assert_eq!(feed.dropped(), 3);
+        delivery.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drain_gives_up_on_a_stuck_sink_at_the_deadline() {
+        let mut sinks = OpportunitySinks::default();
+        sinks.push(StuckSink);
+        let (feed, delivery) = sinks.start().unwrap();
+        feed.send(envelope(1));
+        drop(feed);
+
+        let started = tokio::time::Instant::now();
+        assert!(!finish(delivery, SINK_DRAIN_TIMEOUT).await);
+        assert_eq!(started.elapsed(), SINK_DRAIN_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn file_sink_appends_one_line_per_envelope() {
+        let path =
+            std::env::temp_dir().join(format!("opportunities-{}.ndjson", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sink = FileSink::open(&path).unwrap();
+        sink.emit(&envelope(1)).await.unwrap();
+        sink.emit(&envelope(2)).await.unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written.lines().count(), 2);
+        assert!(written.lines().all(|line| line.contains("\"tx_hash\"")));
+    }
+}
@@ -0,0 +1,365 @@
+//! Point-in-time summary of the pending pool.
+//!
+//! `--snapshot` reads the node's pending pool once, classifies each
+//! transaction by call type, and prints what is waiting: counts by type, the
+//! total value pending, the distribution of gas price bids, and the busiest
+//! senders. Nothing is analyzed for opportunities, built, or sent.
+//!
+//! The pool is read with `txpool_content`. A WebSocket node without the
+//! txpool namespace is instead watched for `--snapshot-window-secs`, and the
+//! pending transactions announced in that window stand in for the pool; over
+//! HTTP there is no such fallback.
+
+use super::TxSource;
+use crate::observe::GasPriceDistribution;
+use crate::searcher;
+use crate::source::{self, PendingTx};
+use crate::types::RpcTransport;
+use crate::units;
+use crate::websocket::{self, WsOptions};
+use ethers::providers::{Http, JsonRpcClient, Middleware, Provider, PubsubClient, StreamExt};
+use ethers::types::{Address, Transaction, TxHash, U256};
+use ethers::utils::to_checksum;
+use futures::stream;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Senders listed in the summary, busiest first.
+pub const TOP_SENDERS: usize = 5;
+
+/// Announced transactions whose bodies are fetched at once.
+const BODY_FETCH_CONCURRENCY: usize = 16;
+
+// ---
+
+/// Where a snapshot's transactions came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotSource {
+    // ---
+    /// The node's `txpool_content` pending set
+    Txpool,
+
+    /// Pending transactions announced over a subscription window this long
+    Window(Duration),
+}
+
+/// Summary of the pending transactions in one snapshot.
+#[derive(Debug, PartialEq)]
+pub struct SnapshotReport {
+    // ---
+    /// Where the transactions came from
+    pub source: SnapshotSource,
+
+    /// Pending transactions seen
+    pub transactions: usize,
+
+    /// Transactions by call type label
+    pub by_type: BTreeMap<&'static str, usize>,
+
+    /// Value the transactions carry, in wei
+    pub total_value: U256,
+
+    /// Distribution of the gas prices offered
+    pub gas_price_gwei: GasPriceDistribution,
+
+    /// Senders with the most pending transactions, busiest first
+    pub top_senders: Vec<(Address, usize)>,
+}
+
+// ---
+
+impl fmt::Display for SnapshotSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        match self {
+            SnapshotSource::Txpool => write!(f, "txpool_content"),
+            SnapshotSource::Window(window) => write!(f, "{:?} subscription window", window),
+        }
+    }
+}
+
+impl SnapshotReport {
+    // ---
+
+    /// Summarizes `transactions`, read from `source`.
+    pub fn of(source: SnapshotSource, transactions: &[Transaction]) -> Self {
+        // ---
+
+        let mut by_type = BTreeMap::new();
+        let mut senders: HashMap<Address, usize> = HashMap::new();
+        let mut total_value = U256::zero();
+        let mut bids_gwei = Vec::with_capacity(transactions.len());
+
+        for tx in transactions {
+            let label = searcher::decode_transaction_type(tx).label();
+            *by_type.entry(label).or_default() += 1;
+            *senders.entry(tx.from).or_default() += 1;
+            total_value = total_value.saturating_add(tx.value);
+            if let Some(bid) = searcher::offered_gas_price(tx) {
+                bids_gwei.push(units::wei_to_eth_f64(bid) * 1e9);
+            }
+        }
+
+        // Ties go to the lower address, so the listing is stable
+        let mut top_senders: Vec<(Address, usize)> = senders.into_iter().collect();
+        top_senders.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        top_senders.truncate(TOP_SENDERS);
+
+        Self {
+            source,
+            transactions: transactions.len(),
+            by_type,
+            total_value,
+            gas_price_gwei: GasPriceDistribution::of(bids_gwei),
+            top_senders,
+        }
+    }
+
+    /// Returns the summary printed by `--snapshot`.
+    pub fn summary_table(&self) -> String {
+        // ---
+
+        let gwei = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{v:.2}"));
+        let mut table = format!(
+            "📸 Snapshot of {} pending txs ({})\n\
+             total value: {} ETH\n\
+             gas price (gwei): min {} / p50 {} / p90 {} / max {}\n\n\
+             {:<22} {:>6} {:>8}\n",
+            self.transactions,
+            self.source,
+            units::format_eth(self.total_value, 4),
+            gwei(self.gas_price_gwei.min),
+            gwei(self.gas_price_gwei.p50),
+            gwei(self.gas_price_gwei.p90),
+            gwei(self.gas_price_gwei.max),
+            "call type",
+            "txs",
+            "share"
+        );
+
+        for (label, count) in &self.by_type {
+            let share = *count as f64 * 100.0 / self.transactions as f64;
+            let _ = writeln!(table, "{:<22} {:>6} {:>7.1}%", label, count, share);
+        }
+
+        let _ = writeln!(table, "\n{:<42} {:>6}", "top senders", "txs");
+        for (sender, count) in &self.top_senders {
+            let _ = writeln!(table, "{:<42} {:>6}", to_checksum(sender, None), count);
+        }
+        table.trim_end().to_string()
+    }
+}
+
+/// Connects to `rpc_url` and snapshots its pending pool, watching the pool
+/// for `window` over WebSocket if the node has no `txpool_content`.
+///
+/// # Errors
+/// Returns an error if the node can't be reached, or can't be read by
+/// either means.
+pub async fn run(
+    rpc_url: &str,
+    ws: &WsOptions,
+    window: Duration,
+) -> anyhow::Result<SnapshotReport> {
+    // ---
+
+    match RpcTransport::from_url(rpc_url)? {
+        RpcTransport::WebSocket => {
+            let provider = Provider::new(websocket::connect(rpc_url, ws).await?);
+            take(&provider, window).await
+        }
+        RpcTransport::Http => {
+            let provider = Provider::new(Http::from_str(rpc_url)?);
+            let transactions = read_txpool(&provider).await.map_err(|e| {
+                anyhow::anyhow!(
+                    "{} (HTTP endpoints cannot fall back to a subscription window)",
+                    e
+                )
+            })?;
+            Ok(SnapshotReport::of(SnapshotSource::Txpool, &transactions))
+        }
+    }
+}
+
+/// Snapshots the pending pool from `txpool_content`, or from the pending
+/// transactions announced over `window` if the node doesn't serve it.
+///
+/// # Errors
+/// Returns an error if the fallback subscription can't be created.
+pub async fn take<P>(provider: &Provider<P>, window: Duration) -> anyhow::Result<SnapshotReport>
+where
+    P: PubsubClient,
+{
+    // ---
+
+    match read_txpool(provider).await {
+        Ok(transactions) => Ok(SnapshotReport::of(SnapshotSource::Txpool, &transactions)),
+        Err(e) => {
+            warn!(
+                "🗂️ {}; watching pending transactions for {:?} instead",
+                e, window
+            );
+            let transactions = drain_window(provider, window).await?;
+            Ok(SnapshotReport::of(
+                SnapshotSource::Window(window),
+                &transactions,
+            ))
+        }
+    }
+}
+
+/// Reads the executable (`pending`) transactions from `txpool_content`.
+///
+/// # Errors
+/// Returns an error if the node doesn't serve `txpool_content`.
+pub async fn read_txpool<P>(provider: &Provider<P>) -> anyhow::Result<Vec<Transaction>>
+where
+    P: JsonRpcClient,
+{
+    // ---
+
+    let content = provider
+        .txpool_content()
+        .await
+        .map_err(|e| anyhow::anyhow!("txpool_content unavailable: {}", e))?;
+
+    // `queued` txs are blocked on a nonce gap, so they aren't counted as pending
+    Ok(content
+        .pending
+        .into_values()
+        .flat_map(|by_nonce| by_nonce.into_values())
+        .collect())
+}
+
+/// Collects the pending transactions announced over `window`, fetching the
+/// bodies of those announced by hash. Transactions gone by the time they are
+/// fetched are left out.
+///
+/// # Errors
+/// Returns an error if the pending-transaction subscription can't be created.
+pub async fn drain_window<P>(
+    provider: &Provider<P>,
+    window: Duration,
+) -> anyhow::Result<Vec<Transaction>>
+where
+    P: PubsubClient,
+{
+    // ---
+
+    info!("📸 Watching pending transactions for {:?}", window);
+    let announced: Vec<PendingTx> =
+        source::pending_txs(provider, TxSource::Subscribe, true, Duration::ZERO)
+            .await?
+            .take_until(tokio::time::sleep(window))
+            .collect()
+            .await;
+
+    let mut seen = HashSet::new();
+    let mut transactions = Vec::new();
+    let mut hashes: Vec<TxHash> = Vec::new();
+    for pending in announced {
+        if !seen.insert(pending.hash()) {
+            continue;
+        }
+        match pending {
+            PendingTx::Full(tx) => transactions.push(*tx),
+            PendingTx::Hash(hash) => hashes.push(hash),
+        }
+    }
+
+    let requested = hashes.len();
+    let fetched: Vec<Transaction> = stream::iter(hashes)
+        .map(|hash| provider.get_transaction(hash))
+        .buffer_unordered(BODY_FETCH_CONCURRENCY)
+        .filter_map(|fetched| async move { fetched.ok().flatten() })
+        .collect()
+        .await;
+    if fetched.len() < requested {
+        debug!(
+            "📸 {} of {} announced transactions could not be fetched",
+            requested - fetched.len(),
+            requested
+        );
+    }
+    transactions.extend(fetched);
+    Ok(transactions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, MockProvider};
+    use ethers::types::{TxpoolContent, H256};
+
+    /// A plain ETH transfer of `value` wei from `from`, bidding `gas_gwei`,
+    /// hashed by its value.
+    fn transfer(from: Address, value: u64, gas_gwei: u64) -> Transaction {
+        Transaction {
+            hash: H256::from_low_u64_be(value),
+            from,
+            value: U256::from(value),
+            gas_price: Some(U256::from(gas_gwei * 1_000_000_000)),
+            ..Default::default()
+        }
+    }
+
+    /// A `txpool_content` answer holding `pending`, one nonce per transaction.
+    fn txpool(pending: &[Transaction]) -> TxpoolContent {
+        let mut content = TxpoolContent::default();
+        for (nonce, tx) in pending.iter().enumerate() {
+            content
+                .pending
+                .entry(tx.from)
+                .or_default()
+                .insert(nonce.to_string(), tx.clone());
+        }
+        content
+    }
+
+    #[tokio::test]
+    async fn txpool_snapshot_is_classified_and_totaled() {
+        let (busy, quiet) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let pending = vec![
+            mock::pair_swap_tx(busy, U256::exp10(18), ["WETH", "USDC"]),
+            transfer(busy, 3_000, 20),
+            transfer(busy, 4_000, 30),
+            transfer(quiet, 5_000, 40),
+        ];
+        let mock = MockProvider::default().with_response("txpool_content", txpool(&pending));
+
+        let report = take(&Provider::new(mock), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(report.source, SnapshotSource::Txpool);
+        assert_eq!(report.transactions, 4);
+        assert_eq!(report.by_type["uniswap_v2_swap"], 1);
+        assert_eq!(report.by_type["unknown"], 3);
+        assert_eq!(report.total_value, U256::from(12_000));
+        assert_eq!(report.top_senders, vec![(busy, 3), (quiet, 1)]);
+
+        // Bids of 10 (the swap), 20, 30, and 40 gwei
+        assert_eq!(report.gas_price_gwei.min, Some(10.0));
+        assert_eq!(report.gas_price_gwei.max, Some(40.0));
+        assert!(report.summary_table().contains("uniswap_v2_swap"));
+    }
+
+    #[tokio::test]
+    async fn nodes_without_txpool_are_watched_instead() {
+        let sender = Address::repeat_byte(0xcc);
+        let first = transfer(sender, 1_000, 10);
+        // Announced twice, counted once
+        let announced = [first.clone(), transfer(sender, 2_000, 10), first];
+        let mock = MockProvider::default()
+            .with_response("eth_subscribe", "0x1")
+            .with_notifications(&announced);
+
+        let window = Duration::from_secs(5);
+        let report = take(&Provider::new(mock), window).await.unwrap();
+        assert_eq!(report.source, SnapshotSource::Window(window));
+        assert_eq!(report.transactions, 2);
+        assert_eq!(report.total_value, U256::from(3_000));
+    }
+}
@@ -0,0 +1,221 @@
+//! Pending transaction sources for the mempool listener.
+//!
+//! The listener consumes a single stream of [`PendingTx`] items regardless of
+//! where they come from: an `eth_subscribe` pending-transaction subscription
+//! (hashes fetched later, or full bodies where the node supports them) or
+//! periodic `txpool_content` polling of a Geth node (full transaction bodies,
+//! no fetch needed).
+
+use super::TxSource;
+use ethers::providers::{JsonRpcClient, Middleware, Provider, PubsubClient, StreamExt};
+use ethers::types::{Transaction, TxHash};
+use futures::future;
+use futures::stream::{self, BoxStream};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+// ---
+
+/// A pending transaction announced by a [`TxSource`].
+#[derive(Debug)]
+pub enum PendingTx {
+    // ---
+    /// Only the hash is known; the body must be fetched
+    Hash(TxHash),
+
+    /// Full transaction body, ready for analysis
+    Full(Box<Transaction>),
+}
+
+/// State carried between `txpool_content` polls.
+struct TxpoolPoller<'a, P> {
+    // ---
+    provider: &'a Provider<P>,
+    interval: tokio::time::Interval,
+
+    /// Hashes present in the pool at the previous poll
+    seen: HashSet<TxHash>,
+}
+
+// ---
+
+impl PendingTx {
+    /// Returns the transaction hash.
+    pub fn hash(&self) -> TxHash {
+        match self {
+            PendingTx::Hash(hash) => *hash,
+            PendingTx::Full(tx) => tx.hash,
+        }
+    }
+}
+
+/// Opens the selected pending transaction source as a stream.
+///
+/// # Arguments
+///
+/// * `provider` - Connected provider to read the mempool from.
+/// * `source` - Which mechanism to use (`subscribe` or `txpool`).
+/// * `full_bodies` - Subscribe to full transaction bodies rather than hashes
+///   (subscribe source only), falling back to hashes if the node refuses.
+/// * `poll_interval` - Delay between `txpool_content` polls (txpool source only).
+///
+/// # Errors
+///
+/// Returns an error if the pending-transaction subscription cannot be created.
+pub async fn pending_txs<P>(
+    provider: &Provider<P>,
+    source: TxSource,
+    full_bodies: bool,
+    poll_interval: Duration,
+) -> anyhow::Result<BoxStream<'_, PendingTx>>
+where
+    P: PubsubClient,
+{
+    // ---
+
+    match source {
+        TxSource::Subscribe if full_bodies => subscribe_full_bodies(provider).await,
+        TxSource::Subscribe => {
+            let hashes = provider.subscribe_pending_txs().await?;
+            Ok(hashes.map(PendingTx::Hash).boxed())
+        }
+        TxSource::Txpool => Ok(poll_txpool(provider, poll_interval)),
+    }
+}
+
+/// Subscribes to `newPendingTransactions` with full bodies, so no transaction
+/// needs fetching.
+///
+/// A node that rejects the subscription gets a hash subscription instead. A
+/// node that accepts it but ignores the flag still announces hashes; those
+/// pass through as [`PendingTx::Hash`] and are fetched as usual.
+async fn subscribe_full_bodies<P>(
+    provider: &Provider<P>,
+) -> anyhow::Result<BoxStream<'_, PendingTx>>
+where
+    P: PubsubClient,
+{
+    // ---
+
+    let params = (Value::from("newPendingTransactions"), Value::from(true));
+    let notifications = match provider.subscribe::<_, Value>(params).await {
+        Ok(notifications) => notifications,
+        Err(e) => {
+            warn!(
+                "📭 Node refused full pending transaction bodies ({}); subscribing to hashes instead",
+                e
+            );
+            let hashes = provider.subscribe_pending_txs().await?;
+            return Ok(hashes.map(PendingTx::Hash).boxed());
+        }
+    };
+    info!("📬 Subscribed to full pending transaction bodies");
+
+    let mut warned = false;
+    Ok(notifications
+        .filter_map(move |notification| {
+            let pending = parse_notification(notification);
+            if matches!(pending, Some(PendingTx::Hash(_))) && !warned {
+                warn!(
+                    "📭 Node announces hashes despite the full-body subscription; fetching bodies"
+                );
+                warned = true;
+            }
+            future::ready(pending)
+        })
+        .boxed())
+}
+
+/// Reads one pending-transaction notification: a full body, or a bare hash
+/// from a node that ignored the full-body flag.
+fn parse_notification(notification: Value) -> Option<PendingTx> {
+    // ---
+    if notification.is_string() {
+        return serde_json::from_value(notification)
+            .ok()
+            .map(PendingTx::Hash);
+    }
+    match serde_json::from_value(notification) {
+        Ok(tx) => Some(PendingTx::Full(Box::new(tx))),
+        Err(e) => {
+            warn!("Skipping malformed pending transaction notification: {}", e);
+            None
+        }
+    }
+}
+
+/// Polls `txpool_content` every `poll_interval`, emitting each pending
+/// transaction once.
+///
+/// Each poll is diffed against the hashes present at the previous poll, so only
+/// newly-arrived transactions are emitted. The seen set is replaced (not grown)
+/// on every poll, which keeps memory bounded by the pool size. Failed polls are
+/// logged and retried at the next interval.
+pub fn poll_txpool<P>(provider: &Provider<P>, poll_interval: Duration) -> BoxStream<'_, PendingTx>
+where
+    P: JsonRpcClient,
+{
+    // ---
+
+    info!("🗂️  Polling txpool_content every {:?}", poll_interval);
+
+    let mut interval = tokio::time::interval(poll_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let poller = TxpoolPoller {
+        provider,
+        interval,
+        seen: HashSet::new(),
+    };
+
+    stream::unfold(poller, |mut poller| async move {
+        // ---
+        poller.interval.tick().await;
+        let fresh = poller.poll().await;
+        Some((stream::iter(fresh), poller))
+    })
+    .flatten()
+    .boxed()
+}
+
+impl<P: JsonRpcClient> TxpoolPoller<'_, P> {
+    // ---
+
+    /// Fetches the pool and returns the pending transactions not seen at the previous poll.
+    async fn poll(&mut self) -> Vec<PendingTx> {
+        // ---
+
+        let content = match self.provider.txpool_content().await {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("txpool_content poll failed: {}", e);
+                return Vec::new();
+            }
+        };
+
+        // Only `pending` is executable; `queued` txs are blocked on a nonce gap
+        let mut current = HashSet::with_capacity(self.seen.len());
+        let mut fresh = Vec::new();
+
+        for tx in content
+            .pending
+            .into_values()
+            .flat_map(|by_nonce| by_nonce.into_values())
+        {
+            current.insert(tx.hash);
+            if !self.seen.contains(&tx.hash) {
+                fresh.push(PendingTx::Full(Box::new(tx)));
+            }
+        }
+
+        debug!(
+            "txpool: {} pending, {} new since last poll",
+            current.len(),
+            fresh.len()
+        );
+        self.seen = current;
+        fresh
+    }
+}
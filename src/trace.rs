@@ -0,0 +1,230 @@
+//! Per-transaction bundle tracing.
+//!
+//! `--trace-bundles` runs each transaction of a built bundle through
+//! `debug_traceCall` with the call tracer before it is sent, and logs the gas
+//! it used against the limit it was given, plus the revert reason of any that
+//! failed. Each transaction is traced on its own against the latest state, so
+//! a leg that depends on an earlier one (a backrun selling what the frontrun
+//! bought) can revert in the trace yet succeed in the bundle. Tracing needs a
+//! node with the `debug` namespace (a fork or a tracing node) and costs a
+//! round trip per transaction, so it is off by default.
+
+use crate::bundler::{BundleTransaction, MEVBundle};
+use ethers::abi::{self, ParamType, Token};
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{
+    Address, CallFrame, GethDebugBuiltInTracerType, GethDebugTracerType,
+    GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, GethTraceFrame, Transaction,
+    U256,
+};
+use tracing::{info, warn};
+
+/// Selector of Solidity's `Error(string)`, raised by `require` and `revert`.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of Solidity's `Panic(uint256)`, raised by failed asserts and
+/// checked arithmetic.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+// ---
+
+/// What one bundle transaction did when traced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionTrace {
+    // ---
+    /// Gas the transaction used
+    pub gas_used: U256,
+
+    /// Why the transaction reverted, or `None` if it succeeded
+    pub revert_reason: Option<String>,
+}
+
+// ---
+
+/// Traces every transaction of `bundle` and logs the gas each used against
+/// its limit and any revert reason.
+///
+/// Searcher transactions are traced as sent from `from`; raw third-party
+/// transactions from their recovered signer. A transaction that can't be
+/// traced is logged and skipped; tracing never stops the bundle.
+pub async fn log_bundle_trace<M: Middleware>(provider: &M, from: Address, bundle: &MEVBundle) {
+    // ---
+
+    info!(
+        "🔍 Tracing bundle {} ({} transactions)",
+        bundle.bundle_id,
+        bundle.transactions.len()
+    );
+
+    for (i, tx) in bundle.transactions.iter().enumerate() {
+        let (request, gas_limit) = match call_request(&tx.request, from) {
+            Ok(call) => call,
+            Err(e) => {
+                warn!("🔍 Bundle tx {} ({}): not traced ({})", i, tx.role, e);
+                continue;
+            }
+        };
+        match trace_transaction(provider, request).await {
+            Ok(trace) => {
+                let limit = gas_limit.map_or("-".to_string(), |limit| limit.to_string());
+                match trace.revert_reason {
+                    None => info!(
+                        "🔍 Bundle tx {} ({}): used {} gas (limit {})",
+                        i, tx.role, trace.gas_used, limit
+                    ),
+                    Some(reason) => warn!(
+                        "🔍 Bundle tx {} ({}): reverted after {} gas (limit {}): {}",
+                        i, tx.role, trace.gas_used, limit, reason
+                    ),
+                }
+            }
+            Err(e) => warn!("🔍 Bundle tx {} ({}): not traced ({})", i, tx.role, e),
+        }
+    }
+}
+
+/// Traces one call with `debug_traceCall` and the call tracer.
+///
+/// # Errors
+/// Returns an error if the node can't trace the call or returns a trace
+/// other than a call frame.
+pub async fn trace_transaction<M: Middleware>(
+    provider: &M,
+    request: TypedTransaction,
+) -> anyhow::Result<TransactionTrace> {
+    // ---
+
+    let options = GethDebugTracingCallOptions {
+        tracing_options: GethDebugTracingOptions {
+            tracer: Some(GethDebugTracerType::BuiltInTracer(
+                GethDebugBuiltInTracerType::CallTracer,
+            )),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let trace = provider
+        .debug_trace_call(request, None, options)
+        .await
+        .map_err(|e| anyhow::anyhow!("debug_traceCall failed: {}", e))?;
+
+    let frame: CallFrame = match trace {
+        GethTrace::Known(GethTraceFrame::CallTracer(frame)) => frame,
+        GethTrace::Unknown(value) => serde_json::from_value(value)
+            .map_err(|e| anyhow::anyhow!("Unexpected trace format: {}", e))?,
+        GethTrace::Known(_) => anyhow::bail!("Node answered with a trace other than a call frame"),
+    };
+
+    let revert_reason = frame.error.map(|error| {
+        frame
+            .output
+            .as_deref()
+            .and_then(decode_revert_reason)
+            .unwrap_or(error)
+    });
+    Ok(TransactionTrace {
+        gas_used: frame.gas_used,
+        revert_reason,
+    })
+}
+
+/// Decodes the reason carried by a call's revert data: the message of an
+/// `Error(string)`, the code of a `Panic(uint256)`, or the selector of a
+/// custom error. Returns `None` for empty data.
+pub fn decode_revert_reason(output: &[u8]) -> Option<String> {
+    // ---
+
+    let (selector, data) = (output.get(..4)?, &output[4..]);
+    let decoded = |kind| abi::decode(&[kind], data).ok()?.pop();
+
+    if selector == ERROR_SELECTOR {
+        if let Some(Token::String(message)) = decoded(ParamType::String) {
+            return Some(message);
+        }
+    } else if selector == PANIC_SELECTOR {
+        if let Some(Token::Uint(code)) = decoded(ParamType::Uint(256)) {
+            return Some(format!("panic 0x{code:02x}"));
+        }
+    }
+    Some(format!(
+        "custom error 0x{}",
+        ethers::utils::hex::encode(selector)
+    ))
+}
+
+/// Returns the call to trace for `tx` and the gas limit it carries.
+fn call_request(
+    tx: &BundleTransaction,
+    from: Address,
+) -> anyhow::Result<(TypedTransaction, Option<U256>)> {
+    // ---
+
+    match tx {
+        BundleTransaction::Searcher(request) => {
+            let request = (**request).clone().from(from);
+            let gas = request.gas;
+            Ok((request.into(), gas))
+        }
+        BundleTransaction::Raw(raw) => {
+            let mut tx: Transaction = ethers::utils::rlp::decode(raw)
+                .map_err(|e| anyhow::anyhow!("Cannot decode raw transaction: {}", e))?;
+            tx.recover_from_mut()
+                .map_err(|e| anyhow::anyhow!("Cannot recover raw transaction signer: {}", e))?;
+            Ok(((&tx).into(), Some(tx.gas)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockProvider;
+    use ethers::providers::Provider;
+    use ethers::types::TransactionRequest;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn reverted_call_reports_its_decoded_reason() {
+        let mut revert_data = ERROR_SELECTOR.to_vec();
+        revert_data.extend(abi::encode(&[Token::String(
+            "UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT".into(),
+        )]));
+        let mock = MockProvider::default().with_response(
+            "debug_traceCall",
+            serde_json::json!({
+                "type": "CALL",
+                "from": Address::zero(),
+                "to": Address::repeat_byte(0x7a),
+                "gas": "0x30d40",
+                "gasUsed": "0x5208",
+                "input": "0x38ed1739",
+                "output": ethers::types::Bytes::from(revert_data),
+                "error": "execution reverted",
+            }),
+        );
+        let provider = Provider::new(Arc::new(mock));
+
+        let trace = trace_transaction(&provider, TransactionRequest::new().into())
+            .await
+            .unwrap();
+        assert_eq!(trace.gas_used, U256::from(21_000));
+        assert_eq!(
+            trace.revert_reason.as_deref(),
+            Some("UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT")
+        );
+    }
+
+    #[test]
+    fn panics_and_custom_errors_are_named() {
+        let mut panic = PANIC_SELECTOR.to_vec();
+        panic.extend(abi::encode(&[Token::Uint(U256::from(0x11))]));
+        assert_eq!(decode_revert_reason(&panic).as_deref(), Some("panic 0x11"));
+
+        assert_eq!(
+            decode_revert_reason(&[0xde, 0xad, 0xbe, 0xef]).as_deref(),
+            Some("custom error 0xdeadbeef")
+        );
+        assert_eq!(decode_revert_reason(&[]), None);
+    }
+}
@@ -3,9 +3,25 @@
 //! This module contains common data structures used across the MEV pipeline,
 //! including configuration management, MEV strategy parameters, and shared utilities.
 
-use ethers::types::{Address, U256};
+use crate::amm::BPS_DENOMINATOR;
+use crate::flashloan::FlashLoanProvider;
+use crate::observe::BlockObserver;
+use crate::relay_stats::RelayStats;
+use crate::searcher::{Protocol, RejectReason, DEX};
+use ethers::types::{Address, Transaction, U256, U64};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::warn;
+
+/// Most recent processing latencies kept for percentiles.
+const LATENCY_WINDOW: usize = 10_000;
+
+/// Config file read from the working directory at startup, and on SIGHUP.
+pub const CONFIG_FILE: &str = "mev_config.json";
 
 // ---
 
@@ -15,9 +31,6 @@ pub struct Config {
     /// Ethereum RPC WebSocket URL
     pub eth_rpc_url: String,
 
-    /// Private key for signing transactions (optional for simulation)
-    pub private_key: Option<String>,
-
     /// MEV strategy configuration
     pub mev_config: MEVConfig,
 
@@ -26,17 +39,67 @@ pub struct Config {
 
     /// Gas price strategy settings
     pub gas_config: GasConfiguration,
+
+    /// DEX router addresses per chain
+    #[serde(default)]
+    pub dex_config: DexConfiguration,
 }
 
 /// MEV-specific configuration parameters.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MEVConfig {
-    /// Minimum profit threshold in ETH to execute opportunities
-    pub min_profit_eth: f64,
+    /// Minimum net profit to execute opportunities, in `profit_denomination` units
+    #[serde(alias = "min_profit_eth")]
+    pub min_profit: f64,
+
+    /// Unit profits are compared against thresholds and reported in
+    #[serde(default)]
+    pub profit_denomination: ProfitDenomination,
 
-    /// Maximum gas price in gwei for profitable execution
+    /// Maximum gas price in gwei for profitable execution: base fees above it
+    /// skip analysis, and bundles bid no more than it (nor the gas config's)
     pub max_gas_price_gwei: u64,
 
+    /// Share of each bundle's expected profit paid to the block builder via a
+    /// coinbase transfer (0.0-100.0; 0 disables)
+    #[serde(default)]
+    pub builder_bribe_percent: f64,
+
+    /// Account receiving a share of each bundle's profit, for searchers
+    /// trading on someone else's behalf (unset disables profit sharing)
+    #[serde(default)]
+    pub fee_recipient: Option<Address>,
+
+    /// Share of each bundle's profit left after the builder payment that is
+    /// transferred to `fee_recipient`, in basis points (0-9999)
+    #[serde(default)]
+    pub fee_bps: u16,
+
+    /// Seconds after the latest block's timestamp that router swaps stay valid
+    #[serde(default = "default_swap_deadline_secs")]
+    pub swap_deadline_secs: u64,
+
+    /// Net profit an opportunity must clear above break-even, in basis points
+    /// of its costs (50 = 0.5% headroom); absorbs slippage and gas estimation error
+    #[serde(default = "default_profit_margin_bps")]
+    pub profit_margin_bps: u16,
+
+    /// Blocks an opportunity may fall behind the chain head between detection
+    /// and bundle creation before it is discarded as stale (0 = same block only)
+    #[serde(default)]
+    pub max_staleness_blocks: u64,
+
+    /// Rank competing opportunities by expected value (net profit times the
+    /// best accepting relay's inclusion probability) rather than net profit;
+    /// off by default, so ranking stays on net profit unless opted into
+    #[serde(default)]
+    pub rank_by_expected_value: bool,
+
+    /// How a strategy's absolute (`min_net_profit_eth`) and relative
+    /// (`min_net_profit_bps`) profit floors combine when both are set
+    #[serde(default)]
+    pub profit_floor_mode: ProfitFloorMode,
+
     /// Arbitrage strategy settings
     pub arbitrage: ArbitrageConfig,
 
@@ -45,22 +108,80 @@ pub struct MEVConfig {
 
     /// Liquidation strategy settings
     pub liquidation: LiquidationConfig,
+
+    /// Risk limits shared by every strategy
+    #[serde(default)]
+    pub risk: RiskParameters,
+}
+
+/// Strategy configuration shared between the detectors and the control API,
+/// which may change it while the pipeline runs.
+pub type SharedConfig = Arc<RwLock<MEVConfig>>;
+
+/// Unit used to express and compare profits.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum ProfitDenomination {
+    /// Native ETH
+    #[default]
+    Eth,
+
+    /// US dollars, converted via the price oracle's ETH/USD price
+    Usd,
+
+    /// Units of an ERC-20 token, converted via the price oracle's token price
+    Token(Address),
+}
+
+/// How a strategy's absolute and relative profit floors combine.
+///
+/// A strategy with either floor answers to its own floors instead of the
+/// global `min_profit`; the mode only matters when both are set.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum ProfitFloorMode {
+    /// Clear every floor set, i.e. the stricter of the two for the trade's size
+    #[default]
+    All,
+
+    /// Clear either floor: small trades pass on the relative floor, large
+    /// ones on the absolute floor
+    Any,
 }
 
 /// Arbitrage strategy configuration.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ArbitrageConfig {
+    /// Enabled for arbitrage, direct and multi-hop
+    #[serde(default = "default_strategy_enabled")]
+    pub enabled: bool,
+
     /// Minimum trade size in ETH to consider for arbitrage
     pub min_trade_size_eth: f64,
 
+    /// Minimum net profit in ETH for arbitrage and multi-hop opportunities;
+    /// unset falls back to the global `min_profit`
+    #[serde(default)]
+    pub min_net_profit_eth: Option<f64>,
+
+    /// Minimum net profit in basis points of the trade size (50 = 0.5%),
+    /// combined with `min_net_profit_eth` per `profit_floor_mode`
+    #[serde(default)]
+    pub min_net_profit_bps: Option<u16>,
+
     /// Maximum slippage tolerance as percentage (0.0-100.0)
     pub max_slippage_percent: f64,
 
-    /// Enabled DEX list for arbitrage detection
-    pub enabled_dexs: Vec<String>,
+    /// DEXs searched for arbitrage, by config name (`uniswap_v2`, `sushiswap`, ...);
+    /// an unknown name fails config loading
+    #[serde(with = "name_set")]
+    pub enabled_dexs: BTreeSet<DEX>,
 
     /// Token whitelist for arbitrage (empty = all tokens)
     pub token_whitelist: Vec<Address>,
+
+    /// Pools with less liquidity than this (USD) are left out of arbitrage
+    /// quotes; 0 disables the filter
+    #[serde(default = "default_min_pool_liquidity_usd")]
+    pub min_pool_liquidity_usd: f64,
 }
 
 /// Sandwich attack strategy configuration.
@@ -69,6 +190,16 @@ pub struct SandwichConfig {
     /// Minimum victim trade size in ETH to sandwich
     pub min_victim_size_eth: f64,
 
+    /// Minimum net profit in ETH for sandwiches; unset falls back to the
+    /// global `min_profit`
+    #[serde(default)]
+    pub min_net_profit_eth: Option<f64>,
+
+    /// Minimum net profit in basis points of the frontrun size (50 = 0.5%),
+    /// combined with `min_net_profit_eth` per `profit_floor_mode`
+    #[serde(default)]
+    pub min_net_profit_bps: Option<u16>,
+
     /// Maximum frontrun amount as percentage of victim trade (0.0-100.0)
     pub max_frontrun_percent: f64,
 
@@ -77,22 +208,62 @@ pub struct SandwichConfig {
 
     /// Enabled for sandwich attacks
     pub enabled: bool,
+
+    /// How the victim transaction is expected to land relative to our bundle
+    #[serde(default)]
+    pub victim_inclusion: VictimInclusion,
+}
+
+/// Where a sandwich bundle expects the victim transaction to come from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum VictimInclusion {
+    /// Include the victim's raw signed tx between frontrun and backrun, so the
+    /// sandwich executes atomically or not at all
+    #[default]
+    Bundled,
+
+    /// Assume the victim lands from the public mempool; if it doesn't, the
+    /// backrun's break-even guard reverts and takes the bundle with it
+    Mempool,
 }
 
 /// Liquidation strategy configuration.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LiquidationConfig {
-    /// Minimum liquidation bonus in ETH to execute
+    /// Enabled for liquidations
+    #[serde(default = "default_strategy_enabled")]
+    pub enabled: bool,
+
+    /// Minimum liquidation bonus in ETH to execute; net profit still answers
+    /// to the global `min_profit`
     pub min_bonus_eth: f64,
 
     /// Health factor threshold below which to attempt liquidation
     pub health_factor_threshold: f64,
 
-    /// Enabled lending protocols for liquidation monitoring
-    pub enabled_protocols: Vec<String>,
+    /// Lending protocols monitored for liquidations, by config name (`aave`,
+    /// `compound`, ...); an unknown name fails config loading
+    #[serde(with = "name_set")]
+    pub enabled_protocols: BTreeSet<Protocol>,
 
-    /// Flash loan providers configuration
+    /// Flash loan providers to borrow liquidation capital from (`aave`, `dydx`,
+    /// `balancer`); the cheapest one lending the debt token is used
     pub flash_loan_providers: Vec<String>,
+
+    /// How much of the close-factor cap to repay per liquidation
+    #[serde(default)]
+    pub sizing: LiquidationSizing,
+}
+
+/// How much of a position's liquidatable debt to repay.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum LiquidationSizing {
+    /// Repay as much as the protocol's close factor allows
+    #[default]
+    Max,
+
+    /// Repay this share of the close-factor cap, in basis points
+    Partial { fraction_bps: u16 },
 }
 
 /// MEV relay configuration.
@@ -106,6 +277,15 @@ pub struct RelayConfiguration {
 
     /// Default timeout for relay submissions in seconds
     pub submission_timeout_secs: u64,
+
+    /// Gas a single bundle may use; larger bundles are split across
+    /// consecutive blocks where the strategy allows, and rejected otherwise
+    #[serde(default = "default_max_bundle_gas")]
+    pub max_bundle_gas: u64,
+
+    /// Transactions a single bundle may carry, split or rejected like `max_bundle_gas`
+    #[serde(default = "default_max_bundle_txs")]
+    pub max_bundle_txs: usize,
 }
 
 /// Individual relay endpoint settings.
@@ -123,6 +303,11 @@ pub struct RelaySettings {
     /// Expected inclusion probability (0.0-1.0)
     pub inclusion_probability: f64,
 
+    /// Strategies the relay won't include (e.g. `sandwich` on an ethical
+    /// relay), by `MEVOpportunity::strategy` name
+    #[serde(default)]
+    pub refused_strategies: Vec<String>,
+
     /// Average submission latency in milliseconds
     pub avg_latency_ms: u64,
 }
@@ -141,6 +326,12 @@ pub struct GasConfiguration {
 
     /// Gas limit multiplier for safety margin (e.g., 1.2 = 20% buffer)
     pub gas_limit_multiplier: f64,
+
+    /// Bump on the computed gas price of bundle transactions, in basis
+    /// points, to outbid competing searchers; `max_gas_price_gwei` still caps
+    /// the bumped price
+    #[serde(default)]
+    pub price_bump_bps: u16,
 }
 
 /// Gas price calculation strategies.
@@ -160,24 +351,68 @@ pub enum GasStrategy {
 }
 
 /// Priority fee strategies for EIP-1559 transactions.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+///
+/// Whatever the strategy, the resulting gas price is capped at
+/// [`GasConfiguration::max_gas_price_gwei`] (see [`crate::gas::bundle_gas_price`]).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum PriorityFeeStrategy {
     /// Fixed priority fee in gwei
     Fixed(u64),
 
-    /// Dynamic based on network congestion
-    Dynamic { base_fee_multiplier: f64 },
+    /// Tip `base_fee * (base_fee_multiplier - 1)`, clamped to
+    /// `[min_priority_gwei, max_priority_gwei]` so a base-fee spike can't
+    /// run it away
+    Dynamic {
+        base_fee_multiplier: f64,
+
+        #[serde(default = "default_min_priority_gwei")]
+        min_priority_gwei: u64,
+
+        #[serde(default = "default_max_priority_gwei")]
+        max_priority_gwei: u64,
+    },
 
-    /// Competitive MEV pricing
+    /// Tip `min_priority_gwei` plus the base fee's predicted rise into the
+    /// next block, keeping pace with competing bids as congestion builds
     Competitive { min_priority_gwei: u64 },
 }
 
+/// DEX router configuration.
+///
+/// Addresses are kept as strings so a malformed entry is reported by
+/// [`DexRegistry::from_config`] at startup rather than discarded with the rest
+/// of the config file.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DexConfiguration {
+    /// Chain the searcher runs on; selects which router overrides apply
+    #[serde(default = "default_chain_id")]
+    pub chain_id: u64,
+
+    /// Router overrides keyed by chain ID, then DEX name (e.g. `"uniswap_v2"`)
+    #[serde(default)]
+    pub routers: HashMap<u64, HashMap<String, String>>,
+}
+
+/// RPC transport implied by an endpoint URL's scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcTransport {
+    /// `ws://` or `wss://`: subscriptions available, full live pipeline
+    WebSocket,
+
+    /// `http://` or `https://`: polling only, suited to observation and simulation
+    Http,
+}
+
 /// Performance metrics for MEV operations.
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct MEVMetrics {
     /// Total transactions analyzed
     pub transactions_analyzed: u64,
 
+    /// Announced transactions no longer known to the node when fetched
+    #[serde(default)]
+    pub txs_not_found: u64,
+
     /// Total opportunities detected
     pub opportunities_detected: u64,
 
@@ -187,6 +422,70 @@ pub struct MEVMetrics {
     /// Total bundles included on-chain
     pub bundles_included: u64,
 
+    /// Total bundles cancelled after submission
+    #[serde(default)]
+    pub bundles_cancelled: u64,
+
+    /// Times the pending-transaction stream was re-established this session
+    #[serde(default)]
+    pub reconnect_count: u64,
+
+    /// Position in the `--rpc-url` list of the endpoint currently streaming
+    #[serde(default)]
+    pub active_endpoint: usize,
+
+    /// Times the stream moved to another endpoint after the active one failed or stalled
+    #[serde(default)]
+    pub endpoint_failovers: u64,
+
+    /// JSON-RPC requests sent to the node
+    #[serde(default)]
+    pub rpc_calls: u64,
+
+    /// Opportunities discarded before bundle creation because the chain moved on
+    #[serde(default)]
+    pub stale_opportunities: u64,
+
+    /// JSON-RPC requests dropped by the rate limiter because its queue was full
+    #[serde(default)]
+    pub rpc_calls_shed: u64,
+
+    /// Transactions whose analysis was abandoned for exceeding the time budget
+    #[serde(default)]
+    pub analysis_timeouts: u64,
+
+    /// Pool reserve lookups answered from the per-block cache
+    #[serde(default)]
+    pub reserve_cache_hits: u64,
+
+    /// Pool reserve lookups that had to call the node
+    #[serde(default)]
+    pub reserve_cache_misses: u64,
+
+    /// Transactions classified from the per-block decode cache
+    #[serde(default)]
+    pub decode_cache_hits: u64,
+
+    /// Transactions whose calldata had to be decoded
+    #[serde(default)]
+    pub decode_cache_misses: u64,
+
+    /// Pending transactions re-sent under the same nonce with a higher gas bid
+    #[serde(default)]
+    pub replacements_detected: u64,
+
+    /// Pending transactions dropped unanalyzed because the pending queue was full
+    #[serde(default)]
+    pub pending_dropped: u64,
+
+    /// Opportunity envelopes dropped because the sink queue was full
+    #[serde(default)]
+    pub sink_envelopes_dropped: u64,
+
+    /// JSON-RPC requests per second over the last full one-second window
+    #[serde(default)]
+    pub rpc_call_rate: f64,
+
     /// Total profit realized in ETH
     pub total_profit_eth: f64,
 
@@ -204,8 +503,46 @@ pub struct MEVMetrics {
     /// Average processing latency in milliseconds
     pub avg_processing_latency_ms: f64,
 
+    /// Transactions whose processing latency was measured
+    #[serde(default)]
+    pub latency_samples: u64,
+
     /// Success rate (included bundles / submitted bundles)
     pub success_rate: f64,
+
+    /// Learned acceptance and inclusion rates, by relay name
+    #[serde(default)]
+    pub relay_stats: BTreeMap<String, RelayStats>,
+
+    /// Per-token-pair breakdown, keyed by the pair in address order.
+    /// Tuple keys aren't valid JSON object keys; exported via [`MEVMetrics::to_prometheus`]
+    #[serde(skip)]
+    pub pair_stats: HashMap<(Address, Address), PairStats>,
+
+    /// Detector rejections keyed by (strategy, reason); exported like `pair_stats`
+    #[serde(skip)]
+    pub rejections: HashMap<(&'static str, RejectReason), u64>,
+
+    /// The last [`LATENCY_WINDOW`] processing latencies in milliseconds, for percentiles
+    #[serde(skip)]
+    pub recent_latencies_ms: VecDeque<f64>,
+
+    /// Per-block observations of an `--observe-blocks` session; exported via the run report
+    #[serde(skip)]
+    pub observed_blocks: Option<BlockObserver>,
+}
+
+/// Activity on a single token pair.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PairStats {
+    /// Opportunities detected on the pair
+    pub opportunities_detected: u64,
+
+    /// Bundles submitted for the pair
+    pub bundles_submitted: u64,
+
+    /// Profit realized by included bundles, in ETH
+    pub realized_profit_eth: f64,
 }
 
 /// Token metadata for MEV analysis.
@@ -233,6 +570,20 @@ pub struct TokenInfo {
     pub avg_daily_volume_usd: f64,
 }
 
+/// Registry of known token metadata, keyed by contract address.
+#[derive(Debug, Clone, Default)]
+pub struct TokenRegistry {
+    // ---
+    tokens: HashMap<Address, TokenInfo>,
+}
+
+/// Router contract address of each DEX on the configured chain.
+#[derive(Debug, Clone, Default)]
+pub struct DexRegistry {
+    // ---
+    routers: HashMap<DEX, Address>,
+}
+
 /// DEX pool information for arbitrage calculations.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PoolInfo {
@@ -264,8 +615,9 @@ pub struct PoolInfo {
     pub last_updated: u64,
 }
 
-/// Risk parameters for MEV strategies.
+/// Risk parameters for MEV strategies; omitted fields take their defaults.
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct RiskParameters {
     /// Maximum position size in ETH
     pub max_position_size_eth: f64,
@@ -279,7 +631,8 @@ pub struct RiskParameters {
     /// Blacklisted addresses (suspicious activity)
     pub address_blacklist: Vec<Address>,
 
-    /// Minimum confirmations before considering transaction final
+    /// Blocks behind the head that liquidation position data is read at, so
+    /// a position is only trusted once its state has this many confirmations
     pub min_confirmations: u8,
 
     /// Circuit breaker: max losses before stopping (ETH)
@@ -292,22 +645,42 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             eth_rpc_url: "wss://eth-mainnet.g.alchemy.com/v2/your_api_key".to_string(),
-            private_key: None,
             mev_config: MEVConfig::default(),
             relay_config: RelayConfiguration::default(),
             gas_config: GasConfiguration::default(),
+            dex_config: DexConfiguration::default(),
         }
     }
 }
 
+/// Default router swap deadline: five blocks past the latest block.
+fn default_swap_deadline_secs() -> u64 {
+    60
+}
+
+/// Default break-even headroom: 0.5% of costs.
+fn default_profit_margin_bps() -> u16 {
+    50
+}
+
 impl Default for MEVConfig {
     fn default() -> Self {
         Self {
-            min_profit_eth: 0.01,    // 0.01 ETH minimum profit
+            min_profit: 0.01, // 0.01 ETH minimum profit
+            profit_denomination: ProfitDenomination::Eth,
+            builder_bribe_percent: 0.0,
+            fee_recipient: None,
+            fee_bps: 0,
+            swap_deadline_secs: default_swap_deadline_secs(),
+            profit_margin_bps: default_profit_margin_bps(),
+            max_staleness_blocks: 0,
+            rank_by_expected_value: false,
+            profit_floor_mode: ProfitFloorMode::All,
             max_gas_price_gwei: 200, // 200 gwei max
             arbitrage: ArbitrageConfig::default(),
             sandwich: SandwichConfig::default(),
             liquidation: LiquidationConfig::default(),
+            risk: RiskParameters::default(),
         }
     }
 }
@@ -315,25 +688,51 @@ impl Default for MEVConfig {
 impl Default for ArbitrageConfig {
     fn default() -> Self {
         Self {
+            enabled: true,
             min_trade_size_eth: 1.0,   // 1 ETH minimum
+            min_net_profit_eth: None,  // Global min_profit
+            min_net_profit_bps: None,  // No relative floor
             max_slippage_percent: 2.0, // 2% max slippage
-            enabled_dexs: vec![
-                "uniswap_v2".to_string(),
-                "uniswap_v3".to_string(),
-                "sushiswap".to_string(),
-            ],
+            enabled_dexs: BTreeSet::from([DEX::UniswapV2, DEX::UniswapV3, DEX::SushiSwap]),
             token_whitelist: Vec::new(), // All tokens allowed by default
+            min_pool_liquidity_usd: default_min_pool_liquidity_usd(),
         }
     }
 }
 
+/// Strategies other than sandwiching run unless configured off.
+fn default_strategy_enabled() -> bool {
+    true
+}
+
+/// Default pool liquidity floor: thinner pools move too much to fill a backrun.
+fn default_min_pool_liquidity_usd() -> f64 {
+    100_000.0
+}
+
 impl Default for SandwichConfig {
     fn default() -> Self {
         Self {
             min_victim_size_eth: 5.0,   // 5 ETH minimum victim trade
+            min_net_profit_eth: None,   // Global min_profit
+            min_net_profit_bps: None,   // No relative floor
             max_frontrun_percent: 15.0, // 15% max frontrun size
             gas_price_buffer_gwei: 5,   // 5 gwei buffer above victim
             enabled: false,             // Disabled by default (more risky)
+            victim_inclusion: VictimInclusion::Bundled,
+        }
+    }
+}
+
+impl Default for RiskParameters {
+    fn default() -> Self {
+        Self {
+            max_position_size_eth: 10.0, // 10 ETH per position
+            max_concurrent_opportunities: 5,
+            token_blacklist: Vec::new(),
+            address_blacklist: Vec::new(),
+            min_confirmations: 0,    // Trust the latest block
+            max_daily_loss_eth: 1.0, // 1 ETH daily loss limit
         }
     }
 }
@@ -341,14 +740,32 @@ impl Default for SandwichConfig {
 impl Default for LiquidationConfig {
     fn default() -> Self {
         Self {
+            enabled: true,
             min_bonus_eth: 0.05,          // 0.05 ETH minimum bonus
             health_factor_threshold: 1.0, // Below 1.0 health factor
-            enabled_protocols: vec!["aave".to_string(), "compound".to_string()],
+            enabled_protocols: BTreeSet::from([Protocol::Aave, Protocol::Compound]),
             flash_loan_providers: vec!["aave".to_string(), "dydx".to_string()],
+            sizing: LiquidationSizing::Max,
         }
     }
 }
 
+impl LiquidationConfig {
+    // ---
+
+    /// Parses `flash_loan_providers` into providers, in configured order.
+    pub fn providers(&self) -> anyhow::Result<Vec<FlashLoanProvider>> {
+        // ---
+        self.flash_loan_providers
+            .iter()
+            .map(|name| {
+                FlashLoanProvider::from_name(name)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown flash loan provider '{}'", name))
+            })
+            .collect()
+    }
+}
+
 impl Default for RelayConfiguration {
     fn default() -> Self {
         let mut relays = HashMap::new();
@@ -360,6 +777,7 @@ impl Default for RelayConfiguration {
                 auth_key: None,
                 enabled: true,
                 inclusion_probability: 0.85,
+                refused_strategies: Vec::new(),
                 avg_latency_ms: 150,
             },
         );
@@ -371,6 +789,7 @@ impl Default for RelayConfiguration {
                 auth_key: None,
                 enabled: true,
                 inclusion_probability: 0.75,
+                refused_strategies: Vec::new(),
                 avg_latency_ms: 120,
             },
         );
@@ -379,19 +798,58 @@ impl Default for RelayConfiguration {
             priority_order: vec!["flashbots".to_string(), "bloXroute".to_string()],
             relays,
             submission_timeout_secs: 10,
+            max_bundle_gas: default_max_bundle_gas(),
+            max_bundle_txs: default_max_bundle_txs(),
         }
     }
 }
 
+/// Default bundle gas cap: the approximate block gas target.
+fn default_max_bundle_gas() -> u64 {
+    12_000_000
+}
+
+/// Default bundle size cap, well above what any single strategy builds.
+fn default_max_bundle_txs() -> usize {
+    16
+}
+
+/// Ethereum mainnet.
+fn default_chain_id() -> u64 {
+    1
+}
+
+impl Default for DexConfiguration {
+    fn default() -> Self {
+        Self {
+            chain_id: default_chain_id(),
+            routers: HashMap::new(),
+        }
+    }
+}
+
+/// Default floor of a dynamic priority fee, in gwei.
+fn default_min_priority_gwei() -> u64 {
+    1
+}
+
+/// Default ceiling of a dynamic priority fee, in gwei.
+fn default_max_priority_gwei() -> u64 {
+    20
+}
+
 impl Default for GasConfiguration {
     fn default() -> Self {
         Self {
             strategy: GasStrategy::NetworkAverage { buffer_gwei: 10 },
             priority_fee_strategy: PriorityFeeStrategy::Dynamic {
                 base_fee_multiplier: 1.5,
+                min_priority_gwei: default_min_priority_gwei(),
+                max_priority_gwei: default_max_priority_gwei(),
             },
             max_gas_price_gwei: 300,
             gas_limit_multiplier: 1.2,
+            price_bump_bps: 0,
         }
     }
 }
@@ -402,30 +860,496 @@ impl MEVMetrics {
         self.transactions_analyzed += 1;
     }
 
-    /// Records a detected MEV opportunity.
-    pub fn record_opportunity(&mut self, opportunity_type: &str) {
+    /// Records a detected MEV opportunity on `pair`.
+    pub fn record_opportunity(&mut self, opportunity_type: &str, pair: (Address, Address)) {
         self.opportunities_detected += 1;
+        self.pair_entry(pair).opportunities_detected += 1;
         match opportunity_type {
-            "arbitrage" => self.arbitrage_count += 1,
+            "arbitrage" | "multi_hop_arbitrage" => self.arbitrage_count += 1,
             "sandwich" => self.sandwich_count += 1,
             "liquidation" => self.liquidation_count += 1,
             _ => {}
         }
     }
 
-    /// Records a bundle submission.
-    pub fn record_bundle_submission(&mut self) {
+    /// Records a bundle submission for `pair`.
+    pub fn record_bundle_submission(&mut self, pair: (Address, Address)) {
         self.bundles_submitted += 1;
+        self.pair_entry(pair).bundles_submitted += 1;
+    }
+
+    /// Records a cancellation of a previously submitted bundle.
+    pub fn record_bundle_cancellation(&mut self) {
+        self.bundles_cancelled += 1;
+    }
+
+    /// Records a re-established pending-transaction stream.
+    ///
+    /// Metrics are owned by the listener for the whole session, so counts
+    /// keep accumulating across reconnects rather than resetting.
+    pub fn record_reconnect(&mut self) {
+        self.reconnect_count += 1;
+    }
+
+    /// Records the stream moving to endpoint `index` of the `--rpc-url` list
+    /// after the active endpoint failed or stalled.
+    pub fn record_failover(&mut self, index: usize) {
+        self.endpoint_failovers += 1;
+        self.active_endpoint = index;
+    }
+
+    /// Records how long a transaction took from receipt to the end of its analysis.
+    pub fn record_processing_latency(&mut self, latency: Duration) {
+        let ms = latency.as_secs_f64() * 1_000.0;
+        self.latency_samples += 1;
+        self.avg_processing_latency_ms +=
+            (ms - self.avg_processing_latency_ms) / self.latency_samples as f64;
+
+        if self.recent_latencies_ms.len() == LATENCY_WINDOW {
+            self.recent_latencies_ms.pop_front();
+        }
+        self.recent_latencies_ms.push_back(ms);
+    }
+
+    /// Returns the processing latency at `quantile` (0.0-1.0) over the recent
+    /// window, in milliseconds, or `None` before any was recorded.
+    pub fn latency_percentile(&self, quantile: f64) -> Option<f64> {
+        let mut sorted: Vec<f64> = self.recent_latencies_ms.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let last = sorted.len().checked_sub(1)?;
+        let index = (last as f64 * quantile).round() as usize;
+        Some(sorted[index.min(last)])
+    }
+
+    /// Moves the block observer, if any, to `head`.
+    pub fn observe_head(&mut self, head: U64) {
+        if let Some(observer) = &mut self.observed_blocks {
+            observer.observe_head(head.as_u64());
+        }
+    }
+
+    /// Records a transaction that arrived while `head` was the latest block,
+    /// when observing blocks.
+    pub fn record_block_transaction(&mut self, head: U64, tx: &Transaction) {
+        if let Some(observer) = &mut self.observed_blocks {
+            observer.record_transaction(head.as_u64(), tx);
+        }
+    }
+
+    /// Records an opportunity found in a transaction that arrived while `head`
+    /// was the latest block, when observing blocks.
+    pub fn record_block_opportunity(&mut self, head: U64, strategy: &'static str) {
+        if let Some(observer) = &mut self.observed_blocks {
+            observer.record_opportunity(head.as_u64(), strategy);
+        }
+    }
+
+    /// Records whether `relay` accepted a submission. Relays not yet tracked
+    /// start from [`RelayStats::default`].
+    pub fn record_relay_response(&mut self, relay: &str, accepted: bool) {
+        self.relay_entry(relay).record_response(accepted);
+    }
+
+    /// Records whether a bundle `relay` reported on landed.
+    pub fn record_relay_inclusion(&mut self, relay: &str, included: bool) {
+        self.relay_entry(relay).record_inclusion(included);
     }
 
-    /// Records a successful bundle inclusion.
-    pub fn record_bundle_inclusion(&mut self, profit_eth: f64, gas_cost_eth: f64) {
+    /// Records an announced transaction that was gone before its body could be fetched.
+    pub fn record_tx_not_found(&mut self) {
+        self.txs_not_found += 1;
+    }
+
+    /// Records an opportunity discarded as stale before its bundle was built.
+    pub fn record_stale_opportunity(&mut self) {
+        self.stale_opportunities += 1;
+    }
+
+    /// Records a transaction whose analysis was abandoned after timing out.
+    pub fn record_analysis_timeout(&mut self) {
+        self.analysis_timeouts += 1;
+    }
+
+    /// Records a pool reserve lookup, answered from the cache or not.
+    pub fn record_reserve_lookup(&mut self, cached: bool) {
+        if cached {
+            self.reserve_cache_hits += 1;
+        } else {
+            self.reserve_cache_misses += 1;
+        }
+    }
+
+    /// Returns the share of reserve lookups served from the cache, if any were made.
+    pub fn reserve_cache_hit_rate(&self) -> Option<f64> {
+        let lookups = self.reserve_cache_hits + self.reserve_cache_misses;
+        (lookups > 0).then(|| self.reserve_cache_hits as f64 / lookups as f64)
+    }
+
+    /// Records a transaction classification, answered from the cache or not.
+    pub fn record_decode_lookup(&mut self, cached: bool) {
+        if cached {
+            self.decode_cache_hits += 1;
+        } else {
+            self.decode_cache_misses += 1;
+        }
+    }
+
+    /// Returns the share of classifications served from the cache, if any were made.
+    pub fn decode_cache_hit_rate(&self) -> Option<f64> {
+        let lookups = self.decode_cache_hits + self.decode_cache_misses;
+        (lookups > 0).then(|| self.decode_cache_hits as f64 / lookups as f64)
+    }
+
+    /// Records a pending transaction replaced by a higher gas bid.
+    pub fn record_replacement(&mut self) {
+        self.replacements_detected += 1;
+    }
+
+    /// Records a pending transaction dropped from the full pending queue.
+    pub fn record_pending_dropped(&mut self) {
+        self.pending_dropped += 1;
+    }
+
+    /// Records that `strategy` passed on a transaction for `reason`.
+    pub fn record_rejection(&mut self, strategy: &'static str, reason: RejectReason) {
+        *self.rejections.entry((strategy, reason)).or_default() += 1;
+    }
+
+    /// Returns rejection counts sorted by strategy, then reason.
+    pub fn rejection_counts(&self) -> Vec<((&'static str, RejectReason), u64)> {
+        let mut counts: Vec<_> = self.rejections.iter().map(|(k, v)| (*k, *v)).collect();
+        counts.sort();
+        counts
+    }
+
+    /// Records a JSON-RPC request sent to the node.
+    pub fn record_rpc_call(&mut self) {
+        self.rpc_calls += 1;
+    }
+
+    /// Records a JSON-RPC request shed by the rate limiter.
+    pub fn record_rpc_shed(&mut self) {
+        self.rpc_calls_shed += 1;
+    }
+
+    /// Records a successful bundle inclusion for `pair`.
+    pub fn record_bundle_inclusion(
+        &mut self,
+        pair: (Address, Address),
+        profit_eth: f64,
+        gas_cost_eth: f64,
+    ) {
         self.bundles_included += 1;
+        self.pair_entry(pair).realized_profit_eth += profit_eth - gas_cost_eth;
         self.total_profit_eth += profit_eth;
         self.total_gas_costs_eth += gas_cost_eth;
         self.net_profit_eth = self.total_profit_eth - self.total_gas_costs_eth;
         self.success_rate = self.bundles_included as f64 / self.bundles_submitted as f64;
     }
+
+    /// Returns up to `n` pairs with the most detected opportunities, most active
+    /// first (ties broken by realized profit).
+    pub fn top_pairs(&self, n: usize) -> Vec<((Address, Address), &PairStats)> {
+        let mut pairs: Vec<_> = self
+            .pair_stats
+            .iter()
+            .map(|(pair, stats)| (*pair, stats))
+            .collect();
+        pairs.sort_by(|(_, a), (_, b)| {
+            b.opportunities_detected
+                .cmp(&a.opportunities_detected)
+                .then(b.realized_profit_eth.total_cmp(&a.realized_profit_eth))
+        });
+        pairs.truncate(n);
+        pairs
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format, with
+    /// per-pair series labeled by `token_a`/`token_b` and a leading
+    /// `mev_build_info` gauge identifying the binary.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = crate::build_info::prometheus_gauge();
+
+        let counters = [
+            (
+                "mev_transactions_analyzed_total",
+                self.transactions_analyzed,
+            ),
+            (
+                "mev_opportunities_detected_total",
+                self.opportunities_detected,
+            ),
+            ("mev_bundles_submitted_total", self.bundles_submitted),
+            ("mev_bundles_included_total", self.bundles_included),
+            ("mev_bundles_cancelled_total", self.bundles_cancelled),
+            ("mev_txs_not_found_total", self.txs_not_found),
+            ("mev_reconnects_total", self.reconnect_count),
+            ("mev_endpoint_failovers_total", self.endpoint_failovers),
+            ("mev_rpc_calls_total", self.rpc_calls),
+            ("mev_rpc_calls_shed_total", self.rpc_calls_shed),
+            ("mev_analysis_timeouts_total", self.analysis_timeouts),
+            ("mev_stale_opportunities_total", self.stale_opportunities),
+            ("mev_reserve_cache_hits_total", self.reserve_cache_hits),
+            ("mev_reserve_cache_misses_total", self.reserve_cache_misses),
+            ("mev_decode_cache_hits_total", self.decode_cache_hits),
+            ("mev_decode_cache_misses_total", self.decode_cache_misses),
+            (
+                "mev_replacements_detected_total",
+                self.replacements_detected,
+            ),
+            ("mev_pending_dropped_total", self.pending_dropped),
+            (
+                "mev_sink_envelopes_dropped_total",
+                self.sink_envelopes_dropped,
+            ),
+        ];
+        for (name, value) in counters {
+            let _ = writeln!(out, "# TYPE {name} counter\n{name} {value}");
+        }
+        let _ = writeln!(
+            out,
+            "# TYPE mev_net_profit_eth gauge\nmev_net_profit_eth {}",
+            self.net_profit_eth
+        );
+        let _ = writeln!(
+            out,
+            "# TYPE mev_rpc_call_rate gauge\nmev_rpc_call_rate {}",
+            self.rpc_call_rate
+        );
+        let _ = writeln!(
+            out,
+            "# TYPE mev_active_endpoint gauge\nmev_active_endpoint {}",
+            self.active_endpoint
+        );
+
+        let mut pairs: Vec<_> = self.pair_stats.iter().collect();
+        pairs.sort_by_key(|(pair, _)| **pair);
+
+        let write_pair_series =
+            |out: &mut String, name: &str, kind: &str, value: fn(&PairStats) -> f64| {
+                let _ = writeln!(out, "# TYPE {name} {kind}");
+                for ((token_a, token_b), stats) in &pairs {
+                    let _ = writeln!(
+                        out,
+                        "{name}{{token_a=\"{token_a:?}\",token_b=\"{token_b:?}\"}} {}",
+                        value(stats)
+                    );
+                }
+            };
+        write_pair_series(
+            &mut out,
+            "mev_pair_opportunities_detected_total",
+            "counter",
+            |s| s.opportunities_detected as f64,
+        );
+        write_pair_series(
+            &mut out,
+            "mev_pair_bundles_submitted_total",
+            "counter",
+            |s| s.bundles_submitted as f64,
+        );
+        write_pair_series(&mut out, "mev_pair_realized_profit_eth", "gauge", |s| {
+            s.realized_profit_eth
+        });
+
+        let write_relay_series =
+            |out: &mut String, name: &str, kind: &str, value: fn(&RelayStats) -> f64| {
+                let _ = writeln!(out, "# TYPE {name} {kind}");
+                for (relay, stats) in &self.relay_stats {
+                    let _ = writeln!(out, "{name}{{relay=\"{relay}\"}} {}", value(stats));
+                }
+            };
+        write_relay_series(&mut out, "mev_relay_submissions_total", "counter", |s| {
+            s.submissions as f64
+        });
+        write_relay_series(&mut out, "mev_relay_acceptance_rate", "gauge", |s| {
+            s.acceptance_rate
+        });
+        write_relay_series(&mut out, "mev_relay_inclusion_rate", "gauge", |s| {
+            s.inclusion_rate
+        });
+
+        let _ = writeln!(out, "# TYPE mev_rejections_total counter");
+        for ((strategy, reason), count) in self.rejection_counts() {
+            let _ = writeln!(
+                out,
+                "mev_rejections_total{{strategy=\"{strategy}\",reason=\"{reason}\"}} {count}"
+            );
+        }
+
+        out
+    }
+
+    /// Returns the stats entry for `relay`.
+    fn relay_entry(&mut self, relay: &str) -> &mut RelayStats {
+        self.relay_stats.entry(relay.to_string()).or_default()
+    }
+
+    /// Returns the stats entry for `pair`, ordering its tokens so both trade
+    /// directions share one entry.
+    fn pair_entry(&mut self, (a, b): (Address, Address)) -> &mut PairStats {
+        self.pair_stats.entry((a.min(b), a.max(b))).or_default()
+    }
+}
+
+impl TokenRegistry {
+    // ---
+
+    /// Creates a registry pre-populated with major mainnet tokens.
+    pub fn with_defaults() -> Self {
+        // ---
+
+        let defaults = [
+            (
+                "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                "WETH",
+                "Wrapped Ether",
+                18,
+            ),
+            (
+                "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                "USDC",
+                "USD Coin",
+                6,
+            ),
+            (
+                "0xdAC17F958D2ee523a2206206994597C13D831ec7",
+                "USDT",
+                "Tether USD",
+                6,
+            ),
+            (
+                "0x6B175474E89094C44Da98b954EedeAC495271d0F",
+                "DAI",
+                "Dai Stablecoin",
+                18,
+            ),
+        ];
+
+        let mut registry = Self::default();
+        for (address, symbol, name, decimals) in defaults {
+            if let Ok(address) = address.parse() {
+                registry.insert(TokenInfo {
+                    address,
+                    symbol: symbol.to_string(),
+                    name: name.to_string(),
+                    decimals,
+                    is_active: true,
+                    liquidity_score: 1.0,
+                    avg_daily_volume_usd: 0.0,
+                });
+            }
+        }
+        registry
+    }
+
+    /// Adds or replaces a token's metadata.
+    pub fn insert(&mut self, info: TokenInfo) {
+        self.tokens.insert(info.address, info);
+    }
+
+    /// Looks up a token's metadata by contract address.
+    pub fn get(&self, address: &Address) -> Option<&TokenInfo> {
+        self.tokens.get(address)
+    }
+
+    /// Looks up a token's address by symbol (case-insensitive).
+    pub fn address_of(&self, symbol: &str) -> Option<Address> {
+        self.tokens
+            .values()
+            .find(|info| info.symbol.eq_ignore_ascii_case(symbol))
+            .map(|info| info.address)
+    }
+
+    /// Returns the token's symbol, or its address when unknown.
+    pub fn symbol(&self, address: &Address) -> String {
+        self.get(address)
+            .map(|info| info.symbol.clone())
+            .unwrap_or_else(|| format!("{:?}", address))
+    }
+}
+
+impl DexRegistry {
+    // ---
+
+    /// Builds the registry for `config.chain_id`: the built-in mainnet routers
+    /// (on chain 1 only), overridden by that chain's configured routers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the entry if a configured DEX is unknown or its
+    /// address does not parse.
+    pub fn from_config(config: &DexConfiguration) -> anyhow::Result<Self> {
+        // ---
+
+        let mainnet = [
+            (DEX::UniswapV2, "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D"),
+            (DEX::UniswapV3, "0xE592427A0AEce92De3Edee1F18E0157C05861564"),
+            (DEX::SushiSwap, "0xd9e1cE17f2641f24aE83637ab66a2cca9C378B9F"),
+        ];
+
+        let mut registry = Self::default();
+        if config.chain_id == 1 {
+            for (dex, router) in mainnet {
+                registry.insert(dex, router)?;
+            }
+        }
+
+        let overrides = config.routers.get(&config.chain_id).into_iter().flatten();
+        for (name, router) in overrides {
+            let dex = DEX::from_name(name).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown DEX '{}' in chain {} routers",
+                    name,
+                    config.chain_id
+                )
+            })?;
+            registry.insert(dex, router)?;
+        }
+        Ok(registry)
+    }
+
+    /// Parses and records `dex`'s router address.
+    fn insert(&mut self, dex: DEX, router: &str) -> anyhow::Result<()> {
+        // ---
+        let address = router
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid {:?} router address '{}': {}", dex, router, e))?;
+        self.routers.insert(dex, address);
+        Ok(())
+    }
+
+    /// Looks up `dex`'s router address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no router is configured for `dex` on this chain.
+    pub fn router(&self, dex: DEX) -> anyhow::Result<Address> {
+        self.routers
+            .get(&dex)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("No router configured for {:?}", dex))
+    }
+}
+
+impl RpcTransport {
+    /// Determines the transport from a URL's scheme (case-insensitive).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scheme is not `ws`, `wss`, `http`, or `https`.
+    pub fn from_url(url: &str) -> anyhow::Result<Self> {
+        let scheme = url.split_once("://").map(|(scheme, _)| scheme);
+
+        match scheme.map(str::to_ascii_lowercase).as_deref() {
+            Some("ws" | "wss") => Ok(Self::WebSocket),
+            Some("http" | "https") => Ok(Self::Http),
+            _ => anyhow::bail!(
+                "Unsupported RPC URL '{}': expected ws://, wss://, http:// or https://",
+                url
+            ),
+        }
+    }
 }
 
 /// Utility functions for configuration management.
@@ -439,38 +1363,291 @@ impl Config {
             config.eth_rpc_url = rpc_url;
         }
 
-        if let Ok(private_key) = std::env::var("PRIVATE_KEY") {
-            config.private_key = Some(private_key);
-        }
-
-        // Load additional config from file if exists
-        if let Ok(config_str) = std::fs::read_to_string("mev_config.json") {
-            if let Ok(file_config) = serde_json::from_str::<Config>(&config_str) {
-                config = file_config;
-            }
+        // Load additional config from file if exists; one that doesn't parse
+        // (say, a misspelled DEX name) must not silently fall back to defaults
+        if let Ok(config_str) = std::fs::read_to_string(CONFIG_FILE) {
+            config = serde_json::from_str(&config_str)
+                .map_err(|e| anyhow::anyhow!("Malformed config {}: {}", CONFIG_FILE, e))?;
         }
 
         Ok(config)
     }
 
+    /// Loads a configuration file in the `mev_config.json` format.
+    ///
+    /// Unlike [`Config::from_env`], a missing or unreadable file is an error.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let config_str = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read config {}: {}", path.display(), e))?;
+        serde_json::from_str(&config_str)
+            .map_err(|e| anyhow::anyhow!("Malformed config {}: {}", path.display(), e))
+    }
+
     /// Validates the configuration for completeness and correctness.
     pub fn validate(&self) -> anyhow::Result<()> {
         if self.eth_rpc_url.is_empty() {
             anyhow::bail!("ETH_RPC_URL cannot be empty");
         }
 
-        if !self.eth_rpc_url.starts_with("wss://") {
-            anyhow::bail!("ETH_RPC_URL must be a WebSocket URL (wss://)");
+        if RpcTransport::from_url(&self.eth_rpc_url)? == RpcTransport::Http {
+            warn!(
+                "ETH_RPC_URL is HTTP; WebSocket (wss://) is preferred for live mempool monitoring"
+            );
+        }
+
+        if self.relay_config.max_bundle_gas == 0 || self.relay_config.max_bundle_txs == 0 {
+            anyhow::bail!("Bundle gas and transaction limits must be positive");
+        }
+
+        if self.gas_config.max_gas_price_gwei == 0 {
+            anyhow::bail!("Gas configuration's maximum gas price must be positive");
         }
 
-        if self.mev_config.min_profit_eth <= 0.0 {
+        if let PriorityFeeStrategy::Dynamic {
+            base_fee_multiplier,
+            min_priority_gwei,
+            max_priority_gwei,
+        } = self.gas_config.priority_fee_strategy
+        {
+            if !base_fee_multiplier.is_finite() || base_fee_multiplier < 1.0 {
+                anyhow::bail!("Dynamic priority fee multiplier must be at least 1.0");
+            }
+            if min_priority_gwei > max_priority_gwei {
+                anyhow::bail!("Dynamic priority fee floor exceeds its ceiling");
+            }
+        }
+
+        self.mev_config.validate()
+    }
+}
+
+impl MEVConfig {
+    /// Validates the strategy parameters; also applied to runtime changes
+    /// made through the control API.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        // ---
+
+        if self.min_profit <= 0.0 {
             anyhow::bail!("Minimum profit must be positive");
         }
 
-        if self.mev_config.max_gas_price_gwei == 0 {
+        if self.max_gas_price_gwei == 0 {
             anyhow::bail!("Maximum gas price must be positive");
         }
 
+        // A 100% bribe hands the builder everything and leaves no profit
+        if !(0.0..100.0).contains(&self.builder_bribe_percent) {
+            anyhow::bail!("Builder bribe percent must be in [0, 100)");
+        }
+
+        // A 100% fee hands the recipient everything and leaves no profit
+        if u64::from(self.fee_bps) >= BPS_DENOMINATOR {
+            anyhow::bail!("Fee share must be below {} bps", BPS_DENOMINATOR);
+        }
+        if self.fee_bps > 0 && self.fee_recipient.is_none() {
+            anyhow::bail!("A fee share needs a fee_recipient to pay");
+        }
+
+        let min_liquidity = self.arbitrage.min_pool_liquidity_usd;
+        if !min_liquidity.is_finite() || min_liquidity < 0.0 {
+            anyhow::bail!("Minimum pool liquidity must be a non-negative number");
+        }
+
+        let floors = [
+            self.arbitrage.min_net_profit_eth,
+            self.sandwich.min_net_profit_eth,
+            Some(self.liquidation.min_bonus_eth),
+        ];
+        if floors
+            .into_iter()
+            .flatten()
+            .any(|floor| !floor.is_finite() || floor < 0.0)
+        {
+            anyhow::bail!("Per-strategy profit floors must be non-negative numbers");
+        }
+
+        if self.swap_deadline_secs == 0 {
+            anyhow::bail!("Swap deadline must be positive");
+        }
+
+        self.liquidation.providers()?;
+
         Ok(())
     }
+
+    /// Returns the names of the enabled strategies, for startup logs.
+    pub fn enabled_strategies(&self) -> Vec<&'static str> {
+        // ---
+        [
+            ("arbitrage", self.arbitrage.enabled),
+            ("sandwich", self.sandwich.enabled),
+            ("liquidation", self.liquidation.enabled),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect()
+    }
+}
+
+/// (De)serializes a set of typed values as a list of their config names:
+/// written with `Display`, read with `FromStr`, failing on the first unknown name.
+mod name_set {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeSet;
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T, S>(set: &BTreeSet<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Display,
+        S: Serializer,
+    {
+        serializer.collect_seq(set.iter().map(ToString::to_string))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<BTreeSet<T>, D::Error>
+    where
+        T: FromStr + Ord,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|name| name.parse().map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prometheus_export_is_well_formed() {
+        let mut metrics = MEVMetrics {
+            transactions_analyzed: 7,
+            endpoint_failovers: 2,
+            sink_envelopes_dropped: 3,
+            ..Default::default()
+        };
+        let pair = (Address::from_low_u64_be(2), Address::from_low_u64_be(1));
+        metrics.record_opportunity("arbitrage", pair);
+        metrics.record_relay_response("flashbots", true);
+        metrics.record_rejection("sandwich", crate::searcher::RejectReason::BelowMinSize);
+        let exported = metrics.to_prometheus();
+
+        // Every sample follows a `# TYPE` line for its metric and has a numeric value
+        let mut typed = BTreeSet::new();
+        for line in exported.lines() {
+            if let Some(declared) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = declared.split_once(' ').unwrap();
+                assert!(matches!(kind, "counter" | "gauge"), "{line}");
+                typed.insert(name.to_string());
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            let (series, value) = line.rsplit_once(' ').unwrap();
+            let name = series.split('{').next().unwrap();
+            assert!(typed.contains(name), "untyped sample: {line}");
+            assert!(value.parse::<f64>().is_ok(), "bad value: {line}");
+            if let Some(labels) = series.strip_prefix(name).filter(|l| !l.is_empty()) {
+                assert!(labels.starts_with('{') && labels.ends_with('}'), "{line}");
+            }
+        }
+
+        for sample in [
+            "mev_transactions_analyzed_total 7",
+            "mev_endpoint_failovers_total 2",
+            "mev_sink_envelopes_dropped_total 3",
+            "mev_opportunities_detected_total 1",
+            "mev_relay_submissions_total{relay=\"flashbots\"} 1",
+            "mev_rejections_total{strategy=\"sandwich\",reason=\"below_min_size\"} 1",
+        ] {
+            assert!(
+                exported.lines().any(|line| line == sample),
+                "missing {sample}"
+            );
+        }
+        assert!(exported.contains(&format!(
+            "mev_pair_opportunities_detected_total{{token_a=\"{:?}\",token_b=\"{:?}\"}} 1",
+            pair.1, pair.0
+        )));
+    }
+
+    fn dex_config(chain_id: u64, routers: &[(&str, &str)]) -> DexConfiguration {
+        let routers = routers
+            .iter()
+            .map(|(dex, router)| (dex.to_string(), router.to_string()))
+            .collect();
+        DexConfiguration {
+            chain_id,
+            routers: HashMap::from([(chain_id, routers)]),
+        }
+    }
+
+    #[test]
+    fn invalid_router_address_fails_at_load() {
+        let config = dex_config(
+            1,
+            &[("uniswap_v2", "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488")],
+        );
+
+        let err = DexRegistry::from_config(&config).unwrap_err();
+        assert!(err.to_string().contains("UniswapV2"), "{err}");
+
+        let config = dex_config(
+            1,
+            &[("uniswap_v9", "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D")],
+        );
+        assert!(DexRegistry::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn configured_routers_override_per_chain() {
+        let fork_router = "0x1111111111111111111111111111111111111111";
+        let registry =
+            DexRegistry::from_config(&dex_config(11155111, &[("sushiswap", fork_router)])).unwrap();
+
+        assert_eq!(
+            registry.router(DEX::SushiSwap).unwrap(),
+            fork_router.parse::<Address>().unwrap()
+        );
+        // Mainnet defaults do not leak onto other chains
+        assert!(registry.router(DEX::UniswapV2).is_err());
+
+        let mainnet = DexRegistry::from_config(&DexConfiguration::default()).unwrap();
+        assert!(mainnet.router(DEX::UniswapV3).is_ok());
+    }
+
+    #[test]
+    fn strategy_name_lists_load_as_typed_sets() {
+        let mut json = serde_json::to_value(MEVConfig::default()).unwrap();
+        assert_eq!(
+            json["arbitrage"]["enabled_dexs"],
+            serde_json::json!(["uniswap_v2", "uniswap_v3", "sushiswap"])
+        );
+
+        json["arbitrage"]["enabled_dexs"] = serde_json::json!(["Uniswap-V2", "sushi"]);
+        json["liquidation"]["enabled_protocols"] = serde_json::json!(["maker"]);
+        let config: MEVConfig = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            config.arbitrage.enabled_dexs,
+            BTreeSet::from([DEX::UniswapV2, DEX::SushiSwap])
+        );
+        assert_eq!(
+            config.liquidation.enabled_protocols,
+            BTreeSet::from([Protocol::MakerDAO])
+        );
+
+        // A typo fails the load instead of quietly disabling the DEX
+        json["arbitrage"]["enabled_dexs"] = serde_json::json!(["uniswap_v2", "sushiswapp"]);
+        let err = serde_json::from_value::<MEVConfig>(json).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown DEX 'sushiswapp'"),
+            "{err}"
+        );
+    }
 }
@@ -0,0 +1,153 @@
+//! ETH amount conversions.
+//!
+//! `format_ether` prints all 18 decimals, which buries the significant digits
+//! of most values. [`format_eth`] rounds to the `--value-decimals` precision
+//! instead. Structured output (JSON records, metrics) keeps full precision.
+//!
+//! Config thresholds are `f64` ETH while on-chain amounts are `U256` wei.
+//! [`eth_f64_to_wei`] and [`wei_to_eth_f64`] convert between the two through
+//! the decimal representation, so amounts beyond `u64::MAX` wei (about 18.4
+//! ETH) neither truncate nor panic the way an `as_u64` round trip does.
+
+use ethers::types::U256;
+
+/// Decimals shown for ETH amounts in logs unless `--value-decimals` says otherwise.
+pub const DEFAULT_VALUE_DECIMALS: usize = 6;
+
+/// Decimals of one ETH in wei.
+const ETH_DECIMALS: usize = 18;
+
+// ---
+
+/// Formats `wei` as ETH rounded half-up to `decimals` places.
+///
+/// A nonzero amount that rounds to zero renders as `<0.000001` (at the chosen
+/// precision) so it isn't mistaken for nothing. Precision beyond 18 decimals
+/// is clamped to the full wei value.
+pub fn format_eth(wei: U256, decimals: usize) -> String {
+    // ---
+
+    let decimals = decimals.min(ETH_DECIMALS);
+    let unit = U256::exp10(ETH_DECIMALS - decimals);
+    let (mut scaled, remainder) = wei.div_mod(unit);
+    // Compare against the remainder's complement so the doubling can't overflow
+    if remainder >= unit - remainder {
+        scaled += U256::one();
+    }
+
+    if scaled.is_zero() && !wei.is_zero() {
+        return format!("<{}", fixed_point(U256::one(), decimals));
+    }
+    fixed_point(scaled, decimals)
+}
+
+/// Converts an ETH amount to wei, truncating below one wei.
+///
+/// Negative and NaN amounts convert to zero; amounts too large for a `U256`
+/// (including infinity) saturate at `U256::MAX`.
+pub fn eth_f64_to_wei(eth: f64) -> U256 {
+    // ---
+
+    if eth.is_nan() || eth <= 0.0 {
+        return U256::zero();
+    }
+    if eth.is_infinite() {
+        return U256::MAX;
+    }
+
+    // f64's Display never uses an exponent, so this is plain `whole.fraction`
+    let decimal = eth.to_string();
+    let (whole, fraction) = decimal.split_once('.').unwrap_or((&decimal, ""));
+    let fraction: String = fraction
+        .chars()
+        .chain(std::iter::repeat('0'))
+        .take(ETH_DECIMALS)
+        .collect();
+    U256::from_dec_str(&format!("{whole}{fraction}")).unwrap_or(U256::MAX)
+}
+
+/// Converts a wei amount to ETH, to the nearest `f64`.
+pub fn wei_to_eth_f64(wei: U256) -> f64 {
+    // ---
+    ethers::utils::format_ether(wei)
+        .parse()
+        .expect("format_ether renders a decimal number")
+}
+
+/// Renders `scaled` with its last `decimals` digits after the point.
+fn fixed_point(scaled: U256, decimals: usize) -> String {
+    // ---
+    if decimals == 0 {
+        return scaled.to_string();
+    }
+    let (whole, fraction) = scaled.div_mod(U256::exp10(decimals));
+    // U256's Display ignores fill and width; pad its string instead
+    format!("{whole}.{:0>decimals$}", fraction.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wei(amount: &str) -> U256 {
+        ethers::utils::parse_ether(amount).unwrap()
+    }
+
+    #[test]
+    fn values_round_half_up_rather_than_truncate() {
+        assert_eq!(format_eth(wei("0.123456789012345678"), 6), "0.123457");
+        assert_eq!(format_eth(wei("0.1234565"), 6), "0.123457");
+        assert_eq!(format_eth(wei("0.12345649999"), 6), "0.123456");
+        // Carries ripple into the whole part
+        assert_eq!(format_eth(wei("1.9999996"), 6), "2.000000");
+        assert_eq!(format_eth(wei("2.5"), 0), "3");
+        assert_eq!(format_eth(wei("12"), 2), "12.00");
+    }
+
+    #[test]
+    fn tiny_nonzero_values_are_not_shown_as_zero() {
+        assert_eq!(format_eth(U256::zero(), 6), "0.000000");
+        assert_eq!(format_eth(U256::one(), 6), "<0.000001");
+        assert_eq!(format_eth(wei("0.0000004"), 6), "<0.000001");
+        assert_eq!(format_eth(wei("0.0000005"), 6), "0.000001");
+        assert_eq!(format_eth(wei("0.4"), 0), "<1");
+    }
+
+    #[test]
+    fn full_precision_and_extremes() {
+        assert_eq!(format_eth(U256::one(), 18), "0.000000000000000001");
+        assert_eq!(format_eth(U256::one(), 30), "0.000000000000000001");
+        assert_eq!(
+            format_eth(U256::MAX, 18),
+            ethers::utils::format_ether(U256::MAX)
+        );
+        // No overflow when rounding the largest value up
+        assert!(format_eth(U256::MAX, 6).ends_with(".584008"));
+    }
+
+    #[test]
+    fn eth_converts_to_wei_exactly() {
+        assert_eq!(eth_f64_to_wei(1.5), wei("1.5"));
+        assert_eq!(eth_f64_to_wei(0.000000000000000001), U256::one());
+        // Below one wei truncates
+        assert_eq!(eth_f64_to_wei(1e-20), U256::zero());
+        assert_eq!(eth_f64_to_wei(-1.0), U256::zero());
+        assert_eq!(eth_f64_to_wei(f64::NAN), U256::zero());
+    }
+
+    #[test]
+    fn amounts_beyond_u64_wei_do_not_overflow() {
+        // 100 ETH is over u64::MAX wei
+        assert!(wei("100") > U256::from(u64::MAX));
+        assert_eq!(eth_f64_to_wei(100.0), wei("100"));
+        assert_eq!(wei_to_eth_f64(wei("100")), 100.0);
+        assert_eq!(eth_f64_to_wei(1e30), wei("1000000000000000000000000000000"));
+        assert_eq!(wei_to_eth_f64(U256::exp10(48)), 1e30);
+
+        // Gas bids are sender-chosen and may be anything up to U256::MAX
+        let max_eth = wei_to_eth_f64(U256::MAX);
+        assert!(max_eth.is_finite() && max_eth > 1.15e59);
+        assert_eq!(eth_f64_to_wei(f64::MAX), U256::MAX);
+        assert_eq!(eth_f64_to_wei(f64::INFINITY), U256::MAX);
+    }
+}
@@ -0,0 +1,84 @@
+//! Searcher signing key loading.
+//!
+//! The key comes from an encrypted JSON keystore (`--keystore`, unlocked with
+//! a password read from an environment variable) or, failing that, from the
+//! plaintext `PRIVATE_KEY` environment variable. It is held only in the
+//! returned [`LocalWallet`] and never copied into [`crate::types::Config`], so
+//! it can't end up in a written-out configuration file.
+
+use anyhow::Context;
+use ethers::signers::LocalWallet;
+use std::path::Path;
+
+/// Environment variable holding a plaintext private key, used without `--keystore`.
+pub const PRIVATE_KEY_VAR: &str = "PRIVATE_KEY";
+
+// ---
+
+/// Loads the searcher wallet, preferring `keystore` over [`PRIVATE_KEY_VAR`].
+///
+/// Returns `None` when neither is configured, which is fine for simulation.
+/// A keystore that can't be unlocked, or a malformed plaintext key, is an error.
+pub fn load_searcher_wallet(
+    keystore: Option<&Path>,
+    password_var: &str,
+) -> anyhow::Result<Option<LocalWallet>> {
+    // ---
+    if let Some(path) = keystore {
+        let password = std::env::var(password_var).with_context(|| {
+            format!(
+                "Keystore {} needs its password in the {} environment variable",
+                path.display(),
+                password_var
+            )
+        })?;
+        return decrypt_keystore(path, &password).map(Some);
+    }
+
+    match std::env::var(PRIVATE_KEY_VAR) {
+        Ok(key) => {
+            let wallet = key
+                .parse::<LocalWallet>()
+                .with_context(|| format!("{PRIVATE_KEY_VAR} is not a valid private key"))?;
+            Ok(Some(wallet))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// Decrypts a Web3 Secret Storage (v3) keystore file into a wallet.
+pub fn decrypt_keystore(path: &Path, password: &str) -> anyhow::Result<LocalWallet> {
+    // ---
+    LocalWallet::decrypt_keystore(path, password)
+        .with_context(|| format!("Failed to decrypt keystore {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::Signer;
+    use ethers::types::Address;
+    use std::path::PathBuf;
+
+    /// Well-known development key (Anvil/Hardhat account #0) in a scrypt keystore.
+    fn test_keystore() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("src/testdata/keystore.json")
+    }
+
+    #[test]
+    fn keystore_decrypts_to_expected_address() {
+        let wallet = decrypt_keystore(&test_keystore(), "vortex-test").unwrap();
+
+        let expected: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+            .parse()
+            .unwrap();
+        assert_eq!(wallet.address(), expected);
+    }
+
+    #[test]
+    fn wrong_keystore_password_is_an_error() {
+        let err = decrypt_keystore(&test_keystore(), "not-the-password").unwrap_err();
+
+        assert!(err.to_string().contains("keystore.json"));
+    }
+}
@@ -0,0 +1,369 @@
+//! WebSocket transport setup with per-provider connection options.
+//!
+//! Some node providers authenticate with a request header rather than a key in
+//! the URL, and `txpool_content` responses from a busy node can outgrow the
+//! default frame limits. [`connect`] performs the WebSocket handshake itself,
+//! so it can attach [`WsOptions`] headers, apply frame size limits, and give
+//! up after a timeout instead of hanging, then hands the open stream to
+//! ethers' [`Ws`] client.
+//!
+//! A node started alongside the bot (e.g. in the same container deployment)
+//! may not accept connections yet when the bot starts. [`connect_with_retry`]
+//! keeps trying the first connection with backoff per [`StartupRetry`], but
+//! only for failures that waiting can fix: a refused or reset connection, a
+//! timeout, or a server error from a proxy in front of the node. A rejected
+//! handshake (bad credentials, wrong path) fails at once.
+
+use ethers::providers::Ws;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Error as WsError;
+use tracing::warn;
+
+/// Default time allowed for the TCP, TLS, and WebSocket handshakes together.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default wait before the first startup retry; it doubles after each attempt.
+pub const DEFAULT_STARTUP_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Longest wait between startup retries, however many attempts have failed.
+const MAX_STARTUP_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+// ---
+
+/// An extra header sent with the WebSocket handshake, given as `Name: value`.
+///
+/// Header values are usually credentials, so `Debug` shows only the name.
+#[derive(Clone, PartialEq, Eq)]
+pub struct WsHeader {
+    // ---
+    /// Header name, validated as an HTTP token
+    pub name: HeaderName,
+
+    /// Header value, validated as visible ASCII
+    pub value: HeaderValue,
+}
+
+/// How the WebSocket connection to the node is opened.
+#[derive(Debug, Clone)]
+pub struct WsOptions {
+    // ---
+    /// Headers added to the handshake request
+    pub headers: Vec<WsHeader>,
+
+    /// Time allowed to establish the connection before giving up
+    pub connect_timeout: Duration,
+
+    /// Largest frame (and at least the largest message) accepted from the node;
+    /// `None` keeps the tungstenite defaults
+    pub max_frame_bytes: Option<usize>,
+}
+
+/// How often the first connection of a session is retried while the node is
+/// not reachable yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartupRetry {
+    // ---
+    /// Attempts made after the first one fails (0 disables retrying)
+    pub retries: u32,
+
+    /// Wait before the first retry, doubled after each further failure
+    pub delay: Duration,
+}
+
+/// Why a single connection attempt failed.
+#[derive(Debug)]
+struct ConnectError {
+    // ---
+    error: anyhow::Error,
+
+    /// Whether a later attempt may succeed
+    retryable: bool,
+}
+
+// ---
+
+impl FromStr for WsHeader {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // ---
+        let (name, value) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected NAME:VALUE, got '{s}'"))?;
+        let name = HeaderName::from_str(name.trim())
+            .map_err(|e| format!("invalid header name '{}': {}", name.trim(), e))?;
+        let mut value = HeaderValue::from_str(value.trim())
+            .map_err(|e| format!("invalid value for header '{name}': {e}"))?;
+        value.set_sensitive(true);
+        Ok(Self { name, value })
+    }
+}
+
+impl fmt::Debug for WsHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(f, "{}: <redacted>", self.name)
+    }
+}
+
+impl Default for WsOptions {
+    fn default() -> Self {
+        Self {
+            headers: Vec::new(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            max_frame_bytes: None,
+        }
+    }
+}
+
+impl Default for StartupRetry {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            delay: DEFAULT_STARTUP_RETRY_DELAY,
+        }
+    }
+}
+
+impl WsOptions {
+    // ---
+
+    /// Returns the tungstenite config carrying `max_frame_bytes`.
+    fn config(&self) -> Option<WebSocketConfig> {
+        // ---
+        self.max_frame_bytes.map(|max_frame| {
+            let mut config = WebSocketConfig::default();
+            config.max_frame_size = Some(max_frame);
+            config.max_message_size = config.max_message_size.map(|max| max.max(max_frame));
+            config
+        })
+    }
+}
+
+/// Opens a WebSocket JSON-RPC connection to `url` with `options` applied.
+///
+/// # Errors
+/// Returns an error if the URL is malformed, the handshake fails, or it does
+/// not complete within `options.connect_timeout`.
+pub async fn connect(url: &str, options: &WsOptions) -> anyhow::Result<Ws> {
+    // ---
+    attempt(url, options).await.map_err(|e| e.error)
+}
+
+/// Opens a WebSocket JSON-RPC connection like [`connect`], retrying per
+/// `retry` while the node is unreachable. Each failed attempt is logged.
+///
+/// # Errors
+/// Returns the last attempt's error once the retries run out, or the first
+/// error that retrying can't fix (a malformed URL, a rejected handshake).
+pub async fn connect_with_retry(
+    url: &str,
+    options: &WsOptions,
+    retry: StartupRetry,
+) -> anyhow::Result<Ws> {
+    // ---
+
+    let attempts = retry.retries.saturating_add(1);
+    let mut delay = retry.delay;
+    for number in 1.. {
+        match attempt(url, options).await {
+            Ok(ws) => return Ok(ws),
+            Err(e) if e.retryable && number < attempts => {
+                warn!(
+                    "⏳ Connection attempt {}/{} failed ({}); retrying in {:?}",
+                    number, attempts, e.error, delay
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_STARTUP_RETRY_DELAY);
+            }
+            Err(e) => {
+                if attempts > 1 {
+                    warn!(
+                        "🔌 Connection attempt {}/{} failed ({}); not retrying",
+                        number, attempts, e.error
+                    );
+                }
+                return Err(e.error);
+            }
+        }
+    }
+    unreachable!("attempts are bounded by retry.retries")
+}
+
+/// Makes one connection attempt, classifying its failure.
+async fn attempt(url: &str, options: &WsOptions) -> Result<Ws, ConnectError> {
+    // ---
+
+    let mut request = url.into_client_request().map_err(|e| ConnectError {
+        error: anyhow::anyhow!("Invalid WebSocket URL: {}", e),
+        retryable: false,
+    })?;
+    for header in &options.headers {
+        request
+            .headers_mut()
+            .insert(header.name.clone(), header.value.clone());
+    }
+
+    let handshake = tokio_tungstenite::connect_async_with_config(request, options.config(), false);
+    let (stream, _) = tokio::time::timeout(options.connect_timeout, handshake)
+        .await
+        .map_err(|_| ConnectError {
+            error: anyhow::anyhow!(
+                "Timed out connecting to the WebSocket endpoint after {:?}",
+                options.connect_timeout
+            ),
+            retryable: true,
+        })?
+        .map_err(|e| ConnectError {
+            retryable: retryable(&e),
+            error: anyhow::anyhow!("WebSocket connection failed: {}", e),
+        })?;
+
+    Ok(Ws::new(stream))
+}
+
+/// Returns whether a failed handshake may succeed later: the node wasn't
+/// listening or dropped the connection (including a name that doesn't resolve
+/// yet), or a proxy in front of it answered with a server error. Rejections
+/// such as 401 or 403 come from the node's configuration and won't change.
+fn retryable(error: &WsError) -> bool {
+    // ---
+    match error {
+        WsError::Io(_) | WsError::ConnectionClosed | WsError::AlreadyClosed => true,
+        WsError::Http(response) => response.status().is_server_error(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+    use tokio_tungstenite::tungstenite::http::StatusCode;
+
+    #[test]
+    fn headers_parse_from_name_value_pairs() {
+        let header: WsHeader = "X-Api-Key: s3cret".parse().unwrap();
+        assert_eq!(header.name, "x-api-key");
+        assert_eq!(header.value, "s3cret");
+        assert_eq!(format!("{header:?}"), "x-api-key: <redacted>");
+
+        assert!("no-separator".parse::<WsHeader>().is_err());
+        assert!("bad name: value".parse::<WsHeader>().is_err());
+    }
+
+    #[tokio::test]
+    #[allow(clippy::result_large_err)] // the handshake callback's error type is tungstenite's
+    async fn handshake_carries_configured_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let seen = Arc::new(Mutex::new(None));
+
+        let server_seen = seen.clone();
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let record = |request: &Request, response: Response| {
+                *server_seen.lock().unwrap() = Some(request.headers().clone());
+                Ok(response)
+            };
+            let _ws = tokio_tungstenite::accept_hdr_async(tcp, record)
+                .await
+                .unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let options = WsOptions {
+            headers: vec!["X-Api-Key: s3cret".parse().unwrap()],
+            max_frame_bytes: Some(64 << 20),
+            ..Default::default()
+        };
+        connect(&url, &options).await.unwrap();
+
+        let headers = seen.lock().unwrap().take().unwrap();
+        assert_eq!(headers["x-api-key"], "s3cret");
+    }
+
+    #[tokio::test]
+    async fn startup_waits_for_the_node_to_come_up() {
+        // Nothing listens on the port at first: connections are refused
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let url = format!("ws://{addr}");
+        let options = WsOptions::default();
+        let retry = StartupRetry {
+            retries: 10,
+            delay: Duration::from_millis(20),
+        };
+        let refused = connect(&url, &options).await.unwrap_err();
+        assert!(refused.to_string().contains("refused"), "{refused}");
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let listener = TcpListener::bind(addr).await.unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let _ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            std::future::pending::<()>().await;
+        });
+        connect_with_retry(&url, &options, retry).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[allow(clippy::result_large_err)] // the handshake callback's error type is tungstenite's
+    async fn rejected_credentials_are_not_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let handshakes = Arc::new(Mutex::new(0));
+
+        let server_handshakes = handshakes.clone();
+        tokio::spawn(async move {
+            loop {
+                let (tcp, _) = listener.accept().await.unwrap();
+                *server_handshakes.lock().unwrap() += 1;
+                let reject = |_: &Request, _: Response| -> Result<Response, ErrorResponse> {
+                    let mut response = ErrorResponse::new(Some("bad api key".to_string()));
+                    *response.status_mut() = StatusCode::UNAUTHORIZED;
+                    Err(response)
+                };
+                let _ = tokio_tungstenite::accept_hdr_async(tcp, reject).await;
+            }
+        });
+
+        let retry = StartupRetry {
+            retries: 3,
+            delay: Duration::from_millis(10),
+        };
+        let err = connect_with_retry(&url, &WsOptions::default(), retry)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("401"), "{err}");
+        assert_eq!(*handshakes.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn unanswered_handshake_times_out() {
+        // Accepts the TCP connection but never answers the upgrade request
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _tcp = listener.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let options = WsOptions {
+            connect_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let err = connect(&url, &options).await.unwrap_err();
+        assert!(err.to_string().starts_with("Timed out connecting"), "{err}");
+    }
+}